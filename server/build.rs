@@ -0,0 +1,17 @@
+//! Bakes the commit being built into the binary as `GIT_SHA`, for the build-info endpoint.
+//! A `GIT_SHA` set in the environment wins, for builds without the `.git` directory.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+    let sha = std::env::var("GIT_SHA").ok().filter(|s| !s.trim().is_empty()).or_else(|| {
+        let out = Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+        out.status.success().then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    });
+    if let Some(sha) = sha {
+        println!("cargo:rustc-env=GIT_SHA={}", sha.trim());
+    }
+}
@@ -0,0 +1,41 @@
+//! Arbitrary client frames through a session's dispatch, one frame per line of input. Run with
+//! `cargo +nightly fuzz run client_msg` from `server/`.
+//!
+//! Nothing a client sends may panic the server: a panicking room actor shows up here as a room
+//! that stops answering. Rooms must also stay self-consistent whatever they were sent.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use server::{AppState, Session};
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap())
+}
+
+fn state() -> &'static AppState {
+    static STATE: OnceLock<AppState> = OnceLock::new();
+    STATE.get_or_init(|| runtime().block_on(AppState::offline()))
+}
+
+fuzz_target!(|data: &[u8]| {
+    let state = state();
+    runtime().block_on(async {
+        let (mut session, _direct) = Session::start(state, None, Some("fuzz".to_string()), None).await;
+        for frame in data.split(|&b| b == b'\n') {
+            let text = String::from_utf8_lossy(frame);
+            assert!(session.handle_text(state, &text).await.is_some(), "room stopped after {text:?}");
+        }
+        if session.room_id().is_some() {
+            let view = session.room_view().await.expect("room stopped");
+            for p in &view.players {
+                assert!(p.position <= view.passage_len, "{} at {} of {}", p.id, p.position, view.passage_len);
+                assert!(p.cursor.wrong.len() <= p.cursor.pos);
+            }
+        }
+        session.close(state).await;
+    });
+});
@@ -0,0 +1,25 @@
+-- Baseline schema. Everything is IF NOT EXISTS so databases created before migrations
+-- were introduced adopt it without changes.
+
+CREATE TABLE IF NOT EXISTS passages (
+    id SERIAL PRIMARY KEY,
+    text TEXT UNIQUE NOT NULL,
+    source_url TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS race_results (
+    id BIGSERIAL PRIMARY KEY,
+    room TEXT NOT NULL,
+    player_name TEXT NOT NULL,
+    wpm DOUBLE PRECISION NOT NULL,
+    accuracy DOUBLE PRECISION NOT NULL,
+    time_secs DOUBLE PRECISION,
+    finished_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS replay JSONB;
+CREATE INDEX IF NOT EXISTS race_results_player_idx ON race_results (player_name, finished_at);
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS passage_len INTEGER;
+-- Keyset pagination of a player's history walks this index backwards by id
+CREATE INDEX IF NOT EXISTS race_results_player_id_idx ON race_results (player_name, id);
@@ -0,0 +1,30 @@
+-- Accounts signed in through an OAuth provider; results link to them when known
+CREATE TABLE IF NOT EXISTS users (
+    id BIGSERIAL PRIMARY KEY,
+    provider TEXT NOT NULL,
+    provider_user_id TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (provider, provider_user_id)
+);
+
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS user_id BIGINT REFERENCES users(id);
+CREATE INDEX IF NOT EXISTS race_results_user_idx ON race_results (user_id, id);
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS guest_id TEXT;
+CREATE INDEX IF NOT EXISTS race_results_guest_idx ON race_results (guest_id, id);
+
+-- Persistent per-identity progression (`user:{id}` or `guest:{id}`)
+CREATE TABLE IF NOT EXISTS profiles (
+    identity TEXT PRIMARY KEY,
+    xp BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Best WPM per identity and passage category
+CREATE TABLE IF NOT EXISTS personal_bests (
+    identity TEXT NOT NULL,
+    category TEXT NOT NULL,
+    wpm DOUBLE PRECISION NOT NULL,
+    achieved_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (identity, category)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS consistency DOUBLE PRECISION;
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS burst_wpm DOUBLE PRECISION;
@@ -0,0 +1,2 @@
+ALTER TABLE passages ADD COLUMN IF NOT EXISTS author TEXT;
+ALTER TABLE passages ADD COLUMN IF NOT EXISTS title TEXT;
@@ -0,0 +1,6 @@
+-- Per-season WPM percentiles (1..=100), refreshed periodically by the server
+CREATE MATERIALIZED VIEW IF NOT EXISTS wpm_percentiles AS
+SELECT r.season, pct, percentile_cont(pct / 100.0) WITHIN GROUP (ORDER BY r.wpm) AS wpm
+FROM (SELECT to_char(finished_at, 'YYYY-"Q"Q') AS season, wpm FROM race_results) r
+CROSS JOIN generate_series(1, 100) AS pct
+GROUP BY r.season, pct;
@@ -0,0 +1,10 @@
+-- Each race's winner per room, for the lobby's hall of fame
+CREATE TABLE IF NOT EXISTS room_winners (
+    id BIGSERIAL PRIMARY KEY,
+    room TEXT NOT NULL,
+    player_name TEXT NOT NULL,
+    wpm DOUBLE PRECISION NOT NULL,
+    won_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS room_winners_room_idx ON room_winners (room, id);
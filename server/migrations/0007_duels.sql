@@ -0,0 +1,10 @@
+-- Head-to-head record between two signed-in racers; user_a is always the lower id
+CREATE TABLE IF NOT EXISTS duels (
+    user_a BIGINT NOT NULL REFERENCES users(id),
+    user_b BIGINT NOT NULL REFERENCES users(id),
+    a_wins INTEGER NOT NULL DEFAULT 0,
+    b_wins INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (user_a, user_b),
+    CHECK (user_a < user_b)
+);
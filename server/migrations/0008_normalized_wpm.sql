@@ -0,0 +1,2 @@
+-- WPM credited for passage difficulty (shared::difficulty), so results on different passages compare
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS normalized_wpm DOUBLE PRECISION;
@@ -0,0 +1,4 @@
+-- Replays past the retention window are dropped once their pace stats are kept on the row
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS replay_samples INTEGER;
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS replay_compacted_at TIMESTAMP WITH TIME ZONE;
+CREATE INDEX IF NOT EXISTS race_results_replay_age ON race_results (finished_at) WHERE replay IS NOT NULL;
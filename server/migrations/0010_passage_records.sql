@@ -0,0 +1,10 @@
+-- Fastest finish on each passage, keyed by the hex SHA-256 of its text
+CREATE TABLE IF NOT EXISTS passage_records (
+    passage_key TEXT PRIMARY KEY,
+    passage TEXT NOT NULL,
+    player_name TEXT NOT NULL,
+    wpm DOUBLE PRECISION NOT NULL,
+    set_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS passage_records_wpm_idx ON passage_records (wpm DESC);
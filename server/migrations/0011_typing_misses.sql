@@ -0,0 +1,10 @@
+-- Wrong keys by identity (account or guest token), counted per lowercase bigram and word,
+-- for practice drills
+CREATE TABLE IF NOT EXISTS typing_misses (
+    identity TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('bigram', 'word')),
+    item TEXT NOT NULL,
+    misses INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (identity, kind, item)
+);
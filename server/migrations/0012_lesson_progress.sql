@@ -0,0 +1,11 @@
+-- Bests per lesson of the typing course, by identity (account or guest token)
+CREATE TABLE IF NOT EXISTS lesson_progress (
+    identity TEXT NOT NULL,
+    lesson TEXT NOT NULL,
+    best_wpm DOUBLE PRECISION NOT NULL,
+    best_accuracy DOUBLE PRECISION NOT NULL,
+    passed BOOLEAN NOT NULL DEFAULT FALSE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (identity, lesson)
+);
@@ -0,0 +1,11 @@
+-- How racers get on with each pool passage, keyed by the hex SHA-256 of its text; retired
+-- passages are left out of rotation until an admin restores them
+CREATE TABLE IF NOT EXISTS passage_health (
+    passage_key TEXT PRIMARY KEY,
+    passage TEXT NOT NULL,
+    finishes INTEGER NOT NULL DEFAULT 0,
+    abandons INTEGER NOT NULL DEFAULT 0,
+    accuracy DOUBLE PRECISION NOT NULL DEFAULT 100,
+    retired BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
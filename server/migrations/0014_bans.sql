@@ -0,0 +1,6 @@
+-- Admin bans on an identity (`user:{id}` / `guest:{id}`) or an address (`ip:{addr}`)
+CREATE TABLE IF NOT EXISTS bans (
+    target TEXT PRIMARY KEY,
+    reason TEXT,
+    banned_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+-- Races that passed the stricter checks (server-timed keys, no lag, a long enough passage, a
+-- signed-in account); only these count towards the global leaderboard
+ALTER TABLE race_results ADD COLUMN IF NOT EXISTS verified BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+-- Each account's friends, one row per (account, friend); an account may invite only its own
+CREATE TABLE IF NOT EXISTS friends (
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    friend_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    added_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (user_id, friend_id)
+);
@@ -0,0 +1,603 @@
+//! HTTP API consumed by the web client and third-party tools.
+//! Response bodies use the DTOs in `shared::api`; failures use its error envelope.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use shared::api::{
+    ApiError, ApiErrorCode, CreatedRoom, ErrorEnvelope, LeaderboardEntry, LessonAttempt, LessonProgress, LeaderboardOrder, Page, PassageCategory, PassageInfo, PassageRecord, PercentileResponse,
+    PracticeDrill, PracticeReport, Profile, RaceResult, RoomSummary, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+use shared::generate::PassageGenerator;
+use shared::lessons::{self, Lesson};
+use shared::protocol::{ClientMsg, ServerMsg};
+use shared::text::char_len;
+use shared::weakness::{Misses, Weaknesses, FOCUS_ITEMS};
+use shared::wpm::adjusted_wpm;
+use sqlx::PgPool;
+use tracing::warn;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::auth::{request_owner, Owner};
+use crate::card::Card;
+use crate::room::MAX_PLAUSIBLE_WPM;
+use crate::room_codes;
+use crate::{db, AppState};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api/percentile", get(percentile))
+        .route("/api/me/export", get(export))
+        .route("/api/card", get(card))
+        .route("/api/leaderboard", get(leaderboard))
+        .route("/api/records", get(records))
+        .route("/api/rooms", get(rooms))
+        .route("/api/rooms/new", get(new_room))
+        .route("/api/passages", get(passages))
+        .route("/api/me/profile", get(profile))
+        .route("/api/me/results", get(player_results))
+        .route("/api/me/drill", get(drill).post(next_drill))
+        .route("/api/lessons", get(course))
+        .route("/api/me/lessons", get(lesson_progress))
+        .route("/api/me/lessons/:lesson", post(lesson_attempt))
+        .route("/api/docs", get(docs))
+}
+
+/// OpenAPI description of the REST API. The WebSocket protocol at `/ws` is not an
+/// HTTP operation, so its `ClientMsg`/`ServerMsg` frames are published as components.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "rracer API", description = "Stats, history and room endpoints for rracer clients"),
+    paths(
+        percentile, export, card, player_results, leaderboard, records, rooms, new_room, passages, profile, drill, next_drill, course, lesson_progress, lesson_attempt,
+        crate::bot_api::create_room, crate::bot_api::room_status, crate::bot_api::room_results,
+        crate::auth::me, crate::friends::friends, crate::friends::add_friend, crate::friends::remove_friend, crate::friends::invite, crate::health::healthz, crate::health::readyz, crate::health::build_info
+    ),
+    components(schemas(ClientMsg, ServerMsg, ErrorEnvelope, ApiError, ApiErrorCode))
+)]
+pub struct ApiDoc;
+
+async fn docs() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+pub struct ApiFailure(StatusCode, ApiError);
+
+impl ApiFailure {
+    pub fn new(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        let status = match code {
+            ApiErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ApiErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ApiErrorCode::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ApiErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self(status, ApiError { code, message: message.into() })
+    }
+
+    pub(crate) fn internal(what: &str, e: anyhow::Error) -> Self {
+        warn!(endpoint = what, error = ?e, "api_request_failed");
+        Self::new(ApiErrorCode::Internal, format!("{what} failed"))
+    }
+}
+
+impl IntoResponse for ApiFailure {
+    fn into_response(self) -> Response {
+        (self.0, Json(ErrorEnvelope { error: self.1 })).into_response()
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiFailure>;
+
+/// Public URL of this server; `PUBLIC_URL` wins over the request's `Host` header.
+pub(crate) fn public_base_url(headers: &HeaderMap) -> String {
+    let base = std::env::var("PUBLIC_URL").ok().unwrap_or_else(|| {
+        let host = headers.get(header::HOST).and_then(|h| h.to_str().ok()).unwrap_or("localhost:3000");
+        format!("http://{host}")
+    });
+    base.trim_end_matches('/').to_string()
+}
+
+fn require_db(state: &AppState) -> ApiResult<&PgPool> {
+    state.db.as_deref().ok_or_else(|| ApiFailure::new(ApiErrorCode::Unavailable, "stats unavailable without a database"))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct PageQuery {
+    /// Page size (default 50, max 200)
+    limit: Option<usize>,
+    /// `next_cursor` from the previous page
+    cursor: Option<i64>,
+}
+
+impl PageQuery {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+/// Trim a `limit + 1` fetch down to one page and derive the next cursor from the last item.
+fn paginate<T>(mut items: Vec<T>, limit: usize, cursor_of: impl Fn(&T) -> i64) -> Page<T> {
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+    let next_cursor = if has_more { items.last().map(cursor_of) } else { None };
+    Page { items, next_cursor }
+}
+
+impl From<db::RaceRecord> for RaceResult {
+    fn from(r: db::RaceRecord) -> Self {
+        Self {
+            id: r.id,
+            room: r.room,
+            player_name: r.player_name,
+            wpm: r.wpm,
+            accuracy: r.accuracy,
+            time_secs: r.time_secs,
+            finished_at: r.finished_at,
+            passage_len: r.passage_len.and_then(|n| usize::try_from(n).ok()),
+            consistency: r.consistency,
+            burst_wpm: r.burst_wpm,
+            adjusted_wpm: adjusted_wpm(r.wpm, r.accuracy),
+            normalized_wpm: r.normalized_wpm,
+            replay: r.replay.map(|j| j.0),
+            verified: r.verified,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+struct LeaderboardQuery {
+    /// `wpm` (the default) or `normalized`, which credits harder passages
+    by: Option<LeaderboardOrder>,
+}
+
+#[derive(Deserialize, IntoParams)]
+struct PercentileQuery {
+    wpm: f64,
+    /// Season label such as `2025-Q3`; defaults to the current season
+    season: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/percentile",
+    params(PercentileQuery),
+    responses(
+        (status = 200, description = "Share of this season's racers slower than `wpm`", body = PercentileResponse),
+        (status = 503, description = "No database configured", body = ErrorEnvelope)
+    )
+)]
+async fn percentile(Query(q): Query<PercentileQuery>, State(state): State<AppState>) -> ApiResult<Json<PercentileResponse>> {
+    let pool = require_db(&state)?;
+    let percentile = db::wpm_percentile(pool, q.wpm, q.season.as_deref())
+        .await
+        .map_err(|e| ApiFailure::internal("percentile", e))?;
+    Ok(Json(PercentileResponse { wpm: q.wpm, percentile }))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct ExportQuery {
+    /// `json` (default) or `csv`
+    format: Option<String>,
+    /// Include progress replays (JSON only)
+    #[serde(default)]
+    replays: bool,
+    /// Guest token, for players who are not signed in
+    guest: Option<String>,
+}
+
+/// Download the caller's race history.
+///
+/// In place of `/api/export/{player}`, which by display name would hand anyone's history out.
+#[utoipa::path(
+    get,
+    path = "/api/me/export",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "The caller's full race history as a JSON or CSV attachment", body = Vec<RaceResult>),
+        (status = 400, description = "Unsupported format", body = ErrorEnvelope),
+        (status = 401, description = "Neither signed in nor a valid guest token", body = ErrorEnvelope),
+        (status = 503, description = "No database configured", body = ErrorEnvelope)
+    )
+)]
+async fn export(Query(q): Query<ExportQuery>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Response> {
+    let pool = require_db(&state)?;
+    let owner = request_owner(&state, &headers, q.guest.as_deref()).ok_or_else(|| ApiFailure::new(ApiErrorCode::Unauthorized, "not signed in and no valid guest token"))?;
+    let results: Vec<RaceResult> = db::player_results(pool, owner.user_id(), owner.guest_id(), q.replays)
+        .await
+        .map_err(|e| ApiFailure::internal("export", e))?
+        .into_iter()
+        .map(RaceResult::from)
+        .collect();
+    let response = match q.format.as_deref() {
+        Some("csv") => {
+            let disposition = "attachment; filename=\"rracer-results.csv\"";
+            ([(header::CONTENT_TYPE, "text/csv; charset=utf-8"), (header::CONTENT_DISPOSITION, disposition)], results_to_csv(&results)).into_response()
+        }
+        None | Some("json") => ([(header::CONTENT_DISPOSITION, "attachment; filename=\"rracer-results.json\"")], Json(results)).into_response(),
+        Some(other) => return Err(ApiFailure::new(ApiErrorCode::BadRequest, format!("unsupported export format '{other}'"))),
+    };
+    Ok(response)
+}
+
+/// Render race results as CSV (replays are omitted; use the JSON export for those).
+fn results_to_csv(results: &[RaceResult]) -> String {
+    let mut out = String::from("id,room,player_name,wpm,accuracy,time_secs,finished_at,passage_len,consistency,burst_wpm,adjusted_wpm,normalized_wpm\n");
+    for r in results {
+        out.push_str(&format!(
+            "{},{},{},{:.2},{:.2},{},{},{},{},{},{:.2},{}\n",
+            r.id,
+            csv_field(&r.room),
+            csv_field(&r.player_name),
+            r.wpm,
+            r.accuracy,
+            r.time_secs.map(|t| format!("{t:.3}")).unwrap_or_default(),
+            r.finished_at,
+            r.passage_len.map(|n| n.to_string()).unwrap_or_default(),
+            r.consistency.map(|c| format!("{c:.1}")).unwrap_or_default(),
+            r.burst_wpm.map(|b| format!("{b:.2}")).unwrap_or_default(),
+            r.adjusted_wpm,
+            r.normalized_wpm.map(|n| format!("{n:.2}")).unwrap_or_default()
+        ));
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+struct CardQuery {
+    name: String,
+    wpm: f64,
+    /// Percent
+    accuracy: f64,
+    /// Finishing place, drawn with `racers`
+    rank: Option<u32>,
+    racers: Option<u32>,
+    /// Passage byline
+    source: Option<String>,
+    /// `png` (default) or `svg`
+    format: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/card",
+    params(CardQuery),
+    responses(
+        (status = 200, description = "A 1200×630 result card for sharing, as PNG or SVG", content_type = "image/png"),
+        (status = 400, description = "Implausible result or unsupported format", body = ErrorEnvelope)
+    )
+)]
+async fn card(Query(q): Query<CardQuery>) -> ApiResult<Response> {
+    if !(0.0..=MAX_PLAUSIBLE_WPM).contains(&q.wpm) || !(0.0..=100.0).contains(&q.accuracy) {
+        return Err(ApiFailure::new(ApiErrorCode::BadRequest, "wpm or accuracy out of range"));
+    }
+    let rank = match (q.rank, q.racers) {
+        (Some(place), Some(racers)) if (1..=racers).contains(&place) => Some((place, racers)),
+        (None, _) => None,
+        _ => return Err(ApiFailure::new(ApiErrorCode::BadRequest, "rank must be between 1 and racers")),
+    };
+    // Cards for a given result never change
+    let cache = (header::CACHE_CONTROL, "public, max-age=86400");
+    let response = match q.format.as_deref() {
+        Some("svg") => {
+            let svg = Card { name: &q.name, wpm: q.wpm, accuracy: q.accuracy, rank, source: q.source.as_deref() }.to_svg();
+            ([(header::CONTENT_TYPE, "image/svg+xml"), cache], svg).into_response()
+        }
+        None | Some("png") => {
+            let png = tokio::task::spawn_blocking(move || Card { name: &q.name, wpm: q.wpm, accuracy: q.accuracy, rank, source: q.source.as_deref() }.to_png())
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|png| png)
+                .map_err(|e| ApiFailure::internal("card", e))?;
+            ([(header::CONTENT_TYPE, "image/png"), cache], png).into_response()
+        }
+        Some(other) => return Err(ApiFailure::new(ApiErrorCode::BadRequest, format!("unsupported card format '{other}'"))),
+    };
+    Ok(response)
+}
+
+#[derive(Deserialize, IntoParams)]
+struct HistoryQuery {
+    /// Page size (default 50, max 200)
+    limit: Option<usize>,
+    /// `next_cursor` from the previous page
+    cursor: Option<i64>,
+    /// Only races finished at or after this time (ms since the Unix epoch)
+    from: Option<i64>,
+    /// Only races finished before this time (ms since the Unix epoch)
+    to: Option<i64>,
+    /// Only races on passages of this length bucket
+    category: Option<PassageCategory>,
+    min_wpm: Option<f64>,
+    room: Option<String>,
+    /// Guest token, for players who are not signed in
+    guest: Option<String>,
+}
+
+impl HistoryQuery {
+    fn page(&self) -> PageQuery {
+        PageQuery { limit: self.limit, cursor: self.cursor }
+    }
+
+    fn filter(&self) -> ApiResult<db::HistoryFilter<'_>> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err(ApiFailure::new(ApiErrorCode::BadRequest, "`from` must be before `to`"));
+            }
+        }
+        let passage_len = self.category.map(|c| {
+            let (min, max) = c.len_range();
+            (min as i64, i64::try_from(max).unwrap_or(i64::MAX))
+        });
+        Ok(db::HistoryFilter { from: self.from, to: self.to, min_wpm: self.min_wpm, room: self.room.as_deref(), passage_len })
+    }
+}
+
+/// Page through the caller's race history.
+///
+/// In place of `/api/players/{player}/results`, which by display name would hand anyone's history out.
+#[utoipa::path(
+    get,
+    path = "/api/me/results",
+    params(HistoryQuery),
+    responses(
+        (status = 200, description = "The caller's race history matching the filters, newest first", body = Page<RaceResult>),
+        (status = 400, description = "Invalid date range", body = ErrorEnvelope),
+        (status = 401, description = "Neither signed in nor a valid guest token", body = ErrorEnvelope),
+        (status = 503, description = "No database configured", body = ErrorEnvelope)
+    )
+)]
+async fn player_results(Query(q): Query<HistoryQuery>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Page<RaceResult>>> {
+    let pool = require_db(&state)?;
+    let owner = request_owner(&state, &headers, q.guest.as_deref()).ok_or_else(|| ApiFailure::new(ApiErrorCode::Unauthorized, "not signed in and no valid guest token"))?;
+    let filter = q.filter()?;
+    let limit = q.page().limit();
+    let rows = db::player_results_page(pool, owner.user_id(), owner.guest_id(), &filter, limit as i64 + 1, q.cursor)
+        .await
+        .map_err(|e| ApiFailure::internal("history", e))?;
+    let items = rows.into_iter().map(RaceResult::from).collect();
+    Ok(Json(paginate(items, limit, |r: &RaceResult| r.id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/leaderboard",
+    params(PageQuery, LeaderboardQuery),
+    responses(
+        (status = 200, description = "Best verified WPM per player", body = Vec<LeaderboardEntry>),
+        (status = 503, description = "No database configured", body = ErrorEnvelope)
+    )
+)]
+async fn leaderboard(
+    Query(q): Query<PageQuery>,
+    Query(order): Query<LeaderboardQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<LeaderboardEntry>>> {
+    let pool = require_db(&state)?;
+    let by_normalized = order.by.unwrap_or_default() == LeaderboardOrder::Normalized;
+    let rows = db::leaderboard(pool, q.limit() as i64, by_normalized)
+        .await
+        .map_err(|e| ApiFailure::internal("leaderboard", e))?;
+    let entries = rows
+        .into_iter()
+        .map(|(player_name, best_wpm, best_normalized_wpm, races)| LeaderboardEntry { player_name, best_wpm, best_normalized_wpm, races })
+        .collect();
+    Ok(Json(entries))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/records",
+    params(PageQuery),
+    responses((status = 200, description = "Fastest finish on each passage, fastest first", body = Vec<PassageRecord>))
+)]
+async fn records(Query(q): Query<PageQuery>, State(state): State<AppState>) -> ApiResult<Json<Vec<PassageRecord>>> {
+    let records = state.records.list(q.limit()).await.map_err(|e| ApiFailure::internal("records", e))?;
+    Ok(Json(records))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/rooms",
+    responses((status = 200, description = "Active rooms", body = Vec<RoomSummary>))
+)]
+async fn rooms(State(state): State<AppState>) -> Json<Vec<RoomSummary>> {
+    let rooms: Vec<_> = state.rooms.iter().map(|r| r.value().clone()).collect();
+    let mut out = Vec::with_capacity(rooms.len());
+    for room in rooms {
+        let Some(view) = room.view().await else { continue };
+        let bots = view.players.iter().filter(|p| p.is_bot).count();
+        out.push(RoomSummary { id: room.id.clone(), state: view.phase.into(), humans: view.players.len() - bots, bots });
+    }
+    Json(out)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/rooms/new",
+    responses((status = 200, description = "A fresh room code and its join link; the room opens when someone joins", body = CreatedRoom))
+)]
+async fn new_room(State(state): State<AppState>, headers: HeaderMap) -> Json<CreatedRoom> {
+    let room = room_codes::generate(|code| state.rooms.contains_key(code));
+    Json(CreatedRoom { join_url: room_codes::join_url(&headers, &room), room })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/passages",
+    params(PageQuery),
+    responses(
+        (status = 200, description = "Passage pool in id order", body = Page<PassageInfo>),
+        (status = 400, description = "Negative cursor", body = ErrorEnvelope)
+    )
+)]
+async fn passages(Query(q): Query<PageQuery>, State(state): State<AppState>) -> ApiResult<Json<Page<PassageInfo>>> {
+    let limit = q.limit();
+    let Some(pool) = state.db.as_deref() else {
+        // Static fallback: index-based cursor over the bundled list
+        let start = match q.cursor {
+            Some(cursor) => usize::try_from(cursor).map_err(|_| ApiFailure::new(ApiErrorCode::BadRequest, "cursor must not be negative"))? + 1,
+            None => 0,
+        };
+        let items = shared::passages::PASSAGES
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(limit + 1)
+            .map(|(i, p)| PassageInfo {
+                id: i as i64,
+                text: p.text.to_string(),
+                source_url: None,
+                author: p.author.map(str::to_string),
+                title: p.title.map(str::to_string),
+            })
+            .collect();
+        return Ok(Json(paginate(items, limit, |p: &PassageInfo| p.id)));
+    };
+    let rows = db::passages_page(pool, limit as i64 + 1, q.cursor)
+        .await
+        .map_err(|e| ApiFailure::internal("passages", e))?;
+    Ok(Json(paginate(rows, limit, |p: &PassageInfo| p.id)))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct ProfileQuery {
+    /// IANA time zone that decides where a day ends, e.g. `Europe/Berlin` (default `UTC`)
+    tz: Option<String>,
+    /// Guest token, for players who are not signed in
+    guest: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me/profile",
+    params(ProfileQuery),
+    responses(
+        (status = 200, description = "Stats for the signed-in account or the given guest", body = Profile),
+        (status = 400, description = "Unknown time zone", body = ErrorEnvelope),
+        (status = 401, description = "Neither signed in nor a valid guest token", body = ErrorEnvelope),
+        (status = 503, description = "No database configured", body = ErrorEnvelope)
+    )
+)]
+async fn profile(Query(q): Query<ProfileQuery>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Profile>> {
+    let pool = require_db(&state)?;
+    let owner = request_owner(&state, &headers, q.guest.as_deref()).ok_or_else(|| ApiFailure::new(ApiErrorCode::Unauthorized, "not signed in and no valid guest token"))?;
+    let tz = q.tz.as_deref().unwrap_or("UTC");
+    let (days, today) = db::race_days(pool, owner.user_id(), owner.guest_id(), tz)
+        .await
+        .map_err(|e| ApiFailure::internal("profile", e))?
+        .ok_or_else(|| ApiFailure::new(ApiErrorCode::BadRequest, format!("unknown time zone '{tz}'")))?;
+    let streaks = shared::streak::streaks(&days, today);
+    let xp = db::profile_xp(pool, &owner.key()).await.map_err(|e| ApiFailure::internal("profile", e))?;
+    Ok(Json(Profile { current_streak: streaks.current, longest_streak: streaks.longest, xp, level: shared::xp::level_for_xp(xp) }))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct GuestQuery {
+    /// Guest token, for players who are not signed in
+    guest: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me/drill",
+    params(GuestQuery),
+    responses((status = 200, description = "A practice passage on the caller's most-missed bigrams and words; common words for anyone without stored misses", body = PracticeDrill))
+)]
+async fn drill(Query(q): Query<GuestQuery>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<PracticeDrill>> {
+    let owner = request_owner(&state, &headers, q.guest.as_deref());
+    let focus = stored_weaknesses(&state, owner.as_ref()).await?.unwrap_or_default();
+    Ok(Json(practice_drill(focus)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/drill",
+    params(GuestQuery),
+    request_body = PracticeReport,
+    responses((status = 200, description = "The report's misses are added to the caller's stored ones, and the next drill is built from them. Without a database or an identity, the next drill works on this report alone", body = PracticeDrill))
+)]
+async fn next_drill(Query(q): Query<GuestQuery>, State(state): State<AppState>, headers: HeaderMap, Json(report): Json<PracticeReport>) -> ApiResult<Json<PracticeDrill>> {
+    let owner = request_owner(&state, &headers, q.guest.as_deref());
+    // About one miss per char at most from a real session; caps the work a forged report makes
+    let missed = &report.missed[..report.missed.len().min(char_len(&report.passage))];
+    let misses = Misses::count(&report.passage, missed);
+    if let (Some(pool), Some(owner)) = (state.db.as_deref(), owner.as_ref()) {
+        db::record_misses(pool, &owner.key(), &misses).await.map_err(|e| ApiFailure::internal("next_drill", e))?;
+    }
+    let focus = stored_weaknesses(&state, owner.as_ref()).await?.unwrap_or_else(|| misses.focus(FOCUS_ITEMS));
+    Ok(Json(practice_drill(focus)))
+}
+
+/// The stored focus for `owner`; None without a database or an identity.
+async fn stored_weaknesses(state: &AppState, owner: Option<&Owner>) -> ApiResult<Option<Weaknesses>> {
+    let (Some(pool), Some(owner)) = (state.db.as_deref(), owner) else { return Ok(None) };
+    let focus = db::weaknesses(pool, &owner.key(), FOCUS_ITEMS as i64).await.map_err(|e| ApiFailure::internal("drill", e))?;
+    Ok(Some(focus))
+}
+
+fn practice_drill(focus: Weaknesses) -> PracticeDrill {
+    PracticeDrill { passage: focus.generate(rand::random()), focus }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/lessons",
+    responses((status = 200, description = "The typing course, first lesson first", body = Vec<Lesson>))
+)]
+async fn course() -> Json<Vec<Lesson>> {
+    Json(lessons::course())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me/lessons",
+    params(GuestQuery),
+    responses((status = 200, description = "Bests on each lesson the caller has tried; none for callers without an identity", body = Vec<LessonProgress>))
+)]
+async fn lesson_progress(Query(q): Query<GuestQuery>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Vec<LessonProgress>>> {
+    let Some(owner) = request_owner(&state, &headers, q.guest.as_deref()) else { return Ok(Json(Vec::new())) };
+    let progress = state.lessons.get(&owner.key()).await.map_err(|e| ApiFailure::internal("lesson_progress", e))?;
+    Ok(Json(progress))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/lessons/{lesson}",
+    params(("lesson" = String, Path, description = "Lesson id"), GuestQuery),
+    request_body = LessonAttempt,
+    responses(
+        (status = 200, description = "The attempt is added to the caller's bests; returns them all", body = Vec<LessonProgress>),
+        (status = 400, description = "WPM or accuracy out of range", body = ErrorEnvelope),
+        (status = 401, description = "Neither signed in nor a valid guest token", body = ErrorEnvelope),
+        (status = 404, description = "No such lesson", body = ErrorEnvelope)
+    )
+)]
+async fn lesson_attempt(
+    Path(lesson): Path<String>,
+    Query(q): Query<GuestQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(attempt): Json<LessonAttempt>,
+) -> ApiResult<Json<Vec<LessonProgress>>> {
+    let lesson = lessons::course().into_iter().find(|l| l.id == lesson).ok_or_else(|| ApiFailure::new(ApiErrorCode::NotFound, format!("no lesson '{lesson}'")))?;
+    if !(0.0..=MAX_PLAUSIBLE_WPM).contains(&attempt.wpm) || !(0.0..=100.0).contains(&attempt.accuracy) {
+        return Err(ApiFailure::new(ApiErrorCode::BadRequest, "wpm or accuracy out of range"));
+    }
+    let owner = request_owner(&state, &headers, q.guest.as_deref()).ok_or_else(|| ApiFailure::new(ApiErrorCode::Unauthorized, "not signed in and no valid guest token"))?;
+    let progress = state.lessons.record(&owner.key(), &lesson, &attempt).await.map_err(|e| ApiFailure::internal("lesson_attempt", e))?;
+    Ok(Json(progress))
+}
@@ -0,0 +1,161 @@
+//! The web client, served from `web/dist` on disk or, with the `embed-web` feature, from a copy
+//! built into the binary so a deploy is one executable. Either way unknown paths get
+//! `index.html` for client-side routing, `.br`/`.gz` siblings of a file are sent to clients that
+//! accept them (and anything else is gzipped on the fly), trunk's content-hashed files are
+//! cached for good, and everything else is revalidated against its ETag.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue},
+    Router,
+};
+use tower_http::compression::CompressionLayer;
+
+use crate::AppState;
+
+const INDEX: &str = "index.html";
+
+pub fn router() -> Router<AppState> {
+    #[cfg(feature = "embed-web")]
+    let router = Router::new().fallback(embedded::serve);
+    #[cfg(not(feature = "embed-web"))]
+    let router = disk::router();
+    router.layer(CompressionLayer::new())
+}
+
+/// Whether `if_none_match` (an `If-None-Match` value) lists `etag`.
+fn etag_matches(if_none_match: Option<&HeaderValue>, etag: &str) -> bool {
+    let Some(tags) = if_none_match.and_then(|v| v.to_str().ok()) else { return false };
+    tags.split(',').map(str::trim).any(|tag| tag == "*" || tag == etag)
+}
+
+/// ETag and caching headers for a response for `path`. Pages are always revalidated, even when
+/// they stand in for a missing hashed file, or a stale page would be cached for good.
+fn set_cache_headers(headers: &mut HeaderMap, path: &str, etag: &str) {
+    let is_page = headers.get(header::CONTENT_TYPE).is_some_and(|v| v.as_bytes().starts_with(b"text/html"));
+    let cache_control = if is_hashed(path) && !is_page { "public, max-age=31536000, immutable" } else { "no-cache" };
+    if let Ok(etag) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+}
+
+/// Trunk names built assets `name-<16 hex digits>.ext` (`name-<hash>_bg.wasm` for the wasm
+/// module), so their content never changes.
+fn is_hashed(path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let stem = file.split('.').next().unwrap_or(file);
+    let stem = stem.strip_suffix("_bg").unwrap_or(stem);
+    stem.rsplit_once('-').is_some_and(|(_, hash)| hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+#[cfg(not(feature = "embed-web"))]
+mod disk {
+    use axum::{
+        extract::Request,
+        http::{header, StatusCode},
+        middleware::{self, Next},
+        response::{IntoResponse, Response},
+        Router,
+    };
+    use sha2::{Digest, Sha256};
+    use tower_http::services::{ServeDir, ServeFile};
+
+    use super::{etag_matches, set_cache_headers, INDEX};
+    use crate::AppState;
+
+    const DIST: &str = "web/dist";
+
+    pub fn router() -> Router<AppState> {
+        let files = ServeDir::new(DIST).precompressed_br().precompressed_gzip().fallback(ServeFile::new(format!("{DIST}/{INDEX}")));
+        Router::new().fallback_service(files).layer(middleware::from_fn(revalidate))
+    }
+
+    /// `ServeDir` only sends `Last-Modified`. Add a weak ETag made from that and the length,
+    /// answer a matching `If-None-Match` with 304, and set `Cache-Control`.
+    async fn revalidate(request: Request, next: Next) -> Response {
+        let path = request.uri().path().to_owned();
+        let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+        let mut response = next.run(request).await;
+        if response.status() != StatusCode::OK {
+            return response;
+        }
+        let headers = response.headers();
+        let (Some(modified), Some(len)) = (headers.get(header::LAST_MODIFIED), headers.get(header::CONTENT_LENGTH)) else {
+            return response;
+        };
+        let digest = Sha256::new().chain_update(modified.as_bytes()).chain_update(len.as_bytes()).finalize();
+        let etag = format!("W/\"{}\"", hex::encode(&digest[..16]));
+        if etag_matches(if_none_match.as_ref(), &etag) {
+            let content_type = headers.get(header::CONTENT_TYPE).cloned();
+            response = StatusCode::NOT_MODIFIED.into_response();
+            if let Some(content_type) = content_type {
+                response.headers_mut().insert(header::CONTENT_TYPE, content_type);
+            }
+        }
+        set_cache_headers(response.headers_mut(), &path, &etag);
+        response
+    }
+}
+
+#[cfg(feature = "embed-web")]
+mod embedded {
+    use axum::{
+        body::Body,
+        http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+        response::{IntoResponse, Response},
+    };
+    use rust_embed::{EmbeddedFile, RustEmbed};
+    use std::borrow::Cow;
+
+    use super::{etag_matches, set_cache_headers, INDEX};
+
+    #[derive(RustEmbed)]
+    #[folder = "../web/dist"]
+    #[allow_missing = true]
+    struct WebDist;
+
+    /// Precompressed variants, preferred in this order.
+    const ENCODINGS: [(&str, &str); 2] = [("br", ".br"), ("gzip", ".gz")];
+
+    /// Fallback handler serving the embedded client.
+    pub async fn serve(uri: Uri, headers: HeaderMap) -> Response {
+        let requested = uri.path().trim_start_matches('/');
+        let path = if requested.is_empty() || WebDist::get(requested).is_none() { INDEX } else { requested };
+        let Some(file) = WebDist::get(path) else {
+            return (StatusCode::NOT_FOUND, "web client not built into this server").into_response();
+        };
+        let etag = format!("\"{}\"", hex::encode(&file.metadata.sha256_hash()[..16]));
+        let mut response = if etag_matches(headers.get(header::IF_NONE_MATCH), &etag) {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            let (body, encoding) = precompressed(path, &headers).unwrap_or((file.data.clone(), None));
+            let mut response = Response::new(Body::from(body));
+            if let Some(encoding) = encoding {
+                response.headers_mut().insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+            }
+            response
+        };
+        let content_type = HeaderValue::from_str(file.metadata.mimetype()).unwrap_or(HeaderValue::from_static("application/octet-stream"));
+        response.headers_mut().insert(header::CONTENT_TYPE, content_type);
+        set_cache_headers(response.headers_mut(), path, &etag);
+        response
+    }
+
+    /// The best precompressed sibling of `path` that the client accepts.
+    fn precompressed(path: &str, headers: &HeaderMap) -> Option<(Cow<'static, [u8]>, Option<&'static str>)> {
+        let accepted = headers.get(header::ACCEPT_ENCODING).and_then(|v| v.to_str().ok()).unwrap_or("");
+        ENCODINGS.iter().filter(|(encoding, _)| accepts(accepted, encoding)).find_map(|&(encoding, suffix)| {
+            let EmbeddedFile { data, .. } = WebDist::get(&format!("{path}{suffix}"))?;
+            Some((data, Some(encoding)))
+        })
+    }
+
+    /// Whether an `Accept-Encoding` value allows `encoding`; `q=0` rules it out.
+    fn accepts(accept_encoding: &str, encoding: &str) -> bool {
+        accept_encoding.split(',').any(|entry| {
+            let mut parts = entry.split(';').map(str::trim);
+            parts.next().is_some_and(|name| name.eq_ignore_ascii_case(encoding)) && !parts.any(|p| p == "q=0" || p == "q=0.0")
+        })
+    }
+}
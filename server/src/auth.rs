@@ -0,0 +1,392 @@
+//! Player identity: password-less sign-in through GitHub or Google OAuth, and signed guest
+//! tokens for everyone else.
+//!
+//! A provider is enabled by setting `GITHUB_CLIENT_ID`/`GITHUB_CLIENT_SECRET` or
+//! `GOOGLE_CLIENT_ID`/`GOOGLE_CLIENT_SECRET`; sign-in as a whole also needs a database and
+//! `SESSION_SECRET`. After the provider redirects back, the account is upserted into
+//! `users` and the browser gets an HttpOnly session cookie holding
+//! `{user_id}.{expires}.{hmac}`, which the WebSocket upgrade reads to link results.
+//!
+//! Guests get a `guest:{id}` token of the same shape on their first connection; the client
+//! keeps it in localStorage and passes it back as `/ws?guest=...`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use shared::api::{ApiErrorCode, ErrorEnvelope, Identity};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::api::{public_base_url, ApiFailure, ApiResult};
+use crate::{db, AppState};
+
+const SESSION_COOKIE: &str = "rracer_session";
+const STATE_COOKIE: &str = "rracer_oauth_state";
+const SESSION_TTL_SECS: u64 = 30 * 24 * 3600;
+const GUEST_TTL_SECS: u64 = 365 * 24 * 3600;
+const GUEST_PREFIX: &str = "guest:";
+const INVITE_PREFIX: &str = "invite:";
+/// How long a friend has to follow an invite link.
+const INVITE_TTL_SECS: u64 = 7 * 24 * 3600;
+/// How long the user has to finish the provider's consent screen.
+const STATE_TTL_SECS: u64 = 600;
+
+struct Provider {
+    name: &'static str,
+    authorize_url: &'static str,
+    token_url: &'static str,
+    userinfo_url: &'static str,
+    scope: &'static str,
+    client_id: String,
+    client_secret: String,
+}
+
+impl Provider {
+    /// `github` or `google`, with the app's credentials there.
+    fn new(name: &'static str, client_id: String, client_secret: String) -> Option<Self> {
+        let (authorize_url, token_url, userinfo_url, scope) = match name {
+            "github" => ("https://github.com/login/oauth/authorize", "https://github.com/login/oauth/access_token", "https://api.github.com/user", "read:user"),
+            "google" => ("https://accounts.google.com/o/oauth2/v2/auth", "https://oauth2.googleapis.com/token", "https://openidconnect.googleapis.com/v1/userinfo", "openid profile"),
+            _ => return None,
+        };
+        Some(Self { name, authorize_url, token_url, userinfo_url, scope, client_id, client_secret })
+    }
+}
+
+/// Issues and checks `{payload}.{expires}.{hmac}` tokens keyed by `SESSION_SECRET`.
+pub struct Signer {
+    secret: String,
+    // False when the secret was generated at startup, so tokens die with the process
+    persistent: bool,
+}
+
+impl Signer {
+    /// Keyed by a configured secret, so tokens outlive the process.
+    pub fn new(secret: String) -> Self {
+        Self { secret, persistent: true }
+    }
+
+    pub fn from_env() -> Self {
+        match std::env::var("SESSION_SECRET").ok().filter(|s| !s.is_empty()) {
+            Some(secret) => Self::new(secret),
+            None => {
+                warn!("session_secret_missing; guest tokens will not survive a restart");
+                Self { secret: uuid::Uuid::new_v4().simple().to_string(), persistent: false }
+            }
+        }
+    }
+
+    fn sign(&self, payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// `{payload}.{expires}.{signature}`, valid until `expires` (unix seconds).
+    fn issue(&self, payload: &str, ttl_secs: u64) -> String {
+        let body = format!("{payload}.{}", crate::current_timestamp() / 1000 + ttl_secs);
+        format!("{body}.{}", self.sign(&body))
+    }
+
+    /// The payload of an unexpired token this server issued.
+    fn verify<'a>(&self, token: &'a str) -> Option<&'a str> {
+        let (body, signature) = token.rsplit_once('.')?;
+        let (payload, expires) = body.rsplit_once('.')?;
+        let expected = hex::decode(signature).ok()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).ok()?;
+        mac.update(body.as_bytes());
+        mac.verify_slice(&expected).ok()?;
+        (expires.parse::<u64>().ok()? > crate::current_timestamp() / 1000).then_some(payload)
+    }
+
+    /// Account id of the session cookie in `headers`, if it is present and valid.
+    pub fn session_user(&self, headers: &HeaderMap) -> Option<i64> {
+        self.verify(cookie(headers, SESSION_COOKIE)?)?.parse().ok()
+    }
+
+    /// Session cookie value for account `user_id`.
+    pub fn session_token(&self, user_id: i64) -> String {
+        self.issue(&user_id.to_string(), SESSION_TTL_SECS)
+    }
+
+    /// A fresh guest id and the token that proves it.
+    pub fn new_guest(&self) -> (String, String) {
+        let guest_id = uuid::Uuid::new_v4().simple().to_string();
+        let token = self.issue(&format!("{GUEST_PREFIX}{guest_id}"), GUEST_TTL_SECS);
+        (guest_id, token)
+    }
+
+    /// Guest id of a token from [`Signer::new_guest`].
+    pub fn guest_id(&self, token: &str) -> Option<String> {
+        self.verify(token)?.strip_prefix(GUEST_PREFIX).map(str::to_string)
+    }
+
+    /// An invite code for `room`, as in `/invite/{code}`.
+    pub fn invite_code(&self, room: &str) -> String {
+        self.issue(&format!("{INVITE_PREFIX}{room}"), INVITE_TTL_SECS)
+    }
+
+    /// Room of an unexpired code from [`Signer::invite_code`].
+    pub fn invite_room(&self, code: &str) -> Option<String> {
+        self.verify(code)?.strip_prefix(INVITE_PREFIX).map(str::to_string)
+    }
+}
+
+/// Whose races a result or stats request is about: an account, else a guest token.
+#[derive(Clone, Debug)]
+pub enum Owner {
+    User(i64),
+    Guest(String),
+}
+
+impl Owner {
+    pub fn from_parts(user_id: Option<i64>, guest_id: Option<String>) -> Option<Self> {
+        match (user_id, guest_id) {
+            (Some(id), _) => Some(Self::User(id)),
+            (None, Some(guest)) => Some(Self::Guest(guest)),
+            (None, None) => None,
+        }
+    }
+
+    /// Single-column form (`user:{id}` / `guest:{id}`) for tables keyed by either.
+    pub fn key(&self) -> String {
+        match self {
+            Self::User(id) => format!("user:{id}"),
+            Self::Guest(guest) => format!("{GUEST_PREFIX}{guest}"),
+        }
+    }
+
+    pub fn user_id(&self) -> Option<i64> {
+        match self {
+            Self::User(id) => Some(*id),
+            Self::Guest(_) => None,
+        }
+    }
+
+    pub fn guest_id(&self) -> Option<&str> {
+        match self {
+            Self::User(_) => None,
+            Self::Guest(guest) => Some(guest),
+        }
+    }
+}
+
+/// The session account behind a request, else the guest whose token it presents.
+pub fn request_owner(state: &AppState, headers: &HeaderMap, guest_token: Option<&str>) -> Option<Owner> {
+    let user_id = state.auth.is_some().then(|| state.signer.session_user(headers)).flatten();
+    Owner::from_parts(user_id, guest_token.and_then(|t| state.signer.guest_id(t)))
+}
+
+pub struct Auth {
+    signer: Arc<Signer>,
+    providers: Vec<Provider>,
+    http: reqwest::Client,
+}
+
+impl Auth {
+    /// Credentials for each provider come from its `{NAME}_CLIENT_ID` and `{NAME}_CLIENT_SECRET`.
+    pub fn from_env(signer: Arc<Signer>) -> Option<Self> {
+        let credentials = ["github", "google"].into_iter().filter_map(|name| {
+            let var = |suffix: &str| std::env::var(format!("{}_{suffix}", name.to_ascii_uppercase())).ok().filter(|v| !v.is_empty());
+            Some((name, var("CLIENT_ID")?, var("CLIENT_SECRET")?))
+        });
+        Self::new(signer, credentials.collect())
+    }
+
+    /// None (sign-in disabled) without a configured `SESSION_SECRET` or credentials, as
+    /// (provider name, client id, client secret), for any provider.
+    pub fn new(signer: Arc<Signer>, credentials: Vec<(&'static str, String, String)>) -> Option<Self> {
+        if !signer.persistent {
+            return None;
+        }
+        let providers: Vec<Provider> = credentials.into_iter().filter_map(|(name, id, secret)| Provider::new(name, id, secret)).collect();
+        if providers.is_empty() {
+            return None;
+        }
+        info!(providers = ?providers.iter().map(|p| p.name).collect::<Vec<_>>(), "oauth_enabled");
+        let http = reqwest::Client::builder().user_agent("rracer").build().ok()?;
+        Some(Self { signer, providers, http })
+    }
+
+    fn provider(&self, name: &str) -> ApiResult<&Provider> {
+        self.providers
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| ApiFailure::new(ApiErrorCode::NotFound, format!("sign-in with '{name}' is not configured")))
+    }
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(name)?.strip_prefix('='))
+}
+
+fn set_cookie(name: &str, value: &str, max_age: u64) -> (header::HeaderName, HeaderValue) {
+    let cookie = format!("{name}={value}; Path=/; HttpOnly; SameSite=Lax; Max-Age={max_age}");
+    (header::SET_COOKIE, HeaderValue::from_str(&cookie).expect("cookie values are ASCII"))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/providers", get(providers))
+        .route("/auth/:provider/login", get(login))
+        .route("/auth/:provider/callback", get(callback))
+        .route("/auth/logout", post(logout))
+        .route("/api/me", get(me))
+}
+
+fn require_auth(state: &AppState) -> ApiResult<&Auth> {
+    state.auth.as_deref().ok_or_else(|| ApiFailure::new(ApiErrorCode::Unavailable, "sign-in is not configured"))
+}
+
+fn callback_url(headers: &HeaderMap, provider: &str) -> String {
+    format!("{}/auth/{provider}/callback", public_base_url(headers))
+}
+
+/// Names of the providers the client may offer sign-in buttons for.
+async fn providers(State(state): State<AppState>) -> Json<Vec<&'static str>> {
+    Json(state.auth.as_deref().map(|a| a.providers.iter().map(|p| p.name).collect()).unwrap_or_default())
+}
+
+async fn login(Path(provider): Path<String>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Response> {
+    let auth = require_auth(&state)?;
+    let provider = auth.provider(&provider)?;
+    // The nonce goes to the provider and into a signed cookie; the callback checks they match
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let url = reqwest::Url::parse_with_params(
+        provider.authorize_url,
+        [
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", &callback_url(&headers, provider.name)),
+            ("response_type", "code"),
+            ("scope", provider.scope),
+            ("state", &nonce),
+        ],
+    )
+    .map_err(|e| ApiFailure::new(ApiErrorCode::Internal, e.to_string()))?;
+    let cookie = set_cookie(STATE_COOKIE, &auth.signer.issue(&nonce, STATE_TTL_SECS), STATE_TTL_SECS);
+    Ok(([cookie], Redirect::to(url.as_str())).into_response())
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+async fn callback(Path(provider): Path<String>, Query(q): Query<CallbackQuery>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Response> {
+    let auth = require_auth(&state)?;
+    let pool = state.db.as_deref().ok_or_else(|| ApiFailure::new(ApiErrorCode::Unavailable, "sign-in needs a database"))?;
+    let provider = auth.provider(&provider)?;
+    if let Some(error) = q.error {
+        return Err(ApiFailure::new(ApiErrorCode::Unauthorized, format!("{} sign-in was cancelled: {error}", provider.name)));
+    }
+    let expected = cookie(&headers, STATE_COOKIE).and_then(|c| auth.signer.verify(c));
+    let (Some(code), Some(nonce)) = (q.code, q.state) else {
+        return Err(ApiFailure::new(ApiErrorCode::BadRequest, "missing code or state"));
+    };
+    if expected != Some(nonce.as_str()) {
+        return Err(ApiFailure::new(ApiErrorCode::Unauthorized, "sign-in state mismatch; please try again"));
+    }
+    let (provider_user_id, display_name) = fetch_profile(auth, provider, &code, &callback_url(&headers, provider.name))
+        .await
+        .map_err(|e| {
+            warn!(provider = provider.name, error = ?e, "oauth_exchange_failed");
+            ApiFailure::new(ApiErrorCode::Unauthorized, format!("could not complete {} sign-in", provider.name))
+        })?;
+    let user_id = db::upsert_user(pool, provider.name, &provider_user_id, &display_name)
+        .await
+        .map_err(|e| ApiFailure::internal("sign-in", e))?;
+    info!(provider = provider.name, user_id, "user_signed_in");
+    let session = set_cookie(SESSION_COOKIE, &auth.signer.session_token(user_id), SESSION_TTL_SECS);
+    Ok(([session, set_cookie(STATE_COOKIE, "", 0)], Redirect::to("/")).into_response())
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct Profile {
+    // GitHub: numeric `id` plus `login`/`name`; Google: `sub` plus `name`
+    id: Option<serde_json::Value>,
+    sub: Option<String>,
+    login: Option<String>,
+    name: Option<String>,
+}
+
+/// Exchange the authorization code and read the provider's (stable id, display name).
+async fn fetch_profile(auth: &Auth, provider: &Provider, code: &str, redirect_uri: &str) -> anyhow::Result<(String, String)> {
+    let token = auth
+        .http
+        .post(provider.token_url)
+        .header(header::ACCEPT, "application/json")
+        .form(&[
+            ("client_id", provider.client_id.as_str()),
+            ("client_secret", provider.client_secret.as_str()),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("grant_type", "authorization_code"),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let token: TokenResponse = serde_json::from_str(&token)?;
+    let profile = auth
+        .http
+        .get(provider.userinfo_url)
+        .bearer_auth(&token.access_token)
+        .header(header::ACCEPT, "application/json")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let profile: Profile = serde_json::from_str(&profile)?;
+    let id = match (profile.sub, profile.id) {
+        (Some(sub), _) => sub,
+        (None, Some(id)) => id.to_string().trim_matches('"').to_string(),
+        (None, None) => anyhow::bail!("profile has no id"),
+    };
+    let name = profile.name.filter(|n| !n.trim().is_empty()).or(profile.login).unwrap_or_else(|| format!("{} user", provider.name));
+    Ok((id, name))
+}
+
+async fn logout() -> impl IntoResponse {
+    ([set_cookie(SESSION_COOKIE, "", 0)], Redirect::to("/"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me",
+    responses(
+        (status = 200, description = "The signed-in account", body = Identity),
+        (status = 401, description = "Not signed in", body = ErrorEnvelope),
+        (status = 503, description = "Sign-in not configured", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn me(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Identity>> {
+    let auth = require_auth(&state)?;
+    let pool = state.db.as_deref().ok_or_else(|| ApiFailure::new(ApiErrorCode::Unavailable, "sign-in needs a database"))?;
+    let user_id = auth.signer.session_user(&headers).ok_or_else(|| ApiFailure::new(ApiErrorCode::Unauthorized, "not signed in"))?;
+    let (provider, display_name) = db::get_user(pool, user_id)
+        .await
+        .map_err(|e| ApiFailure::internal("me", e))?
+        .ok_or_else(|| ApiFailure::new(ApiErrorCode::Unauthorized, "account no longer exists"))?;
+    Ok(Json(Identity { user_id, display_name, provider }))
+}
@@ -0,0 +1,170 @@
+//! Endpoints for chat-bot integrations (e.g. a Discord bot that opens a room for a channel
+//! and posts the standings afterwards).
+//!
+//! Every request must be signed with the shared `BOT_API_SECRET`:
+//! `X-Rracer-Signature` is the hex HMAC-SHA256 of `{timestamp}.{METHOD}.{path?query}.{body}`
+//! and `X-Rracer-Timestamp` the unix time in seconds it was signed at.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Path, Request, State},
+    http::HeaderMap,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use shared::api::{ApiErrorCode, CreateRoomRequest, CreatedRoom, ErrorEnvelope, RaceStanding, RacerStatus, RoomStatus};
+use shared::protocol::MAX_ROOM_CHARS;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::api::{ApiFailure, ApiResult};
+use crate::room_codes::{self, join_url};
+use crate::room::{Room, RoomView};
+use crate::{open_room, release_room, AppState};
+
+const SIGNATURE_HEADER: &str = "x-rracer-signature";
+const TIMESTAMP_HEADER: &str = "x-rracer-timestamp";
+/// Signed requests older (or newer) than this are rejected to limit replays.
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+const MAX_BODY_BYTES: usize = 16 * 1024;
+/// How long a room opened here is kept without anyone in it; players leaving release it as usual.
+const IDLE_ROOM_SECS: u64 = 15 * 60;
+
+pub fn router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/api/bot/rooms", post(create_room))
+        .route("/api/bot/rooms/:room", get(room_status))
+        .route("/api/bot/rooms/:room/results", get(room_results))
+        .route_layer(middleware::from_fn_with_state(state, verify_signature))
+}
+
+async fn verify_signature(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(secret) = state.bot_secret.clone() else {
+        return ApiFailure::new(ApiErrorCode::Unavailable, "bot API disabled (BOT_API_SECRET not set)").into_response();
+    };
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return ApiFailure::new(ApiErrorCode::BadRequest, "request body too large").into_response();
+    };
+    let path = parts.uri.path_and_query().map(|pq| pq.as_str()).unwrap_or_else(|| parts.uri.path());
+    if let Err(reason) = check_signature(&secret, &parts.headers, parts.method.as_str(), path, &body) {
+        return ApiFailure::new(ApiErrorCode::Unauthorized, reason).into_response();
+    }
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+fn check_signature(secret: &str, headers: &HeaderMap, method: &str, path: &str, body: &[u8]) -> Result<(), &'static str> {
+    let header_str = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    let ts = header_str(TIMESTAMP_HEADER).ok_or("missing timestamp")?;
+    let signature = header_str(SIGNATURE_HEADER).and_then(|s| hex::decode(s).ok()).ok_or("missing or malformed signature")?;
+    let signed_at: u64 = ts.parse().map_err(|_| "malformed timestamp")?;
+    if (crate::current_timestamp() / 1000).abs_diff(signed_at) > MAX_CLOCK_SKEW_SECS {
+        return Err("stale timestamp");
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|_| "invalid secret")?;
+    mac.update(format!("{ts}.{method}.{path}.").as_bytes());
+    mac.update(body);
+    mac.verify_slice(&signature).map_err(|_| "bad signature")
+}
+
+fn find_room(state: &AppState, room: &str) -> ApiResult<Arc<Room>> {
+    state
+        .rooms
+        .get(room)
+        .map(|r| r.value().clone())
+        .ok_or_else(|| ApiFailure::new(ApiErrorCode::NotFound, format!("no room '{room}'")))
+}
+
+async fn room_view(room: &Room) -> ApiResult<RoomView> {
+    room.view().await.ok_or_else(|| ApiFailure::new(ApiErrorCode::Unavailable, format!("room '{}' is shutting down", room.id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/bot/rooms",
+    request_body = CreateRoomRequest,
+    responses(
+        (status = 200, description = "Room created (or reused) and its join link; one nobody is in is closed after 15 minutes", body = CreatedRoom),
+        (status = 400, description = "Invalid room name", body = ErrorEnvelope),
+        (status = 401, description = "Missing or invalid signature", body = ErrorEnvelope),
+        (status = 503, description = "Bot API disabled", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn create_room(State(state): State<AppState>, headers: HeaderMap, Json(req): Json<CreateRoomRequest>) -> ApiResult<Json<CreatedRoom>> {
+    let room = match req.room.map(|r| r.trim().to_string()) {
+        Some(r) if r.is_empty() || r.chars().count() > MAX_ROOM_CHARS => {
+            return Err(ApiFailure::new(ApiErrorCode::BadRequest, format!("room name must be 1-{MAX_ROOM_CHARS} characters")));
+        }
+        Some(r) => r,
+        None => room_codes::generate(|code| state.rooms.contains_key(code)),
+    };
+    // A room whose actor panicked is replaced, as when a player joins it
+    let handle = open_room(&state, &room);
+    if let Some(settings) = req.settings {
+        handle.update_settings(None, settings).await;
+    }
+    // Nobody may ever follow the link, and only a player leaving would otherwise let it go
+    let idle = state.clone();
+    tokio::spawn(async move {
+        sleep(Duration::from_secs(IDLE_ROOM_SECS)).await;
+        release_room(&idle, handle);
+    });
+    Ok(Json(CreatedRoom { join_url: join_url(&headers, &room), room }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/bot/rooms/{room}",
+    params(("room" = String, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "Live room state and per-racer progress", body = RoomStatus),
+        (status = 401, description = "Missing or invalid signature", body = ErrorEnvelope),
+        (status = 404, description = "Unknown room", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn room_status(Path(room): Path<String>, State(state): State<AppState>) -> ApiResult<Json<RoomStatus>> {
+    let room = find_room(&state, &room)?;
+    let view = room_view(&room).await?;
+    let racers = view
+        .players
+        .into_iter()
+        .map(|p| RacerStatus {
+            id: p.id,
+            name: p.name,
+            is_bot: p.is_bot,
+            position: p.position,
+            finished: p.finished,
+            wpm: p.result.map(|r| r.wpm),
+            accuracy: p.result.map(|r| r.accuracy),
+        })
+        .collect();
+    Ok(Json(RoomStatus { room: room.id.clone(), state: view.phase.into(), passage_len: view.passage_len, racers }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/bot/rooms/{room}/results",
+    params(("room" = String, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "Finishers of the current race in finishing order", body = Vec<RaceStanding>),
+        (status = 401, description = "Missing or invalid signature", body = ErrorEnvelope),
+        (status = 404, description = "Unknown room", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn room_results(Path(room): Path<String>, State(state): State<AppState>) -> ApiResult<Json<Vec<RaceStanding>>> {
+    let room = find_room(&state, &room)?;
+    let view = room_view(&room).await?;
+    let mut finishers: Vec<_> = view.players.into_iter().filter_map(|p| p.result.map(|r| (r, p))).collect();
+    finishers.sort_by_key(|(r, _)| r.at);
+    let standings = finishers
+        .into_iter()
+        .enumerate()
+        .map(|(i, (r, p))| RaceStanding { rank: i + 1, name: p.name, is_bot: p.is_bot, wpm: r.wpm, accuracy: r.accuracy, normalized_wpm: r.normalized_wpm })
+        .collect();
+    Ok(Json(standings))
+}
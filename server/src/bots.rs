@@ -0,0 +1,173 @@
+//! House bots that fill out races. Each has a name, a speed and a typing style that stay the
+//! same from race to race, so regulars get to know "Turbo Tina (85 WPM)". The built-in roster
+//! can be replaced with a JSON file named by `BOT_ROSTER_FILE`, and whether rooms get bots at
+//! all is set by `BOT_FILL` and `BOT_FILL_TO`.
+
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+/// How a bot's speed varies over a race; every style averages out to the bot's WPM.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Personality {
+    /// The same speed throughout
+    #[default]
+    Steady,
+    /// Fast runs broken up by pauses
+    Bursty,
+    /// Fast, but stalls now and then to fix a typo
+    Sloppy,
+}
+
+impl Personality {
+    /// Speed multiplier for the `step`th progress update of a race.
+    pub fn pace(self, step: u64, rng: &mut impl Rng) -> f64 {
+        match self {
+            Personality::Steady => 1.0,
+            // 1.2s at 140%, then 0.8s at 40%
+            Personality::Bursty => {
+                if step % 20 < 12 {
+                    1.4
+                } else {
+                    0.4
+                }
+            }
+            // A tenth of the updates make no progress at all
+            Personality::Sloppy => {
+                if rng.gen_bool(0.1) {
+                    0.0
+                } else {
+                    1.0 / 0.9
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BotProfile {
+    pub name: String,
+    pub wpm: f64,
+    #[serde(default)]
+    pub personality: Personality,
+}
+
+impl BotProfile {
+    /// Player id for this bot in `room`; the same every race so clients keep its lane.
+    pub fn player_id(&self, room: &str) -> String {
+        let slug: String = self.name.chars().map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' }).collect();
+        format!("bot-{room}-{slug}")
+    }
+}
+
+const BUILT_IN: &[(&str, f64, Personality)] = &[
+    ("Careful Carl", 42.0, Personality::Steady),
+    ("Typo Tom", 50.0, Personality::Sloppy),
+    ("Steady Eddie", 55.0, Personality::Steady),
+    ("Marathon Mary", 60.0, Personality::Steady),
+    ("Sloppy Joe", 65.0, Personality::Sloppy),
+    ("Metronome Max", 70.0, Personality::Steady),
+    ("Quick Quinn", 75.0, Personality::Bursty),
+    ("Nimble Nora", 80.0, Personality::Sloppy),
+    ("Turbo Tina", 85.0, Personality::Bursty),
+    ("Dash Dana", 90.0, Personality::Bursty),
+];
+
+/// The roster, read once from `BOT_ROSTER_FILE` if set and valid, else the built-in one.
+pub fn roster() -> &'static [BotProfile] {
+    static ROSTER: OnceLock<Vec<BotProfile>> = OnceLock::new();
+    ROSTER.get_or_init(|| {
+        let built_in = || BUILT_IN.iter().map(|&(name, wpm, personality)| BotProfile { name: name.to_string(), wpm, personality }).collect();
+        let Some(path) = std::env::var("BOT_ROSTER_FILE").ok().filter(|p| !p.is_empty()) else { return built_in() };
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| Ok(serde_json::from_str::<Vec<BotProfile>>(&text)?));
+        match loaded {
+            Ok(bots) if !bots.is_empty() => {
+                info!(path, count = bots.len(), "bot_roster_loaded");
+                bots
+            }
+            Ok(_) => {
+                warn!(path, "bot_roster_empty; using built-in bots");
+                built_in()
+            }
+            Err(e) => {
+                warn!(path, error = ?e, "bot_roster_load_failed; using built-in bots");
+                built_in()
+            }
+        }
+    })
+}
+
+/// When rooms add bots at the start of a countdown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillPolicy {
+    /// Humans (and their pace cars) only
+    Off,
+    /// Every race, up to this many racers
+    Always(usize),
+    /// Only races with a single human, up to this many racers
+    Solo(usize),
+}
+
+impl FillPolicy {
+    pub const DEFAULT_RACERS: usize = 5;
+    pub const MAX_RACERS: usize = 10;
+
+    /// `BOT_FILL` is `always` (the default), `solo` or `off`; `BOT_FILL_TO` is the racer count
+    /// to fill up to.
+    pub fn from_env() -> Self {
+        let racers = std::env::var("BOT_FILL_TO")
+            .ok()
+            .and_then(|n| n.trim().parse::<usize>().ok())
+            .map_or(Self::DEFAULT_RACERS, |n| n.clamp(1, Self::MAX_RACERS));
+        let policy = match std::env::var("BOT_FILL").map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Ok("off") => FillPolicy::Off,
+            Ok("solo") => FillPolicy::Solo(racers),
+            Ok("always") | Ok("") | Err(_) => FillPolicy::Always(racers),
+            Ok(other) => {
+                warn!(value = other, "unknown BOT_FILL; filling every race");
+                FillPolicy::Always(racers)
+            }
+        };
+        info!(?policy, "bot_fill_policy");
+        policy
+    }
+
+    /// Bots to add to a race that already has `racers` (pace cars included), `humans` of them human.
+    pub fn bots_needed(self, racers: usize, humans: usize) -> usize {
+        match self {
+            FillPolicy::Off => 0,
+            FillPolicy::Always(to) => to.saturating_sub(racers),
+            FillPolicy::Solo(to) if humans == 1 => to.saturating_sub(racers),
+            FillPolicy::Solo(_) => 0,
+        }
+    }
+}
+
+/// Bots race within this fraction of the humans' average WPM when it is known.
+pub const SKILL_BAND: f64 = 0.15;
+
+/// Up to `n` different bots with the speed each should race at, skipping any whose name is
+/// already taken. Given the humans' average `skill`, the bots closest to it are chosen, and
+/// any outside `SKILL_BAND` of it race at a speed drawn from the band instead of their own.
+pub fn pick(n: usize, taken: impl Fn(&str) -> bool, skill: Option<f64>) -> Vec<(&'static BotProfile, f64)> {
+    let mut free: Vec<&BotProfile> = roster().iter().filter(|b| !taken(&b.name)).collect();
+    let mut rng = rand::thread_rng();
+    let Some(skill) = skill.filter(|s| s.is_finite() && *s > 0.0) else {
+        return free.choose_multiple(&mut rng, n).map(|b| (*b, b.wpm)).collect();
+    };
+    let band = skill * (1.0 - SKILL_BAND)..=skill * (1.0 + SKILL_BAND);
+    // Shuffle first so equally close bots take turns
+    free.shuffle(&mut rng);
+    free.sort_by(|a, b| (a.wpm - skill).abs().total_cmp(&(b.wpm - skill).abs()));
+    free.into_iter()
+        .take(n)
+        .map(|b| {
+            let wpm = if band.contains(&b.wpm) { b.wpm } else { rng.gen_range(band.clone()) };
+            (b, wpm)
+        })
+        .collect()
+}
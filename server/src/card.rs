@@ -0,0 +1,109 @@
+//! Shareable result cards: a race result drawn as a 1200×630 image, the size social sites
+//! show as a link preview. The card is composed as SVG and rasterized with resvg for PNG,
+//! using whatever fonts the host has. It draws the result it is given; nothing is looked up.
+
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Context;
+use resvg::{tiny_skia, usvg};
+use svg::node::element::{LinearGradient, Rectangle, Stop, Text};
+use svg::Document;
+
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+/// Longest name and passage source drawn before they are cut short with an ellipsis.
+const MAX_NAME_CHARS: usize = 28;
+const MAX_SOURCE_CHARS: usize = 60;
+
+const FONTS: &str = "Inter, 'DejaVu Sans', Helvetica, Arial, sans-serif";
+
+/// What a card shows.
+pub struct Card<'a> {
+    pub name: &'a str,
+    pub wpm: f64,
+    pub accuracy: f64,
+    /// Finishing place and the number of racers
+    pub rank: Option<(u32, u32)>,
+    /// Who wrote the passage, e.g. "— Jane Austen, Emma"
+    pub source: Option<&'a str>,
+}
+
+impl Card<'_> {
+    pub fn to_svg(&self) -> String {
+        let background = LinearGradient::new()
+            .set("id", "bg")
+            .set("x1", 0)
+            .set("y1", 0)
+            .set("x2", 1)
+            .set("y2", 1)
+            .add(Stop::new().set("offset", 0).set("stop-color", "#54c3ea"))
+            .add(Stop::new().set("offset", 1).set("stop-color", "#2563eb"));
+        let mut document = Document::new()
+            .set("width", WIDTH)
+            .set("height", HEIGHT)
+            .set("viewBox", (0, 0, WIDTH, HEIGHT))
+            .add(background)
+            .add(Rectangle::new().set("width", WIDTH).set("height", HEIGHT).set("fill", "url(#bg)"))
+            .add(Rectangle::new().set("x", 60).set("y", 60).set("width", WIDTH - 120).set("height", HEIGHT - 120).set("rx", 32).set("fill", "#ffffff"))
+            .add(text(110, 150, 36, "#2563eb", "rracer").set("font-weight", "bold"))
+            .add(text(110, 230, 56, "#1f2937", &truncate(self.name, MAX_NAME_CHARS)).set("font-weight", "bold"))
+            .add(text(110, 410, 160, "#2563eb", &format!("{:.0}", self.wpm)).set("font-weight", "bold"))
+            .add(text(110, 460, 36, "#6b7280", "WPM"))
+            .add(text(560, 340, 64, "#16a34a", &format!("{:.1}%", self.accuracy)).set("font-weight", "bold"))
+            .add(text(560, 385, 30, "#6b7280", "accuracy"));
+        if let Some((place, racers)) = self.rank {
+            document = document
+                .add(text(860, 340, 64, "#7c3aed", &ordinal(place)).set("font-weight", "bold"))
+                .add(text(860, 385, 30, "#6b7280", &format!("of {racers}")));
+        }
+        if let Some(source) = self.source.map(str::trim).filter(|s| !s.is_empty()) {
+            document = document.add(text(110, 530, 30, "#4b5563", &truncate(source, MAX_SOURCE_CHARS)).set("font-style", "italic"));
+        }
+        document.to_string()
+    }
+
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let options = usvg::Options { fontdb: fonts(), ..usvg::Options::default() };
+        let tree = usvg::Tree::from_str(&self.to_svg(), &options).context("parsing card SVG")?;
+        let mut pixmap = tiny_skia::Pixmap::new(WIDTH, HEIGHT).context("allocating card image")?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        pixmap.encode_png().context("encoding card PNG")
+    }
+}
+
+/// The host's fonts, loaded on first use.
+fn fonts() -> Arc<usvg::fontdb::Database> {
+    static FONT_DB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONT_DB
+        .get_or_init(|| {
+            let mut db = usvg::fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+fn text(x: u32, y: u32, size: u32, fill: &str, content: &str) -> Text {
+    Text::new(content).set("x", x).set("y", y).set("font-size", size).set("font-family", FONTS).set("fill", fill)
+}
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}…", s[..cut].trim_end()),
+        None => s.to_string(),
+    }
+}
+
+/// "1st", "2nd", "11th", "23rd".
+fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
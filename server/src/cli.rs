@@ -0,0 +1,103 @@
+//! The `server` command line. With no subcommand it serves the game, as `serve` does; the
+//! others manage the database. Everything else is configured through the environment, which
+//! `--config` can load from a file other than `.env`.
+
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::{db, ingest, telemetry};
+
+/// Port served on when neither `--port` nor `PORT` says otherwise.
+pub const DEFAULT_PORT: u16 = 3000;
+
+#[derive(Parser, Debug)]
+#[command(name = "server", version, about = "rracer game server and database tools", args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Env file to read settings from instead of `.env`
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Log filter such as `debug` or `info,server=debug`; overrides `RUST_LOG`
+    #[arg(long, global = true, value_name = "FILTER")]
+    pub log_level: Option<String>,
+    #[command(flatten)]
+    pub serve: ServeArgs,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Serve the game, its API and the web client (the default)
+    Serve(ServeArgs),
+    /// Scrape passages from web pages into the database
+    Ingest {
+        /// Pages to scrape
+        #[arg(required_unless_present = "file")]
+        urls: Vec<String>,
+        /// File of URLs, one per line; `#` starts a comment
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+    },
+    /// Store the bundled passages, with their authors and titles, in the database
+    Seed,
+    /// Apply pending database migrations, then exit
+    Migrate,
+}
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Port to listen on [default: `PORT`, else 3000]
+    #[arg(long)]
+    pub port: Option<u16>,
+}
+
+impl Cli {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match &self.config {
+            Some(path) => {
+                dotenvy::from_path(path).with_context(|| format!("reading config {}", path.display()))?;
+            }
+            // Load .env if present (before logging so RUST_LOG/LOG_FORMAT can come from it)
+            None => {
+                let _ = dotenvy::dotenv();
+            }
+        }
+        let log_filter = telemetry::init(self.log_level.as_deref());
+        match self.command {
+            None => crate::serve(port(&self.serve), log_filter).await,
+            Some(Command::Serve(args)) => crate::serve(port(&args), log_filter).await,
+            Some(Command::Ingest { mut urls, file }) => {
+                if let Some(file) = file {
+                    urls.extend(ingest::read_url_file(&file).with_context(|| format!("reading {}", file.display()))?);
+                }
+                anyhow::ensure!(!urls.is_empty(), "no URLs to ingest");
+                ingest::ingest_urls(&connect().await?, &urls).await?;
+                Ok(())
+            }
+            Some(Command::Seed) => {
+                let seeded = ingest::seed_static_passages(&connect().await?).await?;
+                info!(seeded, bundled = shared::passages::PASSAGES.len(), "static_passages_seeded");
+                Ok(())
+            }
+            Some(Command::Migrate) => {
+                // Connecting applies any pending migrations
+                connect().await?;
+                info!("migrations_applied");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `--port`, else `PORT` (which may come from the config file), else `DEFAULT_PORT`.
+fn port(args: &ServeArgs) -> u16 {
+    args.port.or_else(|| std::env::var("PORT").ok()?.trim().parse().ok()).unwrap_or(DEFAULT_PORT)
+}
+
+/// The database the tools work on, which they can't do without.
+async fn connect() -> anyhow::Result<sqlx::PgPool> {
+    let url = std::env::var("DATABASE_URL").context("DATABASE_URL must be set")?;
+    db::connect(&url).await
+}
@@ -1,4 +1,8 @@
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use shared::api::{Ban, LessonAttempt, LessonProgress, PassageHealth, PassageInfo, PassageRecord, ReplaySample};
+use shared::passages::{Attribution, Passage};
+use shared::protocol::{HallOfFame, RoomRecord};
+use shared::weakness::{Misses, Weaknesses};
+use sqlx::{postgres::PgPoolOptions, types::Json, PgPool};
 
 /// Connect to Postgres using the provided DATABASE_URL.
 pub async fn connect(url: &str) -> anyhow::Result<PgPool> {
@@ -6,43 +10,646 @@ pub async fn connect(url: &str) -> anyhow::Result<PgPool> {
         .max_connections(5)
         .connect(url)
         .await?;
-    // Ensure table exists
+    // Schema lives in server/migrations and is embedded at compile time
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    Ok(pool)
+}
+
+/// Round-trip to the database, for readiness checks.
+pub async fn ping(pool: &PgPool) -> anyhow::Result<()> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
+/// A stored race result row; converted to `shared::api::RaceResult` at the API edge.
+#[derive(sqlx::FromRow, Debug)]
+pub struct RaceRecord {
+    pub id: i64,
+    pub room: String,
+    pub player_name: String,
+    pub wpm: f64,
+    pub accuracy: f64,
+    pub time_secs: Option<f64>,
+    /// Milliseconds since the Unix epoch
+    pub finished_at: i64,
+    pub passage_len: Option<i32>,
+    pub consistency: Option<f64>,
+    pub burst_wpm: Option<f64>,
+    pub normalized_wpm: Option<f64>,
+    pub replay: Option<Json<Vec<ReplaySample>>>,
+    pub verified: bool,
+}
+
+/// Optional history filters; every `None` matches all rows.
+#[derive(Debug, Default)]
+pub struct HistoryFilter<'a> {
+    /// Inclusive lower bound on `finished_at`, in ms since the Unix epoch
+    pub from: Option<i64>,
+    /// Exclusive upper bound on `finished_at`, in ms since the Unix epoch
+    pub to: Option<i64>,
+    pub min_wpm: Option<f64>,
+    pub room: Option<&'a str>,
+    /// Passage length range `[min, max)` in chars
+    pub passage_len: Option<(i64, i64)>,
+}
+
+/// A finished race about to be stored.
+pub struct NewRaceResult<'a> {
+    pub room: &'a str,
+    pub player_name: &'a str,
+    pub wpm: f64,
+    pub accuracy: f64,
+    pub time_secs: Option<f64>,
+    /// In chars
+    pub passage_len: usize,
+    /// 0-100, from the per-second pace
+    pub consistency: f64,
+    pub burst_wpm: f64,
+    /// `wpm` credited for the passage's difficulty
+    pub normalized_wpm: f64,
+    pub replay: &'a [ReplaySample],
+    /// Signed-in account the race belongs to, if any
+    pub user_id: Option<i64>,
+    /// Guest token id for players who are not signed in
+    pub guest_id: Option<&'a str>,
+    /// Passed the checks for the global leaderboard
+    pub verified: bool,
+}
+
+/// Store a finished race result for a human player.
+pub async fn record_result(pool: &PgPool, r: &NewRaceResult<'_>) -> anyhow::Result<()> {
     sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS passages (
-            id SERIAL PRIMARY KEY,
-            text TEXT UNIQUE NOT NULL,
-            source_url TEXT,
-            created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
-        )
-        "#,
+        r#"INSERT INTO race_results (room, player_name, wpm, accuracy, time_secs, passage_len, replay, user_id, guest_id, consistency, burst_wpm, normalized_wpm, verified)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"#,
     )
-    .execute(&pool)
+    .bind(r.room)
+    .bind(r.player_name)
+    .bind(r.wpm)
+    .bind(r.accuracy)
+    .bind(r.time_secs)
+    .bind(i32::try_from(r.passage_len).unwrap_or(i32::MAX))
+    .bind(Json(r.replay))
+    .bind(r.user_id)
+    .bind(r.guest_id)
+    .bind(r.consistency)
+    .bind(r.burst_wpm)
+    .bind(r.normalized_wpm)
+    .bind(r.verified)
+    .execute(pool)
     .await?;
-    Ok(pool)
+    Ok(())
+}
+
+/// Up to `limit` stored replays of races that finished more than `days` days ago, oldest first.
+pub async fn expired_replays(pool: &PgPool, days: u32, limit: i64) -> anyhow::Result<Vec<(i64, Json<Vec<ReplaySample>>)>> {
+    let rows = sqlx::query_as::<_, (i64, Json<Vec<ReplaySample>>)>(
+        r#"SELECT id, replay FROM race_results
+            WHERE replay IS NOT NULL AND finished_at < NOW() - make_interval(days => $1)
+            ORDER BY finished_at, id
+            LIMIT $2"#,
+    )
+    .bind(i32::try_from(days).unwrap_or(i32::MAX))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Aggregates kept for a result once its replay is dropped
+pub struct CompactedReplay {
+    pub id: i64,
+    pub samples: usize,
+    pub consistency: f64,
+    pub burst_wpm: f64,
 }
 
-/// Get a random passage from DB if available; otherwise fall back to static list.
-#[allow(dead_code)]
-pub async fn get_random_passage(db: Option<&PgPool>) -> String {
-    if let Some(pool) = db {
-        match sqlx::query_scalar::<_, String>(
-            "SELECT text FROM passages ORDER BY random() LIMIT 1",
-        )
+/// Drop the replays of `compacted` results in one statement, keeping their sample counts and
+/// filling in pace stats for results stored before those were recorded. Returns the rows changed.
+pub async fn compact_replays(pool: &PgPool, compacted: &[CompactedReplay]) -> anyhow::Result<u64> {
+    let ids: Vec<i64> = compacted.iter().map(|c| c.id).collect();
+    let samples: Vec<i32> = compacted.iter().map(|c| i32::try_from(c.samples).unwrap_or(i32::MAX)).collect();
+    let consistency: Vec<f64> = compacted.iter().map(|c| c.consistency).collect();
+    let burst_wpm: Vec<f64> = compacted.iter().map(|c| c.burst_wpm).collect();
+    let result = sqlx::query(
+        r#"UPDATE race_results r
+            SET replay = NULL, replay_samples = c.samples, replay_compacted_at = NOW(),
+                consistency = COALESCE(r.consistency, c.consistency), burst_wpm = COALESCE(r.burst_wpm, c.burst_wpm)
+            FROM UNNEST($1::BIGINT[], $2::INT[], $3::DOUBLE PRECISION[], $4::DOUBLE PRECISION[]) AS c(id, samples, consistency, burst_wpm)
+            WHERE r.id = c.id AND r.replay IS NOT NULL"#,
+    )
+    .bind(ids)
+    .bind(samples)
+    .bind(consistency)
+    .bind(burst_wpm)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Full race history of an account or guest, oldest first; replays are only loaded on request.
+pub async fn player_results(pool: &PgPool, user_id: Option<i64>, guest_id: Option<&str>, with_replays: bool) -> anyhow::Result<Vec<RaceRecord>> {
+    let rows = sqlx::query_as::<_, RaceRecord>(
+        r#"SELECT id, room, player_name, wpm, accuracy, time_secs,
+                (EXTRACT(EPOCH FROM finished_at) * 1000)::BIGINT AS finished_at,
+                passage_len, consistency, burst_wpm, normalized_wpm, verified,
+                CASE WHEN $3 THEN replay ELSE NULL END AS replay
+            FROM race_results
+            WHERE ($1::BIGINT IS NOT NULL AND user_id = $1) OR ($2::TEXT IS NOT NULL AND guest_id = $2)
+            ORDER BY finished_at, id"#,
+    )
+    .bind(user_id)
+    .bind(guest_id)
+    .bind(with_replays)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// One page of an account's or guest's race history, newest first, keyed by result id.
+pub async fn player_results_page(
+    pool: &PgPool,
+    user_id: Option<i64>,
+    guest_id: Option<&str>,
+    filter: &HistoryFilter<'_>,
+    limit: i64,
+    before_id: Option<i64>,
+) -> anyhow::Result<Vec<RaceRecord>> {
+    let rows = sqlx::query_as::<_, RaceRecord>(
+        r#"SELECT id, room, player_name, wpm, accuracy, time_secs,
+                (EXTRACT(EPOCH FROM finished_at) * 1000)::BIGINT AS finished_at,
+                passage_len, consistency, burst_wpm, normalized_wpm, verified,
+                NULL::JSONB AS replay
+            FROM race_results
+            WHERE (($1::BIGINT IS NOT NULL AND user_id = $1) OR ($10::TEXT IS NOT NULL AND guest_id = $10))
+                AND ($2::BIGINT IS NULL OR id < $2)
+                AND ($4::BIGINT IS NULL OR finished_at >= to_timestamp($4 / 1000.0))
+                AND ($5::BIGINT IS NULL OR finished_at < to_timestamp($5 / 1000.0))
+                AND ($6::DOUBLE PRECISION IS NULL OR wpm >= $6)
+                AND ($7::TEXT IS NULL OR room = $7)
+                AND ($8::BIGINT IS NULL OR (passage_len >= $8 AND passage_len < $9))
+            ORDER BY id DESC
+            LIMIT $3"#,
+    )
+    .bind(user_id)
+    .bind(before_id)
+    .bind(limit)
+    .bind(filter.from)
+    .bind(filter.to)
+    .bind(filter.min_wpm)
+    .bind(filter.room)
+    .bind(filter.passage_len.map(|(min, _)| min))
+    .bind(filter.passage_len.map(|(_, max)| max))
+    .bind(guest_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Raise the stored best for `identity` in `category` if `wpm` beats it. Returns
+/// `Some(previous)` when the record moved (`previous` is None for a first result).
+pub async fn record_personal_best(pool: &PgPool, identity: &str, category: &str, wpm: f64) -> anyhow::Result<Option<Option<f64>>> {
+    let row = sqlx::query_scalar::<_, Option<f64>>(
+        r#"WITH prev AS (SELECT wpm FROM personal_bests WHERE identity = $1 AND category = $2)
+            INSERT INTO personal_bests (identity, category, wpm) VALUES ($1, $2, $3)
+            ON CONFLICT (identity, category) DO UPDATE SET wpm = EXCLUDED.wpm, achieved_at = NOW()
+                WHERE personal_bests.wpm < EXCLUDED.wpm
+            RETURNING (SELECT wpm FROM prev)"#,
+    )
+    .bind(identity)
+    .bind(category)
+    .bind(wpm)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Distinct days (as days since 1970-01-01 in time zone `tz`) on which the owner finished
+/// a race, ascending, plus today's day number. None when `tz` is not a known zone name.
+pub async fn race_days(pool: &PgPool, user_id: Option<i64>, guest_id: Option<&str>, tz: &str) -> anyhow::Result<Option<(Vec<i64>, i64)>> {
+    let known = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1)")
+        .bind(tz)
         .fetch_one(pool)
-        .await {
-            Ok(row) => {
-                tracing::info!("passage_source = db");
-                return row;
-            }
-            Err(e) => {
-                tracing::warn!("db_passage_fetch_failed = {:?}", e);
-            }
-        }
-    } else {
-        tracing::warn!("db_unavailable_for_passage = true");
+        .await?;
+    if !known {
+        return Ok(None);
     }
-    // Fallback to static
-    tracing::error!("passage_source = fallback_static");
-    shared::passages::get_random_passage().to_string()
+    let days = sqlx::query_scalar::<_, i64>(
+        r#"SELECT DISTINCT ((finished_at AT TIME ZONE $3)::DATE - DATE '1970-01-01')::BIGINT AS day
+            FROM race_results
+            WHERE ($1::BIGINT IS NOT NULL AND user_id = $1) OR ($2::TEXT IS NOT NULL AND guest_id = $2)
+            ORDER BY day"#,
+    )
+    .bind(user_id)
+    .bind(guest_id)
+    .bind(tz)
+    .fetch_all(pool)
+    .await?;
+    let today = sqlx::query_scalar::<_, i64>("SELECT ((NOW() AT TIME ZONE $1)::DATE - DATE '1970-01-01')::BIGINT")
+        .bind(tz)
+        .fetch_one(pool)
+        .await?;
+    Ok(Some((days, today)))
+}
+
+/// Mean WPM over the owner's last `recent` races; None before their first.
+pub async fn recent_average_wpm(pool: &PgPool, user_id: Option<i64>, guest_id: Option<&str>, recent: i64) -> anyhow::Result<Option<f64>> {
+    let avg = sqlx::query_scalar::<_, Option<f64>>(
+        r#"SELECT AVG(wpm) FROM (
+                SELECT wpm FROM race_results
+                WHERE ($1::BIGINT IS NOT NULL AND user_id = $1) OR ($2::TEXT IS NOT NULL AND guest_id = $2)
+                ORDER BY id DESC LIMIT $3
+            ) recent"#,
+    )
+    .bind(user_id)
+    .bind(guest_id)
+    .bind(recent)
+    .fetch_one(pool)
+    .await?;
+    Ok(avg)
+}
+
+/// Total XP of an identity (0 if it has never raced).
+pub async fn profile_xp(pool: &PgPool, identity: &str) -> anyhow::Result<u64> {
+    let xp = sqlx::query_scalar::<_, i64>("SELECT xp FROM profiles WHERE identity = $1")
+        .bind(identity)
+        .fetch_optional(pool)
+        .await?;
+    Ok(xp.unwrap_or(0).max(0) as u64)
+}
+
+/// Bests on each lesson `identity` has tried.
+pub async fn lesson_progress(pool: &PgPool, identity: &str) -> anyhow::Result<Vec<LessonProgress>> {
+    let rows = sqlx::query_as::<_, (String, f64, f64, bool, i32)>(
+        "SELECT lesson, best_wpm, best_accuracy, passed, attempts FROM lesson_progress WHERE identity = $1 ORDER BY lesson",
+    )
+    .bind(identity)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(lesson, best_wpm, best_accuracy, passed, attempts)| LessonProgress { lesson, best_wpm, best_accuracy, passed, attempts: attempts.max(0) as u32 })
+        .collect())
+}
+
+/// Fold an attempt at `lesson` into the bests of `identity`.
+pub async fn record_lesson_attempt(pool: &PgPool, identity: &str, lesson: &str, attempt: &LessonAttempt, passed: bool) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO lesson_progress (identity, lesson, best_wpm, best_accuracy, passed, attempts) VALUES ($1, $2, $3, $4, $5, 1)
+            ON CONFLICT (identity, lesson) DO UPDATE SET
+                best_wpm = GREATEST(lesson_progress.best_wpm, EXCLUDED.best_wpm),
+                best_accuracy = GREATEST(lesson_progress.best_accuracy, EXCLUDED.best_accuracy),
+                passed = lesson_progress.passed OR EXCLUDED.passed,
+                attempts = lesson_progress.attempts + 1,
+                updated_at = NOW()"#,
+    )
+    .bind(identity)
+    .bind(lesson)
+    .bind(attempt.wpm)
+    .bind(attempt.accuracy)
+    .bind(passed)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Store a race winner for the room's hall of fame.
+pub async fn record_room_winner(pool: &PgPool, room: &str, player_name: &str, wpm: f64) -> anyhow::Result<()> {
+    sqlx::query("INSERT INTO room_winners (room, player_name, wpm) VALUES ($1, $2, $3)")
+        .bind(room)
+        .bind(player_name)
+        .bind(wpm)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// A room's latest winners and its fastest win.
+pub async fn room_hall_of_fame(pool: &PgPool, room: &str) -> anyhow::Result<HallOfFame> {
+    let record = |(name, wpm, at): (String, f64, i64)| RoomRecord { name, wpm, at };
+    let recent = sqlx::query_as::<_, (String, f64, i64)>(
+        r#"SELECT player_name, wpm, (EXTRACT(EPOCH FROM won_at) * 1000)::BIGINT
+            FROM room_winners WHERE room = $1 ORDER BY id DESC LIMIT $2"#,
+    )
+    .bind(room)
+    .bind(HallOfFame::RECENT_WINNERS as i64)
+    .fetch_all(pool)
+    .await?;
+    let best = sqlx::query_as::<_, (String, f64, i64)>(
+        r#"SELECT player_name, wpm, (EXTRACT(EPOCH FROM won_at) * 1000)::BIGINT
+            FROM room_winners WHERE room = $1 ORDER BY wpm DESC, id LIMIT 1"#,
+    )
+    .bind(room)
+    .fetch_optional(pool)
+    .await?;
+    Ok(HallOfFame { recent_winners: recent.into_iter().map(record).collect(), best: best.map(record) })
+}
+
+/// The stored record on the passage keyed `passage_key`.
+pub async fn passage_record(pool: &PgPool, passage_key: &str) -> anyhow::Result<Option<RoomRecord>> {
+    let row = sqlx::query_as::<_, (String, f64, i64)>(
+        "SELECT player_name, wpm, (EXTRACT(EPOCH FROM set_at) * 1000)::BIGINT FROM passage_records WHERE passage_key = $1",
+    )
+    .bind(passage_key)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(name, wpm, at)| RoomRecord { name, wpm, at }))
+}
+
+/// Store `record` as the passage's record unless the stored one is at least as fast.
+pub async fn record_passage_best(pool: &PgPool, passage_key: &str, passage: &str, record: &RoomRecord) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO passage_records (passage_key, passage, player_name, wpm) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (passage_key) DO UPDATE SET player_name = EXCLUDED.player_name, wpm = EXCLUDED.wpm, set_at = NOW()
+                WHERE passage_records.wpm < EXCLUDED.wpm"#,
+    )
+    .bind(passage_key)
+    .bind(passage)
+    .bind(&record.name)
+    .bind(record.wpm)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Up to `limit` passage records, fastest first.
+pub async fn passage_records(pool: &PgPool, limit: i64) -> anyhow::Result<Vec<PassageRecord>> {
+    let rows = sqlx::query_as::<_, (String, String, f64, i64)>(
+        r#"SELECT passage, player_name, wpm, (EXTRACT(EPOCH FROM set_at) * 1000)::BIGINT
+            FROM passage_records ORDER BY wpm DESC, passage_key LIMIT $1"#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(passage, name, wpm, at)| PassageRecord { passage, record: RoomRecord { name, wpm, at } }).collect())
+}
+
+/// Every pool passage's health, as last stored.
+pub async fn passage_health(pool: &PgPool) -> anyhow::Result<Vec<PassageHealth>> {
+    let rows = sqlx::query_as::<_, (String, String, i32, i32, f64, bool)>(
+        "SELECT passage_key, passage, finishes, abandons, accuracy, retired FROM passage_health",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(key, passage, finishes, abandons, accuracy, retired)| PassageHealth { key, passage, finishes: finishes as u32, abandons: abandons as u32, accuracy, retired })
+        .collect())
+}
+
+/// Store `health` in place of the passage's previous counts.
+pub async fn save_passage_health(pool: &PgPool, health: &PassageHealth) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO passage_health (passage_key, passage, finishes, abandons, accuracy, retired) VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (passage_key) DO UPDATE SET finishes = EXCLUDED.finishes, abandons = EXCLUDED.abandons,
+                accuracy = EXCLUDED.accuracy, retired = EXCLUDED.retired, updated_at = NOW()"#,
+    )
+    .bind(&health.key)
+    .bind(&health.passage)
+    .bind(health.finishes as i32)
+    .bind(health.abandons as i32)
+    .bind(health.accuracy)
+    .bind(health.retired)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Every stored ban.
+pub async fn bans(pool: &PgPool) -> anyhow::Result<Vec<Ban>> {
+    let rows = sqlx::query_as::<_, (String, Option<String>, i64)>("SELECT target, reason, (EXTRACT(EPOCH FROM banned_at) * 1000)::BIGINT FROM bans")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(target, reason, banned_at)| Ban { target, reason, banned_at }).collect())
+}
+
+/// Store `ban`, replacing any earlier ban on the same target.
+pub async fn add_ban(pool: &PgPool, ban: &Ban) -> anyhow::Result<()> {
+    sqlx::query("INSERT INTO bans (target, reason) VALUES ($1, $2) ON CONFLICT (target) DO UPDATE SET reason = EXCLUDED.reason, banned_at = NOW()")
+        .bind(&ban.target)
+        .bind(&ban.reason)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn remove_ban(pool: &PgPool, target: &str) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM bans WHERE target = $1").bind(target).execute(pool).await?;
+    Ok(())
+}
+
+/// Wins of `a` over `b` and of `b` over `a`, from their stored head-to-head record.
+pub async fn duel_wins(pool: &PgPool, a: i64, b: i64) -> anyhow::Result<(u32, u32)> {
+    let (lo, hi) = (a.min(b), a.max(b));
+    let row = sqlx::query_as::<_, (i32, i32)>("SELECT a_wins, b_wins FROM duels WHERE user_a = $1 AND user_b = $2")
+        .bind(lo)
+        .bind(hi)
+        .fetch_optional(pool)
+        .await?;
+    let (lo_wins, hi_wins) = row.map_or((0, 0), |(x, y)| (x as u32, y as u32));
+    Ok(if a == lo { (lo_wins, hi_wins) } else { (hi_wins, lo_wins) })
+}
+
+/// Count a head-to-head win for `winner` over `loser`.
+pub async fn record_duel(pool: &PgPool, winner: i64, loser: i64) -> anyhow::Result<()> {
+    let (lo, hi) = (winner.min(loser), winner.max(loser));
+    let (a_win, b_win) = if winner == lo { (1, 0) } else { (0, 1) };
+    sqlx::query(
+        r#"INSERT INTO duels (user_a, user_b, a_wins, b_wins) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_a, user_b) DO UPDATE
+            SET a_wins = duels.a_wins + EXCLUDED.a_wins, b_wins = duels.b_wins + EXCLUDED.b_wins, updated_at = NOW()"#,
+    )
+    .bind(lo)
+    .bind(hi)
+    .bind(a_win)
+    .bind(b_win)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Add XP to an identity's profile, creating it if needed.
+pub async fn add_xp(pool: &PgPool, identity: &str, xp: u32) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO profiles (identity, xp) VALUES ($1, $2)
+            ON CONFLICT (identity) DO UPDATE SET xp = profiles.xp + EXCLUDED.xp, updated_at = NOW()"#,
+    )
+    .bind(identity)
+    .bind(i64::from(xp))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Add a race's misses to the running counts for `identity`.
+pub async fn record_misses(pool: &PgPool, identity: &str, misses: &Misses) -> anyhow::Result<()> {
+    let rows: Vec<(&str, &str, i32)> = misses.bigrams.iter().map(|(item, n)| ("bigram", item.as_str(), *n as i32))
+        .chain(misses.words.iter().map(|(item, n)| ("word", item.as_str(), *n as i32)))
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let kinds: Vec<&str> = rows.iter().map(|r| r.0).collect();
+    let items: Vec<&str> = rows.iter().map(|r| r.1).collect();
+    let counts: Vec<i32> = rows.iter().map(|r| r.2).collect();
+    sqlx::query(
+        r#"INSERT INTO typing_misses (identity, kind, item, misses)
+            SELECT $1, kind, item, misses FROM UNNEST($2::TEXT[], $3::TEXT[], $4::INTEGER[]) AS m(kind, item, misses)
+            ON CONFLICT (identity, kind, item) DO UPDATE
+            SET misses = typing_misses.misses + EXCLUDED.misses, updated_at = NOW()"#,
+    )
+    .bind(identity)
+    .bind(&kinds)
+    .bind(&items)
+    .bind(&counts)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The `n` most-missed bigrams and words of `identity`.
+pub async fn weaknesses(pool: &PgPool, identity: &str, n: i64) -> anyhow::Result<Weaknesses> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        r#"SELECT kind, item FROM (
+                SELECT kind, item, misses, ROW_NUMBER() OVER (PARTITION BY kind ORDER BY misses DESC, item) AS rank
+                FROM typing_misses WHERE identity = $1
+            ) ranked WHERE rank <= $2 ORDER BY kind, rank"#,
+    )
+    .bind(identity)
+    .bind(n)
+    .fetch_all(pool)
+    .await?;
+    let mut focus = Weaknesses::default();
+    for (kind, item) in rows {
+        if kind == "bigram" { focus.bigrams.push(item) } else { focus.words.push(item) }
+    }
+    Ok(focus)
+}
+
+/// Best raw and normalized WPM per player across their verified results, best first by raw
+/// WPM or, with `by_normalized`, by normalized WPM (players with none last). Only signed-in
+/// races can be verified, so each row is an account, shown under its display name.
+pub async fn leaderboard(pool: &PgPool, limit: i64, by_normalized: bool) -> anyhow::Result<Vec<(String, f64, Option<f64>, i64)>> {
+    let rows = sqlx::query_as::<_, (String, f64, Option<f64>, i64)>(
+        r#"SELECT MAX(COALESCE(u.display_name, r.player_name)) AS player_name, MAX(r.wpm) AS best_wpm,
+                MAX(r.normalized_wpm) AS best_normalized_wpm, COUNT(*) AS races
+            FROM race_results r LEFT JOIN users u ON u.id = r.user_id
+            WHERE r.verified
+            GROUP BY COALESCE('user:' || r.user_id::TEXT, 'guest:' || r.guest_id, 'name:' || r.player_name)
+            ORDER BY CASE WHEN $2 THEN MAX(r.normalized_wpm) END DESC NULLS LAST, best_wpm DESC
+            LIMIT $1"#,
+    )
+    .bind(limit)
+    .bind(by_normalized)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// One page of stored passages in id order.
+pub async fn passages_page(
+    pool: &PgPool,
+    limit: i64,
+    after_id: Option<i64>,
+) -> anyhow::Result<Vec<PassageInfo>> {
+    let rows = sqlx::query_as::<_, (i32, String, Option<String>, Option<String>, Option<String>)>(
+        r#"SELECT id, text, source_url, author, title FROM passages
+            WHERE ($1::INT IS NULL OR id > $1)
+            ORDER BY id
+            LIMIT $2"#,
+    )
+    .bind(after_id.map(|id| id as i32))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, text, source_url, author, title)| PassageInfo { id: i64::from(id), text, source_url, author, title })
+        .collect())
+}
+
+/// Recompute the seasonal WPM percentile distribution.
+pub async fn refresh_percentiles(pool: &PgPool) -> anyhow::Result<()> {
+    sqlx::query("REFRESH MATERIALIZED VIEW wpm_percentiles")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Percentage of racers (0-100) in the given season (default: current) that `wpm` beats.
+pub async fn wpm_percentile(pool: &PgPool, wpm: f64, season: Option<&str>) -> anyhow::Result<i32> {
+    let pct = sqlx::query_scalar::<_, Option<i32>>(
+        r#"SELECT MAX(pct) FROM wpm_percentiles
+            WHERE season = COALESCE($1, to_char(NOW(), 'YYYY-"Q"Q')) AND wpm < $2"#,
+    )
+    .bind(season)
+    .bind(wpm)
+    .fetch_one(pool)
+    .await?;
+    Ok(pct.unwrap_or(0))
+}
+
+/// Find or create the account for a provider identity, refreshing its display name.
+pub async fn upsert_user(pool: &PgPool, provider: &str, provider_user_id: &str, display_name: &str) -> anyhow::Result<i64> {
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"INSERT INTO users (provider, provider_user_id, display_name) VALUES ($1, $2, $3)
+            ON CONFLICT (provider, provider_user_id) DO UPDATE SET display_name = EXCLUDED.display_name
+            RETURNING id"#,
+    )
+    .bind(provider)
+    .bind(provider_user_id)
+    .bind(display_name)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// Provider and display name of an account.
+pub async fn get_user(pool: &PgPool, id: i64) -> anyhow::Result<Option<(String, String)>> {
+    let row = sqlx::query_as::<_, (String, String)>("SELECT provider, display_name FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+/// Ids and display names of `user_id`'s friends, by name.
+pub async fn friends(pool: &PgPool, user_id: i64) -> anyhow::Result<Vec<(i64, String)>> {
+    let rows = sqlx::query_as::<_, (i64, String)>(
+        "SELECT u.id, u.display_name FROM friends f JOIN users u ON u.id = f.friend_id WHERE f.user_id = $1 ORDER BY u.display_name, u.id",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Add `friend_id` to `user_id`'s friends; false if there is no such account.
+pub async fn add_friend(pool: &PgPool, user_id: i64, friend_id: i64) -> anyhow::Result<bool> {
+    let added = sqlx::query("INSERT INTO friends (user_id, friend_id) SELECT $1, id FROM users WHERE id = $2 ON CONFLICT DO NOTHING")
+        .bind(user_id)
+        .bind(friend_id)
+        .execute(pool)
+        .await?;
+    Ok(added.rows_affected() > 0 || get_user(pool, friend_id).await?.is_some())
+}
+
+pub async fn remove_friend(pool: &PgPool, user_id: i64, friend_id: i64) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM friends WHERE user_id = $1 AND friend_id = $2").bind(user_id).bind(friend_id).execute(pool).await?;
+    Ok(())
+}
+
+/// Every stored passage text, for the in-memory passage pool.
+pub async fn all_passages(pool: &PgPool) -> anyhow::Result<Vec<Passage>> {
+    let rows = sqlx::query_as::<_, (String, Option<String>, Option<String>, Option<String>)>(
+        "SELECT text, author, title, source_url FROM passages ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(text, author, title, source)| {
+            let known = author.is_some() || title.is_some() || source.is_some();
+            Passage { text, attribution: known.then_some(Attribution { author, title, source }) }
+        })
+        .collect())
 }
@@ -0,0 +1,183 @@
+//! Friends of signed-in accounts, and invites to race with them. An account keeps its own list
+//! of friends and may invite any of them to a room: an online friend gets `ServerMsg::Invite`
+//! on every connection they have open, and the inviter gets an `/invite/{code}` link either
+//! way, to pass on to a friend who isn't. Invite codes are signed like session cookies, so
+//! nothing is stored for them. With a database friends outlast the process; without one they
+//! last until it exits.
+//!
+//! `GET /api/me/friends`, `PUT`/`DELETE /api/me/friends/:user_id`,
+//! `POST /api/me/friends/:user_id/invite` and `GET /invite/:code`.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Redirect,
+    routing::{get, post, put},
+    Json, Router,
+};
+use dashmap::DashMap;
+use shared::api::{ApiErrorCode, ErrorEnvelope, Friend, Invite, InviteRequest};
+use shared::protocol::{ServerMsg, MAX_ROOM_CHARS};
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::api::{ApiFailure, ApiResult};
+use crate::auth::{request_owner, Owner};
+use crate::{db, room_codes, room_name, AppState};
+
+pub struct FriendStore {
+    db: Option<Arc<PgPool>>,
+    // Friends' account ids by account, only without a database
+    memory: DashMap<i64, Vec<i64>>,
+}
+
+impl FriendStore {
+    pub fn new(db: Option<Arc<PgPool>>) -> Self {
+        Self { db, memory: DashMap::new() }
+    }
+
+    /// `user_id`'s friends as (account id, display name when known).
+    pub async fn list(&self, user_id: i64) -> anyhow::Result<Vec<(i64, Option<String>)>> {
+        match self.db.as_deref() {
+            Some(pool) => Ok(db::friends(pool, user_id).await?.into_iter().map(|(id, name)| (id, Some(name))).collect()),
+            None => Ok(self.memory.get(&user_id).map(|f| f.iter().map(|&id| (id, None)).collect()).unwrap_or_default()),
+        }
+    }
+
+    /// Add `friend_id` to `user_id`'s friends; false if there is no such account.
+    pub async fn add(&self, user_id: i64, friend_id: i64) -> anyhow::Result<bool> {
+        let Some(pool) = self.db.as_deref() else {
+            let mut friends = self.memory.entry(user_id).or_default();
+            if !friends.contains(&friend_id) {
+                friends.push(friend_id);
+            }
+            return Ok(true);
+        };
+        db::add_friend(pool, user_id, friend_id).await
+    }
+
+    pub async fn remove(&self, user_id: i64, friend_id: i64) -> anyhow::Result<()> {
+        match self.db.as_deref() {
+            Some(pool) => db::remove_friend(pool, user_id, friend_id).await,
+            None => {
+                if let Some(mut friends) = self.memory.get_mut(&user_id) {
+                    friends.retain(|&id| id != friend_id);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api/me/friends", get(friends))
+        .route("/api/me/friends/:user_id", put(add_friend).delete(remove_friend))
+        .route("/api/me/friends/:user_id/invite", post(invite))
+        .route("/invite/:code", get(follow_invite))
+}
+
+/// The signed-in account behind a request; friends are for accounts only.
+fn require_account(state: &AppState, headers: &HeaderMap) -> ApiResult<i64> {
+    request_owner(state, headers, None)
+        .and_then(|owner| owner.user_id())
+        .ok_or_else(|| ApiFailure::new(ApiErrorCode::Unauthorized, "sign in to keep friends"))
+}
+
+/// `user_id`'s friends, with who is online now.
+async fn friend_list(state: &AppState, user_id: i64) -> ApiResult<Vec<Friend>> {
+    let friends = state.friends.list(user_id).await.map_err(|e| ApiFailure::internal("friends", e))?;
+    Ok(friends
+        .into_iter()
+        .map(|(id, display_name)| Friend { user_id: id, display_name, online: state.moderation.is_online(&Owner::User(id).key()) })
+        .collect())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me/friends",
+    responses(
+        (status = 200, description = "The signed-in account's friends", body = Vec<Friend>),
+        (status = 401, description = "Not signed in", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn friends(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Vec<Friend>>> {
+    let user_id = require_account(&state, &headers)?;
+    Ok(Json(friend_list(&state, user_id).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/me/friends/{user_id}",
+    params(("user_id" = i64, Path, description = "Account to add")),
+    responses(
+        (status = 200, description = "The account is added; returns every friend", body = Vec<Friend>),
+        (status = 400, description = "The caller's own account", body = ErrorEnvelope),
+        (status = 401, description = "Not signed in", body = ErrorEnvelope),
+        (status = 404, description = "No such account", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn add_friend(Path(friend_id): Path<i64>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Vec<Friend>>> {
+    let user_id = require_account(&state, &headers)?;
+    if friend_id == user_id {
+        return Err(ApiFailure::new(ApiErrorCode::BadRequest, "you can't add yourself"));
+    }
+    if !state.friends.add(user_id, friend_id).await.map_err(|e| ApiFailure::internal("add_friend", e))? {
+        return Err(ApiFailure::new(ApiErrorCode::NotFound, format!("no account {friend_id}")));
+    }
+    Ok(Json(friend_list(&state, user_id).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/me/friends/{user_id}",
+    params(("user_id" = i64, Path, description = "Friend to remove")),
+    responses(
+        (status = 200, description = "The account is no longer a friend; returns the rest", body = Vec<Friend>),
+        (status = 401, description = "Not signed in", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn remove_friend(Path(friend_id): Path<i64>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Vec<Friend>>> {
+    let user_id = require_account(&state, &headers)?;
+    state.friends.remove(user_id, friend_id).await.map_err(|e| ApiFailure::internal("remove_friend", e))?;
+    Ok(Json(friend_list(&state, user_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/friends/{user_id}/invite",
+    params(("user_id" = i64, Path, description = "Friend to invite")),
+    request_body = InviteRequest,
+    responses(
+        (status = 200, description = "The invite, and whether it reached the friend", body = Invite),
+        (status = 400, description = "Not a usable room name", body = ErrorEnvelope),
+        (status = 401, description = "Not signed in", body = ErrorEnvelope),
+        (status = 404, description = "Not one of the caller's friends", body = ErrorEnvelope)
+    )
+)]
+pub(crate) async fn invite(Path(friend_id): Path<i64>, State(state): State<AppState>, headers: HeaderMap, Json(req): Json<InviteRequest>) -> ApiResult<Json<Invite>> {
+    let user_id = require_account(&state, &headers)?;
+    let room = room_name(&req.room).ok_or_else(|| ApiFailure::new(ApiErrorCode::BadRequest, format!("room names must be 1-{MAX_ROOM_CHARS} characters")))?;
+    let friends = state.friends.list(user_id).await.map_err(|e| ApiFailure::internal("invite", e))?;
+    if !friends.iter().any(|&(id, _)| id == friend_id) {
+        return Err(ApiFailure::new(ApiErrorCode::NotFound, format!("account {friend_id} is not one of your friends")));
+    }
+    let from_name = match state.db.as_deref() {
+        Some(pool) => db::get_user(pool, user_id).await.map_err(|e| ApiFailure::internal("invite", e))?.map(|(_, name)| name),
+        None => None,
+    };
+    let delivered = state.moderation.reach(&Owner::User(friend_id).key(), &ServerMsg::Invite { from: user_id, from_name, room: room.clone() });
+    info!(user_id, friend_id, room = %room, delivered, "friend_invited");
+    let url = room_codes::invite_url(&headers, &state.signer.invite_code(&room));
+    Ok(Json(Invite { room, url, delivered }))
+}
+
+/// Into the invite's room, or to the home page once it has expired.
+async fn follow_invite(Path(code): Path<String>, State(state): State<AppState>) -> Redirect {
+    match state.signer.invite_room(&code) {
+        Some(room) => Redirect::to(&room_codes::join_path(&room)),
+        None => Redirect::to("/"),
+    }
+}
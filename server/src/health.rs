@@ -0,0 +1,120 @@
+//! Probes for load balancers and orchestrators: `/healthz` answers while the process is up,
+//! `/readyz` only while it can serve races, and `/api/build-info` says what is running.
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use shared::api::{BuildInfo, CheckStatus, Liveness, Readiness};
+use shared::protocol::PROTOCOL_VERSION;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tokio::time::{interval, timeout, Duration, MissedTickBehavior};
+use tracing::warn;
+
+use crate::{current_timestamp, db, AppState};
+
+/// How often the heartbeat ticks.
+const BEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A heartbeat older than this means the runtime is stalled.
+const STALE_AFTER_MS: u64 = 5_000;
+
+/// Longest a readiness probe waits on the database.
+const DB_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A timer task that records when it last ran, so readiness can tell a runtime that is
+/// blocked or starved from one that is merely idle.
+pub struct Heartbeat {
+    started: u64,
+    last_beat: AtomicU64,
+}
+
+impl Heartbeat {
+    /// Start beating on the current runtime; the task ends once the heartbeat is dropped.
+    pub fn spawn() -> Arc<Self> {
+        let now = current_timestamp();
+        let heartbeat = Arc::new(Self { started: now, last_beat: AtomicU64::new(now) });
+        let weak = Arc::downgrade(&heartbeat);
+        tokio::spawn(async move {
+            let mut ticker = interval(BEAT_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                let Some(heartbeat) = weak.upgrade() else { return };
+                heartbeat.last_beat.store(current_timestamp(), Ordering::Relaxed);
+            }
+        });
+        heartbeat
+    }
+
+    fn status(&self) -> CheckStatus {
+        let age = current_timestamp().saturating_sub(self.last_beat.load(Ordering::Relaxed));
+        if age <= STALE_AFTER_MS {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Down
+        }
+    }
+
+    fn uptime_secs(&self) -> u64 {
+        current_timestamp().saturating_sub(self.started) / 1000
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/api/build-info", get(build_info))
+}
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses((status = 200, description = "The process is up", body = Liveness))
+)]
+pub(crate) async fn healthz(State(state): State<AppState>) -> Json<Liveness> {
+    Json(Liveness { status: "ok".to_string(), uptime_secs: state.heartbeat.uptime_secs() })
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Ready for traffic", body = Readiness),
+        (status = 503, description = "A check is down", body = Readiness)
+    )
+)]
+pub(crate) async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let database = match state.db.as_deref() {
+        None => CheckStatus::Disabled,
+        Some(pool) => match timeout(DB_TIMEOUT, db::ping(pool)).await {
+            Ok(Ok(())) => CheckStatus::Ok,
+            Ok(Err(e)) => {
+                warn!(error = ?e, "readiness_db_check_failed");
+                CheckStatus::Down
+            }
+            Err(_) => {
+                warn!("readiness_db_check_timed_out");
+                CheckStatus::Down
+            }
+        },
+    };
+    let tick_loop = state.heartbeat.status();
+    let ready = database != CheckStatus::Down && tick_loop != CheckStatus::Down;
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(Readiness { ready, database, tick_loop, rooms: state.rooms.len() }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/build-info",
+    responses((status = 200, description = "Version, commit and protocol version", body = BuildInfo))
+)]
+pub(crate) async fn build_info() -> Json<BuildInfo> {
+    Json(BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_sha: option_env!("GIT_SHA").map(str::to_string),
+        protocol_version: PROTOCOL_VERSION,
+    })
+}
@@ -1,48 +1,29 @@
-#[path = "../db.rs"]
-mod db;
+//! Filling the passages table: scraping paragraphs from web pages into typing-length
+//! passages, or storing the bundled ones.
+
+use shared::passages::PASSAGES;
 use sqlx::PgPool;
-use std::{env, fs};
+use std::{fs, path::Path};
 use tracing::{info, warn};
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-
-    let mut args = env::args().skip(1).collect::<Vec<_>>();
-    if args.is_empty() {
-        eprintln!(
-            "Usage: cargo run -p server --bin ingest -- <url1> <url2> ... | --file urls.txt"
-        );
-        std::process::exit(1);
+/// URLs listed in `path`, one per line; blank lines and anything after `#` are skipped.
+pub fn read_url_file(path: &Path) -> anyhow::Result<Vec<String>> {
+    let content = fs::read_to_string(path)?;
+    let mut urls = Vec::new();
+    for line in content.lines() {
+        // Trim whitespace and strip inline comments (anything after '#')
+        let mut line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+        if let Some((head, _)) = line.split_once('#') { line = head.trim(); }
+        if line.is_empty() { continue; }
+        urls.push(line.to_string());
     }
+    Ok(urls)
+}
 
-    // Gather URLs from --file or positional args
-    let mut urls: Vec<String> = Vec::new();
-    if args.len() >= 2 && args[0] == "--file" {
-        let _flag = args.remove(0);
-        let file_path = args.remove(0);
-        let content = fs::read_to_string(&file_path)?;
-        for line in content.lines() {
-            // Trim whitespace and strip inline comments (anything after '#')
-            let mut line = line.trim();
-            if line.is_empty() || line.starts_with('#') { continue; }
-            if let Some((head, _)) = line.split_once('#') { line = head.trim(); }
-            if line.is_empty() { continue; }
-            urls.push(line.to_string());
-        }
-    } else {
-        urls = args;
-    }
-
-    if urls.is_empty() {
-        eprintln!("No URLs provided");
-        std::process::exit(1);
-    }
-
-    let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set for ingestion");
-    let pool = db::connect(&database_url).await?;
-
+/// Scrape each of `urls` and store the passages found. A page that fails to load is logged
+/// and skipped. Returns how many new passages were stored.
+pub async fn ingest_urls(pool: &PgPool, urls: &[String]) -> anyhow::Result<usize> {
     let client = reqwest::Client::builder()
         .user_agent("rracer-ingest/0.1")
         .timeout(std::time::Duration::from_secs(20))
@@ -51,10 +32,10 @@ async fn main() -> anyhow::Result<()> {
     let mut total_inserted = 0usize;
 
     for url in urls {
-        match fetch_and_extract(&client, &url).await {
+        match fetch_and_extract(&client, url).await {
             Ok(passages) => {
                 info!("Fetched {} passages from {}", passages.len(), url);
-                let inserted = insert_passages(&pool, &url, &passages).await?;
+                let inserted = insert_passages(pool, url, &passages).await?;
                 total_inserted += inserted;
                 info!("Inserted {} new passages from {}", inserted, url);
             }
@@ -65,7 +46,7 @@ async fn main() -> anyhow::Result<()> {
     }
 
     info!("Total inserted: {}", total_inserted);
-    Ok(())
+    Ok(total_inserted)
 }
 
 async fn fetch_and_extract(client: &reqwest::Client, url: &str) -> anyhow::Result<Vec<String>> {
@@ -173,6 +154,28 @@ fn normalize_space(s: &str) -> String {
     out.trim().to_string()
 }
 
+/// Insert `shared::passages::PASSAGES`, filling in author and title on rows that already
+/// exist without them. Returns how many rows were added or updated.
+pub async fn seed_static_passages(pool: &PgPool) -> anyhow::Result<usize> {
+    let mut seeded = 0usize;
+    for p in PASSAGES {
+        let res = sqlx::query(
+            r#"INSERT INTO passages (text, author, title) VALUES ($1, $2, $3)
+                ON CONFLICT (text) DO UPDATE
+                SET author = COALESCE(passages.author, EXCLUDED.author), title = COALESCE(passages.title, EXCLUDED.title)
+                WHERE passages.author IS DISTINCT FROM COALESCE(passages.author, EXCLUDED.author)
+                    OR passages.title IS DISTINCT FROM COALESCE(passages.title, EXCLUDED.title)"#,
+        )
+        .bind(p.text)
+        .bind(p.author)
+        .bind(p.title)
+        .execute(pool)
+        .await?;
+        seeded += res.rows_affected() as usize;
+    }
+    Ok(seeded)
+}
+
 async fn insert_passages(pool: &PgPool, source_url: &str, passages: &[String]) -> anyhow::Result<usize> {
     let mut inserted = 0usize;
     for text in passages {
@@ -0,0 +1,73 @@
+//! Timing `Key` messages on the server's clock. Clients stamp each key with their own clock,
+//! which a cheat can forge, so the server times a key by when it arrived, less half the
+//! connection's round trip (measured with WebSocket pings). The client's stamps still give
+//! the finer timing for an honest racer, but only while they agree with the server's: a race
+//! time is never shorter than the server saw, give or take network jitter. Clocks that stop
+//! agreeing are logged.
+
+use tracing::warn;
+
+/// Slack for network jitter when checking a client's race time against the server's.
+const JITTER_MS: u64 = 250;
+
+/// Drift between the two clocks worth a log line, once per racer per race.
+const DRIFT_WARN_MS: u64 = 1_000;
+
+/// Weight of each new round-trip sample in the running estimate.
+const RTT_SMOOTHING: f64 = 0.125;
+
+/// A racer's keys this race: the first one by both clocks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeyClock {
+    // (client stamp, server time) of the first key
+    first: Option<(u64, u64)>,
+    drifted: bool,
+}
+
+impl KeyClock {
+    /// Count a key the client stamped `ts` and the server puts at `at`, logging the first
+    /// time this race that the client's clock has drifted from the server's.
+    pub fn key(&mut self, room_id: &str, player_id: &str, ts: u64, at: u64) {
+        let Some((first_ts, first_at)) = self.first else {
+            self.first = Some((ts, at));
+            return;
+        };
+        let claimed = ts.saturating_sub(first_ts);
+        let observed = at.saturating_sub(first_at);
+        if drifted(claimed, observed) && !self.drifted {
+            self.drifted = true;
+            warn!(room_id, player_id, claimed_ms = claimed, observed_ms = observed, "key_clock_drift");
+        }
+    }
+
+    /// Whether the clocks have drifted apart this race, which costs the race its verification.
+    pub fn drifted(&self) -> bool {
+        self.drifted
+    }
+
+    /// Move the first key `ms` later on both clocks, so a paused race's wait isn't timed.
+    pub fn shift(&mut self, ms: u64) {
+        if let Some((ts, at)) = self.first.as_mut() {
+            *ts += ms;
+            *at += ms;
+        }
+    }
+
+    /// Milliseconds from the first key to this one: the client's own measure, unless the
+    /// server saw it take longer than jitter explains.
+    pub fn elapsed(&self, ts: u64, at: u64) -> u64 {
+        let Some((first_ts, first_at)) = self.first else { return 0 };
+        ts.saturating_sub(first_ts).max(at.saturating_sub(first_at).saturating_sub(JITTER_MS))
+    }
+}
+
+/// Whether a client's `claimed` ms and the server's `observed` ms for the same stretch are
+/// further apart than jitter explains: the client's clock is off, or lag held its messages.
+pub fn drifted(claimed: u64, observed: u64) -> bool {
+    claimed.abs_diff(observed) > DRIFT_WARN_MS
+}
+
+/// Fold a round-trip sample into the running estimate.
+pub fn smooth_rtt(rtt: Option<u64>, sample: u64) -> u64 {
+    rtt.map_or(sample, |rtt| (rtt as f64 + (sample as f64 - rtt as f64) * RTT_SMOOTHING).round() as u64)
+}
@@ -0,0 +1,42 @@
+//! Each identity's progress through the typing course (`shared::lessons`). With a database
+//! progress is stored and outlasts the process; without one it lasts until it exits.
+
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use shared::api::{LessonAttempt, LessonProgress};
+use shared::lessons::{self, Lesson};
+use sqlx::PgPool;
+
+use crate::db;
+
+pub struct LessonProgressStore {
+    db: Option<Arc<PgPool>>,
+    // By identity key, only without a database
+    memory: DashMap<String, Vec<LessonProgress>>,
+}
+
+impl LessonProgressStore {
+    pub fn new(db: Option<Arc<PgPool>>) -> Self {
+        Self { db, memory: DashMap::new() }
+    }
+
+    /// Bests on each lesson `identity` has tried.
+    pub async fn get(&self, identity: &str) -> anyhow::Result<Vec<LessonProgress>> {
+        match self.db.as_deref() {
+            Some(pool) => db::lesson_progress(pool, identity).await,
+            None => Ok(self.memory.get(identity).map(|p| p.clone()).unwrap_or_default()),
+        }
+    }
+
+    /// Fold an attempt at `lesson` into the progress of `identity`, and return it all.
+    pub async fn record(&self, identity: &str, lesson: &Lesson, attempt: &LessonAttempt) -> anyhow::Result<Vec<LessonProgress>> {
+        let Some(pool) = self.db.as_deref() else {
+            let mut progress = self.memory.entry(identity.to_string()).or_default();
+            lessons::record(&mut progress, lesson, attempt);
+            return Ok(progress.clone());
+        };
+        db::record_lesson_attempt(pool, identity, &lesson.id, attempt, lesson.passes(attempt.wpm, attempt.accuracy)).await?;
+        db::lesson_progress(pool, identity).await
+    }
+}
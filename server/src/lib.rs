@@ -0,0 +1,582 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use dashmap::DashMap;
+use serde::Deserialize;
+use futures::{
+    sink::SinkExt,
+    stream::{SplitSink, SplitStream, StreamExt},
+};
+use shared::protocol::{ClientMsg, ErrorCode, ServerMsg, MAX_CLIENT_MSG_BYTES, MAX_ROOM_CHARS};
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{interval, Duration},
+};
+use tower_http::cors::CorsLayer;
+use metrics::METRICS;
+use room::{Player, Room, RoomView};
+use tracing::{debug, field, info, info_span, warn, Instrument, Span};
+use uuid::Uuid;
+
+mod api;
+mod assets;
+mod auth;
+mod bot_api;
+mod bots;
+mod card;
+pub mod cli;
+mod db;
+mod friends;
+mod health;
+mod ingest;
+mod key_clock;
+mod lesson_progress;
+mod metrics;
+mod moderation;
+mod names;
+mod panics;
+mod passage_health;
+mod passage_source;
+mod passages;
+mod records;
+mod retention;
+mod room;
+mod room_codes;
+mod telemetry;
+#[cfg(test)]
+mod tests;
+
+type Rooms = Arc<DashMap<String, Arc<Room>>>;
+
+#[derive(Clone)]
+pub struct AppState {
+    rooms: Rooms,
+    db: Option<Arc<PgPool>>,
+    // Shared secret for signed bot-integration requests; the bot API is disabled without it
+    bot_secret: Option<Arc<String>>,
+    // Bearer token for the admin API, which is disabled without it
+    admin_token: Option<Arc<String>>,
+    // Which bots rooms add at the countdown
+    bot_fill: bots::FillPolicy,
+    log_filter: telemetry::LogHandle,
+    passages: Arc<passages::PassagePool>,
+    // Fastest finish on each pool passage
+    records: Arc<records::PassageRecords>,
+    // How racers get on with each pool passage, and which are retired
+    health: Arc<passage_health::PassageHealthStore>,
+    // Bans, open connections and anti-cheat flags
+    moderation: Arc<moderation::Moderation>,
+    // Where each identity is in the typing course
+    lessons: Arc<lesson_progress::LessonProgressStore>,
+    // Each account's friends
+    friends: Arc<friends::FriendStore>,
+    // OAuth sign-in; None when no provider or SESSION_SECRET is configured
+    auth: Option<Arc<auth::Auth>>,
+    signer: Arc<auth::Signer>,
+    heartbeat: Arc<health::Heartbeat>,
+}
+
+impl AppState {
+    /// No database, the bundled passages, guest sign-in only and a log filter that isn't
+    /// installed: enough to race with, for tests and fuzzing.
+    pub async fn offline() -> Self {
+        let passages = Arc::new(passages::PassagePool::new(Box::new(passage_source::StaticSource)));
+        passages.reload().await;
+        let (_, log_filter) = tracing_subscriber::reload::Layer::new(tracing_subscriber::EnvFilter::new("info"));
+        Self { rooms: Arc::new(DashMap::new()), db: None, bot_secret: None, admin_token: None, bot_fill: bots::FillPolicy::from_env(), log_filter, passages, records: Arc::new(records::PassageRecords::new(None)), health: Arc::new(passage_health::PassageHealthStore::new(None)), moderation: Arc::new(moderation::Moderation::new(None)), lessons: Arc::new(lesson_progress::LessonProgressStore::new(None)), friends: Arc::new(friends::FriendStore::new(None)), auth: None, signer: Arc::new(auth::Signer::from_env()), heartbeat: health::Heartbeat::spawn() }
+    }
+}
+
+// A clock set before 1970 reads as 0 rather than panicking
+fn current_timestamp() -> u64 { SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64) }
+
+/// Configure everything else from the environment and serve on `port`.
+async fn serve(port: u16, log_filter: telemetry::LogHandle) -> anyhow::Result<()> {
+    let db_url = std::env::var("DATABASE_URL").ok();
+    let db_pool: Option<Arc<PgPool>> = if let Some(url) = db_url {
+        match db::connect(&url).await {
+            Ok(pool) => {
+                info!("db_connected");
+                Some(Arc::new(pool))
+            }
+            Err(e) => {
+                warn!(error = ?e, "db_connect_failed");
+                None
+            }
+        }
+    } else {
+        warn!("database_url_missing; using static passages fallback");
+        None
+    };
+    let rooms: Rooms = Arc::new(DashMap::new());
+    let bot_secret = std::env::var("BOT_API_SECRET").ok().filter(|s| !s.is_empty()).map(Arc::new);
+    let admin_token = std::env::var("ADMIN_TOKEN").ok().filter(|s| !s.is_empty()).map(Arc::new);
+    let passages = Arc::new(passages::PassagePool::new(passage_source::from_env(db_pool.clone())));
+    passages.reload().await;
+    passages::reload_on_sighup(passages.clone());
+    passage_source::watch(passages.clone());
+    let health = Arc::new(passage_health::PassageHealthStore::new(db_pool.clone()));
+    health.load().await;
+    let moderation = Arc::new(moderation::Moderation::new(db_pool.clone()));
+    moderation.load().await;
+    let signer = Arc::new(auth::Signer::from_env());
+    let auth = auth::Auth::from_env(signer.clone()).map(Arc::new);
+    let app_state = AppState { rooms: rooms.clone(), db: db_pool.clone(), bot_secret, admin_token, bot_fill: bots::FillPolicy::from_env(), log_filter, passages, records: Arc::new(records::PassageRecords::new(db_pool.clone())), health, moderation, lessons: Arc::new(lesson_progress::LessonProgressStore::new(db_pool.clone())), friends: Arc::new(friends::FriendStore::new(db_pool.clone())), auth, signer, heartbeat: health::Heartbeat::spawn() };
+    if let Some(pool) = db_pool.clone() {
+        retention::spawn(pool.clone(), retention::RetentionPolicy::from_env());
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(300));
+            loop {
+                interval.tick().await;
+                if let Err(e) = db::refresh_percentiles(&pool).await {
+                    warn!(error = ?e, "percentile_refresh_failed");
+                }
+            }
+        });
+    }
+    let addr = format!("0.0.0.0:{port}");
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    info!(addr, "server_listening");
+    // Peer addresses are kept for IP bans
+    axum::serve(listener, app(app_state).into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
+
+/// Every route, sharing `state`, with the web client for every other path.
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .merge(api::router())
+        .merge(auth::router())
+        .merge(bot_api::router(state.clone()))
+        .merge(telemetry::router())
+        .merge(metrics::router())
+        .merge(health::router())
+        .merge(passages::router())
+        .merge(passage_health::router())
+        .merge(moderation::router())
+        .merge(friends::router())
+        .merge(assets::router())
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
+#[derive(Deserialize)]
+struct WsQuery {
+    /// Guest token from an earlier connection
+    guest: Option<String>,
+}
+
+/// Who is on the other end of a socket, resolved from the upgrade request.
+struct Racer {
+    user_id: Option<i64>,
+    guest_id: Option<String>,
+    ip: Option<IpAddr>,
+    // Set when a new guest id was minted and the client still has to be told
+    new_guest_token: Option<String>,
+}
+
+async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>, Query(q): Query<WsQuery>, peer: Option<ConnectInfo<SocketAddr>>, headers: HeaderMap) -> Response {
+    let ip = peer.map(|ConnectInfo(addr)| addr.ip());
+    // Signed-in browsers send their session cookie with the upgrade request; everyone else is a guest
+    let owner = auth::request_owner(&state, &headers, q.guest.as_deref());
+    if state.moderation.is_banned(owner.as_ref().map(auth::Owner::key).as_deref(), ip) {
+        debug!(?ip, "ws_refused_banned");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let racer = match owner {
+        Some(owner) => Racer { user_id: owner.user_id(), guest_id: owner.guest_id().map(str::to_string), ip, new_guest_token: None },
+        None => {
+            let (guest_id, token) = state.signer.new_guest();
+            Racer { user_id: None, guest_id: Some(guest_id), ip, new_guest_token: Some(token) }
+        }
+    };
+    let span = info_span!("conn", player_id = field::Empty, room_id = field::Empty, user_id = racer.user_id, guest_id = racer.guest_id.as_deref());
+    ws.max_message_size(MAX_CLIENT_MSG_BYTES)
+        .max_frame_size(MAX_CLIENT_MSG_BYTES)
+        .on_upgrade(move |socket| handle_socket(socket, state, racer).instrument(span))
+        .into_response()
+}
+
+async fn handle_socket(socket: WebSocket, state: AppState, racer: Racer) {
+    let (mut sender, mut receiver) = socket.split();
+    if let Some(token) = racer.new_guest_token {
+        if let Ok(text) = serde_json::to_string(&ServerMsg::GuestToken { token }) { let _ = sender.send(Message::Text(text)).await; }
+    }
+    let (mut session, mut direct_rx) = Session::start(&state, racer.user_id, racer.guest_id, racer.ip).await;
+    Span::current().record("player_id", session.player_id.as_str());
+    debug!("ws_connected");
+    // A panic ends this connection alone, and it still leaves its rooms
+    panics::guard("connection", pump(&mut sender, &mut receiver, &mut direct_rx, &mut session, &state)).await;
+    session.close(&state).await;
+    debug!("ws_disconnected");
+}
+
+/// Shuttle messages between the socket, the rooms and the session until either side is done.
+async fn pump(sender: &mut SplitSink<WebSocket, Message>, receiver: &mut SplitStream<WebSocket>, direct_rx: &mut mpsc::Receiver<ServerMsg>, session: &mut Session, state: &AppState) {
+    let mut ping = interval(PING_INTERVAL);
+    'socket: loop {
+        tokio::select! {
+            _ = ping.tick() => {
+                // The room's actor panicked; the client reconnects to a fresh one
+                if session.current_room.as_ref().is_some_and(|room| !room.is_running()) { break; }
+                if sender.send(Message::Ping(current_timestamp().to_be_bytes().to_vec())).await.is_err() { break; }
+            }
+            ws_msg = receiver.next() => {
+                match ws_msg {
+                    Some(Ok(Message::Text(text))) => {
+                        let Some(replies) = session.handle_text(state, &text).await else { break };
+                        for msg in replies {
+                            if let Ok(text) = serde_json::to_string(&msg) { if sender.send(Message::Text(text)).await.is_err() { break 'socket; } }
+                        }
+                    }
+                    // Pings are answered for us; anything else but text is refused
+                    Some(Ok(Message::Binary(_))) => {
+                        if let Ok(text) = serde_json::to_string(&invalid_message("Only text frames are accepted")) { if sender.send(Message::Text(text)).await.is_err() { break; } }
+                    }
+                    Some(Ok(Message::Pong(payload))) => {
+                        // Clients can't see pings, so they're told the round trip for their diagnostics
+                        let Some(rtt_ms) = session.pong(&payload) else { continue };
+                        if let Ok(text) = serde_json::to_string(&ServerMsg::Latency { rtt_ms }) { if sender.send(Message::Text(text)).await.is_err() { break; } }
+                    }
+                    Some(Err(e)) => {
+                        // Including messages over MAX_CLIENT_MSG_BYTES
+                        debug!(error = %e, "ws_receive_failed");
+                        break;
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    _ => {}
+                }
+            }
+            Some(msg) = direct_rx.recv() => {
+                if let ServerMsg::XpGained { total_xp, .. } = msg { session.xp = total_xp; }
+                // Out of the room for an admin; a ban closes the connection as well
+                let banned = matches!(msg, ServerMsg::Error { code: ErrorCode::Banned, .. });
+                if banned || matches!(msg, ServerMsg::Error { code: ErrorCode::Removed, .. }) { session.leave(state).await; }
+                if let Ok(text) = serde_json::to_string(&msg) { if sender.send(Message::Text(text)).await.is_err() { break; } }
+                if banned { break; }
+            }
+            (room, watched) = next_watched(&mut session.watching), if !session.watching.is_empty() => {
+                let msg = match watched {
+                    Ok(msg) => Some(msg),
+                    Err(broadcast::error::RecvError::Closed) => {
+                        session.unwatch(state, &room);
+                        None
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        METRICS.broadcast_lagged.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        METRICS.broadcast_lagged_messages.fetch_add(skipped, std::sync::atomic::Ordering::Relaxed);
+                        warn!(skipped, watched_room = %room, "broadcast_lagged");
+                        session.resync_watch(&room).await
+                    }
+                };
+                if let Some(msg) = msg {
+                    if let Ok(text) = serde_json::to_string(&ServerMsg::Watched { room, msg: Box::new(msg) }) { if sender.send(Message::Text(text)).await.is_err() { break; } }
+                }
+            }
+            room_msg = async { if let Some(ref mut rx) = session.room_rx { rx.recv().await } else { std::future::pending().await } } => {
+                match room_msg {
+                    Ok(msg) => { if let Ok(text) = serde_json::to_string(&msg) { if sender.send(Message::Text(text)).await.is_err() { break; } } }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // Drop the stale backlog and resync from a snapshot instead of replaying with gaps
+                        METRICS.broadcast_lagged.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        METRICS.broadcast_lagged_messages.fetch_add(skipped, std::sync::atomic::Ordering::Relaxed);
+                        warn!(skipped, "broadcast_lagged");
+                        if let (Some(room), Some(rx)) = (session.current_room.as_ref(), session.room_rx.as_mut()) {
+                            *rx = rx.resubscribe();
+                            if let Some(snapshot) = room.resync(&session.player_id).await {
+                                if let Ok(text) = serde_json::to_string(&snapshot) { if sender.send(Message::Text(text)).await.is_err() { break; } }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn invalid_message(message: impl Into<String>) -> ServerMsg {
+    ServerMsg::Error { code: ErrorCode::InvalidMessage, message: message.into() }
+}
+
+/// `room` trimmed, if it is a usable room name.
+fn room_name(room: &str) -> Option<String> {
+    let room = room.trim();
+    (!room.is_empty() && room.chars().count() <= MAX_ROOM_CHARS).then(|| room.to_string())
+}
+
+/// The next message from any watched room, with the room's id.
+async fn next_watched(watching: &mut HashMap<String, Watch>) -> (String, Result<ServerMsg, broadcast::error::RecvError>) {
+    let recvs = watching.iter_mut().map(|(room, watch)| Box::pin(async move { (room.clone(), watch.rx.recv().await) }));
+    futures::future::select_all(recvs).await.0
+}
+
+/// How often each connection is pinged to measure its round trip.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Rooms one connection can watch besides the one it is in.
+const MAX_WATCHED_ROOMS: usize = 3;
+
+/// A room followed alongside the current one, as a spectator.
+struct Watch {
+    room: Arc<Room>,
+    rx: broadcast::Receiver<ServerMsg>,
+}
+
+/// One connection's side of the game: which room it is in and what follows it from room to
+/// room. The socket loop hands it each text frame, so the dispatch can be driven without a
+/// socket.
+pub struct Session {
+    player_id: String,
+    user_id: Option<i64>,
+    guest_id: Option<String>,
+    // Peer address, when the server was told it
+    ip: Option<IpAddr>,
+    current_room: Option<Arc<Room>>,
+    room_rx: Option<broadcast::Receiver<ServerMsg>>,
+    // Other rooms followed with ClientMsg::Watch, by room id
+    watching: HashMap<String, Watch>,
+    direct_tx: mpsc::Sender<ServerMsg>,
+    // Pace-car target carried across room switches
+    pace_wpm: Option<f64>,
+    // Lifetime XP, kept current from our own XpGained messages so it follows us between rooms
+    xp: u64,
+    // Typical speed, so rooms can pick bots that give us a race
+    avg_wpm: Option<f64>,
+    // Smoothed round trip to the client from WebSocket pings, once one has come back
+    rtt_ms: Option<u64>,
+}
+
+impl Session {
+    /// A new player's session, plus the queue rooms use to message it alone.
+    pub async fn start(state: &AppState, user_id: Option<i64>, guest_id: Option<String>, ip: Option<IpAddr>) -> (Self, mpsc::Receiver<ServerMsg>) {
+        let (direct_tx, direct_rx) = mpsc::channel::<ServerMsg>(32);
+        let xp = match (state.db.as_deref(), auth::Owner::from_parts(user_id, guest_id.clone())) {
+            (Some(pool), Some(owner)) => db::profile_xp(pool, &owner.key()).await.unwrap_or_else(|e| {
+                warn!(error = ?e, "profile_load_failed");
+                0
+            }),
+            _ => 0,
+        };
+        let avg_wpm = match state.db.as_deref() {
+            Some(pool) if user_id.is_some() || guest_id.is_some() => {
+                db::recent_average_wpm(pool, user_id, guest_id.as_deref(), 20).await.unwrap_or_else(|e| {
+                    warn!(error = ?e, "average_wpm_load_failed");
+                    None
+                })
+            }
+            _ => None,
+        };
+        let session = Self { player_id: Uuid::new_v4().to_string(), user_id, guest_id, ip, current_room: None, room_rx: None, watching: HashMap::new(), direct_tx, pace_wpm: None, xp, avg_wpm, rtt_ms: None };
+        state.moderation.connected(&session.player_id, session.identity(), ip, session.direct_tx.clone());
+        (session, direct_rx)
+    }
+
+    /// `user:{id}` or `guest:{id}`, as bans and stored progress key it.
+    fn identity(&self) -> Option<String> {
+        auth::Owner::from_parts(self.user_id, self.guest_id.clone()).map(|o| o.key())
+    }
+
+    /// Apply one text frame from the client; frames that aren't a `ClientMsg`, or that name a
+    /// room badly, are answered with an `InvalidMessage` error. Returns the replies for this
+    /// socket alone, or None if the room has shut down and the socket should close.
+    pub async fn handle_text(&mut self, state: &AppState, text: &str) -> Option<Vec<ServerMsg>> {
+        let client_msg = match serde_json::from_str::<ClientMsg>(text) {
+            Ok(msg) => msg,
+            Err(e) => {
+                debug!(error = %e, "client_msg_unreadable");
+                return Some(vec![invalid_message(format!("Unreadable message: {e}"))]);
+            }
+        };
+        let room = match &client_msg {
+            ClientMsg::Join { room, .. } | ClientMsg::Spectate { room } | ClientMsg::Watch { room } => match room_name(room) {
+                Some(room) => room,
+                None => return Some(vec![invalid_message(format!("Room names must be 1-{MAX_ROOM_CHARS} characters"))]),
+            },
+            _ => String::new(),
+        };
+        match client_msg {
+            ClientMsg::Join { room: _, name } => {
+                // Refused before leaving the current room, so the player can try another name
+                let name = match names::check(&name) {
+                    Ok(name) => name,
+                    Err(code) => return Some(vec![names::rejection(code)]),
+                };
+                if state.moderation.is_banned(self.identity().as_deref(), self.ip) {
+                    return Some(vec![ServerMsg::Error { code: ErrorCode::Banned, message: "You are banned".to_string() }]);
+                }
+                // Joining the room we're in (again, or to race after watching) keeps us in it
+                let room_arc = match &self.current_room {
+                    Some(current) if current.id == room => current.clone(),
+                    _ => self.switch_room(state, room).await,
+                };
+                let player = Player { pace_wpm: self.pace_wpm, user_id: self.user_id, guest_id: self.guest_id.clone(), xp: self.xp, avg_wpm: self.avg_wpm, ..Player::new(self.player_id.clone(), name) };
+                let (name, snapshot) = room_arc.join(player, self.direct_tx.clone()).await?;
+                Span::current().record("room_id", room_arc.id.as_str());
+                self.current_room = Some(room_arc);
+                // Direct state snapshot so the joiner can render mid-countdown or mid-race
+                return Some(vec![ServerMsg::Joined { id: self.player_id.clone(), name }, snapshot]);
+            }
+            ClientMsg::Spectate { room: _ } => {
+                let room_arc = self.switch_room(state, room).await;
+                let snapshot = room_arc.snapshot().await?;
+                Span::current().record("room_id", room_arc.id.as_str());
+                self.current_room = Some(room_arc);
+                return Some(vec![snapshot]);
+            }
+            ClientMsg::SetPace { wpm } => {
+                self.pace_wpm = wpm.filter(|w| w.is_finite()).map(|w| w.clamp(10.0, 250.0));
+                if let Some(room) = &self.current_room { room.set_pace(&self.player_id, self.pace_wpm).await; }
+            }
+            ClientMsg::Leave => self.leave(state).await,
+            ClientMsg::Watch { room: _ } => return Some(self.watch(state, room).await),
+            ClientMsg::Unwatch { room } => self.unwatch(state, &room),
+            ClientMsg::Resync => {
+                let Some(room) = &self.current_room else { return Some(Vec::new()) };
+                return Some(vec![room.resync(&self.player_id).await?]);
+            }
+            msg => {
+                let (Some(room), player_id) = (&self.current_room, self.player_id.as_str()) else { return Some(Vec::new()) };
+                match msg {
+                    ClientMsg::Key { ch, ts } => {
+                        // When it was typed, by our clock: on arrival, less the trip here
+                        let at = current_timestamp().saturating_sub(self.rtt_ms.unwrap_or(0) / 2);
+                        room.key(player_id, ch, ts, at).await
+                    }
+                    ClientMsg::Progress { pos, ts: _ } => room.progress(player_id, pos).await,
+                    ClientMsg::Finish { wpm, accuracy, time, ts: _, missed, errors } => room.finish(player_id, wpm, accuracy, time, missed, errors).await,
+                    ClientMsg::Reset => room.reset(player_id).await,
+                    ClientMsg::RematchAccept => room.rematch_accept(player_id).await,
+                    ClientMsg::Pause => room.pause(player_id).await,
+                    ClientMsg::Resume => room.resume(player_id).await,
+                    ClientMsg::UpdateSettings { settings } => room.update_settings(Some(player_id), settings).await,
+                    ClientMsg::Join { .. } | ClientMsg::Spectate { .. } | ClientMsg::SetPace { .. } | ClientMsg::Leave | ClientMsg::Watch { .. } | ClientMsg::Unwatch { .. } | ClientMsg::Resync => {}
+                }
+                if !room.is_running() { return None; }
+            }
+        }
+        Some(Vec::new())
+    }
+
+    /// Leave the current room for `room`, subscribing to the new one.
+    async fn switch_room(&mut self, state: &AppState, room: String) -> Arc<Room> {
+        self.leave(state).await;
+        let room_arc = open_room(state, &room);
+        // No need to watch the room we're in
+        self.watching.remove(&room);
+        self.room_rx = Some(room_arc.subscribe());
+        room_arc
+    }
+
+    /// Follow `room` alongside the current one, answering with its snapshot.
+    async fn watch(&mut self, state: &AppState, room: String) -> Vec<ServerMsg> {
+        if self.room_id() == Some(room.as_str()) || self.watching.contains_key(&room) {
+            return Vec::new();
+        }
+        if self.watching.len() >= MAX_WATCHED_ROOMS {
+            return vec![ServerMsg::Error { code: ErrorCode::InvalidState, message: format!("Can't watch more than {MAX_WATCHED_ROOMS} rooms at once") }];
+        }
+        let room_arc = open_room(state, &room);
+        let rx = room_arc.subscribe();
+        let Some(snapshot) = room_arc.snapshot().await else {
+            release_room(state, room_arc);
+            return Vec::new();
+        };
+        self.watching.insert(room.clone(), Watch { room: room_arc, rx });
+        vec![ServerMsg::Watched { room, msg: Box::new(snapshot) }]
+    }
+
+    /// Stop watching `room`, if we were.
+    fn unwatch(&mut self, state: &AppState, room: &str) {
+        if let Some(Watch { room, rx }) = self.watching.remove(room) {
+            drop(rx);
+            release_room(state, room);
+        }
+    }
+
+    /// Skip a watched room's backlog after falling behind, starting over from a snapshot.
+    async fn resync_watch(&mut self, room: &str) -> Option<ServerMsg> {
+        let watch = self.watching.get_mut(room)?;
+        watch.rx = watch.rx.resubscribe();
+        watch.room.snapshot().await
+    }
+
+    pub fn room_id(&self) -> Option<&str> {
+        self.current_room.as_deref().map(|r| r.id.as_str())
+    }
+
+    /// The current room as the server sees it, if in one whose actor is still running.
+    pub async fn room_view(&self) -> Option<RoomView> {
+        self.current_room.as_ref()?.view().await
+    }
+
+    /// Leave the current room, if any.
+    pub async fn leave(&mut self, state: &AppState) {
+        // Unsubscribe first so the room's spectator count no longer includes this socket
+        drop(self.room_rx.take());
+        if let Some(room) = self.current_room.take() { leave_room(state, room, &self.player_id).await; }
+    }
+
+    /// An answer to one of our pings, which carry the time they were sent; returns the new
+    /// round-trip estimate.
+    fn pong(&mut self, payload: &[u8]) -> Option<u64> {
+        let sent = <[u8; 8]>::try_from(payload).map(u64::from_be_bytes).ok()?;
+        let rtt_ms = key_clock::smooth_rtt(self.rtt_ms, current_timestamp().saturating_sub(sent));
+        self.rtt_ms = Some(rtt_ms);
+        Some(rtt_ms)
+    }
+
+    /// Leave the current room and stop watching any others, as the connection closes.
+    pub async fn close(&mut self, state: &AppState) {
+        state.moderation.disconnected(&self.player_id);
+        self.leave(state).await;
+        for (_, Watch { room, rx }) in self.watching.drain() {
+            drop(rx);
+            release_room(state, room);
+        }
+    }
+}
+
+/// The room called `room`, created if it isn't running.
+fn open_room(state: &AppState, room: &str) -> Arc<Room> {
+    let spawn = || Room::spawn(room.to_string(), state.db.clone(), state.passages.clone(), state.records.clone(), state.health.clone(), state.moderation.clone(), state.bot_fill);
+    let mut entry = state.rooms.entry(room.to_string()).or_insert_with(spawn);
+    // One whose actor panicked is replaced; its sockets are closing
+    if !entry.is_running() {
+        warn!(room_id = room, "room_respawned");
+        *entry = spawn();
+    }
+    entry.clone()
+}
+
+/// Leave `room`, releasing it.
+async fn leave_room(state: &AppState, room: Arc<Room>, player_id: &str) {
+    room.leave(player_id).await;
+    release_room(state, room);
+}
+
+/// Drop our hold on `room` and evict it from the registry if no connection holds it any more,
+/// which stops its actor (and timer) and aborts any bot tasks.
+fn release_room(state: &AppState, room: Arc<Room>) {
+    let id = room.id.clone();
+    drop(room);
+    // The predicate runs under the shard lock, so no join can grab the room concurrently
+    if state.rooms.remove_if(&id, |_, r| Arc::strong_count(r) == 1).is_some() {
+        debug!(room_id = %id, "room_evicted");
+    }
+}
@@ -0,0 +1,48 @@
+//! Process-wide counters, exposed in Prometheus text format at `/metrics`.
+
+use axum::{http::header, response::IntoResponse, routing::get, Router};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::AppState;
+
+pub struct Metrics {
+    /// Times a client's broadcast receiver fell behind and was resynchronised.
+    pub broadcast_lagged: AtomicU64,
+    /// Room messages those lagging clients skipped in total.
+    pub broadcast_lagged_messages: AtomicU64,
+    /// Stored keystroke replays dropped by the retention task.
+    pub replays_compacted: AtomicU64,
+    /// Room, bot and connection tasks that panicked; see `panics`.
+    pub panics: AtomicU64,
+}
+
+pub static METRICS: Metrics = Metrics {
+    broadcast_lagged: AtomicU64::new(0),
+    broadcast_lagged_messages: AtomicU64::new(0),
+    replays_compacted: AtomicU64::new(0),
+    panics: AtomicU64::new(0),
+};
+
+impl Metrics {
+    fn render(&self) -> String {
+        let counters = [
+            ("rracer_broadcast_lagged_total", "Client resyncs after falling behind the room broadcast", &self.broadcast_lagged),
+            ("rracer_broadcast_lagged_messages_total", "Room messages skipped by lagging clients", &self.broadcast_lagged_messages),
+            ("rracer_replays_compacted_total", "Keystroke replays dropped after the retention window", &self.replays_compacted),
+            ("rracer_panics_total", "Room, bot and connection tasks stopped by a panic", &self.panics),
+        ];
+        let mut out = String::new();
+        for (name, help, value) in counters {
+            out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} counter\n{name} {}\n", value.load(Ordering::Relaxed)));
+        }
+        out
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+async fn metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], METRICS.render())
+}
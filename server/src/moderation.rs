@@ -0,0 +1,231 @@
+//! Admin moderation: bans, taking a racer out of a room, and reviewing what the rooms'
+//! anti-cheat checks caught. A ban names an identity (`user:{id}` / `guest:{id}`, as
+//! `auth::Owner::key`) or an address (`ip:{addr}`). A guest is banned by address too, since a
+//! fresh guest id is only a reconnect away. Banned sockets are refused at the upgrade and on
+//! Join, and any already open are closed. With a database bans outlast the process;
+//! flags are only kept in memory, the latest `MAX_FLAGS` of them.
+//!
+//! All behind the admin token: `GET`/`POST /api/admin/bans`, `DELETE /api/admin/bans/:target`,
+//! `POST /api/admin/rooms/:room/players/:player/remove` and `GET /api/admin/flags`.
+
+use std::{
+    collections::VecDeque,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use dashmap::DashMap;
+use shared::{
+    api::{ApiErrorCode, Ban, BanRequest, CheatFlag},
+    protocol::{ErrorCode, ServerMsg},
+};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tracing::{info, warn, Instrument, Span};
+
+use crate::api::{ApiFailure, ApiResult};
+use crate::telemetry::require_admin;
+use crate::{current_timestamp, db, AppState};
+
+/// Anti-cheat flags kept for review.
+const MAX_FLAGS: usize = 500;
+
+/// An open connection, so bans (and friends' invites) can reach it.
+struct Live {
+    identity: Option<String>,
+    ip: Option<IpAddr>,
+    direct: mpsc::Sender<ServerMsg>,
+}
+
+pub struct Moderation {
+    db: Option<Arc<PgPool>>,
+    // By target
+    bans: DashMap<String, Ban>,
+    // By player id
+    live: DashMap<String, Live>,
+    // Oldest first
+    flags: Mutex<VecDeque<CheatFlag>>,
+}
+
+impl Moderation {
+    pub fn new(db: Option<Arc<PgPool>>) -> Self {
+        Self { db, bans: DashMap::new(), live: DashMap::new(), flags: Mutex::new(VecDeque::new()) }
+    }
+
+    /// Pick up the stored bans.
+    pub async fn load(&self) {
+        let Some(pool) = self.db.as_deref() else { return };
+        match db::bans(pool).await {
+            Ok(bans) => {
+                for ban in bans {
+                    self.bans.insert(ban.target.clone(), ban);
+                }
+            }
+            Err(e) => warn!(error = ?e, "bans_load_failed"),
+        }
+    }
+
+    /// Whether a socket with `identity` from `ip` is banned.
+    pub fn is_banned(&self, identity: Option<&str>, ip: Option<IpAddr>) -> bool {
+        identity.is_some_and(|id| self.bans.contains_key(id)) || ip.is_some_and(|ip| self.bans.contains_key(&ip_target(ip)))
+    }
+
+    /// Track an open connection until `disconnected`.
+    pub fn connected(&self, player_id: &str, identity: Option<String>, ip: Option<IpAddr>, direct: mpsc::Sender<ServerMsg>) {
+        self.live.insert(player_id.to_string(), Live { identity, ip, direct });
+    }
+
+    pub fn disconnected(&self, player_id: &str) {
+        self.live.remove(player_id);
+    }
+
+    /// Whether `identity` has a connection open.
+    pub fn is_online(&self, identity: &str) -> bool {
+        self.live.iter().any(|live| live.identity.as_deref() == Some(identity))
+    }
+
+    /// Send `msg` to every connection `identity` has open; false if it has none.
+    pub fn reach(&self, identity: &str, msg: &ServerMsg) -> bool {
+        let mut reached = false;
+        for live in self.live.iter().filter(|live| live.identity.as_deref() == Some(identity)) {
+            reached |= live.direct.try_send(msg.clone()).is_ok();
+        }
+        reached
+    }
+
+    /// Ban `target` and close its open connections.
+    pub fn ban(&self, target: String, reason: Option<String>) -> Ban {
+        let ban = Ban { target: target.clone(), reason, banned_at: current_timestamp() as i64 };
+        self.bans.insert(target.clone(), ban.clone());
+        info!(target = %ban.target, reason = ?ban.reason, "player_banned");
+        for live in self.live.iter() {
+            if self.is_banned(live.identity.as_deref(), live.ip) {
+                let _ = live.direct.try_send(ServerMsg::Error { code: ErrorCode::Banned, message: "You are banned".to_string() });
+            }
+        }
+        if let Some(pool) = self.db.clone() {
+            let ban = ban.clone();
+            tokio::spawn(
+                async move {
+                    if let Err(e) = db::add_ban(&pool, &ban).await {
+                        warn!(error = ?e, "ban_save_failed");
+                    }
+                }
+                .instrument(Span::current()),
+            );
+        }
+        ban
+    }
+
+    /// Lift the ban on `target`; false if there was none.
+    pub async fn unban(&self, target: &str) -> anyhow::Result<bool> {
+        if self.bans.remove(target).is_none() {
+            return Ok(false);
+        }
+        info!(target, "player_unbanned");
+        if let Some(pool) = self.db.as_deref() {
+            db::remove_ban(pool, target).await?;
+        }
+        Ok(true)
+    }
+
+    /// Every ban, newest first.
+    pub fn bans(&self) -> Vec<Ban> {
+        let mut bans: Vec<Ban> = self.bans.iter().map(|b| b.value().clone()).collect();
+        bans.sort_by(|a, b| b.banned_at.cmp(&a.banned_at).then_with(|| a.target.cmp(&b.target)));
+        bans
+    }
+
+    /// Keep `flag` for review, dropping the oldest beyond `MAX_FLAGS`.
+    pub fn flag(&self, flag: CheatFlag) {
+        let mut flags = self.flags.lock().unwrap_or_else(|e| e.into_inner());
+        if flags.len() == MAX_FLAGS {
+            flags.pop_front();
+        }
+        flags.push_back(flag);
+    }
+
+    /// Flags kept, newest first.
+    pub fn flags(&self) -> Vec<CheatFlag> {
+        self.flags.lock().unwrap_or_else(|e| e.into_inner()).iter().rev().cloned().collect()
+    }
+
+    /// What to ban for the connected `player_id`: their account, or else their guest id and
+    /// the address they connect from.
+    fn targets_of(&self, player_id: &str) -> Option<Vec<String>> {
+        let live = self.live.get(player_id)?;
+        Some(match live.identity.clone() {
+            Some(account) if account.starts_with("user:") => vec![account],
+            guest => guest.into_iter().chain(live.ip.map(ip_target)).collect(),
+        })
+    }
+}
+
+fn ip_target(ip: IpAddr) -> String {
+    format!("ip:{ip}")
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/bans", get(list_bans).post(ban))
+        .route("/api/admin/bans/:target", delete(unban))
+        .route("/api/admin/rooms/:room/players/:player/remove", post(remove))
+        .route("/api/admin/flags", get(flags))
+}
+
+async fn list_bans(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Vec<Ban>>> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.moderation.bans()))
+}
+
+/// The bans made, one per target.
+async fn ban(State(state): State<AppState>, headers: HeaderMap, Json(req): Json<BanRequest>) -> ApiResult<Json<Vec<Ban>>> {
+    require_admin(&state, &headers)?;
+    if req.player_id.is_none() && req.ip.is_none() {
+        return Err(ApiFailure::new(ApiErrorCode::BadRequest, "give player_id, ip or both"));
+    }
+    let mut targets = match &req.player_id {
+        Some(player_id) => state.moderation.targets_of(player_id).ok_or_else(|| ApiFailure::new(ApiErrorCode::NotFound, format!("no connected player '{player_id}'")))?,
+        None => Vec::new(),
+    };
+    if let Some(ip) = &req.ip {
+        let target = ip_target(ip.parse().map_err(|_| ApiFailure::new(ApiErrorCode::BadRequest, format!("'{ip}' is not an IP address")))?);
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    Ok(Json(targets.into_iter().map(|target| state.moderation.ban(target, req.reason.clone())).collect()))
+}
+
+async fn unban(Path(target): Path<String>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<StatusCode> {
+    require_admin(&state, &headers)?;
+    match state.moderation.unban(&target).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiFailure::new(ApiErrorCode::NotFound, format!("no ban on '{target}'"))),
+        Err(e) => {
+            warn!(error = ?e, "unban_failed");
+            Err(ApiFailure::new(ApiErrorCode::Internal, "failed to lift the ban"))
+        }
+    }
+}
+
+/// Take `player` out of `room`; they may join again unless also banned.
+async fn remove(Path((room, player)): Path<(String, String)>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<StatusCode> {
+    require_admin(&state, &headers)?;
+    let handle = state.rooms.get(&room).map(|r| r.clone()).ok_or_else(|| ApiFailure::new(ApiErrorCode::NotFound, format!("no room '{room}'")))?;
+    match handle.remove(&player).await {
+        Some(true) => Ok(StatusCode::NO_CONTENT),
+        _ => Err(ApiFailure::new(ApiErrorCode::NotFound, format!("no player '{player}' in room '{room}'"))),
+    }
+}
+
+async fn flags(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Vec<CheatFlag>>> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.moderation.flags()))
+}
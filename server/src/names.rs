@@ -0,0 +1,62 @@
+//! Checking the names players join under. On top of the shared rules (`shared::names`), the
+//! house bots keep their own names, and a deployment can refuse names containing any word
+//! listed in the file named by `NAME_BLOCKLIST_FILE`, one per line (`#` starts a comment).
+//! Words match anywhere in the name, ignoring case, spaces and punctuation, so keep the list
+//! to words that don't turn up inside innocent ones.
+
+use std::sync::OnceLock;
+
+use shared::{
+    names,
+    protocol::{ErrorCode, ServerMsg},
+};
+use tracing::{info, warn};
+
+use crate::bots;
+
+/// The name `raw` joins under, or why it can't be used.
+pub fn check(raw: &str) -> Result<String, ErrorCode> {
+    let name = names::clean(raw)?;
+    if bots::roster().iter().any(|bot| bot.name.to_lowercase() == name.to_lowercase()) {
+        return Err(ErrorCode::NameReserved);
+    }
+    let folded = fold(&name);
+    if blocklist().iter().any(|word| folded.contains(word.as_str())) {
+        return Err(ErrorCode::NameNotAllowed);
+    }
+    Ok(name)
+}
+
+/// The error sent back for a name `check` refused.
+pub fn rejection(code: ErrorCode) -> ServerMsg {
+    let message = match code {
+        ErrorCode::NameTooLong => format!("Names can be at most {} characters", names::MAX_NAME_CHARS),
+        ErrorCode::NameReserved => "That name belongs to a bot".to_string(),
+        _ => "That name isn't allowed".to_string(),
+    };
+    ServerMsg::Error { code, message }
+}
+
+/// Lowercase letters and digits only, so "B a.d" is checked as "bad".
+fn fold(s: &str) -> String {
+    s.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Folded words from `NAME_BLOCKLIST_FILE`, read once; empty if unset or unreadable.
+fn blocklist() -> &'static [String] {
+    static BLOCKLIST: OnceLock<Vec<String>> = OnceLock::new();
+    BLOCKLIST.get_or_init(|| {
+        let Some(path) = std::env::var("NAME_BLOCKLIST_FILE").ok().filter(|p| !p.is_empty()) else { return Vec::new() };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let words: Vec<String> = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).map(fold).filter(|w| !w.is_empty()).collect();
+                info!(path, count = words.len(), "name_blocklist_loaded");
+                words
+            }
+            Err(e) => {
+                warn!(path, error = ?e, "name_blocklist_load_failed; names are not filtered");
+                Vec::new()
+            }
+        }
+    })
+}
@@ -0,0 +1,27 @@
+//! Keeping a panic to the task it happened in. Room actors, bot tasks and connections run
+//! under [`guard`], which logs the panic in the task's span (so with its room and player ids)
+//! and counts it in `rracer_panics_total`. A connection that panics is still cleaned up; a
+//! room that panics stops, its sockets close, and the next join starts the room afresh.
+
+use std::{any::Any, future::Future, panic::AssertUnwindSafe, sync::atomic::Ordering};
+
+use futures::FutureExt;
+use tracing::error;
+
+use crate::metrics::METRICS;
+
+/// Run `task`, catching a panic; None if it panicked. `what` names the task in the log.
+pub async fn guard<F: Future>(what: &'static str, task: F) -> Option<F::Output> {
+    match AssertUnwindSafe(task).catch_unwind().await {
+        Ok(output) => Some(output),
+        Err(panic) => {
+            METRICS.panics.fetch_add(1, Ordering::Relaxed);
+            error!(task = what, panic = message(panic.as_ref()), "task_panicked");
+            None
+        }
+    }
+}
+
+fn message(panic: &(dyn Any + Send)) -> &str {
+    panic.downcast_ref::<&str>().copied().or_else(|| panic.downcast_ref::<String>().map(String::as_str)).unwrap_or("(not a string)")
+}
@@ -0,0 +1,123 @@
+//! How racers get on with each pool passage: how many finish it, how accurately, and how many
+//! walk out of the room partway through. A passage that goes badly often enough (see
+//! `shared::api::PassageHealth::should_retire`) is retired from rotation; admins can list
+//! the counts at `GET /api/admin/passages/health` and put a retired passage back with
+//! `POST /api/admin/passages/:key/restore`. With a database the counts outlast the process.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    routing::{get, post},
+    Json, Router,
+};
+use dashmap::DashMap;
+use shared::api::{ApiErrorCode, PassageHealth};
+use sqlx::PgPool;
+use tracing::{info, warn, Instrument, Span};
+
+use crate::api::{ApiFailure, ApiResult};
+use crate::records::key;
+use crate::telemetry::{require_admin, RACE_EVENTS};
+use crate::{db, AppState};
+
+pub struct PassageHealthStore {
+    db: Option<Arc<PgPool>>,
+    // By passage key
+    passages: DashMap<String, PassageHealth>,
+}
+
+impl PassageHealthStore {
+    pub fn new(db: Option<Arc<PgPool>>) -> Self {
+        Self { db, passages: DashMap::new() }
+    }
+
+    /// Pick up the stored counts, so passages retired before a restart stay retired.
+    pub async fn load(&self) {
+        let Some(pool) = self.db.as_deref() else { return };
+        match db::passage_health(pool).await {
+            Ok(rows) => {
+                for health in rows {
+                    self.passages.insert(health.key.clone(), health);
+                }
+            }
+            Err(e) => warn!(error = ?e, "passage_health_load_failed"),
+        }
+    }
+
+    pub fn is_retired(&self, passage: &str) -> bool {
+        self.passages.get(&key(passage)).is_some_and(|h| h.retired)
+    }
+
+    /// A human finished `passage` with `accuracy`.
+    pub fn finished(&self, passage: &str, accuracy: f64) {
+        self.update(passage, |h| h.finish(accuracy));
+    }
+
+    /// A human left the room partway through `passage`.
+    pub fn abandoned(&self, passage: &str) {
+        self.update(passage, PassageHealth::abandon);
+    }
+
+    fn update(&self, passage: &str, change: impl FnOnce(&mut PassageHealth)) {
+        let key = key(passage);
+        let health = {
+            let mut health = self.passages.entry(key.clone()).or_insert_with(|| PassageHealth::new(key, passage.to_string()));
+            change(&mut health);
+            if !health.retired && health.should_retire() {
+                health.retired = true;
+                info!(target: RACE_EVENTS, event = "passage_retired", passage_key = %health.key, races = health.races(), abandon_rate = health.abandon_rate(), accuracy = health.accuracy);
+            }
+            health.clone()
+        };
+        self.save(health);
+    }
+
+    /// Every passage raced so far, retired ones first, then the most walked out of.
+    pub fn list(&self) -> Vec<PassageHealth> {
+        let mut passages: Vec<PassageHealth> = self.passages.iter().map(|h| h.value().clone()).collect();
+        passages.sort_by(|a, b| b.retired.cmp(&a.retired).then_with(|| b.abandon_rate().total_cmp(&a.abandon_rate())).then_with(|| a.key.cmp(&b.key)));
+        passages
+    }
+
+    /// Put the passage keyed `key` back in rotation with fresh counts; None if it's unknown.
+    pub fn restore(&self, key: &str) -> Option<PassageHealth> {
+        let health = {
+            let mut health = self.passages.get_mut(key)?;
+            health.restore();
+            health.clone()
+        };
+        info!(target: RACE_EVENTS, event = "passage_restored", passage_key = %key);
+        self.save(health.clone());
+        Some(health)
+    }
+
+    fn save(&self, health: PassageHealth) {
+        let Some(pool) = self.db.clone() else { return };
+        tokio::spawn(
+            async move {
+                if let Err(e) = db::save_passage_health(&pool, &health).await {
+                    warn!(error = ?e, "save_passage_health_failed");
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/passages/health", get(list))
+        .route("/api/admin/passages/:key/restore", post(restore))
+}
+
+async fn list(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Vec<PassageHealth>>> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.health.list()))
+}
+
+async fn restore(Path(key): Path<String>, State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<PassageHealth>> {
+    require_admin(&state, &headers)?;
+    state.health.restore(&key).map(Json).ok_or_else(|| ApiFailure::new(ApiErrorCode::NotFound, format!("no passage '{key}'")))
+}
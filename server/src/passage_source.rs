@@ -0,0 +1,160 @@
+//! Where the passage pool gets its texts from, chosen with `PASSAGE_SOURCE`:
+//!
+//! - `db`: the Postgres `passages` table (default when a database is connected)
+//! - `file`: a JSON or TOML file at `PASSAGE_FILE` (default `passages.json`), reloaded
+//!   whenever it changes on disk
+//! - `static`: the built-in list (default without a database)
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use shared::passages::{Attribution, Passage, StaticPassage, PASSAGES};
+use sqlx::PgPool;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+
+use crate::db;
+use crate::passages::PassagePool;
+
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub trait PassageSource: Send + Sync {
+    /// Short name for logs.
+    fn name(&self) -> &'static str;
+
+    /// Every passage the source currently offers.
+    fn load(&self) -> BoxFuture<'_, anyhow::Result<Vec<Passage>>>;
+
+    /// File to watch for changes, if the source is backed by one.
+    fn watch_path(&self) -> Option<&Path> {
+        None
+    }
+}
+
+pub struct StaticSource;
+
+impl PassageSource for StaticSource {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+
+    fn load(&self) -> BoxFuture<'_, anyhow::Result<Vec<Passage>>> {
+        Box::pin(async { Ok(PASSAGES.iter().map(StaticPassage::to_passage).collect()) })
+    }
+}
+
+pub struct DbSource(pub Arc<PgPool>);
+
+impl PassageSource for DbSource {
+    fn name(&self) -> &'static str {
+        "db"
+    }
+
+    fn load(&self) -> BoxFuture<'_, anyhow::Result<Vec<Passage>>> {
+        Box::pin(db::all_passages(&self.0))
+    }
+}
+
+/// `.toml` files hold `passages = [...]`; anything else is read as JSON, either a bare
+/// array or `{"passages": [...]}`. Entries are plain strings or
+/// `{ text, author, title, source }` tables.
+pub struct FileSource(pub PathBuf);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PassageFile {
+    List(Vec<FileEntry>),
+    Table { passages: Vec<FileEntry> },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileEntry {
+    Text(String),
+    Attributed {
+        text: String,
+        #[serde(flatten)]
+        attribution: Attribution,
+    },
+}
+
+impl PassageFile {
+    fn into_passages(self) -> Vec<Passage> {
+        let (PassageFile::List(p) | PassageFile::Table { passages: p }) = self;
+        p.into_iter()
+            .map(|entry| match entry {
+                FileEntry::Text(text) => Passage { text: text.trim().to_string(), attribution: None },
+                FileEntry::Attributed { text, attribution } => {
+                    let known = attribution != Attribution::default();
+                    Passage { text: text.trim().to_string(), attribution: known.then_some(attribution) }
+                }
+            })
+            .filter(|p| !p.text.is_empty())
+            .collect()
+    }
+}
+
+impl PassageSource for FileSource {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn load(&self) -> BoxFuture<'_, anyhow::Result<Vec<Passage>>> {
+        Box::pin(async {
+            let text = tokio::fs::read_to_string(&self.0).await?;
+            let is_toml = self.0.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml"));
+            let file: PassageFile = if is_toml { toml::from_str(&text)? } else { serde_json::from_str(&text)? };
+            Ok(file.into_passages())
+        })
+    }
+
+    fn watch_path(&self) -> Option<&Path> {
+        Some(&self.0)
+    }
+}
+
+/// Build the source named by `PASSAGE_SOURCE`, falling back to the database or the static
+/// list when it is unset or unusable.
+pub fn from_env(db: Option<Arc<PgPool>>) -> Box<dyn PassageSource> {
+    let requested = std::env::var("PASSAGE_SOURCE").ok().map(|s| s.to_ascii_lowercase());
+    match (requested.as_deref(), db) {
+        (Some("file"), _) => {
+            let path = std::env::var("PASSAGE_FILE").unwrap_or_else(|_| "passages.json".to_string());
+            Box::new(FileSource(PathBuf::from(path)))
+        }
+        (Some("static"), _) => Box::new(StaticSource),
+        (Some("db") | None, Some(pool)) => Box::new(DbSource(pool)),
+        (Some("db"), None) => {
+            warn!("passage_source_db_unavailable; using static passages");
+            Box::new(StaticSource)
+        }
+        (None, None) => Box::new(StaticSource),
+        (Some(other), _) => {
+            warn!(passage_source = other, "unknown_passage_source; using static passages");
+            Box::new(StaticSource)
+        }
+    }
+}
+
+/// Reload `pool` whenever the watched file's modification time changes.
+pub fn watch(pool: Arc<PassagePool>) {
+    let Some(path) = pool.source().watch_path().map(Path::to_path_buf) else { return };
+    info!(path = %path.display(), "watching_passage_file");
+    tokio::spawn(async move {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last: Option<SystemTime> = modified(&path);
+        let mut ticker = interval(FILE_POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let current = modified(&path);
+            if current != last {
+                last = current;
+                pool.reload().await;
+            }
+        }
+    });
+}
@@ -0,0 +1,134 @@
+//! In-memory passage pool. Races draw from the cached list instead of asking the
+//! [`PassageSource`] each time; the cache is refilled once it is older than
+//! `PASSAGE_CACHE_TTL_SECS` (default 600), or on demand via SIGHUP or
+//! `POST /api/admin/passages/reload`.
+
+use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
+use shared::passages::{Passage, StaticPassage, PASSAGES};
+use shared::text::char_len;
+use std::sync::Arc;
+use tokio::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::api::ApiResult;
+use crate::telemetry::require_admin;
+use crate::passage_source::PassageSource;
+use crate::AppState;
+
+const DEFAULT_TTL_SECS: u64 = 600;
+
+struct Cached {
+    passages: Vec<Passage>,
+    loaded_at: Instant,
+}
+
+pub struct PassagePool {
+    source: Box<dyn PassageSource>,
+    ttl: Duration,
+    cache: RwLock<Option<Cached>>,
+}
+
+impl PassagePool {
+    pub fn new(source: Box<dyn PassageSource>) -> Self {
+        let ttl = std::env::var("PASSAGE_CACHE_TTL_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_TTL_SECS);
+        Self { source, ttl: Duration::from_secs(ttl), cache: RwLock::new(None) }
+    }
+
+    pub fn source(&self) -> &dyn PassageSource {
+        self.source.as_ref()
+    }
+
+    /// A random passage at least `min_chars` long other than those `retired` rules out,
+    /// refilling the cache first if it is missing or stale.
+    pub async fn random(&self, min_chars: usize, retired: impl Fn(&str) -> bool) -> Passage {
+        {
+            let cache = self.cache.read().await;
+            if let Some(c) = cache.as_ref().filter(|c| c.loaded_at.elapsed() < self.ttl) {
+                return pick(&c.passages, min_chars, retired);
+            }
+        }
+        self.reload().await;
+        let cache = self.cache.read().await;
+        pick(cache.as_ref().map(|c| c.passages.as_slice()).unwrap_or_default(), min_chars, retired)
+    }
+
+    /// Re-read the pool from its source; returns how many passages are now cached.
+    /// A failed or empty load keeps the previous list, or the static one on first load.
+    pub async fn reload(&self) -> usize {
+        let source = self.source.name();
+        let loaded = match self.source.load().await {
+            Ok(rows) if !rows.is_empty() => Some(rows),
+            Ok(_) => {
+                warn!(passage_source = source, "passage_source_empty");
+                None
+            }
+            Err(e) => {
+                warn!(passage_source = source, error = ?e, "passage_load_failed");
+                None
+            }
+        };
+        let mut cache = self.cache.write().await;
+        let passages = match (loaded, cache.take()) {
+            (Some(rows), _) => rows,
+            (None, Some(previous)) => previous.passages,
+            (None, None) => {
+                info!(passage_source = "static", "passages_fallback");
+                PASSAGES.iter().map(StaticPassage::to_passage).collect()
+            }
+        };
+        let count = passages.len();
+        *cache = Some(Cached { passages, loaded_at: Instant::now() });
+        info!(passage_source = source, count, "passages_reloaded");
+        count
+    }
+}
+
+/// A pool with every passage retired is raced in full rather than not at all, and one with
+/// none `min_chars` long gives its longest.
+fn pick(passages: &[Passage], min_chars: usize, retired: impl Fn(&str) -> bool) -> Passage {
+    let active: Vec<&Passage> = passages.iter().filter(|p| !retired(&p.text)).collect();
+    let choices = if active.is_empty() { passages.iter().collect() } else { active };
+    let long: Vec<&Passage> = choices.iter().copied().filter(|p| char_len(&p.text) >= min_chars).collect();
+    let choices = if long.is_empty() { choices.into_iter().max_by_key(|p| char_len(&p.text)).into_iter().collect() } else { long };
+    choices
+        .choose(&mut rand::thread_rng())
+        .map(|&p| p.clone())
+        .unwrap_or_else(|| PASSAGES[rand::thread_rng().gen_range(0..PASSAGES.len())].to_passage())
+}
+
+/// Reload the pool whenever the process receives SIGHUP.
+#[cfg(unix)]
+pub fn reload_on_sighup(pool: Arc<PassagePool>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let Ok(mut hangups) = signal(SignalKind::hangup()) else {
+        warn!("sighup_handler_failed");
+        return;
+    };
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            pool.reload().await;
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn reload_on_sighup(_pool: Arc<PassagePool>) {}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/api/admin/passages/reload", post(reload))
+}
+
+#[derive(Serialize)]
+struct Reloaded {
+    passages: usize,
+}
+
+async fn reload(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Reloaded>> {
+    require_admin(&state, &headers)?;
+    Ok(Json(Reloaded { passages: state.passages.reload().await }))
+}
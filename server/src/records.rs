@@ -0,0 +1,95 @@
+//! The fastest finish on each passage, across every room. Records are kept by a hash of the
+//! passage text, since passages from files carry no id, and only races on the passage pool
+//! count: generated drills and word lists are never raced twice. With a database records are
+//! stored and outlast the process; without one they last until it exits.
+
+use std::sync::Arc;
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use sha2::{Digest, Sha256};
+use shared::{api::PassageRecord, protocol::RoomRecord};
+use sqlx::PgPool;
+use tracing::{warn, Instrument, Span};
+
+use crate::db;
+
+pub struct PassageRecords {
+    db: Option<Arc<PgPool>>,
+    // By passage key; None once the database has been asked and had nothing
+    known: DashMap<String, Option<PassageRecord>>,
+}
+
+impl PassageRecords {
+    pub fn new(db: Option<Arc<PgPool>>) -> Self {
+        Self { db, known: DashMap::new() }
+    }
+
+    /// The record on `passage`, if anyone has finished it.
+    pub async fn get(&self, passage: &str) -> Option<RoomRecord> {
+        let key = key(passage);
+        if let Some(known) = self.known.get(&key) {
+            return known.as_ref().map(|r| r.record.clone());
+        }
+        let pool = self.db.as_deref()?;
+        match db::passage_record(pool, &key).await {
+            Ok(record) => {
+                let record = record.map(|record| PassageRecord { passage: passage.to_string(), record });
+                // A finish may have set one while the database was answering
+                self.known.entry(key).or_insert(record).as_ref().map(|r| r.record.clone())
+            }
+            Err(e) => {
+                warn!(error = ?e, "passage_record_load_failed");
+                None
+            }
+        }
+    }
+
+    /// Make `record` the record on `passage` if it is faster. Returns `Some(previous)` when it
+    /// is, `previous` being None for the first finish on the passage.
+    pub fn offer(&self, passage: &str, record: RoomRecord) -> Option<Option<RoomRecord>> {
+        let key = key(passage);
+        let new = PassageRecord { passage: passage.to_string(), record: record.clone() };
+        let previous = match self.known.entry(key.clone()) {
+            Entry::Occupied(mut known) => {
+                let previous = known.get().as_ref().map(|r| r.record.clone());
+                if previous.as_ref().is_some_and(|p| p.wpm >= record.wpm) {
+                    return None;
+                }
+                known.insert(Some(new));
+                previous
+            }
+            Entry::Vacant(known) => {
+                known.insert(Some(new));
+                None
+            }
+        };
+        if let Some(pool) = self.db.clone() {
+            let passage = passage.to_string();
+            tokio::spawn(
+                async move {
+                    if let Err(e) = db::record_passage_best(&pool, &key, &passage, &record).await {
+                        warn!(error = ?e, "record_passage_best_failed");
+                    }
+                }
+                .instrument(Span::current()),
+            );
+        }
+        Some(previous)
+    }
+
+    /// Up to `limit` records, fastest first.
+    pub async fn list(&self, limit: usize) -> anyhow::Result<Vec<PassageRecord>> {
+        if let Some(pool) = self.db.as_deref() {
+            return db::passage_records(pool, limit as i64).await;
+        }
+        let mut records: Vec<PassageRecord> = self.known.iter().filter_map(|r| r.value().clone()).collect();
+        records.sort_by(|a, b| b.record.wpm.total_cmp(&a.record.wpm));
+        records.truncate(limit);
+        Ok(records)
+    }
+}
+
+/// Hex SHA-256 of the passage text.
+pub(crate) fn key(passage: &str) -> String {
+    hex::encode(Sha256::digest(passage.as_bytes()))
+}
@@ -0,0 +1,98 @@
+//! Keeps stored keystroke replays from growing the database without bound. Replays of races
+//! older than `REPLAY_RETENTION_DAYS` (90 by default, `off` to keep them forever) are boiled
+//! down to the stats kept on their result row and dropped, by a background task that runs
+//! every `RETENTION_INTERVAL_SECS`. The results themselves are never deleted.
+
+use shared::{
+    api::ReplaySample,
+    wpm::{burst_wpm, consistency, per_second_wpm},
+};
+use sqlx::PgPool;
+use std::sync::{atomic::Ordering, Arc};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::{db, metrics::METRICS};
+
+/// Replays compacted per statement, so a large backlog is worked through in short steps.
+const BATCH: i64 = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Days a replay is kept; None keeps them forever
+    pub replay_days: Option<u32>,
+    /// Time between compaction runs
+    pub interval: Duration,
+}
+
+impl RetentionPolicy {
+    pub const DEFAULT_REPLAY_DAYS: u32 = 90;
+    pub const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+    /// `REPLAY_RETENTION_DAYS` is a day count, or `off` (or 0) to keep replays;
+    /// `RETENTION_INTERVAL_SECS` is how often to compact.
+    pub fn from_env() -> Self {
+        let replay_days = match std::env::var("REPLAY_RETENTION_DAYS").map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Ok("off") | Ok("0") => None,
+            Ok("") | Err(_) => Some(Self::DEFAULT_REPLAY_DAYS),
+            Ok(other) => match other.parse::<u32>() {
+                Ok(days) => Some(days),
+                Err(_) => {
+                    warn!(value = other, "unknown REPLAY_RETENTION_DAYS; using the default");
+                    Some(Self::DEFAULT_REPLAY_DAYS)
+                }
+            },
+        };
+        let secs = std::env::var("RETENTION_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .filter(|&s| s > 0)
+            .unwrap_or(Self::DEFAULT_INTERVAL_SECS);
+        Self { replay_days, interval: Duration::from_secs(secs) }
+    }
+}
+
+/// Start compacting expired replays on `policy`'s schedule, the first run straight away.
+pub fn spawn(pool: Arc<PgPool>, policy: RetentionPolicy) {
+    let Some(days) = policy.replay_days else {
+        info!("replay_retention_off");
+        return;
+    };
+    info!(days, interval_secs = policy.interval.as_secs(), "replay_retention_on");
+    tokio::spawn(async move {
+        let mut ticker = interval(policy.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match compact_expired(&pool, days).await {
+                Ok(0) => {}
+                Ok(compacted) => info!(compacted, days, "replays_compacted"),
+                Err(e) => warn!(error = ?e, "replay_compaction_failed"),
+            }
+        }
+    });
+}
+
+/// Compact every replay older than `days`, a batch at a time; returns how many were dropped.
+async fn compact_expired(pool: &PgPool, days: u32) -> anyhow::Result<u64> {
+    let mut total = 0;
+    loop {
+        let expired = db::expired_replays(pool, days, BATCH).await?;
+        let compacted: Vec<db::CompactedReplay> = expired.iter().map(|(id, replay)| compact(*id, replay)).collect();
+        let dropped = db::compact_replays(pool, &compacted).await?;
+        METRICS.replays_compacted.fetch_add(dropped, Ordering::Relaxed);
+        total += dropped;
+        if (expired.len() as i64) < BATCH {
+            return Ok(total);
+        }
+    }
+}
+
+/// The stats a replay leaves behind. The race start isn't stored, so the pace is measured
+/// from the first sample, which results recorded with their pace stats never need.
+fn compact(id: i64, replay: &[ReplaySample]) -> db::CompactedReplay {
+    let t0 = replay.first().map_or(0, |s| s.t);
+    let pace: Vec<(f64, usize)> = replay.iter().map(|s| (s.t.saturating_sub(t0) as f64 / 1000.0, s.pos)).collect();
+    let per_second = per_second_wpm(&pace);
+    db::CompactedReplay { id, samples: replay.len(), consistency: consistency(&per_second), burst_wpm: burst_wpm(&per_second) }
+}
@@ -0,0 +1,1888 @@
+//! A race room. Each room is a single task (the actor) that owns all of its state and
+//! processes [`RoomCmd`]s one at a time, so there are no locks to order. Connections, the
+//! HTTP API and bot tasks talk to it through the cloneable [`Room`] handle.
+//!
+//! The actor also runs the room's own timer, polled only while something is time-driven
+//! (a countdown, a race, or a lobby ready to start), and stops once every handle is dropped.
+//!
+//! A room with more humans than its heat size races them in heats instead: each heat is an
+//! actor of its own, holding the race, whose broadcasts come back through the room's commands
+//! to be passed on; the room keeps the roster and ranks everyone once the last heat is over.
+
+use rand::{rngs::StdRng, SeedableRng};
+use rust_fsm::StateMachineImpl;
+use shared::{
+    api::{CheatFlag, FinishCheck, FlagKind, PassageCategory, ReplaySample},
+    difficulty::{self, Difficulty},
+    fsm::{RracerEvent, RracerState},
+    generate::PassageGenerator,
+    heats::{self, HeatResult},
+    passages::{Attribution, Passage},
+    relay::Relay,
+    protocol::{checkpoint_position, lane_color, overtakes, DuelRecord, ErrorCode, FinishRule, GamePhase, HallOfFame, PlayerInfo, RaceMode, RoomRecord, RoomSettings, Scoring, ServerMsg, SPLIT_CHECKPOINTS},
+    text::{casual, char_len, first_chars, first_words, fold_presentation_forms, grapheme_floor, passage_hash},
+    timeline::{Moment, Timeline, BURST_SECS},
+    typing::{Cursor, KeyBurst, BACKSPACE},
+    weakness::Misses,
+    wpm::{accuracy, burst_wpm, consistency, fastest_stretch, gross_wpm, net_wpm, per_second_wpm, raw_from_accuracy, raw_wpm},
+    xp::{level_for_xp, race_xp},
+};
+use sqlx::PgPool;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::HashMap,
+    ops::Range,
+    sync::Arc,
+};
+use tokio::{
+    sync::{broadcast, mpsc, oneshot},
+    task::JoinSet,
+    time::{interval, Duration, MissedTickBehavior},
+};
+use tracing::{debug, info, info_span, warn, Instrument, Span};
+use uuid::Uuid;
+
+use crate::auth::Owner;
+use crate::bots::{self, FillPolicy, Personality};
+use crate::db;
+use crate::key_clock::{self, KeyClock};
+use crate::panics;
+use crate::passages::PassagePool;
+use crate::moderation::Moderation;
+use crate::passage_health::PassageHealthStore;
+use crate::records::PassageRecords;
+use crate::telemetry::RACE_EVENTS;
+use crate::current_timestamp;
+
+#[derive(Clone)]
+pub struct Player {
+    pub id: String,
+    pub name: String,
+    pub position: usize,
+    /// Server time of the first key, for racers who send keys
+    pub start_time: Option<u64>,
+    /// Client stamp of the latest key
+    pub last_keystroke: u64,
+    /// The first key by both clocks, to hold the client's stamps to the server's
+    pub key_clock: KeyClock,
+    /// Keys sharing the latest keystroke's timestamp, which a paste produces
+    pub burst: KeyBurst,
+    /// Server time of the latest advance, for the typing indicator
+    pub last_typed: u64,
+    pub finished: bool,
+    /// Where `Key` messages have got to; `position` follows it for racers who send keys
+    pub cursor: Cursor,
+    pub is_bot: bool,
+    pub bot_speed_wpm: Option<f64>,
+    /// How a bot's speed varies over the race
+    pub personality: Personality,
+    // (server timestamp ms, position) samples kept as a lightweight replay
+    pub trace: Vec<(u64, usize)>,
+    /// Seconds from the start to each checkpoint passed so far, in `SPLIT_CHECKPOINTS` order
+    pub splits: Vec<f64>,
+    // Join order within the room; the roster is sorted by it and the earliest human hosts
+    pub joined_seq: u64,
+    // Humans: requested pace-car WPM. Pace cars: the id of the human they pace.
+    pub pace_wpm: Option<f64>,
+    pub pace_for: Option<String>,
+    /// Signed-in account, so stored results follow the identity rather than the typed name
+    pub user_id: Option<i64>,
+    /// Stable id from a guest token, to correlate a guest's races across reconnects
+    pub guest_id: Option<String>,
+    /// Lifetime XP, loaded on connect and raised as races finish
+    pub xp: u64,
+    /// Recent average WPM from stored results, when the racer has any
+    pub avg_wpm: Option<f64>,
+    pub result: Option<FinishRecord>,
+    /// Lane colour, kept for as long as they're in the room
+    pub color: u8,
+    /// (char, client stamp) of keys typed within `START_WINDOW_MS` of the start, typed at it
+    pub early_keys: Vec<(char, u64)>,
+}
+
+#[derive(Clone, Copy)]
+pub struct FinishRecord {
+    pub at: u64,
+    pub wpm: f64,
+    pub accuracy: f64,
+    pub normalized_wpm: f64,
+    pub errors: usize,
+    pub raw_wpm: f64,
+    /// Seconds from the start
+    pub time: f64,
+    pub consistency: f64,
+    /// Counts towards the global leaderboard (see `FinishCheck`)
+    pub verified: bool,
+    /// `errors` is the server's own count rather than the client's word for it
+    pub counted: bool,
+}
+
+impl FinishRecord {
+    /// The Finish broadcast for player `id`.
+    fn message(&self, id: String) -> ServerMsg {
+        ServerMsg::Finish { id, wpm: self.wpm, accuracy: self.accuracy, normalized_wpm: self.normalized_wpm, errors: self.errors, raw_wpm: self.raw_wpm, time: self.time, consistency: self.consistency, verified: self.verified, counted: self.counted }
+    }
+
+    /// Better results order first: faster, or under golf fewer errors and then the earlier
+    /// finish, with errors the server didn't count itself behind every count it did.
+    fn cmp_by(&self, other: &Self, scoring: Scoring) -> Ordering {
+        match scoring {
+            Scoring::Speed => other.wpm.total_cmp(&self.wpm),
+            Scoring::Golf => (!self.counted, self.errors, self.at).cmp(&(!other.counted, other.errors, other.at)),
+        }
+    }
+}
+
+impl Player {
+    pub fn new(id: String, name: String) -> Self {
+        Self { id, name, position: 0, start_time: None, last_keystroke: 0, key_clock: KeyClock::default(), burst: KeyBurst::default(), last_typed: 0, finished: false, cursor: Cursor::default(), is_bot: false, bot_speed_wpm: None, personality: Personality::Steady, trace: Vec::new(), splits: Vec::new(), joined_seq: 0, pace_wpm: None, pace_for: None, user_id: None, guest_id: None, xp: 0, avg_wpm: None, result: None, color: 0, early_keys: Vec::new() }
+    }
+
+    /// Move this race's clock readings `ms` later, as if a pause that long never happened.
+    fn shift_clock(&mut self, ms: u64) {
+        self.start_time = self.start_time.map(|t| t + ms);
+        self.key_clock.shift(ms);
+        for (t, _) in &mut self.trace {
+            *t += ms;
+        }
+    }
+
+    /// WPM in each whole second since `t0`, from the trace.
+    fn per_second(&self, t0: u64) -> Vec<f64> {
+        let pace: Vec<(f64, usize)> = self.trace.iter().map(|&(t, pos)| (t.saturating_sub(t0) as f64 / 1000.0, pos)).collect();
+        per_second_wpm(&pace)
+    }
+
+    fn reset_progress(&mut self) {
+        self.position = 0;
+        self.start_time = None;
+        self.key_clock = KeyClock::default();
+        self.finished = false;
+        self.cursor = Cursor::default();
+        self.burst = KeyBurst::default();
+        self.trace.clear();
+        self.splits.clear();
+        self.result = None;
+        self.early_keys.clear();
+    }
+}
+
+/// Point-in-time copy of a room for read-only HTTP endpoints; players are in join order.
+pub struct RoomView {
+    pub phase: RracerState,
+    pub passage_len: usize,
+    pub players: Vec<Player>,
+}
+
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+/// Typing faster than this (gross) is treated as cheating, whether seen per key or per progress update.
+pub(crate) const MAX_PLAUSIBLE_WPM: f64 = 300.0;
+/// Extra chars a single progress update may carry on top of the WPM cap, to absorb network bunching.
+const PROGRESS_BURST_SLACK: usize = 15;
+/// How long after a timed race's buzzer clients get to report their own result
+const TIMED_FINISH_GRACE_MS: u64 = 2000;
+/// How far before the server's buzzer a client's own may go off, its clock being a little off ours
+const BUZZER_SKEW_MS: u64 = 500;
+/// Players who advanced this recently show as typing
+const TYPING_WINDOW_MS: u64 = 2000;
+/// Keys typed this close before the start are held and typed at it, to absorb clock skew and
+/// the tick the start waits for; any earlier are false starts and dropped.
+const START_WINDOW_MS: u64 = 100;
+/// Keys held for the start per racer; nobody types more than this in `START_WINDOW_MS`
+const MAX_EARLY_KEYS: usize = 4;
+
+enum RoomCmd {
+    Join { player: Box<Player>, direct: mpsc::Sender<ServerMsg>, reply: oneshot::Sender<(String, ServerMsg)> },
+    Leave { player_id: String },
+    // An admin taking the player out; replies whether they were here
+    Remove { player_id: String, reply: oneshot::Sender<bool> },
+    Key { player_id: String, ch: char, ts: u64, at: u64 },
+    Progress { player_id: String, pos: usize },
+    Finish { player_id: String, wpm: f64, accuracy: f64, time: f64, missed: Vec<usize>, errors: usize },
+    SetPace { player_id: String, wpm: Option<f64> },
+    Reset { player_id: String },
+    RematchAccept { player_id: String },
+    Pause { player_id: String },
+    Resume { player_id: String },
+    // `by` is the requesting player (must be host); None for trusted server-side callers
+    UpdateSettings { by: Option<String>, settings: RoomSettings },
+    BotProgress { bot_id: String, pos: usize, epoch: u64 },
+    Snapshot { reply: oneshot::Sender<ServerMsg> },
+    // The snapshot for a socket of `player_id` that fell behind
+    Resync { player_id: String, reply: oneshot::Sender<ServerMsg> },
+    View { reply: oneshot::Sender<RoomView> },
+    // Broadcast by one of the room's heats, to pass on
+    HeatMsg { heat: usize, msg: Box<ServerMsg> },
+}
+
+/// Handle to a room actor. Commands are queued; methods that return data wait for the reply
+/// and yield `None` only if the actor has stopped.
+pub struct Room {
+    pub id: String,
+    tx: broadcast::Sender<ServerMsg>,
+    cmd: mpsc::Sender<RoomCmd>,
+}
+
+impl Room {
+    /// Start the actor for a new room and return its handle.
+    pub fn spawn(id: String, db: Option<Arc<PgPool>>, passages: Arc<PassagePool>, records: Arc<PassageRecords>, health: Arc<PassageHealthStore>, moderation: Arc<Moderation>, fill: FillPolicy) -> Arc<Self> {
+        let (actor, cmd, cmd_rx) = RoomActor::new(id.clone(), db, passages, records, health, moderation, fill);
+        let tx = actor.tx.clone();
+        let span = info_span!(parent: None, "room", room_id = %id);
+        tokio::spawn(panics::guard("room", actor.run(cmd_rx)).instrument(span));
+        Arc::new(Self { id, tx, cmd })
+    }
+
+    /// False once the actor has stopped, which after a panic leaves the handle in the registry.
+    pub fn is_running(&self) -> bool {
+        !self.cmd.is_closed()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerMsg> {
+        self.tx.subscribe()
+    }
+
+    async fn send(&self, cmd: RoomCmd) {
+        let _ = self.cmd.send(cmd).await;
+    }
+
+    async fn ask<T>(&self, make: impl FnOnce(oneshot::Sender<T>) -> RoomCmd) -> Option<T> {
+        let (reply, rx) = oneshot::channel();
+        self.cmd.send(make(reply)).await.ok()?;
+        rx.await.ok()
+    }
+
+    /// Adds the player under a room-unique display name. Returns that name plus a Sync
+    /// snapshot taken right after the join; `direct` receives messages meant only for them.
+    pub async fn join(&self, player: Player, direct: mpsc::Sender<ServerMsg>) -> Option<(String, ServerMsg)> {
+        self.ask(|reply| RoomCmd::Join { player: Box::new(player), direct, reply }).await
+    }
+
+    pub async fn leave(&self, player_id: &str) {
+        self.send(RoomCmd::Leave { player_id: player_id.to_string() }).await;
+    }
+
+    /// Take `player_id` out of the room for an admin, telling them why. Returns whether they
+    /// were in it.
+    pub async fn remove(&self, player_id: &str) -> Option<bool> {
+        self.ask(|reply| RoomCmd::Remove { player_id: player_id.to_string(), reply }).await
+    }
+
+    /// A key the client stamped `ts` and the server puts at `at` on its own clock.
+    pub async fn key(&self, player_id: &str, ch: char, ts: u64, at: u64) {
+        self.send(RoomCmd::Key { player_id: player_id.to_string(), ch, ts, at }).await;
+    }
+
+    pub async fn progress(&self, player_id: &str, pos: usize) {
+        self.send(RoomCmd::Progress { player_id: player_id.to_string(), pos }).await;
+    }
+
+    /// A client-reported finish; `missed` are the positions of its wrong keys.
+    pub async fn finish(&self, player_id: &str, wpm: f64, accuracy: f64, time: f64, missed: Vec<usize>, errors: usize) {
+        self.send(RoomCmd::Finish { player_id: player_id.to_string(), wpm, accuracy, time, missed, errors }).await;
+    }
+
+    /// Takes effect from the next countdown; pace cars are seeded alongside bots.
+    pub async fn set_pace(&self, player_id: &str, wpm: Option<f64>) {
+        self.send(RoomCmd::SetPace { player_id: player_id.to_string(), wpm }).await;
+    }
+
+    /// Change the room settings as `by` (host only), or unconditionally when `by` is None.
+    pub async fn update_settings(&self, by: Option<&str>, settings: RoomSettings) {
+        self.send(RoomCmd::UpdateSettings { by: by.map(str::to_string), settings }).await;
+    }
+
+    /// Back to the lobby after a finished race; the requester gets an error otherwise.
+    pub async fn reset(&self, player_id: &str) {
+        self.send(RoomCmd::Reset { player_id: player_id.to_string() }).await;
+    }
+
+    /// Propose a rematch after a finished race, or agree to the one already proposed.
+    pub async fn rematch_accept(&self, player_id: &str) {
+        self.send(RoomCmd::RematchAccept { player_id: player_id.to_string() }).await;
+    }
+
+    /// Stop the race clock as the host of a pausable room; the requester gets an error otherwise.
+    pub async fn pause(&self, player_id: &str) {
+        self.send(RoomCmd::Pause { player_id: player_id.to_string() }).await;
+    }
+
+    /// Carry on a race `pause`d by the host.
+    pub async fn resume(&self, player_id: &str) {
+        self.send(RoomCmd::Resume { player_id: player_id.to_string() }).await;
+    }
+
+    /// Full room snapshot for a single client (joining or resynchronising).
+    pub async fn snapshot(&self) -> Option<ServerMsg> {
+        self.ask(|reply| RoomCmd::Snapshot { reply }).await
+    }
+
+    /// The snapshot `player_id` resyncs from: while they race in a heat, their heat's, wrapped
+    /// like the rest of its messages.
+    pub async fn resync(&self, player_id: &str) -> Option<ServerMsg> {
+        self.ask(|reply| RoomCmd::Resync { player_id: player_id.to_string(), reply }).await
+    }
+
+    pub async fn view(&self) -> Option<RoomView> {
+        self.ask(|reply| RoomCmd::View { reply }).await
+    }
+}
+
+/// A race's passage with its credit and record, as `RoomActor` keeps them
+type Drawn = (String, Option<Attribution>, Option<Option<RoomRecord>>);
+
+/// One of the races a room with more humans than its heat size runs at once
+struct Heat {
+    room: Room,
+    // The humans racing in it, less any who left
+    racers: Vec<String>,
+    done: bool,
+}
+
+struct RoomActor {
+    id: String,
+    phase: RracerState,
+    players: HashMap<String, Player>,
+    passage: Option<String>,
+    // The pool passage as drawn, before casual or shortened versions, for its health counts;
+    // set at each countdown, None for generated texts
+    drawn: Option<String>,
+    // Credit for the current passage; generated texts have none
+    attribution: Option<Attribution>,
+    // The current passage's record: outer None for generated texts, which keep none, inner
+    // None for a pool passage nobody has finished yet
+    record: Option<Option<RoomRecord>>,
+    // Teams and legs of the current race when the room races relays
+    relay: Option<Relay>,
+    // Of the current passage, for normalizing finishers' WPM
+    difficulty: Difficulty,
+    // Recent winners and best win, loaded from the database when there is one
+    hall_of_fame: HallOfFame,
+    // Spectator count and typing ids last broadcast, so ticks only send changes
+    last_presence: Option<(usize, Vec<String>)>,
+    // Race order last broadcast, likewise
+    last_ranks: Vec<String>,
+    // The current race's moments so far, for the RaceSummary at the finish
+    timeline: Timeline,
+    // Humans who accepted a rematch of the finished race, proposer first
+    rematch: Vec<String>,
+    // Wins per human since the room last went back to the lobby, kept across rematches
+    series: HashMap<String, u32>,
+    // Head-to-head wins by identity pair, lower identity first; loaded for signed-in pairs
+    duels: HashMap<(String, String), (u32, u32)>,
+    countdown_start: Option<u64>,
+    race_start: Option<u64>,
+    // When the podium filled under `FinishRule::TopThree`, starting the grace period
+    closing_start: Option<u64>,
+    // When the racer in last place is next knocked out under `FinishRule::Elimination`
+    next_elimination: Option<u64>,
+    // When the host paused the race, while it's paused
+    paused_at: Option<u64>,
+    // When the first human started waiting alone, for the lobby auto-start timer
+    waiting_since: Option<u64>,
+    last_timer_second: Option<u64>,
+    join_seq: u64,
+    settings: RoomSettings,
+    // Bumped whenever a race starts or is reset; bot progress still queued from an older race
+    // is dropped on mismatch
+    race_epoch: u64,
+    // The current race's bot tasks, aborted when it ends or is cleared
+    bot_tasks: JoinSet<Option<()>>,
+    // The heats racing in place of one race of the whole room, by heat number; empty otherwise
+    heats: Vec<Heat>,
+    // Finishes in the heats so far, for the HeatResults once the last is over
+    heat_results: Vec<HeatResult>,
+    // When this room is a heat, the passage its room drew for every heat, raced at the countdown
+    heat_passage: Option<Drawn>,
+    // Whether this room is one heat of another, whose winner, duel and series are kept there
+    is_heat: bool,
+    tx: broadcast::Sender<ServerMsg>,
+    // Per-connection channels for messages meant for a single player
+    direct: HashMap<String, mpsc::Sender<ServerMsg>>,
+    // Weak so the actor stops once every handle is gone; bot tasks hold weak clones too
+    cmd: mpsc::WeakSender<RoomCmd>,
+    db: Option<Arc<PgPool>>,
+    passages: Arc<PassagePool>,
+    records: Arc<PassageRecords>,
+    health: Arc<PassageHealthStore>,
+    moderation: Arc<Moderation>,
+    // Which bots join at the countdown
+    fill: FillPolicy,
+}
+
+impl RoomActor {
+    /// A room with nobody in it yet, and both ends of the channel its commands come in on.
+    fn new(id: String, db: Option<Arc<PgPool>>, passages: Arc<PassagePool>, records: Arc<PassageRecords>, health: Arc<PassageHealthStore>, moderation: Arc<Moderation>, fill: FillPolicy) -> (Self, mpsc::Sender<RoomCmd>, mpsc::Receiver<RoomCmd>) {
+        let (tx, _) = broadcast::channel(100);
+        let (cmd, cmd_rx) = mpsc::channel(256);
+        let actor = RoomActor {
+            id,
+            phase: RracerState::Waiting,
+            players: HashMap::new(),
+            passage: None,
+            drawn: None,
+            attribution: None,
+            record: None,
+            relay: None,
+            difficulty: Difficulty::default(),
+            hall_of_fame: HallOfFame::default(),
+            last_presence: None,
+            last_ranks: Vec::new(),
+            timeline: Timeline::default(),
+            rematch: Vec::new(),
+            series: HashMap::new(),
+            duels: HashMap::new(),
+            countdown_start: None,
+            race_start: None,
+            closing_start: None,
+            next_elimination: None,
+            paused_at: None,
+            waiting_since: None,
+            last_timer_second: None,
+            join_seq: 0,
+            settings: RoomSettings::default(),
+            race_epoch: 0,
+            bot_tasks: JoinSet::new(),
+            heats: Vec::new(),
+            heat_results: Vec::new(),
+            heat_passage: None,
+            is_heat: false,
+            tx,
+            direct: HashMap::new(),
+            cmd: cmd.downgrade(),
+            db,
+            passages,
+            records,
+            health,
+            moderation,
+            fill,
+        };
+        (actor, cmd, cmd_rx)
+    }
+
+    async fn run(mut self, mut rx: mpsc::Receiver<RoomCmd>) {
+        if let Some(pool) = self.db.as_deref() {
+            match db::room_hall_of_fame(pool, &self.id).await {
+                Ok(hall) => self.hall_of_fame = hall,
+                Err(e) => warn!(error = ?e, "hall_of_fame_load_failed"),
+            }
+        }
+        // A heat has its racers from the start
+        self.try_start_countdown().await;
+        let mut ticker = interval(TICK_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                cmd = rx.recv() => match cmd {
+                    Some(cmd) => self.handle(cmd).await,
+                    None => break,
+                },
+                _ = ticker.tick(), if self.wants_tick() => self.tick().await,
+            }
+        }
+        debug!("room_actor_stopped");
+    }
+
+    async fn handle(&mut self, cmd: RoomCmd) {
+        let Some(cmd) = self.pass_to_heat(cmd).await else { return };
+        match cmd {
+            RoomCmd::Join { player, direct, reply } => {
+                let name = self.add_player(*player, direct).await;
+                let _ = reply.send((name, self.snapshot()));
+                self.update_presence();
+            }
+            RoomCmd::Leave { player_id } => self.leave(&player_id),
+            RoomCmd::Remove { player_id, reply } => {
+                let here = self.players.contains_key(&player_id);
+                if here {
+                    info!(target: RACE_EVENTS, event = "player_removed", room_id = %self.id, player_id);
+                    self.send_to(&player_id, ServerMsg::Error { code: ErrorCode::Removed, message: "A moderator removed you from the room".to_string() });
+                    self.leave(&player_id);
+                }
+                let _ = reply.send(here);
+            }
+            RoomCmd::Key { player_id, ch, ts, at } => self.handle_keystroke(&player_id, ch, ts, at),
+            RoomCmd::Progress { player_id, pos } => self.update_player_progress(&player_id, pos),
+            RoomCmd::Finish { player_id, wpm, accuracy, time, missed, errors } => self.handle_client_finish(&player_id, wpm, accuracy, time, &missed, errors),
+            RoomCmd::SetPace { player_id, wpm } => {
+                if let Some(p) = self.players.get_mut(&player_id) {
+                    p.pace_wpm = wpm;
+                }
+            }
+            RoomCmd::Reset { player_id } => self.reset(&player_id).await,
+            RoomCmd::RematchAccept { player_id } => self.accept_rematch(&player_id),
+            RoomCmd::Pause { player_id } => self.pause(&player_id),
+            RoomCmd::Resume { player_id } => self.resume(&player_id),
+            RoomCmd::UpdateSettings { by, settings } => {
+                if self.update_settings(by.as_deref(), settings) {
+                    // A lower human minimum may let a waiting lobby start right away
+                    self.try_start_countdown().await;
+                }
+            }
+            RoomCmd::BotProgress { bot_id, pos, epoch } => self.bot_progress(&bot_id, pos, epoch),
+            RoomCmd::Snapshot { reply } => {
+                let _ = reply.send(self.snapshot());
+                // Spectators arrive through a snapshot
+                self.update_presence();
+            }
+            RoomCmd::Resync { player_id, reply } => {
+                let _ = reply.send(self.resync(&player_id).await);
+            }
+            RoomCmd::View { reply } => {
+                let mut players: Vec<Player> = self.players.values().cloned().collect();
+                players.sort_by_key(|p| p.joined_seq);
+                let passage_len = self.passage.as_deref().map(char_len).unwrap_or(0);
+                let _ = reply.send(RoomView { phase: self.phase, passage_len, players });
+            }
+            RoomCmd::HeatMsg { heat, msg } => self.heat_msg(heat, *msg),
+        }
+    }
+
+    /// Pass a racer's command on to the heat they race in. Returns the command when it's the
+    /// room's to handle: anyone else's, and a racer leaving, which both hear about.
+    async fn pass_to_heat(&mut self, cmd: RoomCmd) -> Option<RoomCmd> {
+        let (RoomCmd::Key { player_id, .. } | RoomCmd::Progress { player_id, .. } | RoomCmd::Finish { player_id, .. } | RoomCmd::Pause { player_id } | RoomCmd::Resume { player_id } | RoomCmd::Leave { player_id } | RoomCmd::Remove { player_id, .. }) = &cmd else { return Some(cmd) };
+        let Some(heat) = self.heats.iter().position(|h| h.racers.contains(player_id)) else { return Some(cmd) };
+        if matches!(cmd, RoomCmd::Leave { .. } | RoomCmd::Remove { .. }) {
+            let player_id = player_id.clone();
+            self.heats[heat].room.leave(&player_id).await;
+            self.heats[heat].racers.retain(|id| *id != player_id);
+            // Nobody is left to finish it
+            if self.heats[heat].racers.is_empty() {
+                self.heat_done(heat);
+            }
+            return Some(cmd);
+        }
+        self.heats[heat].room.send(cmd).await;
+        None
+    }
+
+    /// The snapshot `player_id` resyncs from; see `Room::resync`.
+    async fn resync(&self, player_id: &str) -> ServerMsg {
+        if let Some(heat) = self.heats.iter().position(|h| h.racers.iter().any(|id| id == player_id)) {
+            if let Some(msg) = self.heats[heat].room.snapshot().await {
+                return ServerMsg::Heat { heat, msg: Box::new(msg) };
+            }
+        }
+        self.snapshot()
+    }
+
+    /// Idle rooms (lobby short of players, racing, finished) don't poll the timer at all.
+    fn wants_tick(&self) -> bool {
+        match self.phase {
+            RracerState::Countdown => true,
+            // Typing indicators, the buzzer in timed races and the end of a grace period
+            RracerState::Racing | RracerState::Closing => true,
+            // A room racing in heats waits on them, not the clock
+            RracerState::Waiting if !self.heats.is_empty() => false,
+            RracerState::Waiting => {
+                let humans = self.human_count();
+                humans >= self.settings.min_humans as usize || (humans > 0 && self.settings.auto_start_secs.is_some())
+            }
+            _ => false,
+        }
+    }
+
+    fn human_count(&self) -> usize {
+        self.players.values().filter(|p| !p.is_bot).count()
+    }
+
+    /// The humans' average WPM, over those who have one.
+    fn average_wpm(&self) -> Option<f64> {
+        let known: Vec<f64> = self.players.values().filter(|p| !p.is_bot).filter_map(|p| p.avg_wpm).collect();
+        (!known.is_empty()).then(|| known.iter().sum::<f64>() / known.len() as f64)
+    }
+
+    /// Returns whether the settings changed; rejected requests get an error sent back.
+    fn update_settings(&mut self, by: Option<&str>, settings: RoomSettings) -> bool {
+        if let Some(player_id) = by {
+            if host_id(&self.players) != Some(player_id) {
+                self.send_to(player_id, ServerMsg::Error { code: ErrorCode::NotHost, message: "Only the host can change room settings".to_string() });
+                return false;
+            }
+            if !matches!(self.phase, RracerState::Waiting | RracerState::Finished) {
+                self.send_to(player_id, ServerMsg::Error { code: ErrorCode::InvalidState, message: "Settings can only be changed between races".to_string() });
+                return false;
+            }
+        }
+        self.settings = settings.clamped();
+        if self.settings.auto_start_secs.is_none() {
+            self.stop_waiting_timer();
+        }
+        info!(target: RACE_EVENTS, event = "settings_changed", room_id = %self.id, countdown_secs = self.settings.countdown_secs, min_humans = self.settings.min_humans, auto_start_secs = ?self.settings.auto_start_secs, error_mode = ?self.settings.error_mode, casual = self.settings.casual, pausable = self.settings.pausable, mode = ?self.settings.mode);
+        let _ = self.tx.send(ServerMsg::Settings { settings: self.settings });
+        true
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        self.join_seq += 1;
+        self.join_seq - 1
+    }
+
+    /// A lane colour for a newcomer to the room.
+    fn free_color(&self) -> u8 {
+        let taken: Vec<u8> = self.players.values().map(|p| p.color).collect();
+        lane_color(&taken, self.join_seq)
+    }
+
+    /// Keep a failed anti-cheat check on `player_id` for admins to review.
+    fn flag(&self, player_id: &str, kind: FlagKind, wpm: Option<f64>) {
+        let Some(player) = self.players.get(player_id) else { return };
+        let identity = Owner::from_parts(player.user_id, player.guest_id.clone()).map(|o| o.key());
+        self.moderation.flag(CheatFlag { at: current_timestamp() as i64, room: self.id.clone(), player_id: player_id.to_string(), name: player.name.clone(), identity, kind, wpm });
+    }
+
+    fn send_to(&self, player_id: &str, msg: ServerMsg) {
+        // Dropped if they're gone or their queue is full
+        if let Some(tx) = self.direct.get(player_id) {
+            let _ = tx.try_send(msg);
+        }
+    }
+
+    fn set_phase(&mut self, event: RracerEvent) -> bool {
+        match RracerState::transition(&self.phase, &event) {
+            Some(next) => {
+                self.phase = next;
+                let _ = self.tx.send(ServerMsg::StateChange { state: next.into() });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Clear the race so the room is back in the lobby; bots are kept unless `drop_bots`.
+    fn clear_race(&mut self, drop_bots: bool) {
+        self.race_epoch += 1;
+        self.stop_bots();
+        self.passage = None;
+        self.attribution = None;
+        self.record = None;
+        self.relay = None;
+        self.difficulty = Difficulty::default();
+        self.countdown_start = None;
+        self.race_start = None;
+        self.closing_start = None;
+        self.next_elimination = None;
+        self.paused_at = None;
+        self.last_ranks.clear();
+        self.timeline = Timeline::default();
+        self.rematch.clear();
+        self.heats.clear();
+        self.heat_results.clear();
+        self.stop_waiting_timer();
+        if drop_bots {
+            self.players.retain(|_, p| !p.is_bot);
+        }
+        for p in self.players.values_mut() {
+            p.reset_progress();
+        }
+    }
+
+    fn stop_waiting_timer(&mut self) {
+        self.waiting_since = None;
+        self.last_timer_second = None;
+    }
+
+    async fn try_start_countdown(&mut self) {
+        if self.phase != RracerState::Waiting || !self.heats.is_empty() {
+            return;
+        }
+        let human_count = self.human_count();
+        if human_count < self.settings.min_humans as usize {
+            debug!(room_id = %self.id, humans = human_count, "countdown_deferred");
+            return;
+        }
+        self.start_countdown().await;
+    }
+
+    /// Lobby auto-start: once a human has waited `auto_start_secs` without enough company,
+    /// race anyway against bots. Broadcasts WaitingTimer whenever the remaining second changes.
+    async fn tick_waiting_timer(&mut self) {
+        let Some(limit) = self.settings.auto_start_secs else { return self.stop_waiting_timer() };
+        if self.human_count() == 0 {
+            return self.stop_waiting_timer();
+        }
+        let now = current_timestamp();
+        let since = *self.waiting_since.get_or_insert(now);
+        let seconds_left = u64::from(limit).saturating_sub(now.saturating_sub(since) / 1000);
+        if self.last_timer_second != Some(seconds_left) {
+            self.last_timer_second = Some(seconds_left);
+            let _ = self.tx.send(ServerMsg::WaitingTimer { seconds_left });
+        }
+        if seconds_left == 0 {
+            info!(room_id = %self.id, humans = self.human_count(), "lobby_auto_start");
+            self.start_countdown().await;
+        }
+    }
+
+    async fn start_countdown(&mut self) {
+        if let Some(size) = self.settings.heat_size.filter(|&size| self.human_count() > size as usize) {
+            return self.start_heats(size as usize).await;
+        }
+        if !self.set_phase(RracerEvent::Join) {
+            return;
+        }
+        let (passage, attribution, record) = match self.heat_passage.take() {
+            Some(drawn) => drawn,
+            None => self.draw_passage().await,
+        };
+
+        // Relay teams are all human, and bots never miss a key, which would win every golf race
+        if self.settings.relay || self.settings.scoring == Scoring::Golf {
+            self.players.retain(|_, p| !p.is_bot);
+            return self.announce_countdown(passage, attribution, record);
+        }
+        // Seed pace cars for humans who asked for one, then bots as the fill policy allows
+        let pacers: Vec<(String, f64)> = self.players.values().filter_map(|p| p.pace_wpm.map(|w| (p.id.clone(), w))).collect();
+        for (human_id, wpm) in pacers {
+            let pace_id = format!("pace-{}-{}", self.id, Uuid::new_v4());
+            let pace_name = unique_name(&self.players, &format!("Pace {wpm:.0} WPM"));
+            let pace = Player { is_bot: true, bot_speed_wpm: Some(wpm), pace_for: Some(human_id), joined_seq: self.next_seq(), color: self.free_color(), ..Player::new(pace_id.clone(), pace_name) };
+            self.players.insert(pace_id, pace);
+        }
+        let needed = self.fill.bots_needed(self.players.len(), self.human_count());
+        let skill = self.average_wpm();
+        for (profile, wpm) in bots::pick(needed, |name| self.players.values().any(|p| p.name == name), skill) {
+            let bot_id = profile.player_id(&self.id);
+            let bot = Player { is_bot: true, bot_speed_wpm: Some(wpm), personality: profile.personality, joined_seq: self.next_seq(), color: self.free_color(), ..Player::new(bot_id.clone(), profile.name.clone()) };
+            self.players.insert(bot_id, bot);
+        }
+        self.announce_countdown(passage, attribution, record);
+    }
+
+    /// A passage for the next race as the room's settings have it, with its credit and record.
+    async fn draw_passage(&mut self) -> Drawn {
+        // Heats race the passage their room draws, so it's cut for a heat's worth of racers
+        let racers = self.settings.heat_size.map_or(self.human_count(), |size| self.human_count().min(size as usize));
+        let target = self.settings.length.target_chars(racers, self.average_wpm());
+        let (mut passage, attribution) = match self.settings.mode {
+            RaceMode::Passages => {
+                let health = self.health.clone();
+                let Passage { text, attribution } = self.passages.random(target.unwrap_or(0), |text| health.is_retired(text)).await;
+                self.drawn = Some(text.clone());
+                (text, attribution)
+            }
+            RaceMode::Drill(drill) => {
+                self.drawn = None;
+                (drill.generate(rand::random()), None)
+            }
+            RaceMode::Words(words) => {
+                self.drawn = None;
+                (words.generate(rand::random()), None)
+            }
+        };
+        // Type what an Arabic or Hebrew keyboard types, not the glyph shapes
+        passage = fold_presentation_forms(&passage);
+        if self.settings.casual_text() {
+            let simplified = casual(&passage);
+            // A passage that is nothing but punctuation is raced as written
+            if !simplified.is_empty() {
+                passage = simplified;
+            }
+        }
+        // Hosts may race just the start of pool passages; the truncated text goes out in Start
+        // and is what finishing means
+        if let (RaceMode::Passages, Some(words)) = (self.settings.mode, self.settings.max_words) {
+            passage = first_words(&passage, words as usize).to_string();
+        }
+        if let (RaceMode::Passages, Some(chars)) = (self.settings.mode, target) {
+            passage = first_chars(&passage, chars).to_string();
+        }
+        // Records are kept by the text raced, so casual and shortened versions of a passage have their own
+        let record = match self.settings.mode {
+            RaceMode::Passages => Some(self.records.get(&passage).await),
+            _ => None,
+        };
+        (passage, attribution, record)
+    }
+
+    /// Race a room with more humans than `size` in heats of at most `size` instead, all at once
+    /// on one passage. The room itself stays in the lobby until the last heat is over.
+    async fn start_heats(&mut self, size: usize) {
+        self.stop_waiting_timer();
+        // Each heat seeds its own bots and pace cars
+        self.players.retain(|_, p| !p.is_bot);
+        let mut humans: Vec<&Player> = self.players.values().collect();
+        humans.sort_by_key(|p| p.joined_seq);
+        let racers: Vec<(String, Option<f64>)> = humans.into_iter().map(|p| (p.id.clone(), p.avg_wpm)).collect();
+        let draw = heats::draw(&racers, size);
+        let passage = self.draw_passage().await;
+        info!(target: RACE_EVENTS, event = "heats_started", room_id = %self.id, heats = draw.len(), humans = racers.len());
+        let _ = self.tx.send(ServerMsg::Heats { heats: draw.clone() });
+        self.heat_results.clear();
+        self.heats = draw.into_iter().enumerate().map(|(heat, racers)| self.spawn_heat(heat, racers, passage.clone())).collect();
+    }
+
+    /// Start heat number `heat` for `racers` on `passage`: a room of its own, whose broadcasts
+    /// come back through this one's commands to be passed on.
+    fn spawn_heat(&self, heat: usize, racers: Vec<String>, passage: Drawn) -> Heat {
+        let (mut actor, cmd, cmd_rx) = RoomActor::new(self.id.clone(), self.db.clone(), self.passages.clone(), self.records.clone(), self.health.clone(), self.moderation.clone(), self.fill);
+        for id in &racers {
+            if let (Some(player), Some(direct)) = (self.players.get(id), self.direct.get(id)) {
+                actor.players.insert(id.clone(), player.clone());
+                actor.direct.insert(id.clone(), direct.clone());
+            }
+        }
+        // The heat counts down as soon as it starts, and never splits again
+        actor.settings = RoomSettings { min_humans: 1, auto_start_secs: None, heat_size: None, ..self.settings };
+        actor.join_seq = self.join_seq;
+        actor.drawn = self.drawn.clone();
+        actor.heat_passage = Some(passage);
+        actor.is_heat = true;
+        let mut rx = actor.tx.subscribe();
+        let tx = actor.tx.clone();
+        let span = info_span!(parent: None, "room", room_id = %self.id, heat);
+        tokio::spawn(panics::guard("room", actor.run(cmd_rx)).instrument(span));
+        let room = self.cmd.clone();
+        tokio::spawn(async move {
+            loop {
+                let msg = match rx.recv().await {
+                    Ok(msg) => msg,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(heat, skipped, "heat_broadcast_lagged");
+                        continue;
+                    }
+                    // The heat stopped once the room let go of it
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Some(room) = room.upgrade() else { break };
+                if room.send(RoomCmd::HeatMsg { heat, msg: Box::new(msg) }).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Heat { room: Room { id: self.id.clone(), tx, cmd }, racers, done: false }
+    }
+
+    /// Pass on a message from heat number `heat` to the whole room, keeping its finishes for
+    /// the results.
+    fn heat_msg(&mut self, heat: usize, msg: ServerMsg) {
+        if let ServerMsg::Finish { ref id, wpm, accuracy, errors, raw_wpm, time, consistency, counted, .. } = msg {
+            // Bots filling out a heat aren't the room's racers
+            if let Some(player) = self.players.get(id).filter(|p| !p.is_bot) {
+                self.heat_results.push(HeatResult { heat, id: id.clone(), name: player.name.clone(), wpm, raw_wpm, accuracy, errors, time, consistency, counted });
+            }
+        }
+        let over = matches!(msg, ServerMsg::StateChange { state: GamePhase::Finished });
+        let _ = self.tx.send(ServerMsg::Heat { heat, msg: Box::new(msg) });
+        if over {
+            self.heat_done(heat);
+        }
+    }
+
+    /// Mark heat number `heat` over, and the room's race with it once every heat is.
+    fn heat_done(&mut self, heat: usize) {
+        let Some(h) = self.heats.get_mut(heat) else { return };
+        h.done = true;
+        if self.heats.iter().all(|h| h.done) {
+            self.end_heats();
+        }
+    }
+
+    /// Rank everyone the heats finished and switch the room to finished; the heats stop once
+    /// their handles are dropped.
+    fn end_heats(&mut self) {
+        let results = heats::ranked(std::mem::take(&mut self.heat_results), self.settings.scoring);
+        info!(target: RACE_EVENTS, event = "heats_finished", room_id = %self.id, heats = self.heats.len(), finishers = results.len());
+        self.heats.clear();
+        self.set_phase(RracerEvent::HeatsDone);
+        if let Some(winner) = results.first() {
+            self.crown(winner.id.clone(), winner.name.clone(), winner.wpm);
+        }
+        let _ = self.tx.send(ServerMsg::HeatResults { results });
+        self.broadcast_lobby();
+    }
+
+    /// Count down to `passage` with whoever is in the room now.
+    fn announce_countdown(&mut self, passage: String, attribution: Option<Attribution>, record: Option<Option<RoomRecord>>) {
+        self.stop_waiting_timer();
+        let human_count = self.human_count();
+        self.countdown_start = Some(current_timestamp());
+        self.broadcast_lobby();
+        let bots = self.players.values().filter(|p| p.is_bot).count();
+        info!(target: RACE_EVENTS, event = "countdown_started", room_id = %self.id, humans = human_count, bots, passage_len = char_len(&passage));
+        let _ = self.tx.send(ServerMsg::Countdown { passage: passage.clone(), attribution: attribution.clone(), record: record.clone().flatten(), hash: passage_hash(&passage) });
+        self.difficulty = difficulty::estimate(&passage);
+        if self.settings.relay {
+            let mut humans: Vec<&Player> = self.players.values().filter(|p| !p.is_bot).collect();
+            humans.sort_by_key(|p| p.joined_seq);
+            let racers: Vec<String> = humans.into_iter().map(|p| p.id.clone()).collect();
+            let relay = Relay::new(&racers, &passage);
+            info!(target: RACE_EVENTS, event = "relay_teams", room_id = %self.id, teams = relay.teams.len(), legs = relay.legs.len());
+            let _ = self.tx.send(ServerMsg::RelayTeams { relay: relay.clone() });
+            self.relay = Some(relay);
+        }
+        self.passage = Some(passage);
+        self.attribution = attribution;
+        self.record = record;
+    }
+
+    /// A player joining, or one already here joining again (the join on connect plus a click,
+    /// say), who keeps their lane, name and progress as they are.
+    async fn add_player(&mut self, mut player: Player, direct: mpsc::Sender<ServerMsg>) -> String {
+        if let Some(name) = self.players.get(&player.id).map(|p| p.name.clone()) {
+            debug!(room_id = %self.id, player_id = %player.id, "player_rejoined");
+            self.direct.insert(player.id, direct);
+            return name;
+        }
+        player.name = unique_name(&self.players, &player.name);
+        player.joined_seq = self.next_seq();
+        player.color = self.free_color();
+        info!(target: RACE_EVENTS, event = "player_joined", room_id = %self.id, player_id = %player.id, name = %player.name);
+        let name = player.name.clone();
+        self.direct.insert(player.id.clone(), direct);
+        self.players.insert(player.id.clone(), player);
+        debug!(room_id = %self.id, players = self.players.len(), "room_size");
+
+        if self.phase == RracerState::Finished {
+            info!(target: RACE_EVENTS, event = "room_reset", room_id = %self.id, reason = "late_join");
+            self.phase = RracerState::Waiting;
+            self.series.clear();
+            self.clear_race(false);
+        }
+        self.load_duel().await;
+        // Broadcast lobby immediately so all clients see the newcomer
+        self.broadcast_lobby();
+        // Fast path: if 2+ humans, try to start countdown
+        self.try_start_countdown().await;
+        name
+    }
+
+    fn leave(&mut self, player_id: &str) {
+        self.remove_player(player_id);
+        self.update_presence();
+        // The rest may all have accepted already
+        self.start_rematch_if_agreed();
+    }
+
+    fn remove_player(&mut self, player_id: &str) {
+        self.direct.remove(player_id);
+        if let Some(player) = self.players.remove(player_id) {
+            info!(target: RACE_EVENTS, event = "player_left", room_id = %self.id, player_id);
+            let _ = self.tx.send(ServerMsg::PlayerLeft { id: player.id.clone() });
+            let mid_race = matches!(self.phase, RracerState::Racing | RracerState::Closing | RracerState::Paused);
+            if let Some(drawn) = self.drawn.as_deref().filter(|_| mid_race && !player.is_bot && !player.finished && player.position > 0) {
+                self.health.abandoned(drawn);
+            }
+        }
+        // A teammate takes over a leg its runner walked out of
+        if let Some(team) = self.relay.as_mut().and_then(|relay| relay.remove(player_id)) {
+            if self.racing() {
+                self.start_leg(team);
+            }
+        }
+        if self.human_count() == 0 {
+            // Nobody left for the bots to race; drop them so the room idles in the lobby
+            self.clear_race(true);
+        }
+        if self.players.is_empty() {
+            self.phase = RracerState::Waiting;
+            self.passage = None;
+            self.attribution = None;
+            self.countdown_start = None;
+            self.race_start = None;
+        }
+        self.broadcast_lobby();
+    }
+
+    fn accept_rematch(&mut self, player_id: &str) {
+        // Spectators and bots get no say
+        if self.players.get(player_id).is_none_or(|p| p.is_bot) { return; }
+        if self.phase != RracerState::Finished {
+            self.send_to(player_id, ServerMsg::Error { code: ErrorCode::InvalidState, message: "A rematch can only be proposed after a race".to_string() });
+            return;
+        }
+        if self.rematch.iter().any(|id| id == player_id) { return; }
+        self.rematch.push(player_id.to_string());
+        let _ = self.tx.send(ServerMsg::RematchProposed { by: self.rematch[0].clone(), accepted: self.rematch.clone() });
+        self.start_rematch_if_agreed();
+    }
+
+    /// Rerun the finished race's passage, bots and all, once every human has accepted.
+    fn start_rematch_if_agreed(&mut self) {
+        if self.phase != RracerState::Finished || self.rematch.is_empty() { return; }
+        if !self.players.values().filter(|p| !p.is_bot).all(|p| self.rematch.contains(&p.id)) { return; }
+        let Some(passage) = self.passage.clone() else { return };
+        let attribution = self.attribution.clone();
+        let record = self.record.clone();
+        info!(target: RACE_EVENTS, event = "rematch_started", room_id = %self.id, humans = self.rematch.len());
+        self.clear_race(false);
+        if self.set_phase(RracerEvent::Reset) && self.set_phase(RracerEvent::Join) {
+            self.announce_countdown(passage, attribution, record);
+        }
+    }
+
+    /// Whether `player_id` may pause or resume the race, telling them why not otherwise.
+    fn may_pause(&self, player_id: &str, event: RracerEvent) -> bool {
+        if host_id(&self.players) != Some(player_id) {
+            self.send_to(player_id, ServerMsg::Error { code: ErrorCode::NotHost, message: "Only the host can pause the race".to_string() });
+            return false;
+        }
+        if !self.settings.pausable || RracerState::transition(&self.phase, &event).is_none() {
+            self.send_to(player_id, ServerMsg::Error { code: ErrorCode::InvalidState, message: "Only a pausable race under way can be paused or resumed".to_string() });
+            return false;
+        }
+        true
+    }
+
+    /// Stop the race where it is: keys and progress are ignored while paused, and the bots
+    /// stand still.
+    fn pause(&mut self, player_id: &str) {
+        if !self.may_pause(player_id, RracerEvent::Pause) { return; }
+        self.set_phase(RracerEvent::Pause);
+        self.paused_at = Some(current_timestamp());
+        self.stop_bots();
+        info!(target: RACE_EVENTS, event = "race_paused", room_id = %self.id, player_id);
+        let _ = self.tx.send(ServerMsg::Paused { by: player_id.to_string() });
+        self.update_presence();
+    }
+
+    /// Carry on after a pause, with every clock moved on by its length so none of the race's
+    /// times count it.
+    fn resume(&mut self, player_id: &str) {
+        if !self.may_pause(player_id, RracerEvent::Resume) { return; }
+        let Some(paused_at) = self.paused_at.take() else { return };
+        let paused_ms = current_timestamp().saturating_sub(paused_at);
+        self.set_phase(RracerEvent::Resume);
+        self.race_start = self.race_start.map(|t| t + paused_ms);
+        self.next_elimination = self.next_elimination.map(|t| t + paused_ms);
+        for p in self.players.values_mut() {
+            p.shift_clock(paused_ms);
+        }
+        info!(target: RACE_EVENTS, event = "race_resumed", room_id = %self.id, player_id, paused_ms);
+        if let Some(t0) = self.race_start {
+            let _ = self.tx.send(ServerMsg::Resumed { t0 });
+        }
+        // Progress queued by the bots before the pause is stale
+        self.race_epoch += 1;
+        self.start_bots();
+    }
+
+    async fn reset(&mut self, player_id: &str) {
+        // Spectators can't send the room back to the lobby
+        if !self.players.contains_key(player_id) { return; }
+        // Only allow reset when the room is actually Finished
+        if self.phase != RracerState::Finished || RracerState::transition(&self.phase, &RracerEvent::Reset).is_none() {
+            self.send_to(player_id, ServerMsg::Error { code: ErrorCode::InvalidState, message: "Cannot reset until the race is finished".to_string() });
+            return;
+        }
+        info!(target: RACE_EVENTS, event = "room_reset", room_id = %self.id, reason = "player_request");
+        self.series.clear();
+        self.clear_race(true);
+        self.set_phase(RracerEvent::Reset);
+        self.broadcast_lobby();
+        self.try_start_countdown().await;
+    }
+
+    fn snapshot(&self) -> ServerMsg {
+        let positions = self.players.values().map(|p| (p.id.clone(), p.position)).collect();
+        ServerMsg::Sync { state: self.phase.into(), passage: self.passage.clone(), players: roster(&self.players), positions, t0: self.race_start, settings: self.settings, attribution: self.attribution.clone(), record: self.record.clone().flatten(), relay: self.relay.clone().map(Box::new), hash: self.passage.as_deref().map(passage_hash) }
+    }
+
+    fn broadcast_lobby(&self) {
+        // Racers in heats go by their heat's roster until the last one is over
+        if !self.heats.is_empty() {
+            return;
+        }
+        let roster = roster(&self.players);
+        debug!(room_id = %self.id, players = ?roster.iter().map(|p| &p.name).collect::<Vec<_>>(), "lobby_broadcast");
+        let _ = self.tx.send(ServerMsg::Lobby { players: roster, hall_of_fame: self.hall_of_fame.clone(), duel: self.duel_record() });
+    }
+
+    /// A key the client stamped `ts`, which the server puts at `at`. Speed checks and race
+    /// times go by the server's clock (see `key_clock`); the client's stamps only order keys
+    /// and spot pastes.
+    fn handle_keystroke(&mut self, player_id: &str, ch: char, ts: u64, at: u64) {
+        if self.phase == RracerState::Countdown { return self.hold_early_key(player_id, ch, ts, at); }
+        if !self.racing() { return; }
+        let (Some(passage_text), Some(t0)) = (self.passage.as_ref(), self.race_start) else { return };
+        let Some(leg) = relay_leg(self.relay.as_ref(), player_id) else { return };
+        let Some(player) = self.players.get_mut(player_id) else { return };
+        if player.is_bot { return; }
+        if player.burst.key(ts) {
+            warn!(room_id = %self.id, player_id, ts, "paste_detected");
+            self.flag(player_id, FlagKind::Paste, None);
+            self.send_to(player_id, ServerMsg::Error { code: ErrorCode::SuspiciousActivity, message: "Pasted text detected".to_string() });
+            return;
+        }
+        // Also drops timestamps that go backwards (and the rest of a paste), which keeps the elapsed times below positive
+        if ts < player.last_keystroke.saturating_add(20) { return; }
+        player.last_keystroke = ts;
+        player.last_typed = current_timestamp();
+        // Nobody types before the start, however the round trip was estimated
+        let at = at.max(t0);
+        player.key_clock.key(&self.id, player_id, ts, at);
+        // Measured from the start like client progress, so keys bunched by the network early on don't count against the racer
+        let elapsed_seconds = (at - t0) as f64 / 1000.0;
+        if elapsed_seconds > 1.0 { let current_wpm = gross_wpm(player.position, elapsed_seconds); if current_wpm > MAX_PLAUSIBLE_WPM { warn!(room_id = %self.id, player_id, wpm = current_wpm, "suspicious_typing_speed"); self.flag(player_id, FlagKind::KeySpeed, Some(current_wpm)); self.send_to(player_id, ServerMsg::Error { code: ErrorCode::SuspiciousActivity, message: "Suspicious typing speed detected".to_string() }); return; }}
+        // A runner types their own leg, and can't backspace into the one before
+        if leg.as_ref().is_some_and(|leg| player.cursor.pos >= leg.end || (ch == BACKSPACE && player.cursor.pos <= leg.start)) { return; }
+        if !player.cursor.press(passage_text, ch, self.settings.error_mode, |typed, expected| typed == expected) {
+            return;
+        }
+        player.position = grapheme_floor(passage_text, player.cursor.pos);
+        player.trace.push((at, player.position));
+        if player.start_time.is_none() { player.start_time = Some(at); }
+        if leg.as_ref().is_some_and(|leg| player.position >= leg.end) {
+            let _ = self.tx.send(ServerMsg::Progress { id: player.id.clone(), pos: player.position });
+            return self.hand_off(player_id);
+        }
+        if player.position >= char_len(passage_text) {
+            player.finished = true;
+            let elapsed = player.key_clock.elapsed(ts, at) as f64 / 1000.0;
+            let Cursor { keystrokes, errors, .. } = player.cursor;
+            let missed = player.cursor.missed.clone();
+            let wpm = net_wpm(player.cursor.correct(), elapsed, errors);
+            let acc = accuracy(keystrokes - errors, keystrokes);
+            let normalized_wpm = self.difficulty.normalize_wpm(wpm);
+            let verified = FinishCheck { reached_end: true, lagged: player.key_clock.drifted(), passage_len: char_len(passage_text), signed_in: player.user_id.is_some() }.verified();
+            let record = FinishRecord { at, wpm, accuracy: acc, normalized_wpm, errors, raw_wpm: raw_wpm(keystrokes, elapsed), time: elapsed, consistency: consistency(&player.per_second(t0)), verified, counted: true };
+            player.result = Some(record);
+            info!(target: RACE_EVENTS, event = "player_finished", room_id = %self.id, player_id, wpm, accuracy = acc, normalized_wpm, time_secs = elapsed, errors);
+            let _ = self.tx.send(record.message(player.id.clone()));
+            self.award_xp(player_id, wpm, acc);
+            self.record_result(player_id, wpm, acc, Some(elapsed));
+            self.check_passage_record(player_id, wpm);
+            self.record_misses(player_id, &missed);
+            self.check_all_finished();
+        } else {
+            let _ = self.tx.send(ServerMsg::Progress { id: player.id.clone(), pos: player.position });
+            self.record_splits(player_id);
+        }
+    }
+
+    /// A key that beat the start: held to be typed at it when it was typed within
+    /// `START_WINDOW_MS` of the start, by our clock, and otherwise dropped as a false start.
+    fn hold_early_key(&mut self, player_id: &str, ch: char, ts: u64, at: u64) {
+        let Some(countdown_start) = self.countdown_start else { return };
+        let start_at = countdown_start + u64::from(self.settings.countdown_secs) * 1000;
+        let Some(player) = self.players.get_mut(player_id) else { return };
+        if player.is_bot { return; }
+        if at + START_WINDOW_MS < start_at || player.early_keys.len() == MAX_EARLY_KEYS {
+            debug!(room_id = %self.id, player_id, early_ms = start_at.saturating_sub(at), "false_start");
+            return;
+        }
+        player.early_keys.push((ch, ts));
+    }
+
+    /// Type the keys held for the start, as typed at `t0`.
+    fn type_early_keys(&mut self, t0: u64) {
+        let held: Vec<(String, Vec<(char, u64)>)> = self.players.values_mut().filter(|p| !p.early_keys.is_empty()).map(|p| (p.id.clone(), std::mem::take(&mut p.early_keys))).collect();
+        for (player_id, keys) in held {
+            for (ch, ts) in keys {
+                self.handle_keystroke(&player_id, ch, ts, t0);
+            }
+        }
+    }
+
+    /// Broadcast a split for every checkpoint `player_id` has passed since the last call.
+    fn record_splits(&mut self, player_id: &str) {
+        // Relay runners start mid-passage, so their splits would time the team, not them
+        if self.relay.is_some() { return; }
+        let (Some(len), Some(t0)) = (self.passage.as_deref().map(char_len), self.race_start) else { return };
+        let Some(player) = self.players.get_mut(player_id) else { return };
+        let time = current_timestamp().saturating_sub(t0) as f64 / 1000.0;
+        while let Some(&checkpoint) = SPLIT_CHECKPOINTS.get(player.splits.len()) {
+            if player.position < checkpoint_position(len, checkpoint) {
+                break;
+            }
+            player.splits.push(time);
+            if checkpoint == 50 {
+                self.timeline.halfway(&player.id, time);
+            }
+            let _ = self.tx.send(ServerMsg::Split { id: player.id.clone(), checkpoint, time });
+        }
+    }
+
+    async fn tick(&mut self) {
+        self.update_presence();
+        match self.phase {
+            RracerState::Waiting => {
+                // Retry starting countdown if somehow missed on join, else run the lobby timer
+                self.try_start_countdown().await;
+                if self.phase == RracerState::Waiting {
+                    self.tick_waiting_timer().await;
+                }
+            }
+            RracerState::Countdown => {
+                let Some(start_time) = self.countdown_start else { return };
+                if current_timestamp().saturating_sub(start_time) < u64::from(self.settings.countdown_secs) * 1000 { return; }
+                if self.set_phase(RracerEvent::CountdownElapsed) {
+                    // New race epoch so progress from any earlier bots is ignored
+                    self.race_epoch += 1;
+                    let t0 = current_timestamp();
+                    self.race_start = Some(t0);
+                    self.timeline = Timeline::default();
+                    if let Some(passage) = self.passage.as_ref() {
+                        let _ = self.tx.send(ServerMsg::Start { passage: passage.clone(), t0, attribution: self.attribution.clone(), hash: passage_hash(passage) });
+                    }
+                    self.start_bots();
+                    self.schedule_elimination(t0);
+                    info!(target: RACE_EVENTS, event = "race_started", room_id = %self.id, t0);
+                    self.type_early_keys(t0);
+                }
+            }
+            RracerState::Racing | RracerState::Closing => {
+                if self.closing_start.is_some_and(|t| current_timestamp().saturating_sub(t) >= u64::from(FinishRule::GRACE_SECS) * 1000) {
+                    self.cut_race();
+                    return;
+                }
+                self.update_ranks();
+                if self.next_elimination.is_some_and(|t| current_timestamp() >= t) {
+                    self.eliminate();
+                    if !self.racing() { return; }
+                }
+                let (Some(limit), Some(t0)) = (self.settings.mode.time_limit_secs(), self.race_start) else { return };
+                // Clients report their own result at the buzzer; whoever hasn't is finished for them
+                if current_timestamp().saturating_sub(t0) >= u64::from(limit) * 1000 + TIMED_FINISH_GRACE_MS {
+                    self.end_timed_race(limit);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Finish everyone still racing once a timed race runs out, scored on how far they got.
+    fn end_timed_race(&mut self, limit: u32) {
+        let secs = f64::from(limit);
+        let unfinished: Vec<String> = self.players.values().filter(|p| !p.finished).map(|p| p.id.clone()).collect();
+        for id in unfinished {
+            self.finish_in_place(id, secs);
+        }
+        self.check_all_finished();
+    }
+
+    /// Finish `id` where they are after `secs` of racing, scored on how far they got.
+    fn finish_in_place(&mut self, id: String, secs: f64) {
+        let Some(player) = self.players.get_mut(&id) else { return };
+        let wpm = gross_wpm(player.position, secs);
+        if player.is_bot {
+            let normalized_wpm = self.difficulty.normalize_wpm(wpm);
+            player.finished = true;
+            let record = FinishRecord { at: current_timestamp(), wpm, accuracy: 100.0, normalized_wpm, errors: 0, raw_wpm: wpm, time: secs, consistency: 100.0, verified: false, counted: true };
+            player.result = Some(record);
+            let _ = self.tx.send(record.message(id));
+        } else {
+            let Cursor { keystrokes, errors, .. } = player.cursor;
+            let missed = player.cursor.missed.clone();
+            if self.handle_player_finish(&id, wpm, accuracy(keystrokes - errors, keystrokes), secs, errors, false) {
+                self.record_misses(&id, &missed);
+            }
+        }
+    }
+
+    /// Under `FinishRule::Elimination`, set the next elimination for `ELIMINATION_SECS` after
+    /// `now`, as long as there's someone to knock out.
+    fn schedule_elimination(&mut self, now: u64) {
+        let standing = self.players.values().filter(|p| !p.finished || p.result.is_some()).count();
+        let typing = self.players.values().any(|p| !p.finished);
+        self.next_elimination = (self.settings.finish == FinishRule::Elimination && self.relay.is_none() && standing > 1 && typing)
+            .then(|| now + u64::from(FinishRule::ELIMINATION_SECS) * 1000);
+        if self.next_elimination.is_some() {
+            let _ = self.tx.send(ServerMsg::EliminationIn { secs_left: FinishRule::ELIMINATION_SECS });
+        }
+    }
+
+    /// Knock out the racer in last place, who gets no result. Racers who made it to the end
+    /// are safe, and once one racer is left standing they finish where they are.
+    fn eliminate(&mut self) {
+        self.next_elimination = None;
+        // Last in the `update_ranks` order among those still typing
+        let last = self.players.values().filter(|p| !p.finished).min_by_key(|p| (p.position, Reverse(p.last_typed), Reverse(p.joined_seq)));
+        let Some(id) = last.map(|p| p.id.clone()) else { return };
+        if let Some(player) = self.players.get_mut(&id) { player.finished = true; }
+        info!(target: RACE_EVENTS, event = "racer_eliminated", room_id = %self.id, player_id = %id);
+        let _ = self.tx.send(ServerMsg::Eliminated { id });
+        let now = current_timestamp();
+        let typing: Vec<String> = self.players.values().filter(|p| !p.finished).map(|p| p.id.clone()).collect();
+        let finishers = self.players.values().filter(|p| p.result.is_some()).count();
+        match (typing.as_slice(), finishers) {
+            ([survivor], 0) => {
+                let secs = self.race_start.map_or(0.0, |t0| now.saturating_sub(t0) as f64 / 1000.0);
+                self.finish_in_place(survivor.clone(), secs);
+            }
+            _ => self.schedule_elimination(now),
+        }
+        self.check_all_finished();
+    }
+
+    /// Client-reported progress, which is all the web client sends. Positions only move
+    /// forward, are clamped to the passage and rounded down to a whole grapheme (a letter
+    /// whose marks are still to come isn't passed yet), and must be reachable at a plausible
+    /// speed both since the race started and since the previous update.
+    fn update_player_progress(&mut self, player_id: &str, position: usize) {
+        if !self.racing() { return; }
+        let (Some(passage), Some(t0)) = (self.passage.as_deref(), self.race_start) else { return };
+        let Some(leg) = relay_leg(self.relay.as_ref(), player_id) else { return };
+        let position = grapheme_floor(passage, leg.as_ref().map_or(position, |leg| position.min(leg.end)));
+        let Some(player) = self.players.get_mut(player_id) else { return };
+        if player.is_bot || player.finished { return; }
+        if position <= player.position { return; }
+        let now = current_timestamp();
+        let elapsed = now.saturating_sub(t0) as f64 / 1000.0;
+        let since_last = player.trace.last().map_or(elapsed, |&(t, _)| now.saturating_sub(t) as f64 / 1000.0);
+        let step = position - player.position;
+        let max_step = (MAX_PLAUSIBLE_WPM * 5.0 / 60.0 * since_last).ceil() as usize + PROGRESS_BURST_SLACK;
+        let overall_wpm = if elapsed > 1.0 { gross_wpm(position, elapsed) } else { 0.0 };
+        if step > max_step || overall_wpm > MAX_PLAUSIBLE_WPM {
+            warn!(room_id = %self.id, player_id, step, max_step, wpm = overall_wpm, "implausible_progress");
+            self.flag(player_id, FlagKind::ProgressJump, Some(overall_wpm));
+            self.send_to(player_id, ServerMsg::Error { code: ErrorCode::SuspiciousActivity, message: "Suspicious typing speed detected".to_string() });
+            return;
+        }
+        player.position = position;
+        player.last_typed = now;
+        player.trace.push((now, position));
+        let _ = self.tx.send(ServerMsg::Progress { id: player.id.clone(), pos: position });
+        self.record_splits(player_id);
+        if leg.is_some_and(|leg| position >= leg.end) {
+            self.hand_off(player_id);
+        }
+    }
+
+    /// A client's word that it typed to the end, which stands only once its progress got
+    /// there during the race, or that a timed race's buzzer went, which stands once the
+    /// server's has too. The time is the server's, from the start to that last update or the
+    /// buzzer, and the WPM no more than covering what the server saw typed in it allows;
+    /// accuracy and errors, which the server can't see without keys, are taken as reported,
+    /// errors then ranking last under golf.
+    /// Progress only gets to the end at a plausible pace, so a finish there is verified unless
+    /// the client's own time disagrees with the server's as lag leaves it.
+    fn handle_client_finish(&mut self, player_id: &str, wpm: f64, accuracy: f64, claimed: f64, missed: &[usize], errors: usize) {
+        if !self.racing() { return; }
+        let (Some(passage), Some(t0)) = (self.passage.as_deref(), self.race_start) else { return };
+        let len = char_len(passage);
+        let buzzer_ms = self.settings.mode.time_limit_secs().map(|secs| u64::from(secs) * 1000);
+        let Some(player) = self.players.get(player_id).filter(|p| !p.is_bot && !p.finished) else { return };
+        let reached_end = player.position >= len;
+        let reached = match buzzer_ms {
+            Some(buzzer_ms) if !reached_end => {
+                if current_timestamp().saturating_sub(t0) + BUZZER_SKEW_MS < buzzer_ms { return; }
+                t0 + buzzer_ms
+            }
+            _ if reached_end => match player.trace.last() {
+                Some(&(reached, _)) => reached,
+                None => return,
+            },
+            _ => return,
+        };
+        let time = (reached.saturating_sub(t0) as f64 / 1000.0).max(0.1);
+        let wpm = wpm.clamp(0.0, gross_wpm(player.position.min(len), time));
+        let lagged = key_clock::drifted((claimed.max(0.0) * 1000.0) as u64, reached.saturating_sub(t0));
+        let errors = if player.cursor.pos >= player.position { player.cursor.errors } else { errors };
+        let verified = FinishCheck { reached_end, lagged, passage_len: len, signed_in: player.user_id.is_some() }.verified();
+        if self.handle_player_finish(player_id, wpm, accuracy.clamp(0.0, 100.0), time, errors, verified) {
+            self.record_misses(player_id, missed);
+        }
+    }
+
+    /// Finish a human on numbers from outside the keystroke checks. Returns whether they
+    /// finished, for the caller to store where their wrong keys fell.
+    fn handle_player_finish(&mut self, player_id: &str, wpm: f64, accuracy: f64, time: f64, errors: usize, verified: bool) -> bool {
+        // Relay racers finish with their team, on its last handoff; nobody finishes mid-pause
+        if self.relay.is_some() || self.phase == RracerState::Paused { return false; }
+        // A timed race may already have finished a slow-to-report client
+        let Some(player) = self.players.get_mut(player_id).filter(|p| !p.finished) else { return false };
+        let normalized_wpm = self.difficulty.normalize_wpm(wpm);
+        player.finished = true;
+        let t0 = self.race_start.or(player.start_time).unwrap_or(0);
+        // Only keys show the server errors, so it counted them if keys took the racer as far as they got
+        let counted = player.cursor.pos >= player.position;
+        let record = FinishRecord { at: current_timestamp(), wpm, accuracy, normalized_wpm, errors, raw_wpm: raw_from_accuracy(wpm, accuracy), time, consistency: consistency(&player.per_second(t0)), verified, counted };
+        player.result = Some(record);
+        info!(target: RACE_EVENTS, event = "player_finished", room_id = %self.id, player_id, wpm, accuracy, normalized_wpm, time_secs = time, errors);
+        let _ = self.tx.send(record.message(player.id.clone()));
+        self.award_xp(player_id, wpm, accuracy);
+        self.record_result(player_id, wpm, accuracy, Some(time));
+        if let Some(drawn) = self.drawn.as_deref().filter(|_| !self.players.get(player_id).is_some_and(|p| p.is_bot)) {
+            self.health.finished(drawn, accuracy);
+        }
+        self.check_passage_record(player_id, wpm);
+        self.check_all_finished();
+        true
+    }
+
+    /// `player_id` has typed their leg: pass their team's next leg on, or bring it home.
+    fn hand_off(&mut self, player_id: &str) {
+        let Some(relay) = self.relay.as_mut() else { return };
+        let Some(team) = relay.team_of(player_id) else { return };
+        if relay.hand_off(team) {
+            self.start_leg(team);
+        } else {
+            self.finish_team(team);
+        }
+    }
+
+    /// Start `team`'s runner on its current leg, from where the last one handed off.
+    fn start_leg(&mut self, team: usize) {
+        let Some(relay) = self.relay.as_ref() else { return };
+        let leg_no = relay.current[team];
+        let (Some(runner), Some(leg)) = (relay.runner(team), relay.leg(leg_no)) else { return };
+        let runner = runner.to_string();
+        let Some(player) = self.players.get_mut(&runner) else { return };
+        let now = current_timestamp();
+        player.position = leg.start;
+        player.cursor = Cursor { pos: leg.start, ..Cursor::default() };
+        // Speed checks measure the new leg from here
+        player.trace.push((now, leg.start));
+        player.last_typed = now;
+        info!(target: RACE_EVENTS, event = "relay_handoff", room_id = %self.id, team, leg = leg_no, player_id = %runner);
+        let _ = self.tx.send(ServerMsg::Handoff { team, leg: leg_no, id: runner.clone() });
+        let _ = self.tx.send(ServerMsg::Progress { id: runner, pos: leg.start });
+    }
+
+    /// `team` typed its last leg: each member finishes at the team's time.
+    fn finish_team(&mut self, team: usize) {
+        let (Some(relay), Some(t0), Some(len)) = (self.relay.as_ref(), self.race_start, self.passage.as_deref().map(char_len)) else { return };
+        let members = relay.teams[team].clone();
+        let now = current_timestamp();
+        let time = now.saturating_sub(t0) as f64 / 1000.0;
+        let wpm = gross_wpm(len, time);
+        let normalized_wpm = self.difficulty.normalize_wpm(wpm);
+        info!(target: RACE_EVENTS, event = "relay_team_finished", room_id = %self.id, team, time_secs = time, wpm);
+        let _ = self.tx.send(ServerMsg::TeamFinish { team, time, wpm });
+        for id in members {
+            let Some(player) = self.players.get_mut(&id) else { continue };
+            player.finished = true;
+            // Legs don't report accuracy
+            let record = FinishRecord { at: now, wpm, accuracy: 100.0, normalized_wpm, errors: 0, raw_wpm: wpm, time, consistency: consistency(&player.per_second(t0)), verified: false, counted: true };
+            player.result = Some(record);
+            let _ = self.tx.send(record.message(id));
+        }
+        self.check_all_finished();
+    }
+
+    /// Racing, grace period included.
+    fn racing(&self) -> bool {
+        matches!(self.phase, RracerState::Racing | RracerState::Closing)
+    }
+
+    /// After each finish: end the race once everyone is done, or sooner under the room's
+    /// finish rule.
+    fn check_all_finished(&mut self) {
+        let finishers = self.players.values().filter(|p| p.result.is_some()).count();
+        let rule = self.settings.finish;
+        match rule {
+            FinishRule::FirstWins if finishers > 0 => return self.cut_race(),
+            FinishRule::TopThree if finishers >= FinishRule::PODIUM && self.set_phase(RracerEvent::PodiumFilled) => {
+                info!(target: RACE_EVENTS, event = "race_closing", room_id = %self.id, grace_secs = FinishRule::GRACE_SECS);
+                self.closing_start = Some(current_timestamp());
+                let _ = self.tx.send(ServerMsg::RaceEnding { secs_left: FinishRule::GRACE_SECS });
+            }
+            _ => {}
+        }
+        if !self.players.is_empty() && self.players.values().all(|p| p.finished) {
+            self.end_race();
+        }
+    }
+
+    fn end_race(&mut self) {
+        if !self.set_phase(RracerEvent::AllDone) { return; }
+        info!(target: RACE_EVENTS, event = "race_finished", room_id = %self.id);
+        // Timed and cut-short races end with bots still running
+        self.stop_bots();
+        // Relay results belong to teams, not to the racers in them, and a heat's to its room
+        if self.relay.is_none() && !self.is_heat {
+            self.record_duel();
+            self.record_winner();
+        }
+        self.send_summary();
+        self.update_presence();
+    }
+
+    /// Broadcast the finished race's moments, with the fastest burst over everyone's traces.
+    fn send_summary(&mut self) {
+        let t0 = self.race_start.unwrap_or(0);
+        let burst = self
+            .players
+            .values()
+            .filter_map(|p| {
+                let (start, wpm) = fastest_stretch(&p.per_second(t0), BURST_SECS)?;
+                Some(Moment::FastestBurst { id: p.id.clone(), wpm, time: start as f64 })
+            })
+            .max_by(|a, b| match (a, b) {
+                (Moment::FastestBurst { wpm: a, .. }, Moment::FastestBurst { wpm: b, .. }) => a.total_cmp(b),
+                _ => Ordering::Equal,
+            });
+        let order = self.standings().into_iter().filter(|id| self.players.get(id).is_some_and(|p| p.result.is_some())).collect();
+        let moments = std::mem::take(&mut self.timeline).summary(burst, order);
+        let _ = self.tx.send(ServerMsg::RaceSummary { moments });
+    }
+
+    /// End the race now: whoever is still going doesn't finish and gets no result.
+    fn cut_race(&mut self) {
+        let mut unfinished: Vec<String> = self.players.values().filter(|p| !p.finished).map(|p| p.id.clone()).collect();
+        if !unfinished.is_empty() {
+            unfinished.sort_unstable();
+            for id in &unfinished {
+                if let Some(player) = self.players.get_mut(id) { player.finished = true; }
+            }
+            info!(target: RACE_EVENTS, event = "race_cut", room_id = %self.id, rule = ?self.settings.finish, unfinished = unfinished.len());
+            let _ = self.tx.send(ServerMsg::DidNotFinish { ids: unfinished });
+        }
+        self.closing_start = None;
+        if !self.players.is_empty() {
+            self.end_race();
+        }
+    }
+
+    /// Broadcast the race order if it changed since last time, and who passed whom: finishers
+    /// in the order they finished (under golf, fewest errors first), then everyone else by how
+    /// far they've got, whoever got there first ahead.
+    fn update_ranks(&mut self) {
+        let order = self.standings();
+        if order == self.last_ranks {
+            return;
+        }
+        let passes = overtakes(&self.last_ranks, &order);
+        if let (Some(leader), Some(t0)) = (self.last_ranks.first(), self.race_start) {
+            // Nobody leads before they've typed anything
+            if passes.iter().any(|(_, overtaken)| overtaken == leader) && self.players.get(leader).is_some_and(|p| p.position > 0) {
+                let time = current_timestamp().saturating_sub(t0) as f64 / 1000.0;
+                self.timeline.lead(&order[0], leader, time);
+            }
+        }
+        self.last_ranks = order.clone();
+        let _ = self.tx.send(ServerMsg::Ranks { order });
+        for (overtaker, overtaken) in passes {
+            let _ = self.tx.send(ServerMsg::Overtake { overtaker, overtaken });
+        }
+    }
+
+    /// Player ids from first to last place, as `update_ranks` orders them.
+    fn standings(&self) -> Vec<String> {
+        let mut racers: Vec<&Player> = self.players.values().collect();
+        let golf = self.settings.scoring == Scoring::Golf;
+        // Whoever is out without a result (eliminated, say) goes behind those still typing
+        racers.sort_by_key(|p| (p.result.map_or((true, usize::MAX), |r| if golf { (!r.counted, r.errors) } else { (false, 0) }), p.result.map_or(u64::MAX, |r| r.at), p.finished, Reverse(p.position), p.last_typed, p.joined_seq));
+        racers.into_iter().map(|p| p.id.clone()).collect()
+    }
+
+    /// Broadcast who is watching and who is typing, if either changed since last time.
+    fn update_presence(&mut self) {
+        // Every socket in the room is subscribed; those without a player are spectating
+        let spectators = self.tx.receiver_count().saturating_sub(self.direct.len());
+        let now = current_timestamp();
+        let mut typing: Vec<String> = if self.racing() {
+            self.players
+                .values()
+                .filter(|p| !p.finished && now.saturating_sub(p.last_typed) < TYPING_WINDOW_MS)
+                .map(|p| p.id.clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        typing.sort_unstable();
+        let presence = (spectators, typing);
+        if self.last_presence.as_ref() == Some(&presence) {
+            return;
+        }
+        self.last_presence = Some(presence.clone());
+        let (spectators, typing) = presence;
+        let _ = self.tx.send(ServerMsg::Presence { spectators, typing });
+    }
+
+    /// The two humans when there are exactly two, ordered by identity.
+    fn duelists(&self) -> Option<(&Player, &Player)> {
+        let mut humans = self.players.values().filter(|p| !p.is_bot);
+        let (a, b) = (humans.next()?, humans.next()?);
+        if humans.next().is_some() {
+            return None;
+        }
+        Some(if identity(a) <= identity(b) { (a, b) } else { (b, a) })
+    }
+
+    fn duel_record(&self) -> Option<DuelRecord> {
+        let (a, b) = self.duelists()?;
+        let (a_wins, b_wins) = self.duels.get(&(identity(a), identity(b))).copied().unwrap_or_default();
+        Some(DuelRecord { a: a.id.clone(), b: b.id.clone(), a_wins, b_wins })
+    }
+
+    /// Fetch the stored record when two signed-in racers meet for the first time in this room.
+    async fn load_duel(&mut self) {
+        let Some(pool) = self.db.clone() else { return };
+        let Some((a, b)) = self.duelists() else { return };
+        let (Some(a_user), Some(b_user)) = (a.user_id, b.user_id) else { return };
+        let key = (identity(a), identity(b));
+        if self.duels.contains_key(&key) {
+            return;
+        }
+        match db::duel_wins(&pool, a_user, b_user).await {
+            Ok(wins) => {
+                self.duels.insert(key, wins);
+            }
+            Err(e) => warn!(error = ?e, "duel_load_failed"),
+        }
+    }
+
+    /// Count the finished race towards the head-to-head record if it was a two-human race.
+    fn record_duel(&mut self) {
+        let Some((a, b)) = self.duelists() else { return };
+        let (Some(a_result), Some(b_result)) = (a.result, b.result) else { return };
+        let a_won = match a_result.cmp_by(&b_result, self.settings.scoring) {
+            Ordering::Equal => return,
+            order => order == Ordering::Less,
+        };
+        let (winner, loser) = if a_won { (a.user_id, b.user_id) } else { (b.user_id, a.user_id) };
+        let key = (identity(a), identity(b));
+        let entry = self.duels.entry(key).or_default();
+        if a_won { entry.0 += 1 } else { entry.1 += 1 }
+        let (Some(pool), Some(winner), Some(loser)) = (self.db.clone(), winner, loser) else { return };
+        tokio::spawn(
+            async move {
+                if let Err(e) = db::record_duel(&pool, winner, loser).await {
+                    warn!(error = ?e, "record_duel_failed");
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+
+    /// Enter the race's winning human (the fastest, or under golf the most accurate) into the
+    /// hall of fame and show it in the lobby.
+    fn record_winner(&mut self) {
+        let scoring = self.settings.scoring;
+        let Some((id, name, wpm)) = self
+            .players
+            .values()
+            .filter(|p| !p.is_bot)
+            .filter_map(|p| p.result.map(|r| (p, r)))
+            .min_by(|a, b| a.1.cmp_by(&b.1, scoring))
+            .map(|(p, r)| (p.id.clone(), p.name.clone(), r.wpm))
+        else {
+            return;
+        };
+        self.crown(id, name, wpm);
+    }
+
+    /// Count a race won by `id` towards the series and enter it into the hall of fame.
+    fn crown(&mut self, id: String, name: String, wpm: f64) {
+        *self.series.entry(id).or_default() += 1;
+        if self.series.values().sum::<u32>() > 1 {
+            let _ = self.tx.send(ServerMsg::SeriesScore { wins: self.series.clone() });
+        }
+        self.hall_of_fame.record(RoomRecord { name: name.clone(), wpm, at: current_timestamp() as i64 });
+        self.broadcast_lobby();
+        let Some(pool) = self.db.clone() else { return };
+        let room = self.id.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = db::record_room_winner(&pool, &room, &name, wpm).await {
+                    warn!(error = ?e, "record_room_winner_failed");
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+
+    /// Offer a human's finish as the passage's record, telling the room when it beats the one
+    /// shown at the countdown or since.
+    fn check_passage_record(&mut self, player_id: &str, wpm: f64) {
+        let (Some(current), Some(passage)) = (self.record.as_mut(), self.passage.as_deref()) else { return };
+        let Some(player) = self.players.get(player_id).filter(|p| !p.is_bot) else { return };
+        let record = RoomRecord { name: player.name.clone(), wpm, at: current_timestamp() as i64 };
+        let Some(previous) = self.records.offer(passage, record.clone()) else { return };
+        info!(target: RACE_EVENTS, event = "passage_record", room_id = %self.id, player_id, wpm, previous_wpm = previous.as_ref().map(|p| p.wpm));
+        *current = Some(record.clone());
+        if let Some(previous) = previous {
+            let _ = self.tx.send(ServerMsg::NewRecord { record, previous });
+        }
+    }
+
+    /// Credit a human's finish with XP, tell them, and persist it for identified players.
+    fn award_xp(&mut self, player_id: &str, wpm: f64, accuracy: f64) {
+        let Some(player) = self.players.get_mut(player_id).filter(|p| !p.is_bot) else { return };
+        let xp = race_xp(wpm, accuracy);
+        let before = level_for_xp(player.xp);
+        player.xp += u64::from(xp);
+        let level = level_for_xp(player.xp);
+        let msg = ServerMsg::XpGained { xp, total_xp: player.xp, level, leveled_up: level > before };
+        let identity = Owner::from_parts(player.user_id, player.guest_id.clone()).map(|o| o.key());
+        self.send_to(player_id, msg);
+        if level > before {
+            // Levels are shown in the lobby roster
+            self.broadcast_lobby();
+        }
+        let (Some(pool), Some(identity)) = (self.db.clone(), identity) else { return };
+        tokio::spawn(
+            async move {
+                if let Err(e) = db::add_xp(&pool, &identity, xp).await {
+                    warn!(error = ?e, "add_xp_failed");
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+
+    /// Add where an identified human's wrong keys fell to their stored misses, which pick what
+    /// their practice drills focus on. Generated drills of numbers and symbols aren't counted.
+    fn record_misses(&self, player_id: &str, missed: &[usize]) {
+        if missed.is_empty() || matches!(self.settings.mode, RaceMode::Drill(_)) { return; }
+        let (Some(pool), Some(passage)) = (self.db.clone(), self.passage.as_deref()) else { return };
+        let Some(player) = self.players.get(player_id).filter(|p| !p.is_bot) else { return };
+        let Some(identity) = Owner::from_parts(player.user_id, player.guest_id.clone()).map(|o| o.key()) else { return };
+        let misses = Misses::count(passage, missed);
+        tokio::spawn(
+            async move {
+                if let Err(e) = db::record_misses(&pool, &identity, &misses).await {
+                    warn!(error = ?e, "record_misses_failed");
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+
+    /// Persist a human's result in the background, verified as it was at the finish; no-op
+    /// without a database. Players with an identity (account or guest token) also get a
+    /// PersonalBest message when they beat their record for this passage length.
+    fn record_result(&self, player_id: &str, wpm: f64, accuracy: f64, time: Option<f64>) {
+        let (Some(pool), Some(player)) = (self.db.clone(), self.players.get(player_id)) else { return };
+        let identity = Owner::from_parts(player.user_id, player.guest_id.clone()).map(|o| o.key());
+        let direct = self.direct.get(player_id).cloned();
+        let room = self.id.clone();
+        let name = player.name.clone();
+        let user_id = player.user_id;
+        let guest_id = player.guest_id.clone();
+        let replay: Vec<ReplaySample> = player.trace.iter().map(|&(t, pos)| ReplaySample { t, pos }).collect();
+        let passage_len = self.passage.as_deref().map(char_len).unwrap_or(0);
+        let t0 = self.race_start.or(player.start_time).unwrap_or(0);
+        let per_second = player.per_second(t0);
+        let (consistency, burst_wpm) = (consistency(&per_second), burst_wpm(&per_second));
+        let normalized_wpm = self.difficulty.normalize_wpm(wpm);
+        let verified = player.result.is_some_and(|r| r.verified);
+        tokio::spawn(
+            async move {
+                let result = db::NewRaceResult { room: &room, player_name: &name, wpm, accuracy, time_secs: time, passage_len, consistency, burst_wpm, normalized_wpm, replay: &replay, user_id, guest_id: guest_id.as_deref(), verified };
+                if let Err(e) = db::record_result(&pool, &result).await {
+                    warn!(error = ?e, "record_result_failed");
+                    return;
+                }
+                let (Some(identity), Some(direct)) = (identity, direct) else { return };
+                let category = PassageCategory::from_len(passage_len);
+                match db::record_personal_best(&pool, &identity, category.as_str(), wpm).await {
+                    Ok(Some(Some(previous_wpm))) => {
+                        info!(target: RACE_EVENTS, event = "personal_best", room_id = %room, category = category.as_str(), wpm, previous_wpm);
+                        let _ = direct.send(ServerMsg::PersonalBest { category, wpm, previous_wpm }).await;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(error = ?e, "record_personal_best_failed"),
+                }
+            }
+            .instrument(Span::current()),
+        );
+    }
+
+    /// One task per bot advances it at its target speed from where it is and reports back as
+    /// `BotProgress`.
+    fn start_bots(&mut self) {
+        let Some(len) = self.passage.as_deref().map(char_len) else { return };
+        let epoch = self.race_epoch;
+        for p in self.players.values().filter(|p| p.is_bot) {
+            let bot_id = p.id.clone();
+            let cps = p.bot_speed_wpm.unwrap_or(60.0) * 5.0 / 60.0;
+            let personality = p.personality;
+            let from = p.position;
+            let cmd = self.cmd.clone();
+            self.bot_tasks.spawn(
+                panics::guard("bot", async move {
+                    let mut pos = from as f64;
+                    let mut last = current_timestamp();
+                    let tick = Duration::from_millis(100);
+                    let mut rng = StdRng::from_entropy();
+                    for step in 0.. {
+                        tokio::time::sleep(tick).await;
+                        let now = current_timestamp();
+                        let dt = now.saturating_sub(last) as f64 / 1000.0;
+                        last = now;
+                        pos += cps * dt * personality.pace(step, &mut rng);
+                        let ipos = (pos.floor() as usize).min(len);
+                        // The room is gone once no handle is left to upgrade
+                        let Some(cmd) = cmd.upgrade() else { break };
+                        if cmd.send(RoomCmd::BotProgress { bot_id: bot_id.clone(), pos: ipos, epoch }).await.is_err() || ipos >= len {
+                            break;
+                        }
+                    }
+                })
+                .instrument(Span::current()),
+            );
+        }
+    }
+
+    fn stop_bots(&mut self) {
+        self.bot_tasks.abort_all();
+        self.bot_tasks.detach_all();
+    }
+
+    fn bot_progress(&mut self, bot_id: &str, pos: usize, epoch: u64) {
+        if epoch != self.race_epoch || !self.racing() { return; }
+        let Some(passage) = self.passage.as_deref() else { return };
+        let (len, pos) = (char_len(passage), grapheme_floor(passage, pos));
+        let Some(bot) = self.players.get_mut(bot_id) else { return };
+        if bot.finished { return; }
+        bot.position = pos;
+        bot.last_typed = current_timestamp();
+        let _ = self.tx.send(ServerMsg::Progress { id: bot.id.clone(), pos });
+        self.record_splits(bot_id);
+        if pos >= len {
+            let Some(bot) = self.players.get_mut(bot_id) else { return };
+            let wpm = bot.bot_speed_wpm.unwrap_or(60.0);
+            let acc = 100.0;
+            let normalized_wpm = self.difficulty.normalize_wpm(wpm);
+            bot.finished = true;
+            let now = current_timestamp();
+            let time = self.race_start.map_or(0.0, |t0| now.saturating_sub(t0) as f64 / 1000.0);
+            let record = FinishRecord { at: now, wpm, accuracy: acc, normalized_wpm, errors: 0, raw_wpm: wpm, time, consistency: 100.0, verified: false, counted: true };
+            bot.result = Some(record);
+            let _ = self.tx.send(record.message(bot.id.clone()));
+            self.check_all_finished();
+        }
+    }
+}
+
+/// In a relay, the leg `player_id` may type now, or None (outer) when it's not their turn;
+/// Some(None) outside relays, where anyone types anywhere.
+fn relay_leg(relay: Option<&Relay>, player_id: &str) -> Option<Option<Range<usize>>> {
+    match relay {
+        Some(relay) => relay.turn(player_id).map(Some),
+        None => Some(None),
+    }
+}
+
+/// Stable key for a racer across reconnects: their account or guest token, else this connection.
+fn identity(p: &Player) -> String {
+    Owner::from_parts(p.user_id, p.guest_id.clone()).map_or_else(|| p.id.clone(), |o| o.key())
+}
+
+/// The earliest-joined human, who may change room settings.
+fn host_id(players: &HashMap<String, Player>) -> Option<&str> {
+    players.values().filter(|p| !p.is_bot).min_by_key(|p| p.joined_seq).map(|p| p.id.as_str())
+}
+
+/// Lobby roster in join order, with the host flagged.
+fn roster(players: &HashMap<String, Player>) -> Vec<PlayerInfo> {
+    let mut sorted: Vec<&Player> = players.values().collect();
+    sorted.sort_by_key(|p| p.joined_seq);
+    let host = host_id(players);
+    sorted
+        .into_iter()
+        .map(|p| PlayerInfo {
+            id: p.id.clone(),
+            name: p.name.clone(),
+            is_bot: p.is_bot,
+            is_host: host == Some(p.id.as_str()),
+            rating: p.bot_speed_wpm,
+            pace_for: p.pace_for.clone(),
+            level: (!p.is_bot).then(|| level_for_xp(p.xp)),
+            color: p.color,
+        })
+        .collect()
+}
+
+/// Returns `wanted` (trimmed, defaulting to "Player") if no one in the room uses it,
+/// otherwise the first free "name-2", "name-3", ... variant.
+fn unique_name(players: &HashMap<String, Player>, wanted: &str) -> String {
+    let base = match wanted.trim() { "" => "Player", t => t };
+    let taken = |n: &str| players.values().any(|p| p.name == n);
+    if !taken(base) { return base.to_string(); }
+    (2..).map(|i| format!("{base}-{i}")).find(|n| !taken(n)).unwrap_or_else(|| base.to_string())
+}
@@ -0,0 +1,54 @@
+//! Room codes like "brave-otter-42": short enough to read out on a call and type on a phone,
+//! for rooms opened without a name of their own. A room is joined at `/r/{code}`, which the
+//! web client turns into a join, or from a friend's invite at `/invite/{code}`.
+
+use axum::http::HeaderMap;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::api::public_base_url;
+
+const ADJECTIVES: &[&str] = &[
+    "brave", "calm", "clever", "cosy", "eager", "fancy", "gentle", "giddy", "golden", "happy", "jolly", "keen", "lively", "lucky", "merry", "mighty",
+    "nimble", "plucky", "proud", "quick", "quiet", "rapid", "shiny", "silly", "sleepy", "snappy", "speedy", "sunny", "swift", "tidy", "witty", "zesty",
+];
+
+const ANIMALS: &[&str] = &[
+    "badger", "beaver", "bison", "camel", "cheetah", "coyote", "dingo", "eagle", "falcon", "ferret", "gecko", "heron", "ibis", "jaguar", "koala", "lemur",
+    "lynx", "marmot", "moose", "newt", "ocelot", "otter", "panda", "puffin", "quokka", "raven", "salmon", "tapir", "toucan", "walrus", "wombat", "yak",
+];
+
+/// A fresh code that `in_use` says no live room has.
+pub fn generate(in_use: impl Fn(&str) -> bool) -> String {
+    let mut rng = rand::thread_rng();
+    std::iter::repeat_with(|| {
+        let adjective = ADJECTIVES.choose(&mut rng).copied().unwrap_or("quick");
+        let animal = ANIMALS.choose(&mut rng).copied().unwrap_or("otter");
+        format!("{adjective}-{animal}-{}", rng.gen_range(10..100))
+    })
+    .find(|code| !in_use(code))
+    .unwrap_or_default()
+}
+
+/// The link that joins `room`.
+pub fn join_url(headers: &HeaderMap, room: &str) -> String {
+    format!("{}{}", public_base_url(headers), join_path(room))
+}
+
+/// `/r/{room}`, escaped.
+pub fn join_path(room: &str) -> String {
+    format!("/r/{}", urlencoding(room))
+}
+
+/// The link that joins the room an invite `code` is for (`auth::Signer::invite_code`).
+pub fn invite_url(headers: &HeaderMap, code: &str) -> String {
+    format!("{}/invite/{}", public_base_url(headers), urlencoding(code))
+}
+
+fn urlencoding(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
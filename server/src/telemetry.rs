@@ -0,0 +1,74 @@
+//! Logging setup. `RUST_LOG`, or the server's `--log-level` flag, sets the initial filter
+//! (default `info`), `LOG_FORMAT=json` switches to one JSON object per line, and the filter can
+//! be swapped at runtime through `/api/admin/log-level` when `ADMIN_TOKEN` is set.
+//!
+//! Race lifecycle events are logged under the [`RACE_EVENTS`] target with an `event` field,
+//! so `RUST_LOG=rracer::race=info` plus JSON output yields a clean stream for analysis.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    routing::get,
+    Router,
+};
+use shared::api::ApiErrorCode;
+use subtle::ConstantTimeEq;
+use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry};
+
+use crate::api::{ApiFailure, ApiResult};
+use crate::AppState;
+
+pub const RACE_EVENTS: &str = "rracer::race";
+
+pub type LogHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Start logging, with `directives` (if given and valid) in place of `RUST_LOG`.
+pub fn init(directives: Option<&str>) -> LogHandle {
+    let filter = directives
+        .and_then(|d| EnvFilter::try_new(d).ok())
+        .or_else(|| EnvFilter::try_from_default_env().ok())
+        .unwrap_or_else(|| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    let json = std::env::var("LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("json"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json.then(|| fmt::layer().json().with_current_span(true).with_span_list(false)))
+        .with((!json).then(fmt::layer))
+        .init();
+    handle
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/api/admin/log-level", get(log_level).put(set_log_level))
+}
+
+pub(crate) fn require_admin(state: &AppState, headers: &HeaderMap) -> ApiResult<()> {
+    let Some(token) = state.admin_token.as_deref() else {
+        return Err(ApiFailure::new(ApiErrorCode::Unavailable, "admin API disabled (ADMIN_TOKEN not set)"));
+    };
+    let presented = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()).and_then(|v| v.strip_prefix("Bearer "));
+    match presented {
+        // In constant time, so response times don't give the token away a byte at a time
+        Some(p) if bool::from(p.as_bytes().ct_eq(token.as_bytes())) => Ok(()),
+        _ => Err(ApiFailure::new(ApiErrorCode::Unauthorized, "missing or invalid admin token")),
+    }
+}
+
+/// Current filter directives, e.g. `info,server=debug`.
+async fn log_level(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<String> {
+    require_admin(&state, &headers)?;
+    state
+        .log_filter
+        .with_current(|f| f.to_string())
+        .map_err(|e| ApiFailure::new(ApiErrorCode::Internal, e.to_string()))
+}
+
+/// Replace the filter with the directives in the request body (same syntax as `RUST_LOG`).
+async fn set_log_level(State(state): State<AppState>, headers: HeaderMap, body: String) -> ApiResult<String> {
+    require_admin(&state, &headers)?;
+    let directives = body.trim();
+    let filter = EnvFilter::try_new(directives).map_err(|e| ApiFailure::new(ApiErrorCode::BadRequest, e.to_string()))?;
+    state.log_filter.reload(filter).map_err(|e| ApiFailure::new(ApiErrorCode::Internal, e.to_string()))?;
+    tracing::info!(filter = directives, "log_filter_reloaded");
+    Ok(directives.to_string())
+}
@@ -0,0 +1,1131 @@
+//! End-to-end races over real WebSockets: the whole app served on an ephemeral port and driven
+//! by tokio-tungstenite clients, with static passages, no database and no bots.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use shared::api::{Ban, BanRequest, BuildInfo, CheckStatus, CreatedRoom, Friend, Invite, InviteRequest, LessonAttempt, LessonProgress, Page, PassageInfo, PassageRecord, PracticeDrill, PracticeReport, Readiness};
+use shared::difficulty;
+use shared::lessons::{self, Lesson};
+use shared::protocol::{ClientMsg, ErrorCode, FinishRule, GamePhase, PassageLength, RaceMode, RoomSettings, Scoring, ServerMsg, MAX_CLIENT_MSG_BYTES, MAX_ROOM_CHARS, PROTOCOL_VERSION};
+use shared::text::passage_hash;
+use shared::timeline::Moment;
+use shared::typing::PASTE_BURST_KEYS;
+use shared::weakness::{Weaknesses, DRILL_WORDS};
+use shared::words::{WordLimit, WordList, Words};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    time::{sleep, timeout, Duration},
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::auth::{Auth, Signer};
+use crate::bots::FillPolicy;
+use crate::{app, AppState};
+
+/// Longest a test waits for any one message; covers the default 3s countdown.
+const WAIT: Duration = Duration::from_secs(10);
+
+/// Client clock at a racer's first key press.
+const START_TS: u64 = 1_000_000;
+
+/// Serve a fresh app and return its address.
+async fn serve() -> SocketAddr {
+    serve_state(AppState::offline().await).await
+}
+
+/// Serve a fresh app with GitHub sign-in configured, returning its address and the signer
+/// its session cookies are checked with.
+async fn serve_with_sign_in() -> (SocketAddr, Arc<Signer>) {
+    let mut state = AppState::offline().await;
+    state.signer = Arc::new(Signer::new("test-secret".to_string()));
+    state.auth = Some(Arc::new(Auth::new(state.signer.clone(), vec![("github", "test-id".to_string(), "test-secret".to_string())]).expect("sign-in configured")));
+    let signer = state.signer.clone();
+    (serve_state(state).await, signer)
+}
+
+async fn serve_state(mut state: AppState) -> SocketAddr {
+    // Bots would hold every race open until the slowest of them finished
+    state.bot_fill = FillPolicy::Off;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app(state).into_make_service_with_connect_info::<SocketAddr>()).await.unwrap() });
+    addr
+}
+
+struct Client {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    guest: String,
+}
+
+impl Client {
+    /// Connect as a new guest, which is first handed a guest token.
+    async fn connect(addr: SocketAddr) -> Self {
+        let (ws, _) = connect_async(format!("ws://{addr}/ws")).await.unwrap();
+        let mut client = Self { ws, guest: String::new() };
+        let ServerMsg::GuestToken { token } = client.recv().await else { panic!("no guest token") };
+        client.guest = token;
+        client
+    }
+
+    /// Connect signed in to account `user_id`, with the session cookie a browser would send.
+    async fn connect_signed_in(addr: SocketAddr, signer: &Signer, user_id: i64) -> Self {
+        let mut request = format!("ws://{addr}/ws").into_client_request().unwrap();
+        request.headers_mut().insert("cookie", format!("rracer_session={}", signer.session_token(user_id)).parse().unwrap());
+        let (ws, _) = connect_async(request).await.unwrap();
+        Self { ws, guest: String::new() }
+    }
+
+    async fn send(&mut self, msg: &ClientMsg) {
+        self.ws.send(Message::text(serde_json::to_string(msg).unwrap())).await.unwrap();
+    }
+
+    /// The next message, less the round-trip reports that follow the server's pings at any time.
+    async fn recv(&mut self) -> ServerMsg {
+        loop {
+            match self.recv_any().await {
+                ServerMsg::Latency { .. } => continue,
+                msg => return msg,
+            }
+        }
+    }
+
+    async fn recv_any(&mut self) -> ServerMsg {
+        // The server's pings aren't messages, and mustn't keep the wait going
+        let text = timeout(WAIT, async {
+            loop {
+                if let Message::Text(text) = self.ws.next().await.unwrap().unwrap() {
+                    return text;
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for the server");
+        serde_json::from_str(&text).unwrap()
+    }
+
+    /// Skip messages until `f` picks one out, so callers assert on the order of the ones they
+    /// care about and ignore the rest (presence, lobby updates and so on).
+    async fn expect<T>(&mut self, what: &str, mut f: impl FnMut(ServerMsg) -> Option<T>) -> T {
+        let deadline = tokio::time::Instant::now() + WAIT;
+        while tokio::time::Instant::now() < deadline {
+            if let Some(found) = f(self.recv().await) {
+                return found;
+            }
+        }
+        panic!("never got {what}");
+    }
+
+    /// Join `room` and return our player id once the snapshot has arrived.
+    async fn join(&mut self, room: &str, name: &str) -> String {
+        self.send(&ClientMsg::Join { room: room.to_string(), name: name.to_string() }).await;
+        let id = match self.recv().await {
+            ServerMsg::Joined { id, name: joined_as } => {
+                assert_eq!(joined_as, name);
+                id
+            }
+            other => panic!("expected Joined, got {other:?}"),
+        };
+        assert!(matches!(self.recv().await, ServerMsg::Sync { .. }));
+        id
+    }
+
+    /// Wait for the countdown and the start, returning the passage.
+    async fn start(&mut self) -> String {
+        let (countdown, hash) = self.expect("Countdown", |m| match m { ServerMsg::Countdown { passage, hash, .. } => Some((passage, hash)), _ => None }).await;
+        let started = self.expect("Start", |m| match m { ServerMsg::Start { passage, hash, .. } => Some((passage, hash)), _ => None }).await;
+        assert_eq!((countdown, hash), started, "the race runs the passage shown in the countdown");
+        let started = started.0;
+        assert_eq!(hash, passage_hash(&started));
+        started
+    }
+
+    /// Type `passage` without mistakes, one key every `ms_per_key` by the keystroke timestamps
+    /// starting from `ts`.
+    async fn type_passage(&mut self, passage: &str, ts: u64, ms_per_key: u64) {
+        for (i, ch) in passage.chars().enumerate() {
+            self.send(&ClientMsg::Key { ch, ts: ts + i as u64 * ms_per_key }).await;
+            // Let the room and the other sockets keep up; a burst of keys would overflow the
+            // room's broadcast channel and leave slower sockets resyncing from a snapshot
+            sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    /// Report progress through `passage` as the web client does, a few chars at a time at a
+    /// believable pace, returning the seconds it took.
+    async fn progress_passage(&mut self, passage: &str) -> f64 {
+        let started = tokio::time::Instant::now();
+        let len = passage.chars().count();
+        for pos in (4..len).step_by(4).chain([len]) {
+            sleep(Duration::from_millis(250)).await;
+            self.send(&ClientMsg::Progress { pos, ts: 0 }).await;
+        }
+        started.elapsed().as_secs_f64()
+    }
+
+    /// Claim a finish of `wpm` in `time` seconds, as the web client does at the end.
+    async fn finish(&mut self, wpm: f64, time: f64) {
+        self.send(&ClientMsg::Finish { wpm, accuracy: 100.0, time, ts: 0, missed: Vec::new(), errors: 0 }).await;
+    }
+
+    /// Results for `ids` as (id, WPM, accuracy, normalized WPM) in the order they arrive, up
+    /// to the room's switch to finished.
+    async fn results(&mut self, ids: &[&str]) -> Vec<(String, f64, f64, f64)> {
+        let mut results = Vec::new();
+        self.expect("the race to finish", |m| match m {
+            ServerMsg::Finish { id, wpm, accuracy, normalized_wpm, .. } => {
+                results.push((id, wpm, accuracy, normalized_wpm));
+                None
+            }
+            ServerMsg::StateChange { state: GamePhase::Finished } => Some(()),
+            _ => None,
+        })
+        .await;
+        let mut seen: Vec<&str> = results.iter().map(|(id, ..)| id.as_str()).collect();
+        seen.sort_unstable();
+        let mut expected = ids.to_vec();
+        expected.sort_unstable();
+        assert_eq!(seen, expected);
+        results
+    }
+}
+
+#[tokio::test]
+async fn two_racers_race_to_the_finish() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("e2e", "alice").await;
+    let bob_id = bob.join("e2e", "bob").await;
+    let roster = alice
+        .expect("a lobby with both racers", |m| match m {
+            ServerMsg::Lobby { players, .. } if players.len() == 2 => Some(players),
+            _ => None,
+        })
+        .await;
+    assert!(roster.iter().any(|p| p.id == alice_id && p.is_host));
+    assert!(roster.iter().any(|p| p.id == bob_id && !p.is_host));
+    // Each has a lane colour of their own, the same on every client
+    assert_ne!(roster[0].color, roster[1].color, "{roster:?}");
+
+    let passage = alice.start().await;
+    assert_eq!(bob.start().await, passage);
+    // Alice types at 120 WPM by her timestamps, Bob at 80
+    tokio::join!(alice.type_passage(&passage, START_TS, 100), bob.type_passage(&passage, START_TS, 150));
+
+    let ids = [alice_id.as_str(), bob_id.as_str()];
+    let results = alice.results(&ids).await;
+    assert_eq!(bob.results(&ids).await.len(), 2);
+    let wpm = |id: &str| results.iter().find(|r| r.0 == id).map(|r| r.1).unwrap();
+    assert!(wpm(&alice_id) > wpm(&bob_id), "{results:?}");
+    assert!(results.iter().all(|r| r.2 == 100.0), "{results:?}");
+    // Both are credited for the same passage's difficulty
+    let passage_difficulty = difficulty::estimate(&passage);
+    assert!(results.iter().all(|r| (r.3 - passage_difficulty.normalize_wpm(r.1)).abs() < 1e-9), "{results:?}");
+}
+
+#[tokio::test]
+async fn spectators_catch_up_mid_race() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("watched", "alice").await;
+    let bob_id = bob.join("watched", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+    let (head, rest): (String, String) = (passage.chars().take(10).collect(), passage.chars().skip(10).collect());
+    alice.type_passage(&head, START_TS, 100).await;
+    alice.expect("alice's progress", |m| matches!(m, ServerMsg::Progress { ref id, pos: 10 } if *id == alice_id).then_some(())).await;
+
+    let mut watcher = Client::connect(addr).await;
+    watcher.send(&ClientMsg::Spectate { room: "watched".to_string() }).await;
+    match watcher.recv().await {
+        ServerMsg::Sync { state, passage: Some(synced), positions, .. } => {
+            assert_eq!(state, GamePhase::Racing);
+            assert_eq!(synced, passage);
+            assert_eq!(positions.get(&alice_id), Some(&10));
+        }
+        other => panic!("expected a racing Sync, got {other:?}"),
+    }
+    alice.expect("a spectator", |m| matches!(m, ServerMsg::Presence { spectators: 1, .. }).then_some(())).await;
+
+    // From then on the spectator sees the race like the racers do
+    alice.type_passage(&rest, START_TS + 1000, 100).await;
+    bob.type_passage(&passage, START_TS, 150).await;
+    watcher.results(&[alice_id.as_str(), bob_id.as_str()]).await;
+}
+
+#[tokio::test]
+async fn a_racer_whose_passage_drifted_is_resynced() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    alice.join("drift", "alice").await;
+    bob.join("drift", "bob").await;
+    let passage = alice.start().await;
+    alice.send(&ClientMsg::Resync).await;
+    alice.expect("a Sync", |m| match m {
+        ServerMsg::Sync { state: GamePhase::Racing, passage: Some(synced), hash, .. } => {
+            assert_eq!(synced, passage);
+            assert_eq!(hash, Some(passage_hash(&passage)));
+            Some(())
+        }
+        _ => None,
+    }).await;
+}
+
+#[tokio::test]
+async fn joining_the_same_room_again_changes_nothing() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("again", "alice").await;
+    let bob_id = bob.join("again", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+    let (head, rest): (String, String) = (passage.chars().take(10).collect(), passage.chars().skip(10).collect());
+    alice.type_passage(&head, START_TS, 100).await;
+    alice.expect("alice's progress", |m| matches!(m, ServerMsg::Progress { ref id, pos: 10 } if *id == alice_id).then_some(())).await;
+
+    // Mid-race, as if the join on connect and a click both went out
+    alice.send(&ClientMsg::Join { room: "again".to_string(), name: "alice".to_string() }).await;
+    let name = alice.expect("Joined", |m| match m { ServerMsg::Joined { name, .. } => Some(name), _ => None }).await;
+    assert_eq!(name, "alice", "not renamed as if someone else had the name");
+    match alice.recv().await {
+        ServerMsg::Sync { state, players, positions, .. } => {
+            assert_eq!(state, GamePhase::Racing);
+            assert_eq!(players.len(), 2);
+            assert_eq!(positions.get(&alice_id), Some(&10));
+        }
+        other => panic!("expected a racing Sync, got {other:?}"),
+    }
+
+    // Her race carries on from where it was
+    alice.type_passage(&rest, START_TS + 1000, 100).await;
+    bob.type_passage(&passage, START_TS, 150).await;
+    let results = bob.results(&[alice_id.as_str(), bob_id.as_str()]).await;
+    assert_eq!(results.len(), 2, "{results:?}");
+
+    // Joining another room leaves this one
+    alice.send(&ClientMsg::Join { room: "elsewhere".to_string(), name: "alice".to_string() }).await;
+    bob.expect("a lobby without alice", |m| matches!(m, ServerMsg::Lobby { ref players, .. } if players.len() == 1).then_some(())).await;
+}
+
+#[tokio::test]
+async fn leaving_takes_the_lane_away_at_once() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("exit", "alice").await;
+    bob.join("exit", "bob").await;
+    alice.send(&ClientMsg::Leave).await;
+    let left = bob.expect("PlayerLeft", |m| match m { ServerMsg::PlayerLeft { id } => Some(id), _ => None }).await;
+    assert_eq!(left, alice_id);
+    bob.expect("a lobby without alice", |m| matches!(m, ServerMsg::Lobby { ref players, .. } if players.len() == 1).then_some(())).await;
+
+    // Still connected, and free to go elsewhere
+    alice.send(&ClientMsg::Join { room: "elsewhere".to_string(), name: "alice".to_string() }).await;
+    alice.expect("Joined", |m| matches!(m, ServerMsg::Joined { .. }).then_some(())).await;
+}
+
+#[tokio::test]
+async fn lobbies_can_watch_another_room() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    alice.join("lobby", "alice").await;
+    alice.send(&ClientMsg::Watch { room: "featured".to_string() }).await;
+    let snapshot = alice.expect("the watched room", |m| match m { ServerMsg::Watched { room, msg } => Some((room, *msg)), _ => None }).await;
+    assert!(matches!(snapshot, (ref room, ServerMsg::Sync { state: GamePhase::Waiting, .. }) if room == "featured"), "{snapshot:?}");
+
+    let mut bob = Client::connect(addr).await;
+    let mut carol = Client::connect(addr).await;
+    let bob_id = bob.join("featured", "bob").await;
+    carol.join("featured", "carol").await;
+    let passage = bob.start().await;
+    bob.type_passage(&passage.chars().take(3).collect::<String>(), START_TS, 100).await;
+    // Tagged with the room it came from, while Alice stays in her own lobby
+    alice
+        .expect("bob's progress", |m| match m {
+            ServerMsg::Watched { room, msg } if room == "featured" => matches!(*msg, ServerMsg::Progress { ref id, pos: 3 } if *id == bob_id).then_some(()),
+            ServerMsg::Countdown { .. } | ServerMsg::Start { .. } => panic!("alice's lobby started a race"),
+            _ => None,
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn malformed_frames_are_refused_and_backwards_clocks_ignored() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("garbage", "alice").await;
+    let bob_id = bob.join("garbage", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+
+    for junk in ["", "{", "null", "\"Reset\"x", r#"{"Key":{"ch":"ab","ts":1}}"#, r#"{"Nope":{}}"#] {
+        alice.ws.send(Message::text(junk)).await.unwrap();
+    }
+    alice.ws.send(Message::binary(vec![0xff, 0x00])).await.unwrap();
+    // Each one is answered, in order, rather than dropped without a word
+    for _ in 0..7 {
+        let code = alice.expect("an error", |m| match m { ServerMsg::Error { code, .. } => Some(code), _ => None }).await;
+        assert_eq!(code, ErrorCode::InvalidMessage);
+    }
+    let first = passage.chars().next().unwrap();
+    alice.send(&ClientMsg::Key { ch: first, ts: START_TS }).await;
+    // A key stamped before the last one must not count, or crash the room doing the maths
+    alice.send(&ClientMsg::Key { ch: passage.chars().nth(1).unwrap(), ts: START_TS - 500 }).await;
+    alice.expect("alice's first key", |m| matches!(m, ServerMsg::Progress { ref id, pos: 1 } if *id == alice_id).then_some(())).await;
+
+    let rest: String = passage.chars().skip(1).collect();
+    alice.type_passage(&rest, START_TS + 100, 100).await;
+    bob.type_passage(&passage, START_TS, 150).await;
+    // Had the stale key landed, alice's next key would have been a mistake
+    let results = alice.results(&[alice_id.as_str(), bob_id.as_str()]).await;
+    assert!(results.iter().all(|r| r.1.is_finite() && r.1 > 0.0 && r.2 == 100.0), "{results:?}");
+}
+
+#[tokio::test]
+async fn bad_room_names_and_oversized_frames_are_refused() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    for room in ["   ".to_string(), "r".repeat(MAX_ROOM_CHARS + 1)] {
+        alice.send(&ClientMsg::Join { room, name: "alice".to_string() }).await;
+        let code = alice.expect("an error", |m| match m { ServerMsg::Error { code, .. } => Some(code), _ => None }).await;
+        assert_eq!(code, ErrorCode::InvalidMessage);
+    }
+    // Names are trimmed, so this is the longest name allowed
+    let longest = format!(" {} ", "r".repeat(MAX_ROOM_CHARS));
+    alice.join(&longest, "alice").await;
+
+    // A message too big to be anything real closes the connection
+    alice.ws.send(Message::text("x".repeat(MAX_CLIENT_MSG_BYTES + 1))).await.unwrap();
+    let closed = timeout(WAIT, async {
+        while let Some(Ok(msg)) = alice.ws.next().await {
+            if msg.is_close() {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "the connection stayed open");
+}
+
+#[tokio::test]
+async fn keys_are_timed_by_when_they_arrive() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    alice.join("stamped", "alice").await;
+    bob.join("stamped", "bob").await;
+    let passage = alice.start().await;
+
+    // Stamped a steady 60 WPM, but sent all at once well after the start: too fast by the
+    // server's clock, whatever the stamps say
+    sleep(Duration::from_millis(1_200)).await;
+    alice.type_passage(&passage, START_TS, 200).await;
+    let code = alice.expect("an error", |m| match m { ServerMsg::Error { code, .. } => Some(code), _ => None }).await;
+    assert_eq!(code, ErrorCode::SuspiciousActivity);
+}
+
+#[tokio::test]
+async fn keys_just_before_the_start_are_held_and_earlier_ones_dropped() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("jump", "alice").await;
+    let bob_id = bob.join("jump", "bob").await;
+    let passage = alice.expect("Countdown", |m| match m { ServerMsg::Countdown { passage, .. } => Some(passage), _ => None }).await;
+
+    // Seconds early: a false start, dropped (kept, '#' would cost accuracy)
+    alice.send(&ClientMsg::Key { ch: '#', ts: START_TS - 3_000 }).await;
+    // A moment before the start: typed at it
+    sleep(Duration::from_millis(u64::from(RoomSettings::default().countdown_secs) * 1000 - 60)).await;
+    alice.send(&ClientMsg::Key { ch: passage.chars().next().unwrap(), ts: START_TS }).await;
+    alice.expect("Start", |m| matches!(m, ServerMsg::Start { .. }).then_some(())).await;
+    alice.expect("alice's first key", |m| matches!(m, ServerMsg::Progress { ref id, pos: 1 } if *id == alice_id).then_some(())).await;
+
+    bob.start().await;
+    let rest: String = passage.chars().skip(1).collect();
+    tokio::join!(alice.type_passage(&rest, START_TS + 100, 100), bob.type_passage(&passage, START_TS, 150));
+    let results = alice.results(&[alice_id.as_str(), bob_id.as_str()]).await;
+    assert!(results.iter().all(|r| r.2 == 100.0), "{results:?}");
+}
+
+#[tokio::test]
+async fn pasted_keys_are_flagged_and_dropped() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("pasted", "alice").await;
+    bob.join("pasted", "bob").await;
+    let passage = alice.start().await;
+
+    // What a paste looks like: a run of keys all stamped with the same time
+    for ch in passage.chars().take(PASTE_BURST_KEYS) {
+        alice.send(&ClientMsg::Key { ch, ts: START_TS }).await;
+    }
+    let code = alice.expect("an error", |m| match m { ServerMsg::Error { code, .. } => Some(code), _ => None }).await;
+    assert_eq!(code, ErrorCode::SuspiciousActivity);
+    // Only the first key, which could have been typed, counts
+    alice.send(&ClientMsg::Key { ch: passage.chars().nth(1).unwrap(), ts: START_TS + 100 }).await;
+    let pos = alice.expect("alice's second key", |m| match m { ServerMsg::Progress { id, pos } if id == alice_id && pos > 1 => Some(pos), _ => None }).await;
+    assert_eq!(pos, 2);
+}
+
+#[tokio::test]
+async fn ranks_and_overtakes_follow_the_leader() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("ranks", "alice").await;
+    let bob_id = bob.join("ranks", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+
+    let head = |keys: usize| passage.chars().take(keys).collect::<String>();
+    bob.type_passage(&head(5), START_TS, 100).await;
+    let order = alice.expect("bob in the lead", |m| match m { ServerMsg::Ranks { order } if order[0] == bob_id => Some(order), _ => None }).await;
+    assert_eq!(order, vec![bob_id.clone(), alice_id.clone()]);
+    alice.type_passage(&head(6), START_TS, 100).await;
+    let order = bob.expect("alice overtaking", |m| match m { ServerMsg::Ranks { order } if order[0] == alice_id => Some(order), _ => None }).await;
+    assert_eq!(order, vec![alice_id.clone(), bob_id.clone()]);
+    let pass = bob.expect("the overtake", |m| match m { ServerMsg::Overtake { overtaker, overtaken } => Some((overtaker, overtaken)), _ => None }).await;
+    assert_eq!(pass, (alice_id, bob_id));
+}
+
+#[tokio::test]
+async fn the_first_finisher_ends_the_race() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("first-wins", "alice").await;
+    // Set before Bob joins, since his arrival starts the countdown
+    let settings = RoomSettings { finish: FinishRule::FirstWins, ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let bob_id = bob.join("first-wins", "bob").await;
+    let passage = alice.start().await;
+
+    // Bob never types; Alice's finish is the end of it
+    alice.type_passage(&passage, START_TS, 100).await;
+    let left_behind = bob.expect("the stragglers", |m| match m { ServerMsg::DidNotFinish { ids } => Some(ids), _ => None }).await;
+    assert_eq!(left_behind, vec![bob_id]);
+    bob.expect("the race to finish", |m| matches!(m, ServerMsg::StateChange { state: GamePhase::Finished }).then_some(())).await;
+    assert_eq!(alice.results(&[&alice_id]).await.len(), 1);
+}
+
+#[tokio::test]
+async fn finishes_stand_only_at_the_end_and_on_the_servers_clock() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("forged", "alice").await;
+    let settings = RoomSettings { finish: FinishRule::FirstWins, max_words: Some(RoomSettings::MIN_WORDS), ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    // Neither before the race nor before reaching the end of the passage
+    alice.finish(9_999.0, 0.1).await;
+    bob.join("forged", "bob").await;
+    alice.finish(9_999.0, 0.1).await;
+    let passage = alice.start().await;
+    alice.finish(9_999.0, 0.1).await;
+
+    alice.progress_passage(&passage).await;
+    alice.finish(9_999.0, 0.1).await;
+    let (wpm, time) = alice.expect("Alice's finish", |m| match m { ServerMsg::Finish { id, wpm, time, .. } if id == alice_id => Some((wpm, time)), _ => None }).await;
+    let len = passage.chars().count() as f64;
+    assert!(time >= 0.25 * (len / 4.0).floor(), "{time}");
+    assert!(wpm <= len / 5.0 / (time / 60.0) + 0.01, "{wpm}");
+}
+
+#[tokio::test]
+async fn timed_races_take_the_clients_result_at_the_buzzer() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("timed", "alice").await;
+    let limit = WordLimit::MIN_SECONDS;
+    let mode = RaceMode::Words(Words { list: WordList::Top200, limit: WordLimit::Seconds(limit) });
+    alice.send(&ClientMsg::UpdateSettings { settings: RoomSettings { mode, ..RoomSettings::default() } }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let bob_id = bob.join("timed", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+    let typed: String = passage.chars().take(20).collect();
+    // Alice reports progress as the web client does, Bob types keys
+    tokio::join!(alice.progress_passage(&typed), bob.type_passage(&typed, START_TS, 150));
+
+    // Before the buzzer a report counts for nothing
+    bob.send(&ClientMsg::Finish { wpm: 200.0, accuracy: 90.0, time: 3.0, ts: 0, missed: vec![1], errors: 5 }).await;
+    sleep(Duration::from_secs(u64::from(limit))).await;
+    alice.send(&ClientMsg::Finish { wpm: 500.0, accuracy: 90.0, time: f64::from(limit), ts: 0, missed: vec![1, 2], errors: 2 }).await;
+    let finish = |id: String| move |m| match m { ServerMsg::Finish { id: finished, wpm, time, errors, .. } if finished == id => Some((wpm, time, errors)), _ => None };
+    let (wpm, time, errors) = alice.expect("alice's finish", finish(alice_id)).await;
+    // Her errors, which the server couldn't see, stand; her speed is what it saw her get through by the buzzer
+    assert_eq!((time, errors), (f64::from(limit), 2));
+    assert!(wpm <= shared::wpm::gross_wpm(20, f64::from(limit)) + 1e-9, "{wpm}");
+    // The server scored his keys itself
+    let (_, time, errors) = alice.expect("bob's finish", finish(bob_id)).await;
+    assert_eq!((time, errors), (f64::from(limit), 0));
+}
+
+#[tokio::test]
+async fn signed_in_web_races_count_for_the_leaderboard() {
+    let (addr, signer) = serve_with_sign_in().await;
+    let mut alice = Client::connect_signed_in(addr, &signer, 1).await;
+    let mut bob = Client::connect_signed_in(addr, &signer, 2).await;
+    let mut carol = Client::connect(addr).await;
+    let alice_id = alice.join("verified", "alice").await;
+    // A passage long enough to count
+    let settings = RoomSettings { length: PassageLength::Racers, ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let bob_id = bob.join("verified", "bob").await;
+    let carol_id = carol.join("verified", "carol").await;
+    let passage = alice.start().await;
+    bob.start().await;
+    // Carol joined during the countdown, and waits for the start
+    carol.expect("Start", |m| matches!(m, ServerMsg::Start { .. }).then_some(())).await;
+
+    // Progress and a Finish, as the web client races; Bob's clock says he took no time at all
+    // and Carol is a guest
+    tokio::join!(
+        async {
+            let secs = alice.progress_passage(&passage).await;
+            alice.finish(60.0, secs).await;
+        },
+        async {
+            bob.progress_passage(&passage).await;
+            bob.finish(60.0, 0.1).await;
+        },
+        async {
+            let secs = carol.progress_passage(&passage).await;
+            carol.finish(60.0, secs).await;
+        },
+    );
+    let mut verified = Vec::new();
+    alice
+        .expect("the race to finish", |m| match m {
+            ServerMsg::Finish { id, verified: true, .. } => {
+                verified.push(id);
+                None
+            }
+            ServerMsg::StateChange { state: GamePhase::Finished } => Some(()),
+            _ => None,
+        })
+        .await;
+    assert_eq!(verified, vec![alice_id], "not {bob_id} or {carol_id}");
+}
+
+#[tokio::test]
+async fn the_race_is_summed_up_at_the_finish() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("summary", "alice").await;
+    let settings = RoomSettings { finish: FinishRule::FirstWins, ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    bob.join("summary", "bob").await;
+    let passage = alice.start().await;
+
+    alice.type_passage(&passage, START_TS, 100).await;
+    let moments = bob.expect("the summary", |m| match m { ServerMsg::RaceSummary { moments } => Some(moments), _ => None }).await;
+    assert!(moments.iter().any(|m| matches!(m, Moment::FirstToHalf { id, .. } if *id == alice_id)), "{moments:?}");
+    assert_eq!(moments.last(), Some(&Moment::FinalOrder { ids: vec![alice_id] }));
+}
+
+#[tokio::test]
+async fn friends_are_invited_over_their_socket_or_by_link() {
+    let (addr, signer) = serve_with_sign_in().await;
+    let http = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+    let friends = format!("http://{addr}/api/me/friends");
+    let cookie = |user_id: i64| format!("rracer_session={}", signer.session_token(user_id));
+    let list = |resp: reqwest::Response| async move { serde_json::from_str::<Vec<Friend>>(&resp.text().await.unwrap()).unwrap() };
+    // Guests have no friends list
+    assert_eq!(http.get(&friends).send().await.unwrap().status(), 401);
+    assert_eq!(http.put(format!("{friends}/1")).header("cookie", cookie(1)).send().await.unwrap().status(), 400);
+
+    let added = list(http.put(format!("{friends}/2")).header("cookie", cookie(1)).send().await.unwrap()).await;
+    assert_eq!(added, [Friend { user_id: 2, display_name: None, online: false }]);
+    let mut bob = Client::connect_signed_in(addr, &signer, 2).await;
+    let listed = list(http.get(&friends).header("cookie", cookie(1)).send().await.unwrap()).await;
+    assert!(listed[0].online, "{listed:?}");
+
+    // An online friend hears of it at once; the link joins the room for anyone who didn't
+    let body = serde_json::to_string(&InviteRequest { room: "friendly".to_string() }).unwrap();
+    let invite = |user_id: i64| http.post(format!("{friends}/{user_id}/invite")).header("cookie", cookie(1)).header("content-type", "application/json").body(body.clone()).send();
+    let sent: Invite = serde_json::from_str(&invite(2).await.unwrap().text().await.unwrap()).unwrap();
+    assert!(sent.delivered);
+    let (from, room) = bob.expect("the invite", |m| match m { ServerMsg::Invite { from, room, .. } => Some((from, room)), _ => None }).await;
+    assert_eq!((from, room.as_str()), (1, "friendly"));
+    let followed = http.get(&sent.url).send().await.unwrap();
+    assert_eq!(followed.headers()["location"], "/r/friendly");
+    let forged = http.get(sent.url.replace("friendly", "elsewhere")).send().await.unwrap();
+    assert_eq!(forged.headers()["location"], "/");
+    // Only friends can be invited
+    assert_eq!(invite(3).await.unwrap().status(), 404);
+
+    let removed = list(http.delete(format!("{friends}/2")).header("cookie", cookie(1)).send().await.unwrap()).await;
+    assert!(removed.is_empty());
+    assert_eq!(invite(2).await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn admins_remove_and_ban_players() {
+    let mut state = AppState::offline().await;
+    state.admin_token = Some(Arc::new("moderator".to_string()));
+    let addr = serve_state(state).await;
+    let admin = reqwest::Client::new();
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    alice.join("moderated", "alice").await;
+    let bob_id = bob.join("moderated", "bob").await;
+
+    let remove = format!("http://{addr}/api/admin/rooms/moderated/players/{bob_id}/remove");
+    assert_eq!(admin.post(&remove).send().await.unwrap().status(), 401);
+    assert_eq!(admin.post(&remove).bearer_auth("moderator").send().await.unwrap().status(), 204);
+    bob.expect("the removal", |m| matches!(m, ServerMsg::Error { code: ErrorCode::Removed, .. }).then_some(())).await;
+    let roster = alice.expect("bob gone", |m| match m { ServerMsg::Lobby { players, .. } => Some(players), _ => None }).await;
+    assert_eq!(roster.len(), 1);
+
+    // Removed isn't banned: Bob can come back, until the ban
+    let bob_id = bob.join("moderated", "bob").await;
+    let body = serde_json::to_string(&BanRequest { player_id: Some(bob_id), reason: Some("pasting".into()), ..BanRequest::default() }).unwrap();
+    let resp = admin.post(format!("http://{addr}/api/admin/bans")).bearer_auth("moderator").header("content-type", "application/json").body(body).send().await.unwrap();
+    let bans: Vec<Ban> = serde_json::from_str(&resp.text().await.unwrap()).unwrap();
+    let targets: Vec<&str> = bans.iter().map(|b| b.target.as_str()).collect();
+    assert!(matches!(targets.as_slice(), [guest, "ip:127.0.0.1"] if guest.starts_with("guest:")), "{bans:?}");
+    bob.expect("the ban", |m| matches!(m, ServerMsg::Error { code: ErrorCode::Banned, .. }).then_some(())).await;
+    assert!(connect_async(format!("ws://{addr}/ws?guest={}", bob.guest)).await.is_err(), "a banned guest can't reconnect");
+    assert!(connect_async(format!("ws://{addr}/ws")).await.is_err(), "nor come back as a new guest");
+
+    for ban in &bans {
+        let unban = format!("http://{addr}/api/admin/bans/{}", ban.target);
+        assert_eq!(admin.delete(&unban).bearer_auth("moderator").send().await.unwrap().status(), 204);
+    }
+    assert!(connect_async(format!("ws://{addr}/ws?guest={}", bob.guest)).await.is_ok());
+}
+
+#[tokio::test]
+async fn relay_teams_hand_off_leg_by_leg() {
+    let addr = serve().await;
+    let mut ann = Client::connect(addr).await;
+    let mut ben = Client::connect(addr).await;
+    let mut cat = Client::connect(addr).await;
+    let ann_id = ann.join("relay", "ann").await;
+    let settings = RoomSettings { min_humans: 3, relay: true, ..RoomSettings::default() };
+    ann.send(&ClientMsg::UpdateSettings { settings }).await;
+    ann.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let ben_id = ben.join("relay", "ben").await;
+    let cat_id = cat.join("relay", "cat").await;
+    let relay = ann.expect("the teams", |m| match m { ServerMsg::RelayTeams { relay } => Some(relay), _ => None }).await;
+    assert_eq!(relay.teams, vec![vec![ann_id.clone(), cat_id.clone()], vec![ben_id.clone()]]);
+    assert_eq!(relay.legs.len(), 2);
+    let passage = ann.expect("Start", |m| match m { ServerMsg::Start { passage, .. } => Some(passage), _ => None }).await;
+    ben.start().await;
+    cat.start().await;
+
+    // Cat's keys don't count until Ann hands off
+    let handoff = relay.legs[0];
+    let (first_leg, second_leg): (String, String) = (passage.chars().take(handoff).collect(), passage.chars().skip(handoff).collect());
+    cat.type_passage(&second_leg, START_TS, 100).await;
+    ann.type_passage(&first_leg, START_TS, 100).await;
+    let next = cat.expect("the handoff", |m| match m { ServerMsg::Handoff { team: 0, leg, id } => Some((leg, id)), _ => None }).await;
+    assert_eq!(next, (1, cat_id.clone()));
+    cat.type_passage(&second_leg, START_TS, 100).await;
+    ann.expect("team one home", |m| matches!(m, ServerMsg::TeamFinish { team: 0, .. }).then_some(())).await;
+    // Ben is a team of one and runs both legs
+    ben.type_passage(&passage, START_TS, 100).await;
+
+    let results = ann.results(&[&ann_id, &cat_id, &ben_id]).await;
+    let wpm = |id: &str| results.iter().find(|r| r.0 == id).map(|r| r.1).unwrap();
+    assert_eq!(wpm(&ann_id), wpm(&cat_id), "{results:?}");
+}
+
+#[tokio::test]
+async fn big_rooms_race_in_heats_and_are_ranked_together() {
+    let addr = serve().await;
+    let mut clients = Vec::new();
+    for _ in 0..4 {
+        clients.push(Client::connect(addr).await);
+    }
+    let mut ids = vec![clients[0].join("heats", "p0").await];
+    let settings = RoomSettings { min_humans: 4, heat_size: Some(2), ..RoomSettings::default() };
+    clients[0].send(&ClientMsg::UpdateSettings { settings }).await;
+    clients[0].expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    for (i, client) in clients.iter_mut().enumerate().skip(1) {
+        ids.push(client.join("heats", &format!("p{i}")).await);
+    }
+    let heats = clients[0].expect("the heats", |m| match m { ServerMsg::Heats { heats } => Some(heats), _ => None }).await;
+    assert_eq!(heats.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2], "{heats:?}");
+
+    // Each racer hears their own heat's race; both heats race the same passage
+    let mut passages = Vec::new();
+    for (client, id) in clients.iter_mut().zip(&ids) {
+        let mine = heats.iter().position(|h| h.contains(id)).unwrap();
+        let passage = client.expect("our heat's start", |m| match m { ServerMsg::Heat { heat, msg } if heat == mine => match *msg { ServerMsg::Start { passage, .. } => Some(passage), _ => None }, _ => None }).await;
+        passages.push(passage);
+    }
+    assert!(passages.iter().all(|p| *p == passages[0]));
+    let passage = passages[0].clone();
+    let [a, b, c, d] = &mut clients[..] else { unreachable!() };
+    tokio::join!(a.type_passage(&passage, START_TS, 100), b.type_passage(&passage, START_TS, 150), c.type_passage(&passage, START_TS, 120), d.type_passage(&passage, START_TS, 200));
+
+    let results = clients[0].expect("everyone's results", |m| match m { ServerMsg::HeatResults { results } => Some(results), _ => None }).await;
+    assert_eq!(results.len(), 4, "{results:?}");
+    assert!(results.windows(2).all(|w| w[0].wpm >= w[1].wpm), "{results:?}");
+    // The fastest typist tops the room, whichever heat they were in
+    assert_eq!(results[0].id, ids[0]);
+    assert_eq!(results[0].heat, heats.iter().position(|h| h.contains(&ids[0])).unwrap());
+    // And is the room's one winner, not one per heat
+    let hall = clients[0].expect("the winner", |m| match m { ServerMsg::Lobby { hall_of_fame, .. } => Some(hall_of_fame), _ => None }).await;
+    assert_eq!(hall.recent_winners.iter().map(|w| w.name.as_str()).collect::<Vec<_>>(), ["p0"]);
+}
+
+#[tokio::test]
+async fn last_place_is_eliminated_until_one_is_left() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("elimination", "alice").await;
+    let settings = RoomSettings { finish: FinishRule::Elimination, ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let bob_id = bob.join("elimination", "bob").await;
+    let passage = alice.start().await;
+    let secs_left = alice.expect("the elimination timer", |m| match m { ServerMsg::EliminationIn { secs_left } => Some(secs_left), _ => None }).await;
+    assert_eq!(secs_left, FinishRule::ELIMINATION_SECS);
+
+    // Bob never types, so he is last when time runs out and Alice is left standing
+    alice.type_passage(&passage.chars().take(5).collect::<String>(), START_TS, 200).await;
+    sleep(Duration::from_secs(u64::from(FinishRule::ELIMINATION_SECS))).await;
+    let out = alice.expect("an elimination", |m| match m { ServerMsg::Eliminated { id } => Some(id), _ => None }).await;
+    assert_eq!(out, bob_id);
+    let results = alice.results(&[&alice_id]).await;
+    assert!(results[0].1 > 0.0, "{results:?}");
+}
+
+#[tokio::test]
+async fn golf_is_won_on_fewest_errors() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("golf", "alice").await;
+    let settings = RoomSettings { scoring: Scoring::Golf, ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let bob_id = bob.join("golf", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+
+    // Alice is quicker but slips twice; Bob is slow and clean
+    for ts in [START_TS, START_TS + 100] {
+        alice.send(&ClientMsg::Key { ch: '~', ts }).await;
+    }
+    alice.type_passage(&passage, START_TS + 200, 100).await;
+    let (wpm, raw_wpm, time) = alice.expect("alice's finish", |m| match m { ServerMsg::Finish { id, wpm, raw_wpm, time, .. } if id == alice_id => Some((wpm, raw_wpm, time)), _ => None }).await;
+    // Her slips count toward her raw speed but not her net one
+    assert!(raw_wpm > wpm && time > 0.0, "{wpm} {raw_wpm} {time}");
+    bob.type_passage(&passage, START_TS, 150).await;
+    let errors = bob.expect("bob's finish", |m| match m { ServerMsg::Finish { id, errors, .. } if id == bob_id => Some(errors), _ => None }).await;
+    assert_eq!(errors, 0);
+    let hall = alice.expect("the winner", |m| match m { ServerMsg::Lobby { hall_of_fame, .. } if !hall_of_fame.recent_winners.is_empty() => Some(hall_of_fame), _ => None }).await;
+    assert_eq!(hall.recent_winners[0].name, "bob");
+}
+
+#[tokio::test]
+async fn golf_doesnt_take_a_clients_word_for_its_errors() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("forged-golf", "alice").await;
+    let settings = RoomSettings { scoring: Scoring::Golf, max_words: Some(RoomSettings::MIN_WORDS), ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let bob_id = bob.join("forged-golf", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+
+    // Bob gets to the end on progress alone and claims a clean run first
+    let secs = bob.progress_passage(&passage).await;
+    bob.send(&ClientMsg::Finish { wpm: 60.0, accuracy: 100.0, time: secs, ts: 0, missed: Vec::new(), errors: 0 }).await;
+    let errors = bob.expect("bob's finish", |m| match m { ServerMsg::Finish { id, errors, .. } if id == bob_id => Some(errors), _ => None }).await;
+    assert_eq!(errors, 0);
+    // Alice slips once, and the server sees it
+    alice.send(&ClientMsg::Key { ch: '~', ts: START_TS }).await;
+    alice.type_passage(&passage, START_TS + 200, 150).await;
+    let errors = alice.expect("alice's finish", |m| match m { ServerMsg::Finish { id, errors, .. } if id == alice_id => Some(errors), _ => None }).await;
+    assert_eq!(errors, 1);
+    let hall = alice.expect("the winner", |m| match m { ServerMsg::Lobby { hall_of_fame, .. } if !hall_of_fame.recent_winners.is_empty() => Some(hall_of_fame), _ => None }).await;
+    assert_eq!(hall.recent_winners[0].name, "alice");
+}
+
+#[tokio::test]
+async fn hosts_can_race_just_the_first_words() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("short", "alice").await;
+    let settings = RoomSettings { max_words: Some(RoomSettings::MIN_WORDS), ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    let bob_id = bob.join("short", "bob").await;
+    let passage = alice.start().await;
+    assert_eq!(passage.split_whitespace().count(), RoomSettings::MIN_WORDS as usize, "{passage}");
+
+    // Typing the shortened passage is the whole race
+    tokio::join!(alice.type_passage(&passage, START_TS, 100), bob.type_passage(&passage, START_TS, 150));
+    assert_eq!(alice.results(&[&alice_id, &bob_id]).await.len(), 2);
+}
+
+#[tokio::test]
+async fn passages_are_drawn_and_cut_for_the_field() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    alice.join("sized", "alice").await;
+    let settings = RoomSettings { length: PassageLength::Racers, ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    bob.join("sized", "bob").await;
+    let passage = alice.start().await;
+    // Two racers get a passage of at least their hundred chars, cut at the end of the word it reaches
+    let target = 2 * PassageLength::WORDS_PER_RACER * 5;
+    let len = passage.chars().count();
+    assert!((target..target + 20).contains(&len), "{len}: {passage}");
+    assert!(!passage.ends_with(char::is_whitespace));
+}
+
+#[tokio::test]
+async fn the_host_pauses_a_pausable_race() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("paused", "alice").await;
+    alice.send(&ClientMsg::UpdateSettings { settings: RoomSettings { pausable: true, ..RoomSettings::default() } }).await;
+    alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    bob.join("paused", "bob").await;
+    alice.expect("Countdown", |m| matches!(m, ServerMsg::Countdown { .. }).then_some(())).await;
+    let (passage, t0) = alice.expect("Start", |m| match m { ServerMsg::Start { passage, t0, .. } => Some((passage, t0)), _ => None }).await;
+    let chars: Vec<char> = passage.chars().collect();
+    alice.type_passage(&chars[..3].iter().collect::<String>(), START_TS, 100).await;
+    alice.expect("alice's progress", |m| matches!(m, ServerMsg::Progress { ref id, pos: 3 } if *id == alice_id).then_some(())).await;
+
+    bob.send(&ClientMsg::Pause).await;
+    bob.expect("a refusal", |m| matches!(m, ServerMsg::Error { code: ErrorCode::NotHost, .. }).then_some(())).await;
+    alice.send(&ClientMsg::Pause).await;
+    alice.expect("the pause", |m| matches!(m, ServerMsg::Paused { ref by } if *by == alice_id).then_some(())).await;
+    // Keys while paused don't count
+    alice.send(&ClientMsg::Key { ch: chars[3], ts: START_TS + 1000 }).await;
+    sleep(Duration::from_millis(300)).await;
+    alice.send(&ClientMsg::Resume).await;
+    let resumed = alice.expect("the resume", |m| match m { ServerMsg::Resumed { t0 } => Some(t0), _ => None }).await;
+    assert!(resumed >= t0 + 300, "{resumed} vs {t0}");
+    alice.send(&ClientMsg::Key { ch: chars[3], ts: START_TS + 2000 }).await;
+    let pos = alice.expect("alice's progress", |m| match m { ServerMsg::Progress { id, pos } if id == alice_id => Some(pos), _ => None }).await;
+    assert_eq!(pos, 4);
+}
+
+#[tokio::test]
+async fn the_round_trip_is_reported_after_each_ping() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    // The first ping goes out on connect, and the socket answers it as we read
+    alice.join("latency", "alice").await;
+    let rtt_ms = loop {
+        if let ServerMsg::Latency { rtt_ms } = alice.recv_any().await {
+            break rtt_ms;
+        }
+    };
+    assert!(rtt_ms < 1_000, "{rtt_ms}");
+}
+
+#[tokio::test]
+async fn names_are_checked_on_join() {
+    let addr = serve().await;
+    let mut client = Client::connect(addr).await;
+    for (name, refused) in [("Bot 3", ErrorCode::NameReserved), ("Turbo Tina", ErrorCode::NameReserved), ("a-very-long-name-indeed", ErrorCode::NameTooLong)] {
+        client.send(&ClientMsg::Join { room: "names".to_string(), name: name.to_string() }).await;
+        assert!(matches!(client.recv().await, ServerMsg::Error { code, .. } if code == refused), "{name}");
+    }
+    client.send(&ClientMsg::Join { room: "names".to_string(), name: " Ada\u{200B}  Lovelace ".to_string() }).await;
+    assert!(matches!(client.recv().await, ServerMsg::Joined { name, .. } if name == "Ada Lovelace"));
+}
+
+#[tokio::test]
+async fn only_the_host_changes_settings() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    alice.join("settings", "alice").await;
+    // Room for a third racer, so Bob joining doesn't start a countdown that locks the settings
+    let settings = RoomSettings { countdown_secs: 5, min_humans: 3, ..RoomSettings::default() };
+    alice.send(&ClientMsg::UpdateSettings { settings }).await;
+    let applied = alice.expect("the new settings", |m| match m { ServerMsg::Settings { settings } => Some(settings), _ => None }).await;
+    assert_eq!(applied, settings);
+
+    bob.join("settings", "bob").await;
+    bob.send(&ClientMsg::UpdateSettings { settings: RoomSettings::default() }).await;
+    let code = bob.expect("an error", |m| match m { ServerMsg::Error { code, .. } => Some(code), _ => None }).await;
+    assert_eq!(code, ErrorCode::NotHost);
+}
+
+#[tokio::test]
+async fn new_rooms_get_a_code_and_a_join_link() {
+    let addr = serve().await;
+    let created: CreatedRoom = serde_json::from_str(&reqwest::get(format!("http://{addr}/api/rooms/new")).await.unwrap().text().await.unwrap()).unwrap();
+    let words: Vec<&str> = created.room.split('-').collect();
+    assert!(matches!(words[..], [adjective, animal, number] if !adjective.is_empty() && !animal.is_empty() && number.parse::<u8>().is_ok()), "{}", created.room);
+    assert_eq!(created.join_url, format!("http://{addr}/r/{}", created.room));
+}
+
+#[tokio::test]
+async fn passage_records_are_kept_and_announced() {
+    let addr = serve().await;
+    let mut alice = Client::connect(addr).await;
+    let mut bob = Client::connect(addr).await;
+    let alice_id = alice.join("records", "alice").await;
+    let bob_id = bob.join("records", "bob").await;
+    let passage = alice.start().await;
+    bob.start().await;
+    alice.type_passage(&passage, START_TS, 150).await;
+    bob.type_passage(&passage, START_TS, 200).await;
+    alice.results(&[alice_id.as_str(), bob_id.as_str()]).await;
+
+    // The rematch reruns the passage, now with alice's record on it
+    alice.send(&ClientMsg::RematchAccept).await;
+    bob.send(&ClientMsg::RematchAccept).await;
+    let record = bob.expect("the rematch countdown", |m| match m { ServerMsg::Countdown { record, .. } => Some(record), _ => None }).await;
+    assert_eq!(record.map(|r| r.name), Some("alice".to_string()));
+    bob.expect("the rematch start", |m| matches!(m, ServerMsg::Start { .. }).then_some(())).await;
+    // Key stamps carry on from the first race
+    bob.type_passage(&passage, START_TS + 100_000, 100).await;
+    let (record, previous) = alice.expect("a new record", |m| match m { ServerMsg::NewRecord { record, previous } => Some((record, previous)), _ => None }).await;
+    assert_eq!((record.name.as_str(), previous.name.as_str()), ("bob", "alice"));
+    assert!(record.wpm > previous.wpm);
+
+    let records: Vec<PassageRecord> = serde_json::from_str(&reqwest::get(format!("http://{addr}/api/records")).await.unwrap().text().await.unwrap()).unwrap();
+    assert_eq!(records, vec![PassageRecord { passage, record }]);
+}
+
+#[tokio::test]
+async fn practice_drills_work_on_what_was_missed() {
+    let addr = serve().await;
+    let url = format!("http://{addr}/api/me/drill");
+    let first: PracticeDrill = serde_json::from_str(&reqwest::get(&url).await.unwrap().text().await.unwrap()).unwrap();
+    assert_eq!(first.focus, Weaknesses::default());
+    assert_eq!(first.passage.split(' ').count(), DRILL_WORDS);
+
+    // "through" missed twice, at its 'h' and its 'g'
+    let report = PracticeReport { passage: "walk through the quiet town".to_string(), missed: vec![6, 10, 99_999] };
+    let resp = reqwest::Client::new().post(&url).header("content-type", "application/json").body(serde_json::to_string(&report).unwrap()).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let next: PracticeDrill = serde_json::from_str(&resp.text().await.unwrap()).unwrap();
+    assert_eq!(next.focus, Weaknesses { bigrams: vec!["th".to_string(), "ug".to_string()], words: vec!["through".to_string()] });
+    // Common words with those pairs in them may bring it up again
+    assert!(next.passage.split(' ').filter(|w| *w == "through").count() >= 2, "{}", next.passage);
+}
+
+#[tokio::test]
+async fn passing_a_lesson_unlocks_the_next() {
+    let addr = serve().await;
+    let guest = Client::connect(addr).await.guest;
+    let course: Vec<Lesson> = serde_json::from_str(&reqwest::get(format!("http://{addr}/api/lessons")).await.unwrap().text().await.unwrap()).unwrap();
+    assert_eq!(course, lessons::course());
+    let attempt = |lesson: &str, guest: &str, wpm: f64, accuracy: f64| {
+        let body = serde_json::to_string(&LessonAttempt { wpm, accuracy }).unwrap();
+        reqwest::Client::new().post(format!("http://{addr}/api/me/lessons/{lesson}?guest={guest}")).header("content-type", "application/json").body(body).send()
+    };
+    let progress = |guest: String| async move {
+        serde_json::from_str::<Vec<LessonProgress>>(&reqwest::get(format!("http://{addr}/api/me/lessons?guest={guest}")).await.unwrap().text().await.unwrap()).unwrap()
+    };
+    assert!(progress(guest.clone()).await.is_empty());
+
+    let first = &course[0];
+    assert_eq!(attempt(&first.id, &guest, first.min_wpm - 1.0, 100.0).await.unwrap().status(), 200);
+    assert_eq!(lessons::unlocked(&course, &progress(guest.clone()).await), 1);
+    assert_eq!(attempt(&first.id, &guest, first.min_wpm, first.min_accuracy).await.unwrap().status(), 200);
+    let mine = progress(guest.clone()).await;
+    assert_eq!(mine, vec![LessonProgress { lesson: first.id.clone(), best_wpm: first.min_wpm, best_accuracy: 100.0, passed: true, attempts: 2 }]);
+    assert_eq!(lessons::unlocked(&course, &mine), 2);
+
+    assert_eq!(attempt("no-such-lesson", &guest, 30.0, 100.0).await.unwrap().status(), 404);
+    assert_eq!(attempt(&first.id, &guest, 30.0, 101.0).await.unwrap().status(), 400);
+    assert_eq!(attempt(&first.id, "forged", 30.0, 100.0).await.unwrap().status(), 401);
+}
+
+#[tokio::test]
+async fn result_cards_render_as_png_and_svg() {
+    let addr = serve().await;
+    let card = |query: &str| reqwest::get(format!("http://{addr}/api/card?name=%3Calice%3E&wpm=112.4&accuracy=97.26{query}"));
+    let png = card("&rank=2&racers=5").await.unwrap();
+    assert_eq!(png.status(), 200);
+    assert_eq!(png.headers()["content-type"], "image/png");
+    let png = png.bytes().await.unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    // Width and height open the IHDR chunk
+    assert_eq!((u32::from_be_bytes(png[16..20].try_into().unwrap()), u32::from_be_bytes(png[20..24].try_into().unwrap())), (1200, 630));
+
+    let svg = card("&rank=2&racers=5&source=%E2%80%94%20Jane%20Austen&format=svg").await.unwrap();
+    assert_eq!(svg.headers()["content-type"], "image/svg+xml");
+    let svg = svg.text().await.unwrap();
+    for drawn in ["&lt;alice&gt;", "112", "97.3%", "2nd", "of 5", "— Jane Austen"] {
+        assert!(svg.contains(drawn), "{drawn} missing from {svg}");
+    }
+    for bad in ["&rank=6&racers=5", "&rank=1", "&format=gif"] {
+        assert_eq!(card(bad).await.unwrap().status(), 400, "{bad}");
+    }
+    assert_eq!(reqwest::get(format!("http://{addr}/api/card?name=x&wpm=9000&accuracy=100")).await.unwrap().status(), 400);
+}
+
+#[tokio::test]
+async fn the_passage_pool_pages_by_cursor() {
+    let addr = serve().await;
+    let page = |query: &str| reqwest::get(format!("http://{addr}/api/passages?{query}"));
+    let first: Page<PassageInfo> = serde_json::from_str(&page("limit=2").await.unwrap().text().await.unwrap()).unwrap();
+    assert_eq!(first.items.iter().map(|p| p.id).collect::<Vec<_>>(), [0, 1]);
+    let next: Page<PassageInfo> = serde_json::from_str(&page(&format!("limit=2&cursor={}", first.next_cursor.unwrap())).await.unwrap().text().await.unwrap()).unwrap();
+    assert_eq!(next.items[0].id, 2);
+    assert_eq!(page("cursor=-1").await.unwrap().status(), 400);
+}
+
+#[tokio::test]
+async fn probes_report_health_and_build() {
+    let addr = serve().await;
+    let get = |path: &str| reqwest::get(format!("http://{addr}{path}"));
+    assert_eq!(get("/healthz").await.unwrap().status(), 200);
+
+    let mut alice = Client::connect(addr).await;
+    alice.join("probed", "alice").await;
+    let ready = get("/readyz").await.unwrap();
+    assert_eq!(ready.status(), 200);
+    let ready: Readiness = serde_json::from_str(&ready.text().await.unwrap()).unwrap();
+    assert!(ready.ready);
+    assert_eq!((ready.database, ready.tick_loop, ready.rooms), (CheckStatus::Disabled, CheckStatus::Ok, 1));
+
+    let build: BuildInfo = serde_json::from_str(&get("/api/build-info").await.unwrap().text().await.unwrap()).unwrap();
+    assert_eq!(build.protocol_version, PROTOCOL_VERSION);
+    assert_eq!(build.version, env!("CARGO_PKG_VERSION"));
+}
+
+#[tokio::test]
+async fn a_panic_stays_in_its_task_and_is_counted() {
+    let addr = serve().await;
+    assert_eq!(crate::panics::guard("test", async { 7 }).await, Some(7));
+    assert_eq!(crate::panics::guard("test", async { panic!("boom") }).await, None::<()>);
+    let metrics = reqwest::get(format!("http://{addr}/metrics")).await.unwrap().text().await.unwrap();
+    let panics: u64 = metrics.lines().find_map(|l| l.strip_prefix("rracer_panics_total ")).unwrap().parse().unwrap();
+    assert!(panics >= 1, "{metrics}");
+
+    // The server carries on
+    let mut alice = Client::connect(addr).await;
+    alice.join("after-panic", "alice").await;
+}
@@ -201,9 +201,9 @@ ingest_file() {
   rb="$(rustup_bin_dir || true)"
   log "Ingesting passages from $file"
   if [[ -n "${rb}" ]]; then
-    PATH="${rb}:$PATH" cargo run -p server --bin ingest -- --file "$file"
+    PATH="${rb}:$PATH" cargo run -p server -- ingest --file "$file"
   else
-    cargo run -p server --bin ingest -- --file "$file"
+    cargo run -p server -- ingest --file "$file"
   fi
 }
 
@@ -0,0 +1,550 @@
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{GamePhase, RoomRecord, RoomSettings};
+use crate::weakness::Weaknesses;
+
+/// Default and maximum page sizes for paginated endpoints
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 200;
+
+/// A page of results with an opaque keyset cursor for the next page (None when exhausted)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorCode {
+    BadRequest,
+    Unauthorized,
+    NotFound,
+    Unavailable,
+    Internal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiError {
+    pub code: ApiErrorCode,
+    pub message: String,
+}
+
+/// Every non-2xx API response body is wrapped as `{ "error": { code, message } }`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorEnvelope {
+    pub error: ApiError,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PercentileResponse {
+    pub wpm: f64,
+    /// Share of this season's racers (0-100) slower than `wpm`
+    pub percentile: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReplaySample {
+    /// Server timestamp in ms
+    pub t: u64,
+    pub pos: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RaceResult {
+    pub id: i64,
+    pub room: String,
+    pub player_name: String,
+    pub wpm: f64,
+    pub accuracy: f64,
+    pub time_secs: Option<f64>,
+    /// Milliseconds since the Unix epoch
+    pub finished_at: i64,
+    /// Passage length in chars; unknown for results stored before it was recorded
+    #[serde(default)]
+    pub passage_len: Option<usize>,
+    /// Steadiness of the per-second pace, 0-100; unknown for older results
+    #[serde(default)]
+    pub consistency: Option<f64>,
+    /// Fastest single second of the race
+    #[serde(default)]
+    pub burst_wpm: Option<f64>,
+    /// WPM scaled by accuracy
+    #[serde(default)]
+    pub adjusted_wpm: f64,
+    /// WPM credited for the passage's difficulty; unknown for older results
+    #[serde(default)]
+    pub normalized_wpm: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<Vec<ReplaySample>>,
+    /// Whether the race passed the checks for the global leaderboard (see `FinishCheck`);
+    /// false for results stored before it was recorded
+    #[serde(default)]
+    pub verified: bool,
+}
+
+/// What the server saw of a finish, to tell whether it counts towards the global
+/// leaderboard. Races that don't are still stored, marked unverified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FinishCheck {
+    /// The server saw the racer get to the end of the passage, by keys or by progress, at a
+    /// pace it found plausible all the way
+    pub reached_end: bool,
+    /// The client's clock and the server's disagree on the race, as heavy lag (or a forged
+    /// clock) leaves them
+    pub lagged: bool,
+    /// In chars
+    pub passage_len: usize,
+    pub signed_in: bool,
+}
+
+impl FinishCheck {
+    /// Passages shorter than this many chars are over too quickly for a race on them to count.
+    pub const MIN_CHARS: usize = PassageCategory::SHORT_MAX_CHARS;
+
+    pub fn verified(&self) -> bool {
+        self.reached_end && !self.lagged && self.passage_len >= Self::MIN_CHARS && self.signed_in
+    }
+}
+
+/// Passage buckets by length, for filtering history and comparing like with like
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PassageCategory {
+    Short,
+    Medium,
+    Long,
+}
+
+impl PassageCategory {
+    /// Passages shorter than this many chars are short, and medium below twice that.
+    pub const SHORT_MAX_CHARS: usize = 100;
+
+    pub fn from_len(chars: usize) -> Self {
+        match chars {
+            n if n < Self::SHORT_MAX_CHARS => Self::Short,
+            n if n < 2 * Self::SHORT_MAX_CHARS => Self::Medium,
+            _ => Self::Long,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Medium => "medium",
+            Self::Long => "long",
+        }
+    }
+
+    /// Char-length range `[min, max)` covered by this bucket.
+    pub fn len_range(self) -> (usize, usize) {
+        match self {
+            Self::Short => (0, Self::SHORT_MAX_CHARS),
+            Self::Medium => (Self::SHORT_MAX_CHARS, 2 * Self::SHORT_MAX_CHARS),
+            Self::Long => (2 * Self::SHORT_MAX_CHARS, usize::MAX),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardEntry {
+    pub player_name: String,
+    pub best_wpm: f64,
+    /// Best difficulty-normalized WPM; None if every race predates it
+    #[serde(default)]
+    pub best_normalized_wpm: Option<f64>,
+    pub races: i64,
+}
+
+/// The fastest finish on one passage
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PassageRecord {
+    pub passage: String,
+    pub record: RoomRecord,
+}
+
+/// Races on a pool passage before it can be retired.
+pub const RETIRE_AFTER_RACES: u32 = 20;
+
+/// Share of racers leaving a passage mid-race that retires it.
+pub const RETIRE_ABANDON_RATE: f64 = 0.5;
+
+/// Average finishing accuracy, in percent, below which a passage is retired.
+pub const RETIRE_BELOW_ACCURACY: f64 = 80.0;
+
+/// How racers get on with one pool passage. Passages most of them walk out of, or that few
+/// can type accurately, are retired from rotation until an admin restores them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PassageHealth {
+    /// Hex SHA-256 of the passage text, as in the admin restore path
+    pub key: String,
+    pub passage: String,
+    pub finishes: u32,
+    /// Humans who left the room mid-race
+    pub abandons: u32,
+    /// Average accuracy of the finishes; 100 before the first
+    pub accuracy: f64,
+    pub retired: bool,
+}
+
+impl PassageHealth {
+    pub fn new(key: String, passage: String) -> Self {
+        Self { key, passage, finishes: 0, abandons: 0, accuracy: 100.0, retired: false }
+    }
+
+    pub fn races(&self) -> u32 {
+        self.finishes + self.abandons
+    }
+
+    pub fn abandon_rate(&self) -> f64 {
+        if self.races() == 0 { 0.0 } else { f64::from(self.abandons) / f64::from(self.races()) }
+    }
+
+    pub fn finish(&mut self, accuracy: f64) {
+        self.accuracy = (self.accuracy * f64::from(self.finishes) + accuracy.clamp(0.0, 100.0)) / f64::from(self.finishes + 1);
+        self.finishes += 1;
+    }
+
+    pub fn abandon(&mut self) {
+        self.abandons += 1;
+    }
+
+    /// Whether enough races have gone badly enough to take the passage out of rotation.
+    pub fn should_retire(&self) -> bool {
+        self.races() >= RETIRE_AFTER_RACES && (self.abandon_rate() > RETIRE_ABANDON_RATE || self.accuracy < RETIRE_BELOW_ACCURACY)
+    }
+
+    /// Back in rotation with a clean slate, so old races don't retire it again straight away.
+    pub fn restore(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.key), std::mem::take(&mut self.passage));
+    }
+}
+
+/// A ban on an identity (`user:{id}` or `guest:{id}`) or an address (`ip:{addr}`)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Ban {
+    pub target: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Milliseconds since the Unix epoch
+    pub banned_at: i64,
+}
+
+/// Body of `POST /api/admin/bans`: a connected player by id, which bans their account (or, for
+/// a guest, their guest id and address), an address outright, or both
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BanRequest {
+    #[serde(default)]
+    pub player_id: Option<String>,
+    #[serde(default)]
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// What a room's anti-cheat checks caught
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FlagKind {
+    /// Keys arriving in a burst no hand types
+    Paste,
+    /// Keys adding up to an impossible speed
+    KeySpeed,
+    /// A progress report further on than the time since the last allows
+    ProgressJump,
+}
+
+/// One anti-cheat check a racer failed, for admins to review
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CheatFlag {
+    /// Milliseconds since the Unix epoch
+    pub at: i64,
+    pub room: String,
+    pub player_id: String,
+    pub name: String,
+    /// `user:{id}` or `guest:{id}`, when the racer has one
+    #[serde(default)]
+    pub identity: Option<String>,
+    pub kind: FlagKind,
+    /// Gross WPM at the time, for the speed checks
+    #[serde(default)]
+    pub wpm: Option<f64>,
+}
+
+/// What the leaderboard ranks players by
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardOrder {
+    /// Raw WPM
+    #[default]
+    Wpm,
+    /// WPM normalized for passage difficulty, fairer across passages
+    Normalized,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomSummary {
+    pub id: String,
+    pub state: GamePhase,
+    pub humans: usize,
+    pub bots: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PassageInfo {
+    pub id: i64,
+    pub text: String,
+    pub source_url: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateRoomRequest {
+    /// Room to create or reuse; a room code ("brave-otter-42") is generated when omitted
+    pub room: Option<String>,
+    /// Settings to apply, e.g. a longer countdown for streamed races
+    #[serde(default)]
+    pub settings: Option<RoomSettings>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatedRoom {
+    pub room: String,
+    pub join_url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RacerStatus {
+    pub id: String,
+    pub name: String,
+    pub is_bot: bool,
+    pub position: usize,
+    pub finished: bool,
+    pub wpm: Option<f64>,
+    pub accuracy: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomStatus {
+    pub room: String,
+    pub state: GamePhase,
+    /// Passage length in characters (0 when no race is set up)
+    pub passage_len: usize,
+    pub racers: Vec<RacerStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RaceStanding {
+    pub rank: usize,
+    pub name: String,
+    pub is_bot: bool,
+    pub wpm: f64,
+    pub accuracy: f64,
+    #[serde(default)]
+    pub normalized_wpm: f64,
+}
+
+/// Stats for the caller's own identity (account or guest token)
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Profile {
+    /// Consecutive days, up to today or yesterday, with at least one finished race
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub xp: u64,
+    pub level: u32,
+}
+
+/// A practice passage built around the caller's most-missed bigrams and words
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PracticeDrill {
+    pub passage: String,
+    /// Empty until the caller has missed something
+    pub focus: Weaknesses,
+}
+
+/// A finished practice passage and where its mistakes fell
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PracticeReport {
+    pub passage: String,
+    /// Char positions where a wrong key was pressed, as in `Cursor::missed`
+    pub missed: Vec<usize>,
+}
+
+/// The caller's bests on one lesson of the course
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LessonProgress {
+    pub lesson: String,
+    pub best_wpm: f64,
+    pub best_accuracy: f64,
+    /// Some attempt reached the lesson's pass mark
+    pub passed: bool,
+    pub attempts: u32,
+}
+
+/// One finished attempt at a lesson
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LessonAttempt {
+    pub wpm: f64,
+    pub accuracy: f64,
+}
+
+/// The signed-in account behind the current session
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Identity {
+    pub user_id: i64,
+    pub display_name: String,
+    /// OAuth provider the account signed in with, e.g. `github`
+    pub provider: String,
+}
+
+/// An account on the signed-in account's friends list
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Friend {
+    pub user_id: i64,
+    /// None when the server has no database to look the account up in
+    pub display_name: Option<String>,
+    /// Connected to the server right now, so an invite reaches them at once
+    pub online: bool,
+}
+
+/// A room to invite a friend to race in
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct InviteRequest {
+    pub room: String,
+}
+
+/// An invite made: pushed to the friend as `ServerMsg::Invite` if they are online, and a link
+/// to pass on to them if not
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Invite {
+    pub room: String,
+    /// `/invite/{code}`, which joins the room until the invite expires
+    pub url: String,
+    /// Reached at least one of the friend's open connections
+    pub delivered: bool,
+}
+
+/// `/healthz`: the process is up and serving requests
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Liveness {
+    pub status: String,
+    pub uptime_secs: u64,
+}
+
+/// State of one dependency checked by `/readyz`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Down,
+    /// Not configured, which the server runs fine without
+    Disabled,
+}
+
+/// `/readyz`: whether the server can take traffic, and why not
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Readiness {
+    /// True when no check is down
+    pub ready: bool,
+    pub database: CheckStatus,
+    /// The server's background timers are firing on time
+    pub tick_loop: CheckStatus,
+    /// Rooms currently open
+    pub rooms: usize,
+}
+
+/// What is running: crate version, the commit it was built from, and the WebSocket
+/// protocol version it speaks
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BuildInfo {
+    pub version: String,
+    /// Unknown for builds made outside a git checkout without `GIT_SHA` set
+    pub git_sha: Option<String>,
+    pub protocol_version: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health() -> PassageHealth {
+        PassageHealth::new("key".into(), "text".into())
+    }
+
+    #[test]
+    fn a_passage_most_racers_walk_out_of_is_retired() {
+        let mut health = health();
+        for _ in 0..RETIRE_AFTER_RACES / 2 - 1 {
+            health.finish(98.0);
+        }
+        for _ in 0..RETIRE_AFTER_RACES / 2 + 1 {
+            health.abandon();
+        }
+        assert!(health.should_retire());
+        health.restore();
+        assert_eq!(health.races(), 0);
+        assert!(!health.should_retire());
+    }
+
+    #[test]
+    fn only_finished_signed_in_races_on_long_enough_passages_are_verified() {
+        let check = FinishCheck { reached_end: true, lagged: false, passage_len: FinishCheck::MIN_CHARS, signed_in: true };
+        assert!(check.verified());
+        assert!(!FinishCheck { reached_end: false, ..check }.verified());
+        assert!(!FinishCheck { lagged: true, ..check }.verified());
+        assert!(!FinishCheck { passage_len: FinishCheck::MIN_CHARS - 1, ..check }.verified());
+        assert!(!FinishCheck { signed_in: false, ..check }.verified());
+    }
+
+    #[test]
+    fn a_passage_few_type_accurately_is_retired() {
+        let mut health = health();
+        for _ in 1..RETIRE_AFTER_RACES {
+            health.finish(70.0);
+        }
+        // Too few races yet to judge
+        assert!(!health.should_retire());
+        health.finish(70.0);
+        assert_eq!(health.accuracy, 70.0);
+        assert!(health.should_retire());
+    }
+}
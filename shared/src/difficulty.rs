@@ -0,0 +1,161 @@
+//! How hard a passage is to type, from the text alone: long words, punctuation and shifted
+//! chars, and letter pairs that are rare in English all slow typists down. Scores are
+//! comparable across passages of any length.
+
+use serde::{Deserialize, Serialize};
+
+/// The letter pairs that make up most English text, commonest first.
+const COMMON_BIGRAMS: [&str; 40] = [
+    "th", "he", "in", "er", "an", "re", "on", "at", "en", "nd", "ti", "es", "or", "te", "of", "ed", "is", "it", "al", "ar",
+    "st", "to", "nt", "ng", "se", "ha", "as", "ou", "io", "le", "ve", "co", "me", "de", "hi", "ri", "ro", "ic", "ne", "ea",
+];
+
+/// Measurements behind a passage's difficulty
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Difficulty {
+    /// 0 (plain lowercase text of short words) to 100
+    pub score: f64,
+    /// Mean chars per whitespace-separated word
+    pub avg_word_len: f64,
+    /// Share of chars that are neither letters, digits nor spaces, or need Shift
+    pub symbol_density: f64,
+    /// Share of adjacent letter pairs outside `COMMON_BIGRAMS`
+    pub rare_bigram_share: f64,
+}
+
+/// Difficulty buckets for filtering
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DifficultyLevel {
+    Easy,
+    Moderate,
+    Hard,
+}
+
+impl DifficultyLevel {
+    /// Scores below this are easy; most everyday prose lands here.
+    pub const MODERATE_FROM: f64 = 30.0;
+    /// Scores from this up are hard: code, symbol drills and the like.
+    pub const HARD_FROM: f64 = 60.0;
+
+    pub fn from_score(score: f64) -> Self {
+        match score {
+            s if s < Self::MODERATE_FROM => Self::Easy,
+            s if s < Self::HARD_FROM => Self::Moderate,
+            _ => Self::Hard,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Moderate => "moderate",
+            Self::Hard => "hard",
+        }
+    }
+}
+
+/// Score of everyday prose, at which normalized WPM is the raw WPM.
+pub const REFERENCE_SCORE: f64 = 20.0;
+
+/// Share of a race's WPM credited per point of score above `REFERENCE_SCORE` (and taken off
+/// per point below it), so a passage scoring 100 is worth 40% more than everyday prose.
+const WPM_CREDIT_PER_POINT: f64 = 0.005;
+
+impl Difficulty {
+    pub fn level(&self) -> DifficultyLevel {
+        DifficultyLevel::from_score(self.score)
+    }
+
+    /// `wpm` on this passage as if it had been everyday prose, for comparing races on
+    /// different passages.
+    pub fn normalize_wpm(&self, wpm: f64) -> f64 {
+        wpm.max(0.0) * (1.0 + (self.score - REFERENCE_SCORE) * WPM_CREDIT_PER_POINT)
+    }
+}
+
+/// Score `text`. Each measure is scaled from what plain prose shows to where it stops getting
+/// harder, then weighted: word length and symbols count for 35 points each, rare pairs for 30.
+pub fn estimate(text: &str) -> Difficulty {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return Difficulty::default();
+    }
+    let avg_word_len = words.iter().map(|w| w.chars().count()).sum::<usize>() as f64 / words.len() as f64;
+
+    let typed = text.chars().filter(|c| !c.is_whitespace()).count().max(1);
+    let symbols = text.chars().filter(|c| !c.is_whitespace() && (c.is_uppercase() || !c.is_alphanumeric())).count();
+    let symbol_density = symbols as f64 / typed as f64;
+
+    let (mut pairs, mut rare) = (0usize, 0usize);
+    for word in &words {
+        let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).flat_map(char::to_lowercase).collect();
+        for pair in letters.windows(2) {
+            pairs += 1;
+            let bigram: String = pair.iter().collect();
+            if !COMMON_BIGRAMS.contains(&bigram.as_str()) {
+                rare += 1;
+            }
+        }
+    }
+    let rare_bigram_share = if pairs == 0 { 0.0 } else { rare as f64 / pairs as f64 };
+
+    let scaled = |value: f64, plain: f64, hardest: f64| ((value - plain) / (hardest - plain)).clamp(0.0, 1.0);
+    let score = 35.0 * scaled(avg_word_len, 3.5, 8.0) + 35.0 * scaled(symbol_density, 0.02, 0.25) + 30.0 * scaled(rare_bigram_share, 0.45, 0.85);
+    Difficulty { score, avg_word_len, symbol_density, rare_bigram_share }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{Drill, DrillKind, PassageGenerator};
+    use crate::passages::PASSAGES;
+
+    #[test]
+    fn plain_prose_is_easier_than_symbols_and_long_words() {
+        let prose = estimate("the cat sat on the mat and then it went to the den");
+        let long = estimate("internationalization considerations notwithstanding, extraordinarily");
+        let symbols = estimate("if (x[i] != y) { return $a->b; } // #todo: 50% @ 3pm");
+        assert_eq!(prose.level(), DifficultyLevel::Easy, "{prose:?}");
+        assert!(long.score > prose.score && symbols.score > prose.score);
+        assert_eq!(symbols.level(), DifficultyLevel::Hard, "{symbols:?}");
+    }
+
+    #[test]
+    fn rare_letter_pairs_count() {
+        let common = estimate("there then the other");
+        let rare = estimate("xylyx zqwpq jvkzf");
+        assert!(rare.rare_bigram_share > common.rare_bigram_share);
+        assert!(rare.score > common.score);
+    }
+
+    #[test]
+    fn length_alone_changes_nothing() {
+        let once = estimate("All happy families are alike.");
+        let thrice = estimate("All happy families are alike. All happy families are alike. All happy families are alike.");
+        assert!((once.score - thrice.score).abs() < 1e-9);
+    }
+
+    #[test]
+    fn harder_passages_earn_more_credit() {
+        let at = |score| Difficulty { score, ..Difficulty::default() };
+        assert_eq!(at(REFERENCE_SCORE).normalize_wpm(80.0), 80.0);
+        assert!((at(100.0).normalize_wpm(80.0) - 112.0).abs() < 1e-9);
+        assert!((at(0.0).normalize_wpm(80.0) - 72.0).abs() < 1e-9);
+        assert_eq!(at(100.0).normalize_wpm(-5.0), 0.0);
+    }
+
+    #[test]
+    fn scores_stay_in_range() {
+        assert_eq!(estimate("   ").score, 0.0);
+        for p in PASSAGES {
+            let d = estimate(p.text);
+            assert!((0.0..=100.0).contains(&d.score), "{d:?}");
+            assert_ne!(d.level(), DifficultyLevel::Hard, "bundled prose rated hard: {}", p.text);
+        }
+        let drill = Drill { kind: DrillKind::Symbols, difficulty: 5 }.generate(1);
+        assert_eq!(estimate(&drill).level(), DifficultyLevel::Hard);
+    }
+}
@@ -5,6 +5,10 @@ pub enum RracerState {
     Waiting,
     Countdown,
     Racing,
+    /// Still racing, but the finish rule has called the end: the rest have a grace period
+    Closing,
+    /// Racing, but stopped by the host: no keys count and the clock is held
+    Paused,
     Finished,
 }
 
@@ -12,7 +16,14 @@ pub enum RracerState {
 pub enum RracerEvent {
     Join,
     CountdownElapsed,
+    /// Enough racers are done for a `FinishRule::TopThree` room to start its grace period
+    PodiumFilled,
+    /// The host stops, or restarts, the race clock in a pausable room
+    Pause,
+    Resume,
     AllDone,
+    /// The last heat of a room raced in heats is over, the room itself having stayed in the lobby
+    HeatsDone,
     Reset,
 }
 
@@ -32,7 +43,11 @@ impl StateMachineImpl for RracerState {
         match (state, input) {
             (RracerState::Waiting, RracerEvent::Join) => Some(RracerState::Countdown),
             (RracerState::Countdown, RracerEvent::CountdownElapsed) => Some(RracerState::Racing),
-            (RracerState::Racing, RracerEvent::AllDone) => Some(RracerState::Finished),
+            (RracerState::Racing, RracerEvent::PodiumFilled) => Some(RracerState::Closing),
+            (RracerState::Racing, RracerEvent::Pause) => Some(RracerState::Paused),
+            (RracerState::Paused, RracerEvent::Resume) => Some(RracerState::Racing),
+            (RracerState::Racing | RracerState::Closing, RracerEvent::AllDone) => Some(RracerState::Finished),
+            (RracerState::Waiting, RracerEvent::HeatsDone) => Some(RracerState::Finished),
             (RracerState::Finished, RracerEvent::Reset) => Some(RracerState::Waiting),
             _ => None,
         }
@@ -0,0 +1,170 @@
+//! Generated practice passages for rooms that drill something other than prose. Generators
+//! are seeded so the same seed gives the same passage on any platform.
+
+use serde::{Deserialize, Serialize};
+
+/// Builds a passage from a seed.
+pub trait PassageGenerator {
+    fn generate(&self, seed: u64) -> String;
+}
+
+/// What a drill passage is made of
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DrillKind {
+    /// Runs of digits, with decimals and signs at higher difficulties
+    Numbers,
+    /// Letters mixed with punctuation and symbols
+    Symbols,
+    /// Programming operators such as `+=`, `->` and `>>=`
+    Operators,
+}
+
+/// A drill at a difficulty from 1 to `MAX_DIFFICULTY`. Higher difficulties make longer
+/// passages out of longer tokens and draw on rarer symbols.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Drill {
+    pub kind: DrillKind,
+    pub difficulty: u8,
+}
+
+impl Drill {
+    pub const MAX_DIFFICULTY: u8 = 5;
+
+    pub fn clamped(self) -> Self {
+        Self { difficulty: self.difficulty.clamp(1, Self::MAX_DIFFICULTY), ..self }
+    }
+
+    /// Tokens in a passage at this difficulty.
+    fn token_count(self) -> usize {
+        8 + 4 * usize::from(self.clamped().difficulty)
+    }
+}
+
+/// Symbols unlocked at each difficulty, commonest first.
+const SYMBOL_TIERS: [&str; 5] = [".,;:!?'\"", "()[]{}<>", "@#$%&*", "+-=_/\\|", "~^`"];
+
+/// Operators unlocked at each difficulty.
+const OPERATOR_TIERS: [&[&str]; 5] = [
+    &["+", "-", "*", "/", "=", "<", ">"],
+    &["==", "!=", "<=", ">=", "&&", "||", "!"],
+    &["->", "=>", "::", "+=", "-=", "*=", "/=", "%"],
+    &["<<", ">>", "..", "..=", "?.", "??", "|>", "&="],
+    &["<<=", ">>=", "^=", "|=", "**", "===", "!=="],
+];
+
+impl PassageGenerator for Drill {
+    fn generate(&self, seed: u64) -> String {
+        let drill = self.clamped();
+        let level = usize::from(drill.difficulty);
+        let mut rng = Rng(seed);
+        let tokens: Vec<String> = (0..drill.token_count())
+            .map(|_| match drill.kind {
+                DrillKind::Numbers => number(&mut rng, level),
+                DrillKind::Symbols => symbol_word(&mut rng, level),
+                DrillKind::Operators => {
+                    let tier = OPERATOR_TIERS[rng.below(level)];
+                    tier[rng.below(tier.len())].to_string()
+                }
+            })
+            .collect();
+        tokens.join(" ")
+    }
+}
+
+/// Up to `level + 1` digits; decimals from level 3, negatives at level 5.
+fn number(rng: &mut Rng, level: usize) -> String {
+    let digits = 1 + rng.below(level + 1);
+    let mut n: String = (0..digits).map(|_| char::from(b'0' + rng.below(10) as u8)).collect();
+    if level >= 3 && digits > 1 && rng.below(3) == 0 {
+        n.insert(1 + rng.below(digits - 1), '.');
+    }
+    if level >= 5 && rng.below(4) == 0 {
+        n.insert(0, '-');
+    }
+    n
+}
+
+/// `3 + level` chars at most, about `level / (level + 3)` of them symbols.
+fn symbol_word(rng: &mut Rng, level: usize) -> String {
+    let symbols: Vec<char> = SYMBOL_TIERS[..level].iter().flat_map(|t| t.chars()).collect();
+    let len = 3 + rng.below(level + 1);
+    (0..len)
+        .map(|_| {
+            if rng.below(level + 3) < level {
+                symbols[rng.below(symbols.len())]
+            } else {
+                char::from(b'a' + rng.below(26) as u8)
+            }
+        })
+        .collect()
+}
+
+/// splitmix64: tiny, seedable and the same on the server and in wasm.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform-enough pick from `0..n`; `n` must be non-zero.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drill(kind: DrillKind, difficulty: u8) -> Drill {
+        Drill { kind, difficulty }
+    }
+
+    #[test]
+    fn same_seed_same_passage() {
+        let d = drill(DrillKind::Symbols, 3);
+        assert_eq!(d.generate(42), d.generate(42));
+        assert_ne!(d.generate(42), d.generate(43));
+    }
+
+    #[test]
+    fn numbers_use_only_digits_and_unlock_decimals_and_signs() {
+        let easy = drill(DrillKind::Numbers, 1).generate(7);
+        assert!(easy.split(' ').all(|t| t.len() <= 2 && t.chars().all(|c| c.is_ascii_digit())), "{easy}");
+        let hard: String = (0..20).map(|s| drill(DrillKind::Numbers, 5).generate(s)).collect();
+        assert!(hard.contains('.') && hard.contains('-'));
+        assert!(hard.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | ' ')));
+    }
+
+    #[test]
+    fn symbols_stay_within_the_unlocked_tiers() {
+        let easy: String = (0..20).map(|s| drill(DrillKind::Symbols, 1).generate(s)).collect();
+        assert!(easy.chars().all(|c| c.is_ascii_lowercase() || c == ' ' || SYMBOL_TIERS[0].contains(c)), "{easy}");
+        let hard: String = (0..20).map(|s| drill(DrillKind::Symbols, 5).generate(s)).collect();
+        assert!(hard.chars().any(|c| SYMBOL_TIERS[4].contains(c)));
+    }
+
+    #[test]
+    fn operators_are_whole_tokens_from_the_unlocked_tiers() {
+        let passage = drill(DrillKind::Operators, 2).generate(3);
+        let allowed: Vec<&str> = OPERATOR_TIERS[..2].iter().flat_map(|t| t.iter().copied()).collect();
+        assert!(passage.split(' ').all(|t| allowed.contains(&t)), "{passage}");
+    }
+
+    #[test]
+    fn harder_drills_are_longer() {
+        let len = |d| drill(DrillKind::Numbers, d).generate(1).split(' ').count();
+        assert!(len(1) < len(3) && len(3) < len(5));
+        // Out-of-range difficulties are clamped rather than panicking
+        assert_eq!(len(0), len(1));
+        assert_eq!(len(200), len(5));
+    }
+}
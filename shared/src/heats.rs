@@ -0,0 +1,89 @@
+//! Heats: a room with more humans than its `RoomSettings::heat_size` races them in several
+//! smaller races at once, all on the same passage, and ranks everyone together once the last
+//! heat is over.
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::Scoring;
+
+/// One racer's finish in one of the room's heats
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HeatResult {
+    /// From 0, as in `ServerMsg::Heats`
+    pub heat: usize,
+    pub id: String,
+    pub name: String,
+    pub wpm: f64,
+    pub raw_wpm: f64,
+    pub accuracy: f64,
+    pub errors: usize,
+    /// Seconds from the heat's start
+    pub time: f64,
+    pub consistency: f64,
+    /// The server counted `errors` itself, as in `ServerMsg::Finish`
+    pub counted: bool,
+}
+
+/// Split `racers` (id, average WPM if known) into the fewest heats of at most `size`, dealt
+/// fastest first back and forth across the heats so each gets about as many and as fast.
+pub fn draw(racers: &[(String, Option<f64>)], size: usize) -> Vec<Vec<String>> {
+    let count = racers.len().div_ceil(size.max(1));
+    let mut order: Vec<&(String, Option<f64>)> = racers.iter().collect();
+    // Stable, so racers with no average yet go last in the order given
+    order.sort_by(|a, b| b.1.unwrap_or(f64::MIN).total_cmp(&a.1.unwrap_or(f64::MIN)));
+    let mut heats = vec![Vec::new(); count];
+    for (i, (id, _)) in order.into_iter().enumerate() {
+        let across = i % count;
+        let heat = if (i / count).is_multiple_of(2) { across } else { count - 1 - across };
+        heats[heat].push(id.clone());
+    }
+    heats
+}
+
+/// Every heat's finishers together, best first: by WPM, or under golf by fewest errors, those
+/// the server counted first, and then WPM. The heats race the same passage, so their speeds compare.
+pub fn ranked(mut results: Vec<HeatResult>, scoring: Scoring) -> Vec<HeatResult> {
+    let golf = scoring == Scoring::Golf;
+    results.sort_by(|a, b| (if golf { (!a.counted, a.errors).cmp(&(!b.counted, b.errors)) } else { std::cmp::Ordering::Equal }).then(b.wpm.total_cmp(&a.wpm)));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn racers(speeds: &[Option<f64>]) -> Vec<(String, Option<f64>)> {
+        speeds.iter().enumerate().map(|(i, wpm)| (format!("p{i}"), *wpm)).collect()
+    }
+
+    fn result(id: &str, wpm: f64, errors: usize) -> HeatResult {
+        HeatResult { heat: 0, id: id.to_string(), name: id.to_string(), wpm, raw_wpm: wpm, accuracy: 100.0, errors, time: 30.0, consistency: 80.0, counted: true }
+    }
+
+    #[test]
+    fn heats_are_as_even_as_they_can_be() {
+        let heats = draw(&racers(&[None; 7]), 3);
+        assert_eq!(heats.iter().map(Vec::len).collect::<Vec<_>>(), [3, 2, 2]);
+        assert_eq!(draw(&racers(&[None; 4]), 4).len(), 1);
+        assert!(draw(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn the_fastest_are_dealt_across_the_heats() {
+        // p1 is fastest, then p3, p0 and p2; nobody knows how fast p4 is
+        let heats = draw(&racers(&[Some(60.0), Some(90.0), Some(40.0), Some(80.0), None]), 3);
+        assert_eq!(heats, vec![vec!["p1".to_string(), "p2".to_string(), "p4".to_string()], vec!["p3".to_string(), "p0".to_string()]]);
+    }
+
+    #[test]
+    fn everyone_is_ranked_together() {
+        let results = vec![result("a", 70.0, 0), result("b", 90.0, 3), result("c", 80.0, 0)];
+        let ids = |rs: Vec<HeatResult>| rs.into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids(ranked(results.clone(), Scoring::Speed)), ["b", "c", "a"]);
+        assert_eq!(ids(ranked(results.clone(), Scoring::Golf)), ["c", "a", "b"]);
+        // A clean run on the client's word alone goes behind the errors the server counted
+        let unchecked = HeatResult { counted: false, ..result("d", 99.0, 0) };
+        assert_eq!(ids(ranked([results, vec![unchecked]].concat(), Scoring::Golf)), ["c", "a", "b", "d"]);
+    }
+}
@@ -0,0 +1,101 @@
+//! The typing course: lessons in a fixed order, each bringing in a few keys on top of the
+//! ones before. A lesson is passed by typing its text at its pass mark, which unlocks the
+//! next one.
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{LessonAttempt, LessonProgress};
+
+/// One lesson of the course
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Lesson {
+    pub id: String,
+    pub title: String,
+    /// Keys this lesson brings in
+    pub keys: String,
+    pub text: String,
+    /// Pass mark: at least this WPM at this accuracy (0-100) in the same attempt
+    pub min_wpm: f64,
+    pub min_accuracy: f64,
+}
+
+impl Lesson {
+    pub fn passes(&self, wpm: f64, accuracy: f64) -> bool {
+        wpm >= self.min_wpm && accuracy >= self.min_accuracy
+    }
+}
+
+/// (id, title, keys, text, min WPM, min accuracy), in course order.
+const COURSE: [(&str, &str, &str, &str, f64, f64); 5] = [
+    ("home-row", "Home row", "asdfghjkl;", "as sad lad; a flask falls; dad asks a lass; all fads; glad gals ask dad; hall flags; a salad; ska lads", 15.0, 90.0),
+    ("top-row", "Top row", "qwertyuiop", "we were quite pleased with our trip; your typewriter sits quietly; poetry opposite the tower; the upper row is yours", 18.0, 90.0),
+    ("bottom-row", "Bottom row", "zxcvbnm,./", "men can move boxes to the zoo. carve a bench, cover the cabin. my cozy van is next to a big bank.", 20.0, 90.0),
+    ("capitals", "Capitals", "Shift", "Paris and Rome are in Europe. Ada met Tom in May. The Nile, the Amazon and the Thames all run to the sea.", 22.0, 92.0),
+    ("punctuation", "Punctuation", "!?:'\"()-", "Wait! Is it ready? Yes: the tea, the cake and the jam. \"Fine,\" she said; (quietly) it's done - at last.", 22.0, 92.0),
+];
+
+/// Every lesson, first to last.
+pub fn course() -> Vec<Lesson> {
+    COURSE
+        .iter()
+        .map(|&(id, title, keys, text, min_wpm, min_accuracy)| Lesson { id: id.to_string(), title: title.to_string(), keys: keys.to_string(), text: text.to_string(), min_wpm, min_accuracy })
+        .collect()
+}
+
+/// How many lessons from the start of `course` are open: the first, and each one after a
+/// passed lesson.
+pub fn unlocked(course: &[Lesson], progress: &[LessonProgress]) -> usize {
+    let passed = |lesson: &Lesson| progress.iter().any(|p| p.lesson == lesson.id && p.passed);
+    (1 + course.iter().take_while(|l| passed(l)).count()).min(course.len())
+}
+
+/// Fold an attempt at `lesson` into `progress`, keeping the bests.
+pub fn record(progress: &mut Vec<LessonProgress>, lesson: &Lesson, attempt: &LessonAttempt) {
+    let passed = lesson.passes(attempt.wpm, attempt.accuracy);
+    match progress.iter_mut().find(|p| p.lesson == lesson.id) {
+        Some(p) => {
+            p.best_wpm = p.best_wpm.max(attempt.wpm);
+            p.best_accuracy = p.best_accuracy.max(attempt.accuracy);
+            p.passed |= passed;
+            p.attempts += 1;
+        }
+        None => progress.push(LessonProgress { lesson: lesson.id.clone(), best_wpm: attempt.wpm, best_accuracy: attempt.accuracy, passed, attempts: 1 }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_row_lessons_only_use_keys_already_taught() {
+        let course = course();
+        let mut taught = String::from(" ");
+        for lesson in course.iter().take(3) {
+            taught.push_str(&lesson.keys);
+            assert!(lesson.text.chars().all(|c| taught.contains(c)), "{} uses keys not yet taught", lesson.id);
+        }
+    }
+
+    #[test]
+    fn passing_a_lesson_unlocks_the_next() {
+        let course = course();
+        let mut progress = Vec::new();
+        assert_eq!(unlocked(&course, &progress), 1);
+        // Fast but sloppy, then accurate but slow: the bests add up, but no single attempt passes
+        record(&mut progress, &course[0], &LessonAttempt { wpm: 40.0, accuracy: 80.0 });
+        record(&mut progress, &course[0], &LessonAttempt { wpm: 10.0, accuracy: 100.0 });
+        assert_eq!((progress[0].best_wpm, progress[0].best_accuracy, progress[0].passed, progress[0].attempts), (40.0, 100.0, false, 2));
+        assert_eq!(unlocked(&course, &progress), 1);
+        record(&mut progress, &course[0], &LessonAttempt { wpm: 15.0, accuracy: 90.0 });
+        assert_eq!(unlocked(&course, &progress), 2);
+        // Passing out of order opens nothing past the first gap
+        record(&mut progress, &course[2], &LessonAttempt { wpm: 60.0, accuracy: 100.0 });
+        assert_eq!(unlocked(&course, &progress), 2);
+        for lesson in &course {
+            record(&mut progress, lesson, &LessonAttempt { wpm: 60.0, accuracy: 100.0 });
+        }
+        assert_eq!(unlocked(&course, &progress), course.len());
+    }
+}
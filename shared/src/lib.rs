@@ -1,4 +1,18 @@
+pub mod api;
+pub mod difficulty;
 pub mod fsm;
+pub mod generate;
+pub mod heats;
+pub mod lessons;
+pub mod names;
 pub mod passages;
 pub mod protocol;
+pub mod relay;
+pub mod streak;
+pub mod text;
+pub mod timeline;
+pub mod typing;
+pub mod weakness;
+pub mod words;
 pub mod wpm;
+pub mod xp;
@@ -0,0 +1,79 @@
+//! Player names as the server accepts them on Join. Whitespace is tidied and characters that
+//! draw nothing (control codes, zero-width and direction-override characters) are dropped,
+//! so two names that look the same are the same. Names that pass for a house bot or a pace
+//! car are refused, so nobody can pose as one on the track.
+
+use crate::protocol::ErrorCode;
+
+/// Longest name accepted, in chars, after cleaning.
+pub const MAX_NAME_CHARS: usize = 20;
+
+/// What a racer who gives no name (or only invisible characters) is called.
+pub const DEFAULT_NAME: &str = "Player";
+
+/// The name `raw` joins under, or why it can't be used (`NameTooLong` or `NameReserved`).
+pub fn clean(raw: &str) -> Result<String, ErrorCode> {
+    let visible: String = raw.chars().map(|c| if c.is_whitespace() { ' ' } else { c }).filter(|&c| !is_hidden(c)).collect();
+    let name = visible.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Ok(DEFAULT_NAME.to_string());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(ErrorCode::NameTooLong);
+    }
+    if passes_for_a_bot(&name) {
+        return Err(ErrorCode::NameReserved);
+    }
+    Ok(name)
+}
+
+/// Control characters, plus the format and filler characters that render as nothing: soft
+/// hyphens, zero-width spaces and joiners, bidi marks and overrides, the BOM, Hangul fillers.
+fn is_hidden(c: char) -> bool {
+    c.is_control()
+        || matches!(
+            c,
+            '\u{00AD}' | '\u{034F}' | '\u{061C}' | '\u{115F}' | '\u{1160}' | '\u{17B4}' | '\u{17B5}' | '\u{180E}'
+                | '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{206F}' | '\u{3164}' | '\u{FEFF}' | '\u{FFA0}'
+        )
+}
+
+/// "Bot", "Bot 3", "bot_7" or "Pace 60 WPM"; "Bottle" and "Pacer" are fine.
+fn passes_for_a_bot(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    if let Some(rest) = lower.strip_prefix("bot") {
+        return rest.chars().next().is_none_or(|c| !c.is_alphabetic());
+    }
+    lower.strip_prefix("pace").is_some_and(|rest| rest.trim_start().starts_with(|c: char| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_tidied() {
+        assert_eq!(clean("  Ada \t Lovelace "), Ok("Ada Lovelace".to_string()));
+        assert_eq!(clean("A\u{200B}da\u{202E}"), Ok("Ada".to_string()));
+        assert_eq!(clean("\u{200B}\u{FEFF} "), Ok(DEFAULT_NAME.to_string()));
+        // Emoji and accents are welcome
+        assert_eq!(clean("Zoë 🚀"), Ok("Zoë 🚀".to_string()));
+    }
+
+    #[test]
+    fn long_names_are_refused_after_cleaning() {
+        let longest = "x".repeat(MAX_NAME_CHARS);
+        assert_eq!(clean(&format!("{longest}\u{200B}\u{200B}")), Ok(longest.clone()));
+        assert_eq!(clean(&format!("{longest}y")), Err(ErrorCode::NameTooLong));
+    }
+
+    #[test]
+    fn bots_and_pace_cars_cant_be_impersonated() {
+        for name in ["Bot", "Bot 3", "bot_7", "BOT-X", "Pace 60 WPM", "pace80", "B\u{200B}ot 1"] {
+            assert_eq!(clean(name), Err(ErrorCode::NameReserved), "{name}");
+        }
+        for name in ["Bottle", "Botany Bay", "Pacer", "Robot 3"] {
+            assert!(clean(name).is_ok(), "{name}");
+        }
+    }
+}
@@ -1,25 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+/// Who wrote a passage and where it comes from; any part may be unknown
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Attribution {
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The work it is taken from
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Where the text was found, usually a URL
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl Attribution {
+    /// Credit line such as "— Herman Melville, Moby Dick"; None without an author or title.
+    pub fn byline(&self) -> Option<String> {
+        let parts: Vec<&str> = [self.author.as_deref(), self.title.as_deref()].into_iter().flatten().collect();
+        (!parts.is_empty()).then(|| format!("— {}", parts.join(", ")))
+    }
+}
+
+/// A race text with its attribution, when known
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Passage {
+    pub text: String,
+    pub attribution: Option<Attribution>,
+}
+
+/// One entry of the built-in list
+pub struct StaticPassage {
+    pub text: &'static str,
+    pub author: Option<&'static str>,
+    pub title: Option<&'static str>,
+}
+
+impl StaticPassage {
+    pub fn to_passage(&self) -> Passage {
+        let attribution = (self.author.is_some() || self.title.is_some()).then(|| Attribution {
+            author: self.author.map(str::to_string),
+            title: self.title.map(str::to_string),
+            source: None,
+        });
+        Passage { text: self.text.to_string(), attribution }
+    }
+}
+
 /// Static passages for typing races
-pub const PASSAGES: &[&str] = &[
-    "The quick brown fox jumps over the lazy dog. This pangram contains every letter of the alphabet at least once.",
-    "To be or not to be, that is the question: Whether 'tis nobler in the mind to suffer the slings and arrows of outrageous fortune.",
-    "In the beginning was the Word, and the Word was with God, and the Word was God.",
-    "It was the best of times, it was the worst of times, it was the age of wisdom, it was the age of foolishness.",
-    "Call me Ishmael. Some years ago-never mind how long precisely-having little or no money in my purse.",
-    "All happy families are alike; each unhappy family is unhappy in its own way.",
-    "The only way to do great work is to love what you do. If you haven't found it yet, keep looking.",
-    "Programming is not about typing, it's about thinking. The keyboard is just the interface between your thoughts and the computer.",
-    "Rust empowers everyone to build reliable and efficient software. It prevents segfaults and guarantees thread safety.",
-    "WebAssembly is a binary instruction format for a stack-based virtual machine designed as a portable compilation target.",
-    "In a hole in the ground there lived a hobbit. Not a nasty, dirty, wet hole filled with the ends of worms and an oozy smell.",
-    "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.",
-    "Space: the final frontier. These are the voyages of the starship Enterprise, to boldly go where no one has gone before.",
-    "Two roads diverged in a yellow wood, and sorry I could not travel both and be one traveler, long I stood.",
-    "The best time to plant a tree was 20 years ago. The second best time is now. Every moment is a fresh beginning.",
-    "Success is not final, failure is not fatal: it is the courage to continue that counts. Never give up on your dreams.",
-    "Life is what happens to you while you're busy making other plans. The journey of a thousand miles begins with one step.",
-    "Yesterday is history, tomorrow is a mystery, today is a gift. That's why they call it the present moment.",
-    "The only impossible journey is the one you never begin. Believe you can and you're halfway there to success.",
-    "In the middle of difficulty lies opportunity. Every problem is a gift without the wrapping paper of solutions."
+pub const PASSAGES: &[StaticPassage] = &[
+    StaticPassage {
+        text: "The quick brown fox jumps over the lazy dog. This pangram contains every letter of the alphabet at least once.",
+        author: None,
+        title: None,
+    },
+    StaticPassage {
+        text: "To be or not to be, that is the question: Whether 'tis nobler in the mind to suffer the slings and arrows of outrageous fortune.",
+        author: Some("William Shakespeare"),
+        title: Some("Hamlet"),
+    },
+    StaticPassage {
+        text: "In the beginning was the Word, and the Word was with God, and the Word was God.",
+        author: None,
+        title: Some("The Gospel of John"),
+    },
+    StaticPassage {
+        text: "It was the best of times, it was the worst of times, it was the age of wisdom, it was the age of foolishness.",
+        author: Some("Charles Dickens"),
+        title: Some("A Tale of Two Cities"),
+    },
+    StaticPassage {
+        text: "Call me Ishmael. Some years ago-never mind how long precisely-having little or no money in my purse.",
+        author: Some("Herman Melville"),
+        title: Some("Moby Dick"),
+    },
+    StaticPassage {
+        text: "All happy families are alike; each unhappy family is unhappy in its own way.",
+        author: Some("Leo Tolstoy"),
+        title: Some("Anna Karenina"),
+    },
+    StaticPassage {
+        text: "The only way to do great work is to love what you do. If you haven't found it yet, keep looking.",
+        author: Some("Steve Jobs"),
+        title: Some("Stanford commencement address"),
+    },
+    StaticPassage {
+        text: "Programming is not about typing, it's about thinking. The keyboard is just the interface between your thoughts and the computer.",
+        author: None,
+        title: None,
+    },
+    StaticPassage {
+        text: "Rust empowers everyone to build reliable and efficient software. It prevents segfaults and guarantees thread safety.",
+        author: None,
+        title: None,
+    },
+    StaticPassage {
+        text: "WebAssembly is a binary instruction format for a stack-based virtual machine designed as a portable compilation target.",
+        author: None,
+        title: None,
+    },
+    StaticPassage {
+        text: "In a hole in the ground there lived a hobbit. Not a nasty, dirty, wet hole filled with the ends of worms and an oozy smell.",
+        author: Some("J. R. R. Tolkien"),
+        title: Some("The Hobbit"),
+    },
+    StaticPassage {
+        text: "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.",
+        author: Some("Jane Austen"),
+        title: Some("Pride and Prejudice"),
+    },
+    StaticPassage {
+        text: "Space: the final frontier. These are the voyages of the starship Enterprise, to boldly go where no one has gone before.",
+        author: Some("Gene Roddenberry"),
+        title: Some("Star Trek"),
+    },
+    StaticPassage {
+        text: "Two roads diverged in a yellow wood, and sorry I could not travel both and be one traveler, long I stood.",
+        author: Some("Robert Frost"),
+        title: Some("The Road Not Taken"),
+    },
+    StaticPassage {
+        text: "The best time to plant a tree was 20 years ago. The second best time is now. Every moment is a fresh beginning.",
+        author: None,
+        title: None,
+    },
+    StaticPassage {
+        text: "Success is not final, failure is not fatal: it is the courage to continue that counts. Never give up on your dreams.",
+        author: Some("Winston Churchill"),
+        title: None,
+    },
+    StaticPassage {
+        text: "Life is what happens to you while you're busy making other plans. The journey of a thousand miles begins with one step.",
+        author: Some("John Lennon"),
+        title: Some("Beautiful Boy"),
+    },
+    StaticPassage {
+        text: "Yesterday is history, tomorrow is a mystery, today is a gift. That's why they call it the present moment.",
+        author: None,
+        title: None,
+    },
+    StaticPassage {
+        text: "The only impossible journey is the one you never begin. Believe you can and you're halfway there to success.",
+        author: None,
+        title: None,
+    },
+    StaticPassage {
+        text: "In the middle of difficulty lies opportunity. Every problem is a gift without the wrapping paper of solutions.",
+        author: None,
+        title: None,
+    },
 ];
 
 /// Get a random passage for typing practice
@@ -36,12 +165,12 @@ pub fn get_random_passage() -> &'static str {
         .hash(&mut hasher);
     
     let index = (hasher.finish() as usize) % PASSAGES.len();
-    PASSAGES[index]
+    PASSAGES[index].text
 }
 
 /// Get passage by index (for deterministic testing)
 pub fn get_passage_by_index(index: usize) -> Option<&'static str> {
-    PASSAGES.get(index).copied()
+    PASSAGES.get(index).map(|p| p.text)
 }
 
 #[cfg(test)]
@@ -64,6 +193,16 @@ mod tests {
     fn test_random_passage() {
         let passage = get_random_passage();
         assert!(!passage.is_empty());
-        assert!(PASSAGES.contains(&passage));
+        assert!(PASSAGES.iter().any(|p| p.text == passage));
+    }
+
+    #[test]
+    fn byline_joins_what_is_known() {
+        let melville = PASSAGES.iter().find(|p| p.author == Some("Herman Melville")).unwrap().to_passage();
+        assert_eq!(melville.attribution.unwrap().byline().as_deref(), Some("— Herman Melville, Moby Dick"));
+        let author_only = Attribution { author: Some("Winston Churchill".into()), ..Attribution::default() };
+        assert_eq!(author_only.byline().as_deref(), Some("— Winston Churchill"));
+        assert_eq!(Attribution { source: Some("https://example.com".into()), ..Attribution::default() }.byline(), None);
+        assert_eq!(PASSAGES[0].to_passage().attribution, None);
     }
 }
@@ -1,23 +1,666 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::api::PassageCategory;
+use crate::fsm::RracerState;
+use crate::generate::Drill;
+use crate::heats::HeatResult;
+use crate::passages::Attribution;
+use crate::relay::Relay;
+use crate::timeline::Moment;
+use crate::words::Words;
+use crate::typing::ErrorMode;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum ClientMsg {
     Join { room: String, name: String },
+    // Watch a room's races without a lane; answered with a Sync snapshot
+    Spectate { room: String },
     Key { ch: char, ts: u64 },
     Progress { pos: usize, ts: u64 },
-    Finish { wpm: f64, accuracy: f64, time: f64, ts: u64 },
+    Finish {
+        wpm: f64,
+        accuracy: f64,
+        time: f64,
+        ts: u64,
+        // Char positions where a wrong key was pressed, for practice drills
+        #[serde(default)]
+        missed: Vec<usize>,
+        // Wrong key presses over the race, for golf scoring
+        #[serde(default)]
+        errors: usize,
+    },
     Reset,
+    // After a race: propose, or agree to, racing the same passage again with the same players
+    RematchAccept,
+    // Race against a server-driven pace car at this WPM from the next race (None to disable)
+    SetPace { wpm: Option<f64> },
+    // Host only, between races; the server clamps values and answers with ServerMsg::Settings
+    UpdateSettings { settings: RoomSettings },
+    // Leave the room (or stop watching it) but keep the connection for joining another
+    Leave,
+    // Also follow another room's races while in this one, e.g. a featured race from the lobby;
+    // answered with its Sync, and everything it sends arrives wrapped in ServerMsg::Watched
+    Watch { room: String },
+    Unwatch { room: String },
+    // Host only, in pausable rooms: stop the race clock until Resume; answered with
+    // ServerMsg::Paused and ServerMsg::Resumed
+    Pause,
+    Resume,
+    // Ask again for the current room's Sync, e.g. when our passage no longer matches its hash
+    Resync,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum ServerMsg {
-    Lobby { players: Vec<String> },
+    // Sent only to the joining socket; `name` may differ from the requested one if it was taken
+    Joined { id: String, name: String },
+    Lobby {
+        players: Vec<PlayerInfo>,
+        #[serde(default)]
+        hall_of_fame: HallOfFame,
+        // Set while exactly two humans are in the room
+        #[serde(default)]
+        duel: Option<DuelRecord>,
+    },
+    // A player left the room (or was taken out of it), ahead of the Lobby without them, so
+    // their lane can go straight away
+    PlayerLeft { id: String },
     // Sent when countdown starts so clients can render the passage instantly
-    Countdown { passage: String },
-    Start { passage: String, t0: u64 },
+    Countdown {
+        passage: String,
+        #[serde(default)]
+        attribution: Option<Attribution>,
+        // Fastest finish on this passage in any room; generated texts have none
+        #[serde(default)]
+        record: Option<RoomRecord>,
+        // text::passage_hash of `passage`, for the client to check its copy against
+        #[serde(default)]
+        hash: u32,
+    },
+    Start {
+        passage: String,
+        t0: u64,
+        #[serde(default)]
+        attribution: Option<Attribution>,
+        #[serde(default)]
+        hash: u32,
+    },
+    // `id` is the player id from the Lobby roster, never the display name
     Progress { id: String, pos: usize },
-    Finish { id: String, wpm: f64, accuracy: f64 },
-    StateChange { state: String },
+    // Player ids from first to last place, on a race tick whenever the order changes
+    Ranks { order: Vec<String> },
+    // Alongside Ranks, for each racer who moved ahead of another since the last order
+    Overtake { overtaker: String, overtaken: String },
+    // `normalized_wpm` credits the passage's difficulty so races on different passages compare;
+    // `raw_wpm` counts every key, right or wrong, `time` is seconds from the start and
+    // `consistency` is wpm::consistency over the racer's per-second pace; `verified` when the
+    // finish counts towards the global leaderboard (api::FinishCheck); `counted` when the
+    // server counted `errors` from the racer's keys rather than taking the client's word
+    Finish {
+        id: String,
+        wpm: f64,
+        accuracy: f64,
+        #[serde(default)]
+        normalized_wpm: f64,
+        #[serde(default)]
+        errors: usize,
+        #[serde(default)]
+        raw_wpm: f64,
+        #[serde(default)]
+        time: f64,
+        #[serde(default)]
+        consistency: f64,
+        #[serde(default)]
+        verified: bool,
+        #[serde(default)]
+        counted: bool,
+    },
+    // Seconds from the start until `id` passed the `checkpoint` percent of the passage
+    Split { id: String, checkpoint: u8, time: f64 },
+    StateChange { state: GamePhase },
     WaitingTimer { seconds_left: u64 },
-    Error { message: String },
+    Error { code: ErrorCode, message: String },
+    // Broadcast whenever the host changes the room settings
+    Settings { settings: RoomSettings },
+    // After each RematchAccept; the rematch starts once every human in the room has accepted
+    RematchProposed { by: String, accepted: Vec<String> },
+    // Wins per player id across a run of rematches, after each race from the second on
+    SeriesScore { wins: HashMap<String, u32> },
+    // Direct, after a finish that beats the player's stored best for this passage length
+    PersonalBest { category: PassageCategory, wpm: f64, previous_wpm: f64 },
+    // A finish in this room that beat the passage's record
+    NewRecord { record: RoomRecord, previous: RoomRecord },
+    // Direct, after each of the player's own finishes
+    XpGained { xp: u32, total_xp: u64, level: u32, leveled_up: bool },
+    // Direct, after each answered WebSocket ping: the smoothed round trip to this socket
+    Latency { rtt_ms: u64 },
+    // Sent once on connect to guests without a valid token; present it as `/ws?guest=<token>` next time
+    GuestToken { token: String },
+    // The room's finish rule has called time: the race ends in `secs_left` seconds
+    RaceEnding { secs_left: u32 },
+    // After Countdown in a relay race: the teams and their legs
+    RelayTeams { relay: Relay },
+    // `id` now types `team`'s leg number `leg`, from where the last runner handed off
+    Handoff { team: usize, leg: usize, id: String },
+    // Under FinishRule::Elimination, at the start and after each elimination: whoever is in
+    // last place in `secs_left` seconds is out
+    EliminationIn { secs_left: u32 },
+    // `id` was in last place when the elimination timer ran out, and is out with no result
+    Eliminated { id: String },
+    // A relay team typed its last leg `time` seconds after the start; each member also gets a
+    // Finish at the team's WPM
+    TeamFinish { team: usize, time: f64, wpm: f64 },
+    // The host stopped the race: keys are ignored and the clock is held until Resumed
+    Paused { by: String },
+    // The race is back on from `t0`, the start moved on by however long it was paused
+    Resumed { t0: u64 },
+    // After the race ends: its notable moments in time order, the final order last
+    RaceSummary { moments: Vec<Moment> },
+    // Racers still going when the finish rule ended the race; they get no result
+    DidNotFinish { ids: Vec<String> },
+    // Sent when it changes: sockets watching without racing, and ids of players who typed
+    // in the last couple of seconds
+    Presence { spectators: usize, typing: Vec<String> },
+    // Direct (non-broadcast) snapshot of the whole room, e.g. right after Join
+    Sync {
+        state: GamePhase,
+        passage: Option<String>,
+        players: Vec<PlayerInfo>,
+        positions: HashMap<String, usize>,
+        t0: Option<u64>,
+        #[serde(default)]
+        settings: RoomSettings,
+        #[serde(default)]
+        attribution: Option<Attribution>,
+        #[serde(default)]
+        record: Option<RoomRecord>,
+        // Boxed, as the biggest part of the biggest message
+        #[serde(default)]
+        relay: Option<Box<Relay>>,
+        // text::passage_hash of `passage`, when there is one
+        #[serde(default)]
+        hash: Option<u32>,
+    },
+    // A message from a room followed with ClientMsg::Watch rather than the one we're in
+    Watched {
+        room: String,
+        #[cfg_attr(feature = "openapi", schema(no_recursion))]
+        msg: Box<ServerMsg>,
+    },
+    // Instead of a countdown, when there are more humans than the room's heat size: player
+    // ids per heat (`shared::heats`), each heat racing the same passage at once
+    Heats { heats: Vec<Vec<String>> },
+    // A message from heat number `heat` of the room's race, as it would come from a room of
+    // its own; clients follow the heat they're in
+    Heat {
+        heat: usize,
+        #[cfg_attr(feature = "openapi", schema(no_recursion))]
+        msg: Box<ServerMsg>,
+    },
+    // After the room switches to finished, once the last heat is over: every heat's
+    // finishers, best first
+    HeatResults { results: Vec<HeatResult> },
+    // Direct, to every open connection of a signed-in account: account `from` (named when
+    // the server has a database) invites us to race in `room`
+    Invite { from: i64, from_name: Option<String>, room: String },
+}
+
+/// A room's phase, as the server reports it in `StateChange`, `Sync` and the room APIs
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GamePhase {
+    #[default]
+    Waiting,
+    Countdown,
+    Racing,
+    Paused,
+    Finished,
+}
+
+impl From<RracerState> for GamePhase {
+    fn from(state: RracerState) -> Self {
+        match state {
+            RracerState::Waiting => GamePhase::Waiting,
+            RracerState::Countdown => GamePhase::Countdown,
+            // Clients keep racing through the grace period
+            RracerState::Racing | RracerState::Closing => GamePhase::Racing,
+            RracerState::Paused => GamePhase::Paused,
+            RracerState::Finished => GamePhase::Finished,
+        }
+    }
+}
+
+/// Bumped whenever a change to `ClientMsg` or `ServerMsg` would break clients built
+/// against the previous version; reported by the server's build-info endpoint.
+pub const PROTOCOL_VERSION: u32 = 15;
+
+/// Longest room name accepted, in chars.
+pub const MAX_ROOM_CHARS: usize = 64;
+
+/// Largest WebSocket message a client may send, in bytes; bigger ones close the connection.
+pub const MAX_CLIENT_MSG_BYTES: usize = 4 * 1024;
+
+/// Passage percentages that get a `ServerMsg::Split`.
+pub const SPLIT_CHECKPOINTS: [u8; 3] = [25, 50, 75];
+
+/// Char position of the `percent` checkpoint in a passage of `len` chars.
+pub fn checkpoint_position(len: usize, percent: u8) -> usize {
+    (len * usize::from(percent) + 50) / 100
+}
+
+/// Lane colours a room hands out, as `PlayerInfo::color`.
+pub const LANE_COLORS: u8 = 8;
+
+/// The lane colour for a newcomer: the first one none of `taken` has, or once every colour is
+/// in use the one their join order `seq` comes round to.
+pub fn lane_color(taken: &[u8], seq: u64) -> u8 {
+    (0..LANE_COLORS).find(|c| !taken.contains(c)).unwrap_or((seq % u64::from(LANE_COLORS)) as u8)
+}
+
+/// (overtaker, overtaken) for every pair of racers whose order flipped between two race
+/// orders, as sent in `ServerMsg::Ranks`. Racers missing from either order are left out.
+pub fn overtakes(before: &[String], after: &[String]) -> Vec<(String, String)> {
+    let place = |id: &String| before.iter().position(|b| b == id);
+    let mut passes = Vec::new();
+    for (i, overtaker) in after.iter().enumerate() {
+        let Some(was) = place(overtaker) else { continue };
+        for overtaken in &after[i + 1..] {
+            if place(overtaken).is_some_and(|p| p < was) {
+                passes.push((overtaker.clone(), overtaken.clone()));
+            }
+        }
+    }
+    passes
+}
+
+/// Per-room options chosen by the host
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomSettings {
+    /// Length of the pre-race countdown
+    pub countdown_secs: u32,
+    /// Humans needed before a countdown starts; 1 allows solo races against bots
+    pub min_humans: u32,
+    /// Seconds a lone human waits before the race starts anyway, filled with bots; None disables
+    #[serde(default)]
+    pub auto_start_secs: Option<u32>,
+    /// What a wrong key press does; see `shared::typing`
+    #[serde(default)]
+    pub error_mode: ErrorMode,
+    /// Lowercase the passage and strip its punctuation (`shared::text::casual`)
+    #[serde(default)]
+    pub casual: bool,
+    /// The host may stop the race clock mid-race (`ClientMsg::Pause`)
+    #[serde(default)]
+    pub pausable: bool,
+    #[serde(default)]
+    pub mode: RaceMode,
+    #[serde(default)]
+    pub finish: FinishRule,
+    /// Race in teams, each typing the passage in legs (`shared::relay`); not with a time limit,
+    /// eliminations or golf scoring
+    #[serde(default)]
+    pub relay: bool,
+    #[serde(default)]
+    pub scoring: Scoring,
+    /// Race only the first this many words of pool passages (`shared::text::first_words`);
+    /// None races them whole
+    #[serde(default)]
+    pub max_words: Option<u32>,
+    /// Race in heats of at most this many humans at once when more are in the room
+    /// (`shared::heats`); None races everyone together
+    #[serde(default)]
+    pub heat_size: Option<u32>,
+    /// Cut pool passages to suit the racers (`PassageLength`), within `max_words`
+    #[serde(default)]
+    pub length: PassageLength,
+}
+
+impl RoomSettings {
+    pub const MAX_COUNTDOWN_SECS: u32 = 30;
+    pub const MAX_MIN_HUMANS: u32 = 8;
+    pub const MIN_AUTO_START_SECS: u32 = 5;
+    pub const MAX_AUTO_START_SECS: u32 = 300;
+    pub const MIN_WORDS: u32 = 5;
+    pub const MAX_WORDS: u32 = 500;
+    pub const MIN_HEAT_SIZE: u32 = 2;
+    pub const MAX_HEAT_SIZE: u32 = 50;
+
+    /// Bring out-of-range values back into what the server supports.
+    pub fn clamped(self) -> Self {
+        Self {
+            countdown_secs: self.countdown_secs.clamp(1, Self::MAX_COUNTDOWN_SECS),
+            min_humans: self.min_humans.clamp(1, Self::MAX_MIN_HUMANS),
+            auto_start_secs: self.auto_start_secs.map(|s| s.clamp(Self::MIN_AUTO_START_SECS, Self::MAX_AUTO_START_SECS)),
+            error_mode: self.error_mode,
+            casual: self.casual,
+            pausable: self.pausable,
+            mode: self.mode.clamped(),
+            finish: self.finish,
+            relay: self.relay && self.mode.time_limit_secs().is_none() && self.finish != FinishRule::Elimination && self.scoring == Scoring::Speed,
+            scoring: self.scoring,
+            max_words: self.max_words.map(|w| w.clamp(Self::MIN_WORDS, Self::MAX_WORDS)),
+            heat_size: self.heat_size.map(|n| n.clamp(Self::MIN_HEAT_SIZE, Self::MAX_HEAT_SIZE)),
+            length: self.length.clamped(),
+        }
+    }
+
+    /// Whether races are run through `shared::text::casual`: only prose is, since a drill's
+    /// capitals and symbols are what it drills.
+    pub fn casual_text(self) -> bool {
+        self.casual && !matches!(self.mode, RaceMode::Drill(_))
+    }
+}
+
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self { countdown_secs: 3, min_humans: 2, auto_start_secs: None, error_mode: ErrorMode::Strict, casual: false, pausable: false, mode: RaceMode::Passages, finish: FinishRule::WaitForAll, relay: false, scoring: Scoring::Speed, max_words: None, heat_size: None, length: PassageLength::Whole }
+    }
+}
+
+/// Where a room's race texts come from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RaceMode {
+    /// The server's passage pool
+    #[default]
+    Passages,
+    /// A freshly generated drill (`shared::generate`)
+    Drill(Drill),
+    /// Random common words (`shared::words`), by count or against the clock
+    Words(Words),
+}
+
+impl RaceMode {
+    fn clamped(self) -> Self {
+        match self {
+            RaceMode::Drill(d) => RaceMode::Drill(d.clamped()),
+            RaceMode::Words(w) => RaceMode::Words(Words { limit: w.limit.clamped(), ..w }),
+            other => other,
+        }
+    }
+
+    /// Seconds after the start at which a timed race ends for everyone still typing.
+    pub fn time_limit_secs(self) -> Option<u32> {
+        match self {
+            RaceMode::Words(w) => w.limit.time_limit_secs(),
+            _ => None,
+        }
+    }
+}
+
+/// When a race ends once racers start crossing the line
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum FinishRule {
+    /// The race runs until every racer is done
+    #[default]
+    WaitForAll,
+    /// Once `PODIUM` racers are done the rest have `GRACE_SECS` to finish
+    TopThree,
+    /// The first finisher wins and the race ends there
+    FirstWins,
+    /// Every `ELIMINATION_SECS` the racer in last place is out, until one is left standing
+    Elimination,
+}
+
+impl FinishRule {
+    pub const PODIUM: usize = 3;
+    pub const GRACE_SECS: u32 = 10;
+    pub const ELIMINATION_SECS: u32 = 20;
+}
+
+/// How finishers are placed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+    /// Fastest first
+    #[default]
+    Speed,
+    /// Fewest errors first, the earlier finish ahead on a tie
+    Golf,
+}
+
+/// How long a room's pool passages are raced
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PassageLength {
+    /// As drawn
+    #[default]
+    Whole,
+    /// `WORDS_PER_RACER` words (of five chars, as WPM counts them) for each racer, so a
+    /// bigger field has longer to spread out
+    Racers,
+    /// About this many seconds for the racers' average typist, or `DEFAULT_WPM` for racers
+    /// nobody has timed yet
+    Seconds(u32),
+}
+
+impl PassageLength {
+    pub const WORDS_PER_RACER: usize = 10;
+    pub const MIN_SECS: u32 = 15;
+    pub const MAX_SECS: u32 = 300;
+    pub const DEFAULT_SECS: u32 = 60;
+    pub const DEFAULT_WPM: f64 = 40.0;
+
+    fn clamped(self) -> Self {
+        match self {
+            PassageLength::Seconds(secs) => PassageLength::Seconds(secs.clamp(Self::MIN_SECS, Self::MAX_SECS)),
+            other => other,
+        }
+    }
+
+    /// Chars to cut a passage to (see `text::first_chars`) for `racers` racers whose average
+    /// WPM is `avg_wpm`, when known; None races it whole. Pool passages are drawn at least
+    /// this long where the pool has them.
+    pub fn target_chars(self, racers: usize, avg_wpm: Option<f64>) -> Option<usize> {
+        match self {
+            PassageLength::Whole => None,
+            PassageLength::Racers => Some(racers.max(1) * Self::WORDS_PER_RACER * 5),
+            PassageLength::Seconds(secs) => Some((avg_wpm.unwrap_or(Self::DEFAULT_WPM) * 5.0 * f64::from(secs) / 60.0).round() as usize),
+        }
+    }
+}
+
+/// One roster entry in ServerMsg::Lobby
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PlayerInfo {
+    pub id: String,
+    pub name: String,
+    pub is_bot: bool,
+    /// The longest-present human in the room
+    pub is_host: bool,
+    /// Expected WPM when known (bots: their configured speed)
+    pub rating: Option<f64>,
+    /// Set on pace cars: the id of the player this car is pacing
+    #[serde(default)]
+    pub pace_for: Option<String>,
+    /// Humans only; see `shared::xp`
+    #[serde(default)]
+    pub level: Option<u32>,
+    /// Lane colour, below `LANE_COLORS`, picked by the room when the player joined so every
+    /// client draws them alike
+    #[serde(default)]
+    pub color: u8,
+}
+
+/// A room's own records, so regular rooms build up a local history
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HallOfFame {
+    /// Latest first, at most `HallOfFame::RECENT_WINNERS`
+    pub recent_winners: Vec<RoomRecord>,
+    /// Fastest winning race the room has seen
+    pub best: Option<RoomRecord>,
+}
+
+impl HallOfFame {
+    pub const RECENT_WINNERS: usize = 5;
+
+    /// Add a race winner, dropping the oldest one beyond the limit.
+    pub fn record(&mut self, winner: RoomRecord) {
+        if self.best.as_ref().is_none_or(|b| winner.wpm > b.wpm) {
+            self.best = Some(winner.clone());
+        }
+        self.recent_winners.insert(0, winner);
+        self.recent_winners.truncate(Self::RECENT_WINNERS);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomRecord {
+    pub name: String,
+    pub wpm: f64,
+    /// Milliseconds since the Unix epoch
+    pub at: i64,
+}
+
+/// Wins between the two humans in a room, by player id, over every race they've had together
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DuelRecord {
+    pub a: String,
+    pub b: String,
+    pub a_wins: u32,
+    pub b_wins: u32,
+}
+
+/// Machine-readable reason attached to ServerMsg::Error; `message` stays human-readable
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum ErrorCode {
+    RoomFull,
+    NameTaken,
+    InvalidState,
+    RateLimited,
+    SuspiciousActivity,
+    InvalidMessage,
+    NotHost,
+    // Join refused: see `crate::names`
+    NameTooLong,
+    NameReserved,
+    NameNotAllowed,
+    // An admin took the player out of the room
+    Removed,
+    // Join refused, or the connection closed, for a ban
+    Banned,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsm::RracerEvent;
+    use rust_fsm::StateMachineImpl;
+
+    #[test]
+    fn passages_are_cut_to_the_field() {
+        assert_eq!(PassageLength::Whole.target_chars(4, Some(80.0)), None);
+        assert_eq!(PassageLength::Racers.target_chars(3, None), Some(150));
+        // A minute at 60 WPM is 60 words of five chars; nobody timed goes at the default
+        assert_eq!(PassageLength::Seconds(60).target_chars(2, Some(60.0)), Some(300));
+        assert_eq!(PassageLength::Seconds(30).target_chars(2, None), Some(100));
+        let settings = RoomSettings { length: PassageLength::Seconds(1), ..RoomSettings::default() }.clamped();
+        assert_eq!(settings.length, PassageLength::Seconds(PassageLength::MIN_SECS));
+    }
+
+    #[test]
+    fn overtakes_pair_up_whoever_swapped_places() {
+        let order = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let pass = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(overtakes(&order(&["a", "b", "c"]), &order(&["a", "b", "c"])), vec![]);
+        assert_eq!(overtakes(&order(&["a", "b", "c"]), &order(&["c", "a", "b"])), vec![pass("c", "a"), pass("c", "b")]);
+        // Newcomers and leavers overtake no one
+        assert_eq!(overtakes(&order(&["a", "b"]), &order(&["d", "b", "a"])), vec![pass("b", "a")]);
+    }
+
+    #[test]
+    fn lane_colors_go_to_whoever_has_none_free_last() {
+        assert_eq!(lane_color(&[], 0), 0);
+        assert_eq!(lane_color(&[0, 1, 3], 5), 2);
+        // A leaver's colour goes to the next newcomer
+        assert_eq!(lane_color(&[1, 2], 7), 0);
+        let all: Vec<u8> = (0..LANE_COLORS).collect();
+        assert_eq!(lane_color(&all, 10), 2);
+    }
+
+    fn win(name: &str, wpm: f64) -> RoomRecord {
+        RoomRecord { name: name.to_string(), wpm, at: 0 }
+    }
+
+    #[test]
+    fn checkpoints_round_to_the_nearest_char() {
+        let positions: Vec<usize> = SPLIT_CHECKPOINTS.iter().map(|&c| checkpoint_position(10, c)).collect();
+        assert_eq!(positions, vec![3, 5, 8]);
+        assert_eq!(checkpoint_position(0, 50), 0);
+    }
+
+    #[test]
+    fn hall_of_fame_keeps_the_latest_winners_and_the_fastest() {
+        let mut hall = HallOfFame::default();
+        hall.record(win("ann", 90.0));
+        for i in 0..HallOfFame::RECENT_WINNERS {
+            hall.record(win(&format!("p{i}"), 60.0));
+        }
+        assert_eq!(hall.recent_winners.len(), HallOfFame::RECENT_WINNERS);
+        assert_eq!(hall.recent_winners[0].name, format!("p{}", HallOfFame::RECENT_WINNERS - 1));
+        assert_eq!(hall.best, Some(win("ann", 90.0)));
+    }
+
+    #[test]
+    fn settings_from_older_clients_wait_for_everyone() {
+        let json = r#"{"countdown_secs":3,"min_humans":2}"#;
+        let settings: RoomSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.finish, FinishRule::WaitForAll);
+        let first = serde_json::to_string(&RoomSettings { finish: FinishRule::FirstWins, ..settings }).unwrap();
+        assert!(first.contains(r#""finish":"first_wins""#), "{first}");
+    }
+
+    #[test]
+    fn drills_are_raced_as_generated_in_casual_rooms() {
+        let casual = RoomSettings { casual: true, ..RoomSettings::default() };
+        assert!(casual.casual_text());
+        let drill = RaceMode::Drill(Drill { kind: crate::generate::DrillKind::Symbols, difficulty: 3 });
+        assert!(!RoomSettings { mode: drill, ..casual }.casual_text());
+    }
+
+    #[test]
+    fn the_grace_period_is_still_racing() {
+        assert_eq!(GamePhase::from(RracerState::Closing), GamePhase::Racing);
+        assert_eq!(RracerState::transition(&RracerState::Racing, &RracerEvent::PodiumFilled), Some(RracerState::Closing));
+        assert_eq!(RracerState::transition(&RracerState::Closing, &RracerEvent::AllDone), Some(RracerState::Finished));
+        assert_eq!(RracerState::transition(&RracerState::Closing, &RracerEvent::PodiumFilled), None);
+    }
+
+    #[test]
+    fn a_room_in_heats_finishes_from_the_lobby() {
+        assert_eq!(RracerState::transition(&RracerState::Waiting, &RracerEvent::HeatsDone), Some(RracerState::Finished));
+        assert_eq!(RracerState::transition(&RracerState::Racing, &RracerEvent::HeatsDone), None);
+    }
+
+    #[test]
+    fn only_a_running_race_pauses() {
+        assert_eq!(RracerState::transition(&RracerState::Racing, &RracerEvent::Pause), Some(RracerState::Paused));
+        assert_eq!(RracerState::transition(&RracerState::Paused, &RracerEvent::Resume), Some(RracerState::Racing));
+        assert_eq!(RracerState::transition(&RracerState::Closing, &RracerEvent::Pause), None);
+        assert_eq!(RracerState::transition(&RracerState::Paused, &RracerEvent::AllDone), None);
+        assert_eq!(GamePhase::from(RracerState::Paused), GamePhase::Paused);
+    }
+
+    #[test]
+    fn phases_keep_their_wire_names() {
+        let json = serde_json::to_string(&ServerMsg::StateChange { state: RracerState::Racing.into() }).unwrap();
+        assert_eq!(json, r#"{"StateChange":{"state":"racing"}}"#);
+        assert_eq!(serde_json::from_str::<GamePhase>(r#""finished""#).unwrap(), GamePhase::Finished);
+        assert!(serde_json::from_str::<GamePhase>(r#""finshed""#).is_err());
+    }
 }
@@ -0,0 +1,146 @@
+//! Relay races: the room's racers are dealt into teams and each team types the passage
+//! in legs, one teammate at a time. A teammate's leg starts where the one before handed off,
+//! and a team's time is from the start until its last leg is typed.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::text::char_len;
+
+/// Teams in a relay race; fewer when there aren't enough racers.
+pub const MAX_TEAMS: usize = 2;
+
+/// The teams of a relay race and how far each has got
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Relay {
+    /// Player ids per team, in running order; members take turns when there are more legs
+    pub teams: Vec<Vec<String>>,
+    /// Where each leg ends, as a char position; the last one is the passage length
+    pub legs: Vec<usize>,
+    /// The leg each team is on, `legs.len()` once it is home
+    pub current: Vec<usize>,
+}
+
+impl Relay {
+    /// Deal `racers` (in join order) into teams by turns and cut `passage` into as many legs
+    /// as the biggest team has members.
+    pub fn new(racers: &[String], passage: &str) -> Self {
+        let count = racers.len().clamp(1, MAX_TEAMS);
+        let mut teams = vec![Vec::new(); count];
+        for (i, id) in racers.iter().enumerate() {
+            teams[i % count].push(id.clone());
+        }
+        let legs = split_legs(passage, teams.iter().map(Vec::len).max().unwrap_or(1));
+        Self { current: vec![0; count], teams, legs }
+    }
+
+    /// The chars leg `leg` covers.
+    pub fn leg(&self, leg: usize) -> Option<Range<usize>> {
+        let end = *self.legs.get(leg)?;
+        let start = leg.checked_sub(1).map_or(0, |before| self.legs[before]);
+        Some(start..end)
+    }
+
+    /// Who is typing `team`'s current leg; None once the team is home or has nobody left.
+    pub fn runner(&self, team: usize) -> Option<&str> {
+        let members = self.teams.get(team).filter(|m| !m.is_empty())?;
+        let leg = self.current[team];
+        (leg < self.legs.len()).then(|| members[leg % members.len()].as_str())
+    }
+
+    pub fn team_of(&self, id: &str) -> Option<usize> {
+        self.teams.iter().position(|members| members.iter().any(|m| m == id))
+    }
+
+    /// The chars `id` is to type now, when it's their turn.
+    pub fn turn(&self, id: &str) -> Option<Range<usize>> {
+        let team = self.team_of(id)?;
+        if self.runner(team)? != id {
+            return None;
+        }
+        self.leg(self.current[team])
+    }
+
+    /// Move `team` on to its next leg. Returns false when that was its last one.
+    pub fn hand_off(&mut self, team: usize) -> bool {
+        self.current[team] += 1;
+        !self.home(team)
+    }
+
+    pub fn home(&self, team: usize) -> bool {
+        self.current[team] >= self.legs.len()
+    }
+
+    /// Take `id` off their team. Returns the team when `id` was typing its current leg, which
+    /// a teammate now has to pick up.
+    pub fn remove(&mut self, id: &str) -> Option<usize> {
+        let team = self.team_of(id)?;
+        let running = self.runner(team) == Some(id);
+        self.teams[team].retain(|m| m != id);
+        running.then_some(team)
+    }
+}
+
+/// Ends of up to `n` legs of about equal length, each cut just after a space so no word is
+/// split between two typists. Short passages get fewer legs.
+pub fn split_legs(passage: &str, n: usize) -> Vec<usize> {
+    let len = char_len(passage);
+    let chars: Vec<char> = passage.chars().collect();
+    let mut legs = Vec::new();
+    for i in 1..n {
+        let target = len * i / n;
+        let after = legs.last().copied().unwrap_or(0);
+        if let Some(cut) = (target.max(after + 1)..len).find(|&p| chars[p - 1].is_whitespace()) {
+            legs.push(cut);
+        }
+    }
+    legs.push(len);
+    legs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn legs_end_after_a_space() {
+        assert_eq!(split_legs("aaa bbb ccc ddd", 2), vec![8, 15]);
+        assert_eq!(split_legs("aaa bbb ccc ddd", 1), vec![15]);
+        // Not enough words for everyone
+        assert_eq!(split_legs("one two", 3), vec![4, 7]);
+        assert_eq!(split_legs("word", 2), vec![4]);
+    }
+
+    #[test]
+    fn teams_take_turns_leg_by_leg() {
+        let mut relay = Relay::new(&ids(&["a", "b", "c", "d", "e"]), "one two three four five six");
+        assert_eq!(relay.teams, vec![ids(&["a", "c", "e"]), ids(&["b", "d"])]);
+        assert_eq!(relay.legs.len(), 3);
+        assert_eq!(relay.turn("a"), Some(0..relay.legs[0]));
+        assert_eq!(relay.turn("c"), None);
+        assert!(relay.hand_off(0));
+        assert_eq!(relay.turn("c"), relay.leg(1));
+        // A two-member team's first runner takes the third leg too
+        assert!(relay.hand_off(1));
+        assert!(relay.hand_off(1));
+        assert_eq!(relay.runner(1), Some("b"));
+        assert!(!relay.hand_off(1));
+        assert!(relay.home(1) && relay.runner(1).is_none());
+    }
+
+    #[test]
+    fn a_teammate_picks_up_a_dropped_leg() {
+        let mut relay = Relay::new(&ids(&["a", "b", "c"]), "one two three four");
+        assert_eq!(relay.remove("b"), Some(1));
+        assert_eq!(relay.runner(1), None);
+        assert_eq!(relay.remove("c"), None);
+        assert_eq!(relay.runner(0), Some("a"));
+        assert_eq!(relay.remove("nobody"), None);
+    }
+}
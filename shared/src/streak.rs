@@ -0,0 +1,58 @@
+//! Daily race streaks. Days are plain day numbers (days since 1970-01-01) already taken
+//! in the player's own time zone, so the day boundary is wherever their midnight falls.
+
+/// Current and longest run of consecutive days with at least one finished race.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Streaks {
+    /// Still alive if the last race day is today or yesterday; 0 otherwise
+    pub current: u32,
+    pub longest: u32,
+}
+
+/// `days` must be sorted ascending; duplicates are ignored.
+pub fn streaks(days: &[i64], today: i64) -> Streaks {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<i64> = None;
+    for &day in days {
+        run = match prev {
+            Some(p) if day == p => continue,
+            Some(p) if day == p + 1 => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        prev = Some(day);
+    }
+    let current = match prev {
+        Some(last) if last == today || last + 1 == today => run,
+        _ => 0,
+    };
+    Streaks { current, longest }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_races_no_streak() {
+        assert_eq!(streaks(&[], 100), Streaks::default());
+    }
+
+    #[test]
+    fn counts_consecutive_days_ending_today_or_yesterday() {
+        assert_eq!(streaks(&[97, 98, 99, 100], 100), Streaks { current: 4, longest: 4 });
+        assert_eq!(streaks(&[97, 98, 99], 100), Streaks { current: 3, longest: 3 });
+        assert_eq!(streaks(&[97, 98], 100), Streaks { current: 0, longest: 2 });
+    }
+
+    #[test]
+    fn longest_survives_a_broken_streak() {
+        assert_eq!(streaks(&[1, 2, 3, 4, 5, 10, 11], 11), Streaks { current: 2, longest: 5 });
+    }
+
+    #[test]
+    fn several_races_on_one_day_count_once() {
+        assert_eq!(streaks(&[5, 5, 6, 6, 6, 7], 7), Streaks { current: 3, longest: 3 });
+    }
+}
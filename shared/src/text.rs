@@ -0,0 +1,328 @@
+//! Passage positions are counted in chars everywhere (protocol, server, client), never in
+//! bytes, so passages with curly quotes or dashes behave like ASCII ones. Combining marks
+//! (Arabic harakat, Hebrew niqqud) are typed as keys of their own and so keep their own
+//! positions, but a racer only counts as past a letter once its marks are typed too
+//! (`grapheme_floor`), and the caret covers whole graphemes so marks never come loose.
+
+use unicode_normalization::char::decompose_compatible;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Passage length in chars; a racer has finished once their position reaches it.
+pub fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// The char a racer at `pos` has to type next.
+pub fn char_at(s: &str, pos: usize) -> Option<char> {
+    s.chars().nth(pos)
+}
+
+/// Split into (typed, current char, remaining) for rendering a racer at `pos`.
+pub fn split_at_pos(s: &str, pos: usize) -> (&str, Option<char>, &str) {
+    let start = byte_offset(s, pos);
+    let rest = &s[start..];
+    match rest.chars().next() {
+        Some(c) => (&s[..start], Some(c), &rest[c.len_utf8()..]),
+        None => (s, None, ""),
+    }
+}
+
+/// Which way a passage reads
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+impl Direction {
+    /// The HTML `dir` value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ltr => "ltr",
+            Self::Rtl => "rtl",
+        }
+    }
+}
+
+/// Direction of `s` by its first letter, as `dir="auto"` decides it: Hebrew, Arabic and the
+/// other right-to-left scripts read right to left, everything else left to right.
+pub fn direction(s: &str) -> Direction {
+    match s.chars().find(|c| c.is_alphabetic()) {
+        Some(c) if is_rtl(c) => Direction::Rtl,
+        _ => Direction::Ltr,
+    }
+}
+
+/// Char position of the start of the grapheme holding char `pos`, clamped to the passage
+/// length: how far a racer at `pos` has got in whole letters.
+pub fn grapheme_floor(s: &str, pos: usize) -> usize {
+    let mut start = 0;
+    for g in s.graphemes(true) {
+        let next = start + g.chars().count();
+        if next > pos {
+            return start;
+        }
+        start = next;
+    }
+    start
+}
+
+/// Split into (typed, current grapheme, remaining) for drawing a racer at `pos`. Unlike
+/// `split_at_pos` a letter is never cut off from its marks: partway through a grapheme,
+/// all of it is still current.
+pub fn split_at_grapheme(s: &str, pos: usize) -> (&str, &str, &str) {
+    let start = byte_offset(s, grapheme_floor(s, pos));
+    let rest = &s[start..];
+    let current = rest.graphemes(true).next().unwrap_or("");
+    (&s[..start], current, &rest[current.len()..])
+}
+
+/// Arabic and Hebrew presentation forms (letters in their joined shapes, and ligatures
+/// such as lam-alef) replaced by the plain letters keyboards type, and tatweel, which only
+/// stretches a join, dropped. Text copied out of PDFs and legacy encodings is full of them.
+pub fn fold_presentation_forms(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            TATWEEL => {}
+            '\u{FB1D}'..='\u{FB4F}' | '\u{FB50}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFE}' => decompose_compatible(c, |d| {
+                // The isolated harakat forms decompose to a space or tatweel plus the mark
+                if !(d == TATWEEL || (d == ' ' && ('\u{FE70}'..='\u{FE7F}').contains(&c))) {
+                    out.push(d);
+                }
+            }),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+const TATWEEL: char = '\u{0640}';
+
+/// Letters of the right-to-left scripts: Hebrew through Arabic Extended-A, and the Hebrew
+/// and Arabic presentation forms.
+fn is_rtl(c: char) -> bool {
+    matches!(c, '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFE}')
+}
+
+/// Beginner-friendly version of a passage for casual rooms: lowercase with punctuation
+/// dropped. Dashes and slashes become spaces so the words they join stay apart, and
+/// whitespace runs collapse to single spaces.
+pub fn casual(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        let separates = c.is_whitespace() || matches!(c, '-' | '/' | '\u{2010}'..='\u{2015}' | '\u{2212}');
+        if separates {
+            if !out.is_empty() && !out.ends_with(' ') {
+                out.push(' ');
+            }
+        } else if c.is_alphanumeric() {
+            out.push(c);
+        }
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// The start of `s` up to the end of the word its `n`th char is in, or all of it when it has
+/// no more (or `n` is 0), so a passage cut to about `n` chars ends on a whole word.
+pub fn first_chars(s: &str, n: usize) -> &str {
+    let mut in_word = false;
+    for (count, (i, c)) in s.char_indices().enumerate() {
+        if !c.is_whitespace() {
+            in_word = true;
+        } else if in_word {
+            in_word = false;
+            if n > 0 && count >= n {
+                return &s[..i];
+            }
+        }
+    }
+    s
+}
+
+/// The start of `s` up to the end of its `n`th word, or all of it when it has no more (or
+/// `n` is 0). Words are runs of non-whitespace, so punctuation stays with its word.
+pub fn first_words(s: &str, n: usize) -> &str {
+    let mut words = 0;
+    let mut in_word = false;
+    for (i, c) in s.char_indices() {
+        if !c.is_whitespace() {
+            in_word = true;
+        } else if in_word {
+            in_word = false;
+            words += 1;
+            if words == n {
+                return &s[..i];
+            }
+        }
+    }
+    s
+}
+
+/// FNV-1a of the passage's bytes, sent with Countdown, Start and Sync so a client can tell
+/// its passage has drifted from the room's. 32 bits, so it survives a trip through JS numbers.
+pub fn passage_hash(s: &str) -> u32 {
+    s.bytes().fold(0x811c_9dc5, |h, b| (h ^ u32::from(b)).wrapping_mul(0x0100_0193))
+}
+
+/// Byte offset of char `pos`, clamped to the end of the string.
+fn byte_offset(s: &str, pos: usize) -> usize {
+    s.char_indices().nth(pos).map(|(i, _)| i).unwrap_or(s.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FANCY: &str = "“Don’t panic” — it’s fine…";
+
+    #[test]
+    fn passage_hash_is_fnv1a() {
+        assert_eq!(passage_hash(""), 0x811c_9dc5);
+        assert_eq!(passage_hash("a"), 0xe40c_292c);
+        assert_ne!(passage_hash(FANCY), passage_hash(&casual(FANCY)));
+    }
+
+    #[test]
+    fn counts_chars_not_bytes() {
+        assert_eq!(char_len("abc"), 3);
+        assert_eq!(char_len(FANCY), 26);
+        assert!(FANCY.len() > char_len(FANCY));
+    }
+
+    #[test]
+    fn char_at_indexes_by_char() {
+        assert_eq!(char_at(FANCY, 0), Some('“'));
+        assert_eq!(char_at(FANCY, 4), Some('’'));
+        assert_eq!(char_at(FANCY, 14), Some('—'));
+        assert_eq!(char_at(FANCY, 25), Some('…'));
+        assert_eq!(char_at(FANCY, 26), None);
+    }
+
+    #[test]
+    fn split_at_pos_keeps_multibyte_chars_whole() {
+        assert_eq!(split_at_pos(FANCY, 0), ("", Some('“'), "Don’t panic” — it’s fine…"));
+        assert_eq!(split_at_pos(FANCY, 5), ("“Don’", Some('t'), " panic” — it’s fine…"));
+        assert_eq!(split_at_pos(FANCY, 25), ("“Don’t panic” — it’s fine", Some('…'), ""));
+        assert_eq!(split_at_pos(FANCY, 26), (FANCY, None, ""));
+        assert_eq!(split_at_pos(FANCY, 99), (FANCY, None, ""));
+    }
+
+    #[test]
+    fn casual_lowercases_and_drops_punctuation() {
+        assert_eq!(casual(FANCY), "dont panic its fine");
+        assert_eq!(casual("Well-known  facts, e.g. 42/7!"), "well known facts eg 42 7");
+        assert_eq!(casual("Déjà vu."), "déjà vu");
+    }
+
+    #[test]
+    fn casual_never_leaves_stray_spaces() {
+        assert_eq!(casual(" -- Hi -- "), "hi");
+        assert_eq!(casual("..."), "");
+    }
+
+    #[test]
+    fn first_words_cut_after_a_whole_word() {
+        assert_eq!(first_words(FANCY, 2), "“Don’t panic”");
+        assert_eq!(first_words("one  two\nthree four", 3), "one  two\nthree");
+        assert_eq!(first_words("one two", 5), "one two");
+        assert_eq!(first_words("one two ", 2), "one two");
+        assert_eq!(first_words("one two", 0), "one two");
+    }
+
+    #[test]
+    fn first_chars_finish_the_word_they_end_in() {
+        assert_eq!(first_chars("one two three", 3), "one");
+        assert_eq!(first_chars("one two three", 5), "one two");
+        assert_eq!(first_chars("one  two", 4), "one  two");
+        assert_eq!(first_chars("one two", 50), "one two");
+        assert_eq!(first_chars("one two", 0), "one two");
+    }
+
+    #[test]
+    fn typing_a_fancy_passage_reaches_its_length() {
+        let mut pos = 0;
+        while let Some(c) = char_at(FANCY, pos) {
+            assert_eq!(split_at_pos(FANCY, pos).1, Some(c));
+            pos += 1;
+        }
+        assert_eq!(pos, char_len(FANCY));
+    }
+
+    // "Marhaban" with a fatha on the mim, and "shalom" with niqqud
+    const ARABIC: &str = "مَرحبا";
+    const HEBREW: &str = "שָׁלוֹם";
+
+    #[test]
+    fn direction_follows_the_first_letter() {
+        assert_eq!(direction(ARABIC), Direction::Rtl);
+        assert_eq!(direction(HEBREW), Direction::Rtl);
+        assert_eq!(direction("“42” — مرحبا"), Direction::Rtl);
+        assert_eq!(direction("Hello مرحبا"), Direction::Ltr);
+        assert_eq!(direction(FANCY), Direction::Ltr);
+        assert_eq!(direction("123"), Direction::Ltr);
+    }
+
+    #[test]
+    fn marks_stay_with_their_letter() {
+        // Mim and its fatha are one grapheme
+        assert_eq!(grapheme_floor(ARABIC, 0), 0);
+        assert_eq!(grapheme_floor(ARABIC, 1), 0);
+        assert_eq!(grapheme_floor(ARABIC, 2), 2);
+        assert_eq!(grapheme_floor(ARABIC, 99), char_len(ARABIC));
+        assert_eq!(split_at_grapheme(ARABIC, 1), ("", "مَ", "رحبا"));
+        assert_eq!(split_at_grapheme(ARABIC, 2), ("مَ", "ر", "حبا"));
+        // Shin carries a qamats and a shin dot
+        assert_eq!(split_at_grapheme(HEBREW, 2), ("", "שָׁ", "לוֹם"));
+        assert_eq!(split_at_grapheme(HEBREW, 3), ("שָׁ", "ל", "וֹם"));
+        assert_eq!(split_at_grapheme(FANCY, 26), (FANCY, "", ""));
+    }
+
+    #[test]
+    fn presentation_forms_fold_to_letters() {
+        // Initial, medial and final forms of meem, hah and beh
+        assert_eq!(fold_presentation_forms("\u{FEE3}\u{FEA4}\u{FE92}\u{FE8E}"), "محبا");
+        // Lam-alef ligature and a tatweel-stretched join
+        assert_eq!(fold_presentation_forms("\u{FEFB}"), "لا");
+        assert_eq!(fold_presentation_forms("مـرحبا"), "مرحبا");
+        // Isolated fatha form, and Hebrew shin with its dot
+        assert_eq!(fold_presentation_forms("\u{FE76}"), "\u{064E}");
+        assert_eq!(fold_presentation_forms("\u{FB2A}"), "\u{05E9}\u{05C1}");
+        assert_eq!(fold_presentation_forms(FANCY), FANCY);
+    }
+
+    mod props {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn split_at_pos_reassembles_the_passage(s in "\\PC*", pos in 0usize..64) {
+                let (typed, current, rest) = split_at_pos(&s, pos);
+                let mut joined = typed.to_string();
+                joined.extend(current);
+                joined.push_str(rest);
+                prop_assert_eq!(&joined, &s);
+                prop_assert_eq!(char_len(typed), pos.min(char_len(&s)));
+                prop_assert_eq!(current, char_at(&s, pos));
+            }
+
+            #[test]
+            fn split_at_grapheme_reassembles_the_passage(s in "\\PC*", pos in 0usize..64) {
+                let (typed, current, rest) = split_at_grapheme(&s, pos);
+                prop_assert_eq!(format!("{typed}{current}{rest}"), s.clone());
+                prop_assert!(char_len(typed) <= pos);
+                prop_assert_eq!(current.is_empty(), pos >= char_len(&s));
+            }
+
+            #[test]
+            fn casual_is_idempotent_and_tidy(s in "\\PC*") {
+                let once = casual(&s);
+                prop_assert_eq!(casual(&once), once.clone());
+                prop_assert!(!once.starts_with(' ') && !once.ends_with(' ') && !once.contains("  "), "{:?}", once);
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! The notable moments of a race, kept by the room as it runs and sent in
+//! `ServerMsg::RaceSummary` at the finish so results can tell the story of the race: who got
+//! halfway first, when the lead changed hands, who had the fastest burst, and how it ended.
+
+use serde::{Deserialize, Serialize};
+
+/// Length of the burst worth a mention, in seconds.
+pub const BURST_SECS: usize = 5;
+
+/// Lead changes kept; a see-saw race only tells its first few.
+pub const MAX_LEAD_CHANGES: usize = 5;
+
+/// Something worth telling about a race; times are seconds from the start
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Moment {
+    /// The first racer past the halfway mark
+    FirstToHalf { id: String, time: f64 },
+    /// `overtaker` went into first place ahead of `overtaken`
+    TookLead { overtaker: String, overtaken: String, time: f64 },
+    /// The fastest `BURST_SECS` anyone typed, starting `time` seconds in
+    FastestBurst { id: String, wpm: f64, time: f64 },
+    /// Player ids with a result, from first to last place
+    FinalOrder { ids: Vec<String> },
+}
+
+impl Moment {
+    /// When it happened; the final order comes after everything else.
+    pub fn time(&self) -> f64 {
+        match self {
+            Moment::FirstToHalf { time, .. } | Moment::TookLead { time, .. } | Moment::FastestBurst { time, .. } => *time,
+            Moment::FinalOrder { .. } => f64::INFINITY,
+        }
+    }
+}
+
+/// A race's moments so far
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Timeline {
+    moments: Vec<Moment>,
+    lead_changes: usize,
+}
+
+impl Timeline {
+    /// `id` passed halfway `time` seconds in; only the first one counts.
+    pub fn halfway(&mut self, id: &str, time: f64) {
+        if self.moments.iter().any(|m| matches!(m, Moment::FirstToHalf { .. })) {
+            return;
+        }
+        self.moments.push(Moment::FirstToHalf { id: id.to_string(), time });
+    }
+
+    /// `overtaker` took first place from `overtaken`, up to `MAX_LEAD_CHANGES` times.
+    pub fn lead(&mut self, overtaker: &str, overtaken: &str, time: f64) {
+        if self.lead_changes == MAX_LEAD_CHANGES {
+            return;
+        }
+        self.lead_changes += 1;
+        self.moments.push(Moment::TookLead { overtaker: overtaker.to_string(), overtaken: overtaken.to_string(), time });
+    }
+
+    /// The race's moments in time order, with the fastest burst, if anyone kept one up, and
+    /// the final order last.
+    pub fn summary(mut self, burst: Option<Moment>, order: Vec<String>) -> Vec<Moment> {
+        self.moments.extend(burst);
+        self.moments.push(Moment::FinalOrder { ids: order });
+        self.moments.sort_by(|a, b| a.time().total_cmp(&b.time()));
+        self.moments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_first_to_halfway_counts() {
+        let mut timeline = Timeline::default();
+        timeline.halfway("a", 10.0);
+        timeline.halfway("b", 11.0);
+        assert_eq!(timeline.summary(None, vec![]), vec![
+            Moment::FirstToHalf { id: "a".into(), time: 10.0 },
+            Moment::FinalOrder { ids: vec![] },
+        ]);
+    }
+
+    #[test]
+    fn moments_come_in_time_order_with_the_final_order_last() {
+        let mut timeline = Timeline::default();
+        timeline.lead("b", "a", 4.0);
+        timeline.halfway("b", 12.0);
+        let burst = Moment::FastestBurst { id: "a".into(), wpm: 90.0, time: 8.0 };
+        let times: Vec<f64> = timeline.summary(Some(burst), vec!["b".into(), "a".into()]).iter().map(Moment::time).collect();
+        assert_eq!(times, vec![4.0, 8.0, 12.0, f64::INFINITY]);
+    }
+
+    #[test]
+    fn lead_changes_are_capped() {
+        let mut timeline = Timeline::default();
+        for i in 0..MAX_LEAD_CHANGES + 3 {
+            timeline.lead("a", "b", i as f64);
+        }
+        let leads = timeline.summary(None, vec![]).iter().filter(|m| matches!(m, Moment::TookLead { .. })).count();
+        assert_eq!(leads, MAX_LEAD_CHANGES);
+    }
+}
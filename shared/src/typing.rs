@@ -0,0 +1,214 @@
+//! Applying key presses to a racer's cursor under the room's error mode. The web client and
+//! the server's keystroke path both go through `Cursor` so they agree on where a racer is.
+
+use serde::{Deserialize, Serialize};
+
+use crate::text::{char_at, char_len};
+
+/// Sent as `ClientMsg::Key` for the Backspace key.
+pub const BACKSPACE: char = '\u{8}';
+
+/// This many keys in a row stamped with the same millisecond weren't typed one at a time:
+/// they were pasted, or injected by a script.
+pub const PASTE_BURST_KEYS: usize = 5;
+
+/// What a wrong key press does
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorMode {
+    /// Counted, but the cursor stays put until the right char is typed
+    #[default]
+    Strict,
+    /// The cursor moves on anyway and the char stays marked wrong
+    Forgiving,
+    /// Like forgiving inside a word, but the word can't be finished until every mistake in
+    /// it has been backspaced and fixed
+    StopOnWord,
+}
+
+/// A racer's place in the passage.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cursor {
+    /// Chars typed so far, right or wrong
+    pub pos: usize,
+    /// Positions before `pos` that were typed wrong and not fixed, ascending
+    pub wrong: Vec<usize>,
+    /// Every key press that reached the passage, except backspaces
+    pub keystrokes: usize,
+    pub errors: usize,
+    /// Positions where a wrong key was pressed, fixed or not, in the order they happened
+    pub missed: Vec<usize>,
+}
+
+impl Cursor {
+    /// Chars before the cursor that were typed right.
+    pub fn correct(&self) -> usize {
+        self.pos - self.wrong.len()
+    }
+
+    pub fn is_wrong(&self, pos: usize) -> bool {
+        self.wrong.binary_search(&pos).is_ok()
+    }
+
+    /// Apply one key press, `BACKSPACE` included. `matches(typed, expected)` decides whether
+    /// a key is right, so callers can normalize look-alike chars. Returns whether the
+    /// cursor moved.
+    pub fn press(&mut self, passage: &str, typed: char, mode: ErrorMode, matches: impl Fn(char, char) -> bool) -> bool {
+        if typed == BACKSPACE {
+            return self.backspace(passage, mode);
+        }
+        let Some(expected) = char_at(passage, self.pos) else { return false };
+        let right = matches(typed, expected);
+        let ends_word = expected.is_whitespace() || self.pos + 1 == char_len(passage);
+        if mode == ErrorMode::StopOnWord && ends_word && right && !self.wrong.is_empty() {
+            // The right key, but the word still has mistakes to fix first
+            return false;
+        }
+        self.keystrokes += 1;
+        if !right {
+            self.errors += 1;
+            if self.missed.last() != Some(&self.pos) {
+                self.missed.push(self.pos);
+            }
+        }
+        let advance = match mode {
+            ErrorMode::Strict => right,
+            ErrorMode::Forgiving => true,
+            ErrorMode::StopOnWord => right || !ends_word,
+        };
+        if advance {
+            if !right {
+                self.wrong.push(self.pos);
+            }
+            self.pos += 1;
+        }
+        advance
+    }
+
+    /// Step back one char within the current word; strict mode has nothing to undo and
+    /// finished words stay finished.
+    fn backspace(&mut self, passage: &str, mode: ErrorMode) -> bool {
+        if mode == ErrorMode::Strict || self.pos == 0 {
+            return false;
+        }
+        if char_at(passage, self.pos - 1).is_some_and(char::is_whitespace) {
+            return false;
+        }
+        self.pos -= 1;
+        if self.wrong.last() == Some(&self.pos) {
+            self.wrong.pop();
+        }
+        true
+    }
+}
+
+/// Keys in a row stamped with the same time, for spotting pastes. The client stamps keys
+/// with its own clock, so this is checked on both sides.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyBurst {
+    ts: u64,
+    run: usize,
+}
+
+impl KeyBurst {
+    /// Count a key stamped `ts`; true for the key that makes the run a paste, so each paste
+    /// is reported once.
+    pub fn key(&mut self, ts: u64) -> bool {
+        if self.run > 0 && ts == self.ts {
+            self.run += 1;
+        } else {
+            *self = KeyBurst { ts, run: 1 };
+        }
+        self.run == PASTE_BURST_KEYS
+    }
+
+    /// Whether the latest key was part of a paste.
+    pub fn pasting(&self) -> bool {
+        self.run >= PASTE_BURST_KEYS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_all(cursor: &mut Cursor, passage: &str, keys: &str, mode: ErrorMode) {
+        for k in keys.chars() {
+            cursor.press(passage, k, mode, |a, b| a == b);
+        }
+    }
+
+    #[test]
+    fn strict_waits_for_the_right_key() {
+        let mut c = Cursor::default();
+        type_all(&mut c, "ab cd", "axb", ErrorMode::Strict);
+        assert_eq!((c.pos, c.errors, c.keystrokes), (2, 1, 3));
+        assert!(c.wrong.is_empty());
+        assert!(!c.press("ab cd", BACKSPACE, ErrorMode::Strict, |a, b| a == b));
+        // Where the mistakes were is kept even though none is left standing, each place once
+        type_all(&mut c, "ab cd", "yy cz", ErrorMode::Strict);
+        assert_eq!(c.missed, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn forgiving_moves_on_and_marks_mistakes() {
+        let mut c = Cursor::default();
+        type_all(&mut c, "ab cd", "xb_cd", ErrorMode::Forgiving);
+        assert_eq!(c.pos, 5);
+        assert_eq!(c.wrong, vec![0, 2]);
+        assert_eq!(c.correct(), 3);
+        assert!(c.is_wrong(2) && !c.is_wrong(3));
+    }
+
+    #[test]
+    fn backspace_fixes_mistakes_within_the_word_only() {
+        let mut c = Cursor::default();
+        type_all(&mut c, "ab cd", "ab x", ErrorMode::Forgiving);
+        assert_eq!(c.wrong, vec![3]);
+        type_all(&mut c, "ab cd", "\u{8}c\u{8}\u{8}", ErrorMode::Forgiving);
+        // Back at the start of "cd"; the finished word "ab " can't be reopened
+        assert_eq!(c.pos, 3);
+        assert!(c.wrong.is_empty());
+        assert_eq!(c.errors, 1);
+    }
+
+    #[test]
+    fn stop_on_word_blocks_the_space_until_the_word_is_fixed() {
+        let p = "ab cd";
+        let mut c = Cursor::default();
+        type_all(&mut c, p, "xb ", ErrorMode::StopOnWord);
+        assert_eq!(c.pos, 2, "space refused while 'a' is wrong");
+        type_all(&mut c, p, "\u{8}\u{8}ab ", ErrorMode::StopOnWord);
+        assert_eq!(c.pos, 3);
+        assert!(c.wrong.is_empty());
+        // A wrong key where the space goes doesn't advance either
+        let mut c = Cursor::default();
+        type_all(&mut c, p, "abx", ErrorMode::StopOnWord);
+        assert_eq!((c.pos, c.errors), (2, 1));
+    }
+
+    #[test]
+    fn stop_on_word_needs_a_clean_last_word_to_finish() {
+        let p = "ab cd";
+        let mut c = Cursor::default();
+        type_all(&mut c, p, "ab xd", ErrorMode::StopOnWord);
+        assert_eq!(c.pos, 4);
+        type_all(&mut c, p, "\u{8}cd", ErrorMode::StopOnWord);
+        assert_eq!(c.pos, char_len(p));
+        assert_eq!(c.correct(), 5);
+    }
+
+    #[test]
+    fn bursts_of_identical_timestamps_are_pastes() {
+        let mut burst = KeyBurst::default();
+        let reports: Vec<bool> = (0..PASTE_BURST_KEYS + 2).map(|_| burst.key(1_000)).collect();
+        assert_eq!(reports.iter().filter(|r| **r).count(), 1);
+        assert!(reports[PASTE_BURST_KEYS - 1] && burst.pasting());
+        // A key at a new time starts over, however fast
+        assert!(!burst.key(1_001));
+        assert!(!burst.pasting());
+        let mut typed = KeyBurst::default();
+        assert!((0..100).all(|i| !typed.key(1_000 + i * 30)));
+    }
+}
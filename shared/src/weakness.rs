@@ -0,0 +1,136 @@
+//! Where a racer's mistakes fall, and practice drills built from them. Each wrong key counts
+//! against the word it was in and, inside a word, against the letter pair it completed (the
+//! letter before and the one missed). Added up over races, the most-missed of each pick the
+//! words of a personal drill.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::generate::{PassageGenerator, Rng};
+use crate::words::WordList;
+
+/// Bigrams and words a drill focuses on, of each.
+pub const FOCUS_ITEMS: usize = 5;
+
+/// Words in a drill.
+pub const DRILL_WORDS: usize = 30;
+
+/// Miss counts by lowercase bigram and by lowercase word.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Misses {
+    pub bigrams: HashMap<String, u32>,
+    pub words: HashMap<String, u32>,
+}
+
+impl Misses {
+    /// Count the misses at char positions `missed` of `passage`; positions past the end are
+    /// ignored. A miss on a space counts against the word before it.
+    pub fn count(passage: &str, missed: &[usize]) -> Self {
+        let chars: Vec<char> = passage.chars().collect();
+        let mut misses = Self::default();
+        for &pos in missed {
+            let Some(&c) = chars.get(pos) else { continue };
+            if let Some(&before) = pos.checked_sub(1).and_then(|p| chars.get(p)) {
+                if before.is_alphabetic() && c.is_alphabetic() {
+                    let bigram: String = [before, c].into_iter().flat_map(char::to_lowercase).collect();
+                    *misses.bigrams.entry(bigram).or_default() += 1;
+                }
+            }
+            let in_word = if c.is_whitespace() { pos.checked_sub(1) } else { Some(pos) };
+            if let Some(word) = in_word.and_then(|p| word_at(&chars, p)) {
+                *misses.words.entry(word).or_default() += 1;
+            }
+        }
+        misses
+    }
+
+    /// The `n` most-missed bigrams and words.
+    pub fn focus(&self, n: usize) -> Weaknesses {
+        Weaknesses { bigrams: most_missed(&self.bigrams, n), words: most_missed(&self.words, n) }
+    }
+}
+
+/// The word around char `pos`, lowercased and without the punctuation at either end; None
+/// for whitespace or a word that is all punctuation.
+fn word_at(chars: &[char], pos: usize) -> Option<String> {
+    if chars.get(pos)?.is_whitespace() {
+        return None;
+    }
+    let start = chars[..pos].iter().rposition(|c| c.is_whitespace()).map_or(0, |i| i + 1);
+    let end = chars[pos..].iter().position(|c| c.is_whitespace()).map_or(chars.len(), |i| pos + i);
+    let word: String = chars[start..end].iter().collect::<String>().trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    (!word.is_empty()).then_some(word)
+}
+
+/// Keys of `counts`, most first and alphabetically among equals, at most `n`.
+fn most_missed(counts: &HashMap<String, u32>, n: usize) -> Vec<String> {
+    let mut items: Vec<(&String, &u32)> = counts.iter().collect();
+    items.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    items.into_iter().take(n).map(|(item, _)| item.clone()).collect()
+}
+
+/// What a personal drill works on, most-missed first
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Weaknesses {
+    pub bigrams: Vec<String>,
+    pub words: Vec<String>,
+}
+
+impl PassageGenerator for Weaknesses {
+    /// `DRILL_WORDS` words in a shuffled order: each missed word twice, then common words
+    /// holding a missed bigram with a few others mixed in. With nothing to focus on it is
+    /// just common words.
+    fn generate(&self, seed: u64) -> String {
+        let mut rng = Rng(seed);
+        let common: Vec<&str> = WordList::Top1000.words().collect();
+        let drilling: Vec<&str> = common.iter().copied().filter(|w| self.bigrams.iter().any(|b| w.contains(b.as_str()))).collect();
+        let mut picked: Vec<&str> = self.words.iter().flat_map(|w| [w.as_str(), w.as_str()]).take(DRILL_WORDS / 3).collect();
+        while picked.len() < DRILL_WORDS {
+            let from = if drilling.is_empty() || rng.below(4) == 0 { &common } else { &drilling };
+            picked.push(from[rng.below(from.len())]);
+        }
+        for i in (1..picked.len()).rev() {
+            picked.swap(i, rng.below(i + 1));
+        }
+        picked.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misses_count_against_their_bigram_and_word() {
+        // "The" missed at 'h', "quick," at 'u' and at the space after it
+        let misses = Misses::count("The quick, brown fox", &[1, 5, 10, 99]);
+        assert_eq!(misses.bigrams, HashMap::from([("th".to_string(), 1), ("qu".to_string(), 1)]));
+        assert_eq!(misses.words, HashMap::from([("the".to_string(), 1), ("quick".to_string(), 2)]));
+        // A word's first letter has no bigram
+        assert!(Misses::count("fox", &[0]).bigrams.is_empty());
+    }
+
+    #[test]
+    fn the_focus_is_the_most_missed() {
+        let misses = Misses::count("then the that other", &[1, 5, 10, 16, 17]);
+        let focus = misses.focus(2);
+        assert_eq!(focus.bigrams, vec!["th".to_string(), "he".to_string()]);
+        assert_eq!(focus.words, vec!["other".to_string(), "that".to_string()]);
+    }
+
+    #[test]
+    fn drills_work_on_the_focus() {
+        let focus = Weaknesses { bigrams: vec!["qu".to_string()], words: vec!["rhythm".to_string()] };
+        let drill = focus.generate(5);
+        let words: Vec<&str> = drill.split(' ').collect();
+        assert_eq!(words.len(), DRILL_WORDS);
+        assert_eq!(words.iter().filter(|w| **w == "rhythm").count(), 2);
+        assert!(words.iter().filter(|w| w.contains("qu")).count() >= DRILL_WORDS / 3, "{drill}");
+        assert_eq!(drill, focus.generate(5));
+        // Nothing to work on yet: common words
+        let common: Vec<&str> = WordList::Top1000.words().collect();
+        assert!(Weaknesses::default().generate(1).split(' ').all(|w| common.contains(&w)));
+    }
+}
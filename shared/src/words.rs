@@ -0,0 +1,129 @@
+//! Random-word races: passages made of common English words instead of literature.
+
+use serde::{Deserialize, Serialize};
+
+use crate::generate::{PassageGenerator, Rng};
+
+/// A thousand common English words, one per line, most common first.
+const ENGLISH_1K: &str = include_str!("english_1k.txt");
+
+/// Which slice of the word list a race draws from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WordList {
+    #[default]
+    Top200,
+    Top1000,
+}
+
+impl WordList {
+    pub fn words(self) -> impl Iterator<Item = &'static str> {
+        let n = match self {
+            WordList::Top200 => 200,
+            WordList::Top1000 => 1000,
+        };
+        ENGLISH_1K.lines().take(n)
+    }
+}
+
+/// How long a word race runs
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WordLimit {
+    /// Until this many words are typed
+    Count(u32),
+    /// For this many seconds; the passage has more words than anyone can type in time
+    Seconds(u32),
+}
+
+impl WordLimit {
+    pub const MIN_COUNT: u32 = 10;
+    pub const MAX_COUNT: u32 = 500;
+    pub const MIN_SECONDS: u32 = 15;
+    pub const MAX_SECONDS: u32 = 300;
+
+    pub fn clamped(self) -> Self {
+        match self {
+            WordLimit::Count(n) => WordLimit::Count(n.clamp(Self::MIN_COUNT, Self::MAX_COUNT)),
+            WordLimit::Seconds(s) => WordLimit::Seconds(s.clamp(Self::MIN_SECONDS, Self::MAX_SECONDS)),
+        }
+    }
+
+    /// Words to generate: timed races get four words a second (240 WPM) plus a margin.
+    pub fn word_count(self) -> usize {
+        match self.clamped() {
+            WordLimit::Count(n) => n as usize,
+            WordLimit::Seconds(s) => s as usize * 4 + 10,
+        }
+    }
+
+    pub fn time_limit_secs(self) -> Option<u32> {
+        match self.clamped() {
+            WordLimit::Seconds(s) => Some(s),
+            WordLimit::Count(_) => None,
+        }
+    }
+}
+
+/// Random words from `list`, never the same word twice in a row.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Words {
+    pub list: WordList,
+    pub limit: WordLimit,
+}
+
+impl PassageGenerator for Words {
+    fn generate(&self, seed: u64) -> String {
+        let words: Vec<&str> = self.list.words().collect();
+        let mut rng = Rng(seed);
+        let mut picked: Vec<&str> = Vec::with_capacity(self.limit.word_count());
+        while picked.len() < self.limit.word_count() {
+            let word = words[rng.below(words.len())];
+            if picked.last() != Some(&word) {
+                picked.push(word);
+            }
+        }
+        picked.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_list_is_a_thousand_distinct_lowercase_words() {
+        let words: Vec<&str> = WordList::Top1000.words().collect();
+        assert_eq!(words.len(), 1000);
+        let mut unique = words.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), 1000);
+        assert!(words.iter().all(|w| !w.is_empty() && w.chars().all(|c| c.is_ascii_lowercase())));
+        assert_eq!(WordList::Top200.words().count(), 200);
+    }
+
+    #[test]
+    fn count_races_have_exactly_that_many_words_from_the_list() {
+        let w = Words { list: WordList::Top200, limit: WordLimit::Count(25) };
+        let passage = w.generate(9);
+        let allowed: Vec<&str> = WordList::Top200.words().collect();
+        let words: Vec<&str> = passage.split(' ').collect();
+        assert_eq!(words.len(), 25);
+        assert!(words.iter().all(|w| allowed.contains(w)));
+        assert!(words.windows(2).all(|p| p[0] != p[1]));
+        assert_eq!(passage, w.generate(9));
+    }
+
+    #[test]
+    fn timed_races_outlast_fast_typists() {
+        let limit = WordLimit::Seconds(30);
+        assert_eq!(limit.time_limit_secs(), Some(30));
+        assert_eq!(limit.word_count(), 130);
+        assert_eq!(WordLimit::Count(3).clamped(), WordLimit::Count(WordLimit::MIN_COUNT));
+        assert_eq!(WordLimit::Seconds(9999).time_limit_secs(), Some(WordLimit::MAX_SECONDS));
+    }
+}
@@ -16,6 +16,15 @@ pub fn raw_wpm(total_chars: usize, seconds: f64) -> f64 {
     (total_chars as f64 / 5.0) * (60.0 / seconds)
 }
 
+/// Raw WPM from `wpm` counted on right keys and the `accuracy` they were typed at, for when
+/// the keystrokes themselves aren't to hand
+pub fn raw_from_accuracy(wpm: f64, accuracy: f64) -> f64 {
+    if accuracy <= 0.0 {
+        return wpm.max(0.0);
+    }
+    wpm.max(0.0) * 100.0 / accuracy.min(100.0)
+}
+
 /// Legacy gross WPM function (kept for compatibility)
 pub fn gross_wpm(chars: usize, seconds: f64) -> f64 {
     wpm(chars, seconds)
@@ -38,6 +47,66 @@ pub fn accuracy(correct_chars: usize, total_chars: usize) -> f64 {
     (correct_chars as f64 / total_chars as f64) * 100.0
 }
 
+/// WPM typed within each whole second of a race, from `(seconds since start, chars typed)`
+/// samples in time order. Each second is credited with the progress made between the last
+/// sample at or before its start and the last sample at or before its end; a trailing
+/// partial second is left out.
+pub fn per_second_wpm(trace: &[(f64, usize)]) -> Vec<f64> {
+    let Some(&(end, _)) = trace.last() else { return Vec::new() };
+    let chars_at = |t: f64| trace.iter().take_while(|&&(s, _)| s <= t).last().map_or(0, |&(_, c)| c);
+    let mut prev = 0;
+    (1..=end.max(0.0).floor() as usize)
+        .map(|second| {
+            let now = chars_at(second as f64);
+            let typed = now.saturating_sub(prev);
+            prev = prev.max(now);
+            wpm(typed, 1.0)
+        })
+        .collect()
+}
+
+/// How steady the pace was, from 0 to 100: 100 minus the coefficient of variation
+/// (standard deviation over mean, as a percentage) of `samples`, such as per-second WPM
+/// or inter-keystroke intervals. Fewer than two samples, or a zero mean, count as 100.
+pub fn consistency(samples: &[f64]) -> f64 {
+    if samples.len() < 2 {
+        return 100.0;
+    }
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    if mean <= 0.0 {
+        return 100.0;
+    }
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+    (100.0 - variance.sqrt() / mean * 100.0).clamp(0.0, 100.0)
+}
+
+/// Fastest single second of a race, from `per_second_wpm` output.
+pub fn burst_wpm(per_second: &[f64]) -> f64 {
+    per_second.iter().cloned().fold(0.0, f64::max)
+}
+
+/// Fastest run of `secs` whole seconds, from `per_second_wpm` output: the second it starts
+/// at (0 for the first) and its average WPM. None for a race shorter than `secs`.
+pub fn fastest_stretch(per_second: &[f64], secs: usize) -> Option<(usize, f64)> {
+    if secs == 0 {
+        return None;
+    }
+    per_second
+        .windows(secs)
+        .map(|w| w.iter().sum::<f64>() / secs as f64)
+        .enumerate()
+        .fold(None, |best, (start, wpm)| match best {
+            Some((_, top)) if top >= wpm => best,
+            _ => Some((start, wpm)),
+        })
+}
+
+/// WPM scaled by accuracy, so a fast but sloppy race ranks below a clean one.
+pub fn adjusted_wpm(wpm: f64, accuracy: f64) -> f64 {
+    wpm.max(0.0) * accuracy.clamp(0.0, 100.0) / 100.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,4 +159,67 @@ mod tests {
         assert_eq!(accuracy(100, 100), 100.0);
         assert_eq!(accuracy(240, 260), 240.0 / 260.0 * 100.0); // ~92.31%
     }
+
+    #[test]
+    fn per_second_wpm_buckets_progress_by_whole_seconds() {
+        // 5 chars in the first second, 10 in the second, then half a second left over
+        let trace = [(0.2, 1), (0.9, 5), (1.5, 10), (2.0, 15), (2.4, 18)];
+        assert_eq!(per_second_wpm(&trace), vec![60.0, 120.0]);
+    }
+
+    #[test]
+    fn per_second_wpm_handles_gaps_and_short_races() {
+        assert!(per_second_wpm(&[]).is_empty());
+        assert!(per_second_wpm(&[(0.5, 3)]).is_empty());
+        // No samples during the second second: it counts as a pause
+        assert_eq!(per_second_wpm(&[(0.5, 5), (2.5, 10), (3.0, 15)]), vec![60.0, 0.0, 120.0]);
+    }
+
+    #[test]
+    fn raw_wpm_from_accuracy_counts_the_wrong_keys_back_in() {
+        // 60 WPM on right keys at 80% accuracy: a fifth of the keys were wrong
+        assert_eq!(raw_from_accuracy(60.0, 80.0), 75.0);
+        assert_eq!(raw_from_accuracy(60.0, 100.0), 60.0);
+        assert_eq!(raw_from_accuracy(60.0, 0.0), 60.0);
+    }
+
+    #[test]
+    fn consistency_is_100_for_an_even_pace() {
+        assert_eq!(consistency(&[80.0, 80.0, 80.0]), 100.0);
+        assert_eq!(consistency(&[]), 100.0);
+        assert_eq!(consistency(&[42.0]), 100.0);
+        assert_eq!(consistency(&[0.0, 0.0]), 100.0);
+    }
+
+    #[test]
+    fn consistency_drops_with_variation() {
+        // Mean 60, standard deviation 20: coefficient of variation 1/3
+        let c = consistency(&[40.0, 80.0, 40.0, 80.0]);
+        assert!((c - 100.0 / 1.5).abs() < 1e-9, "{c}");
+        assert!(consistency(&[50.0, 55.0, 60.0]) > consistency(&[20.0, 55.0, 90.0]));
+        // Wild swings bottom out at 0 rather than going negative
+        assert_eq!(consistency(&[0.0, 0.0, 0.0, 300.0]), 0.0);
+    }
+
+    #[test]
+    fn burst_is_the_fastest_second() {
+        assert_eq!(burst_wpm(&[40.0, 95.5, 60.0]), 95.5);
+        assert_eq!(burst_wpm(&[]), 0.0);
+    }
+
+    #[test]
+    fn fastest_stretch_averages_the_best_run() {
+        assert_eq!(fastest_stretch(&[40.0, 90.0, 30.0, 80.0, 80.0], 2), Some((3, 80.0)));
+        // Ties go to the earlier run
+        assert_eq!(fastest_stretch(&[60.0, 60.0, 60.0], 2), Some((0, 60.0)));
+        assert_eq!(fastest_stretch(&[60.0], 2), None);
+    }
+
+    #[test]
+    fn adjusted_wpm_scales_by_accuracy() {
+        assert_eq!(adjusted_wpm(80.0, 100.0), 80.0);
+        assert_eq!(adjusted_wpm(80.0, 75.0), 60.0);
+        assert_eq!(adjusted_wpm(-3.0, 90.0), 0.0);
+        assert_eq!(adjusted_wpm(50.0, 130.0), 50.0);
+    }
 }
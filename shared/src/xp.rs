@@ -0,0 +1,73 @@
+//! Race XP and levels. Every finished race earns a flat completion bonus plus its
+//! accuracy-weighted WPM; levels get progressively further apart.
+
+/// XP for finishing at all, so slow but complete races still count.
+pub const COMPLETION_XP: u32 = 10;
+
+/// XP earned by a finished race.
+pub fn race_xp(wpm: f64, accuracy: f64) -> u32 {
+    let weighted = wpm.max(0.0) * (accuracy.clamp(0.0, 100.0) / 100.0);
+    COMPLETION_XP + weighted.round() as u32
+}
+
+/// Total XP needed to reach `level` (level 1 is free; each step costs 100 more than the last).
+pub fn xp_for_level(level: u32) -> u64 {
+    let l = u64::from(level.max(1));
+    50 * (l - 1) * l
+}
+
+/// Level reached with `xp` total XP.
+pub fn level_for_xp(xp: u64) -> u32 {
+    let mut level = 1;
+    while xp_for_level(level + 1) <= xp {
+        level += 1;
+    }
+    level
+}
+
+/// XP progress into the current level as (earned, needed for the next).
+pub fn level_progress(xp: u64) -> (u64, u64) {
+    let level = level_for_xp(xp);
+    let floor = xp_for_level(level);
+    (xp - floor, xp_for_level(level + 1) - floor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn race_xp_weights_wpm_by_accuracy() {
+        assert_eq!(race_xp(80.0, 100.0), 90);
+        assert_eq!(race_xp(80.0, 50.0), 50);
+        assert_eq!(race_xp(0.0, 100.0), COMPLETION_XP);
+        assert_eq!(race_xp(-5.0, 120.0), COMPLETION_XP);
+    }
+
+    #[test]
+    fn level_thresholds_grow() {
+        assert_eq!(xp_for_level(1), 0);
+        assert_eq!(xp_for_level(2), 100);
+        assert_eq!(xp_for_level(3), 300);
+        assert_eq!(xp_for_level(4), 600);
+    }
+
+    #[test]
+    fn level_for_xp_matches_thresholds() {
+        assert_eq!(level_for_xp(0), 1);
+        assert_eq!(level_for_xp(99), 1);
+        assert_eq!(level_for_xp(100), 2);
+        assert_eq!(level_for_xp(299), 2);
+        assert_eq!(level_for_xp(300), 3);
+        for level in 1..50 {
+            assert_eq!(level_for_xp(xp_for_level(level)), level);
+        }
+    }
+
+    #[test]
+    fn progress_is_relative_to_the_current_level() {
+        assert_eq!(level_progress(0), (0, 100));
+        assert_eq!(level_progress(150), (50, 200));
+        assert_eq!(level_progress(300), (0, 300));
+    }
+}
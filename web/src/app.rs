@@ -1,33 +1,75 @@
+use leptos::ev;
 use leptos::prelude::*;
-use shared::protocol::{ClientMsg, ServerMsg};
+use shared::api::{CreatedRoom, Friend, Identity, Invite, InviteRequest, LessonAttempt, LessonProgress, Page, PassageCategory, PercentileResponse, PracticeDrill, PracticeReport, Profile, RaceResult};
+use shared::generate::{Drill, DrillKind};
+use shared::lessons::{self, Lesson};
+use shared::names::MAX_NAME_CHARS;
+use shared::words::{WordLimit, WordList, Words};
+use shared::relay::Relay;
+use shared::protocol::{ClientMsg, DuelRecord, ErrorCode, FinishRule, GamePhase, HallOfFame, PassageLength, PlayerInfo, RaceMode, LANE_COLORS, RoomRecord, RoomSettings, Scoring, ServerMsg, MAX_ROOM_CHARS};
+use shared::text::{casual, char_at, char_len, direction, grapheme_floor, split_at_grapheme};
+use shared::timeline::{Moment, BURST_SECS};
+use shared::xp::{level_for_xp, level_progress};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlElement, WebSocket};
 use std::cell::RefCell;
-use crate::normalize::{normalize_char, is_skippable};
+use crate::client_state::{passage_agrees, ClientState};
+use crate::diagnostics::{self, InputLatency, Lag, Quality, Resyncs};
+use crate::side_race::SideRace;
+use crate::hotkeys::{self, hotkey, Chord, Command, Focus, Hotkey};
+use crate::history::{self, RecentRace, MAX_RECENT};
+use crate::i18n::{self, Locale, Phrase};
+use crate::markers::{with_marks, Piece};
+use crate::pacer::{self, Pacer};
+use crate::pause::{Pause, PauseReason, IDLE_PAUSE_MS};
+use crate::replays::{self, LocalReplay};
+use crate::results::{self, Column, ResultRow, Sort};
+use crate::sparkline::polyline_points;
+use crate::track::{checkpoint_positions, lane_percent, visible_span, TrackScale};
+use crate::typing_engine::{finished, key_char, live_wpm, press, progress_due};
+use shared::typing::{Cursor, ErrorMode, KeyBurst, BACKSPACE};
+use shared::weakness::Weaknesses;
+use shared::wpm::{adjusted_wpm, burst_wpm, consistency, per_second_wpm};
 // no std::rc needed
 
 // Thread-local storage for the active WebSocket. This avoids capturing non-Send/Sync
 // types inside Leptos children closures, which require Fn + Send + Sync.
 thread_local! { static WS_REF: RefCell<Option<WebSocket>> = const { RefCell::new(None) }; }
+// Shown in the lobby of casual rooms, run through the same transform the server applies
+const CASUAL_SAMPLE: &str = "“Well, it’s a well-known fact!”";
 // Only enable testing UI in debug builds
 const ALLOW_TEST_UI: bool = cfg!(debug_assertions);
+// How often the race clock ticks (ms)
+const TICK_MS: f64 = 100.0;
+// Chars listed as the slowest in a replay's analysis
+const SLOWEST_KEYS: usize = 5;
+// How long an overtake stays on screen (ms)
+const OVERTAKE_FLASH_MS: u32 = 2_500;
 
 #[component]
 pub fn App() -> impl IntoView {
-    let (game_state, set_game_state) = signal("waiting".to_string());
-    let (players, set_players) = signal(Vec::<String>::new());
+    let (game_state, set_game_state) = signal(ClientState::Waiting);
+    let (players, set_players) = signal(Vec::<PlayerInfo>::new());
     let (passage, set_passage) = signal(String::new());
+    // The room's hash of the passage, to check our copy against
+    let (expected_hash, set_expected_hash) = signal(None::<u32>);
     let (player_positions, set_player_positions) = signal(HashMap::<String, usize>::new());
     let (current_position, set_current_position) = signal(0usize);
     let (errors, set_errors) = signal(0usize);
+    // Our place in the passage under the room's error mode; current_position/errors mirror it
+    let (cursor, set_cursor) = signal(Cursor::default());
+    let (key_burst, set_key_burst) = signal(KeyBurst::default());
     let (start_time, set_start_time) = signal(None::<f64>);
     let (last_progress_sent, set_last_progress_sent) = signal(0.0f64);
-    let (room_name, set_room_name) = signal("main".to_string());
+    let (room_name, set_room_name) = signal(room_from_link().or_else(room_from_url).unwrap_or_else(|| "main".to_string()));
     let (player_name, set_player_name) = signal("Player".to_string());
+    // Our player id as assigned by the server (Joined); lanes and positions are keyed by id
+    let (my_id, set_my_id) = signal(String::new());
     let (connected, set_connected) = signal(false);
-    let (_error_message, set_error_message) = signal(None::<String>);
+    let (_error_message, set_error_message) = signal(None::<ErrorCode>);
     let (wpm, set_wpm) = signal(0.0);
     let (accuracy, set_accuracy) = signal(100.0);
     let (time_elapsed, set_time_elapsed) = signal(0.0f64);
@@ -35,34 +77,276 @@ pub fn App() -> impl IntoView {
     let (joined, set_joined) = signal(false);
     let (connecting, set_connecting) = signal(false);
     let (finish_time, set_finish_time) = signal(None::<f64>);
-    let (leaderboard, set_leaderboard) = signal(Vec::<(String, f64, f64)>::new());
+    // (name, WPM, accuracy, splits) in finishing order
+    let (leaderboard, set_leaderboard) = signal(Vec::<ResultRow>::new());
+    let (results_sort, set_results_sort) = signal(Sort::default());
+    // Player id -> (checkpoint %, seconds) from ServerMsg::Split this race
+    let (splits, set_splits) = signal(HashMap::<String, Vec<(u8, f64)>>::new());
     let (test_mode, set_test_mode) = signal(false);
+    // Our place in the race and the one before it, from ServerMsg::Ranks
+    let (rank, set_rank) = signal(None::<(usize, usize)>);
+    // (sequence, phrase, other racer's name) for the overtake on screen; the sequence lets
+    // an expiring flash tell whether a newer one replaced it
+    let (overtake, set_overtake) = signal(None::<(u32, Phrase, String)>);
+    // Another room followed from the lobby, and the name typed to pick one
+    let (side_race, set_side_race) = signal(None::<SideRace>);
+    let (side_room, set_side_room) = signal(String::new());
+    // The lobby's join link was just copied
+    let (link_copied, set_link_copied) = signal(false);
+    // When the grace period after a full podium runs out (ms), and who it left behind
+    let (race_ends, set_race_ends) = signal(None::<f64>);
+    let (dnf, set_dnf) = signal(Vec::<String>::new());
+    // The finished race's moments, told under the results
+    let (race_summary, set_race_summary) = signal(Vec::<Moment>::new());
+    // Under FinishRule::Elimination: when last place is next out, who is out so far, and the
+    // latest race order from ServerMsg::Ranks, which puts whoever is on the bubble last
+    let (elimination_at, set_elimination_at) = signal(None::<f64>);
+    let (eliminated, set_eliminated) = signal(Vec::<String>::new());
+    let (race_order, set_race_order) = signal(Vec::<String>::new());
+    // Local practice only: the current pause, and when a key was last pressed (ms)
+    let (paused, set_paused) = signal(None::<Pause>);
+    let (last_input, set_last_input) = signal(0.0f64);
+    // Who paused the room's race (ServerMsg::Paused), while the host has it stopped
+    let (paused_by, set_paused_by) = signal(None::<String>);
     let (debug_flag, set_debug_flag) = signal(false);
+    // The latency overlay: whether it's open, our recent key-to-screen times, and the server's
+    // round trip to us (ServerMsg::Latency)
+    let (diagnostics_open, set_diagnostics_open) = signal(false);
+    let (input_latency, set_input_latency) = signal(InputLatency::default());
+    let (rtt_ms, set_rtt_ms) = signal(None::<u64>);
+    // Mid-race snapshots we were sent after falling behind on broadcasts; with the round trip
+    // they grade the connection and set how often progress goes out
+    let (resyncs, set_resyncs) = signal(Resyncs::default());
+    let quality = move || Quality::of(rtt_ms.get(), resyncs.with(|r| r.recent(js_sys::Date::now())));
+    let (percentile, set_percentile) = signal(None::<i32>);
+    // (category, new wpm, previous best) when our last finish set a personal best
+    let (personal_best, set_personal_best) = signal(None::<(PassageCategory, f64, f64)>);
+    // (xp gained, total xp, leveled up) for our last finish
+    let (xp_gained, set_xp_gained) = signal(None::<(u32, u64, bool)>);
+    // WPM so far, sampled once per second of the race for the finish-screen graph
+    let (wpm_samples, set_wpm_samples) = signal(Vec::<f64>::new());
+    // (seconds, position) every timer tick, for consistency and burst speed at the finish
+    let (pace_trace, set_pace_trace) = signal(Vec::<(f64, usize)>::new());
+    // (ms from the start, key) for every key this race, kept as a local replay at the finish
+    let (key_log, set_key_log) = signal(Vec::<(u32, char)>::new());
+    // (consistency, burst WPM, adjusted WPM) of our finished race
+    let (pace_stats, set_pace_stats) = signal(None::<(f64, f64, f64)>);
+    let (pace_target, set_pace_target) = signal(None::<f64>);
+    // "— Author, Title" for the current passage, when the server knows it
+    let (byline, set_byline) = signal(None::<String>);
+    // Fastest finish on the current passage in any room, and (new, previous) once it's beaten this race
+    let (passage_record, set_passage_record) = signal(None::<RoomRecord>);
+    let (new_record, set_new_record) = signal(None::<(RoomRecord, RoomRecord)>);
+    // What the personal drill being practised works on, and the next drill once the server has built it
+    let (drill_focus, set_drill_focus) = signal(None::<Weaknesses>);
+    let (next_drill, set_next_drill) = signal(None::<PracticeDrill>);
+    // The typing course, our bests on it, and the lesson being typed
+    let (lessons_open, set_lessons_open) = signal(false);
+    let (course, set_course) = signal(Vec::<Lesson>::new());
+    let (lesson_progress, set_lesson_progress) = signal(Vec::<LessonProgress>::new());
+    let (current_lesson, set_current_lesson) = signal(None::<Lesson>);
+    let (hall_of_fame, set_hall_of_fame) = signal(HallOfFame::default());
+    // Teams and legs of a relay race (ServerMsg::RelayTeams), and (team, seconds, WPM) as each comes home
+    let (relay, set_relay) = signal(None::<Relay>);
+    let (team_times, set_team_times) = signal(Vec::<(usize, f64, f64)>::new());
+    // Player ids per heat while the room races in heats (ServerMsg::Heats)
+    let (heats, set_heats) = signal(Vec::<Vec<String>>::new());
+    // Head-to-head score while exactly two humans share the room
+    let (duel, set_duel) = signal(None::<DuelRecord>);
+    // Watching the room rather than racing in it
+    let (spectating, set_spectating) = signal(false);
+    // Latest ServerMsg::Presence: sockets watching, and ids of players typing right now
+    let (spectators, set_spectators) = signal(0usize);
+    let (typing_ids, set_typing_ids) = signal(Vec::<String>::new());
+    let (track_scale, set_track_scale) = signal(TrackScale::default());
+    // Mark where the other racers are inside the passage too
+    let (marks_in_text, set_marks_in_text) = signal(false);
+    // (proposer id, ids who accepted) of the rematch on offer after a race
+    let (rematch, set_rematch) = signal(None::<(String, Vec<String>)>);
+    // Wins per player id across the current run of rematches
+    let (series_score, set_series_score) = signal(HashMap::<String, u32>::new());
+    let (room_settings, set_room_settings) = signal(RoomSettings::default());
+    // Local time the countdown ends, derived from the room's countdown length
+    let (countdown_ends, set_countdown_ends) = signal(None::<f64>);
+    let (countdown_left, set_countdown_left) = signal(0u32);
+    // Signed-in account (if any) and the OAuth providers the server offers
+    let (identity, set_identity) = signal(None::<Identity>);
+    // The account's friends, the friend code being typed, the outcome of the last invite we
+    // made, and the latest invite to us as (who, room)
+    let (friends, set_friends) = signal(Vec::<Friend>::new());
+    let (friend_code, set_friend_code) = signal(String::new());
+    let (invite_note, set_invite_note) = signal(None::<String>);
+    let (invited, set_invited) = signal(None::<(String, String)>);
+    // This browser's latest results, and the account's from the server once signed in
+    let (recent_races, set_recent_races) = signal(history::stored());
+    let (server_races, set_server_races) = signal(Vec::<RaceResult>::new());
+    let recent = Memo::new(move |_| history::merge(&recent_races.get(), server_races.get()));
+    // Replays kept on this browser, newest first, and the one being watched from `watch_started`
+    let (local_replays, set_local_replays) = signal(Vec::<LocalReplay>::new());
+    let (watching, set_watching) = signal(None::<LocalReplay>);
+    let (watch_started, set_watch_started) = signal(0.0);
+    let (watch_ms, set_watch_ms) = signal(0u32);
+    let reload_replays = move || wasm_bindgen_futures::spawn_local(async move {
+        match replays::all().await {
+            Ok(kept) => set_local_replays.set(kept),
+            Err(e) => web_sys::console::warn_2(&"Local replays unavailable".into(), &e),
+        }
+    });
+    reload_replays();
+    let (auth_providers, set_auth_providers) = signal(Vec::<String>::new());
+    let (profile, set_profile) = signal(None::<Profile>);
+    // Interface language, picked in the header; passages are shown as written whatever it is
+    let (locale, set_locale) = signal(i18n::detect());
+    Effect::new(move |_| i18n::set_document_lang(locale.get()));
+    let tr = move |phrase: Phrase| locale.get().text(phrase);
+    // Right-to-left passages run the typing area and the track from the right
+    let passage_dir = Memo::new(move |_| direction(&passage.get()).as_str());
+    // The pacing aid picked on the home screen; it only runs in practice or with no other humans
+    let (pacer, set_pacer) = signal(pacer::stored());
+    let pacing = move || pacer.get().filter(|_| !spectating.get() && (test_mode.get() || players.with(|p| p.iter().filter(|p| !p.is_bot).count() <= 1)));
+    // The other racers' markers for the passage, by position: (position, colour, name)
+    let text_marks = Memo::new(move |_| {
+        if !marks_in_text.get() { return Vec::new(); }
+        let (passage, me, positions) = (passage.get(), my_id.get(), player_positions.get());
+        let mut marks: Vec<(usize, (&'static str, String))> = players.with(|players| players.iter()
+            .filter(|p| p.id != me)
+            .filter_map(|p| positions.get(&p.id).map(|&pos| (grapheme_floor(&passage, pos), (car_color(p.color, p.pace_for.is_some()), p.name.clone()))))
+            .collect());
+        marks.sort_by_key(|(pos, _)| *pos);
+        marks
+    });
+    // Where its ghost caret is, while racing
+    let ghost = move || pacing().filter(|_| game_state.get() == ClientState::Racing).map(|p| p.position(time_elapsed.get(), char_len(&passage.get())));
+    wasm_bindgen_futures::spawn_local(async move { set_profile.set(fetch_profile().await); });
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Some(me) = fetch_json::<Identity>("/api/me").await {
+            set_player_name.set(me.display_name.clone());
+            set_server_races.set(fetch_races().await);
+            set_friends.set(fetch_json("/api/me/friends").await.unwrap_or_default());
+            set_identity.set(Some(me));
+        }
+        if let Some(providers) = fetch_json::<Vec<String>>("/auth/providers").await { set_auth_providers.set(providers); }
+    });
     
     // WebSocket is managed via thread-local storage (WS_REF)
 
+    // Wrap up our own race after `elapsed` seconds: final stats, the pace summary, and the
+    // Finish message (typed to the end, or out of time in a timed race)
+    let finish_race = move |elapsed: f64| {
+        let (next_pos, correct, keystrokes, errs) = cursor.with_untracked(|c| (c.pos, c.correct(), c.keystrokes, c.errors));
+        let w = if elapsed > 0.0 { (correct as f64 / 5.0) / (elapsed / 60.0) } else { 0.0 };
+        let a = shared::wpm::accuracy(keystrokes - errs, keystrokes);
+        set_wpm.set(w.max(0.0));
+        set_accuracy.set(a);
+        set_finish_time.set(Some(elapsed));
+        set_wpm_samples.update(|s| s.push(w.max(0.0)));
+        set_pace_trace.update(|t| t.push((elapsed, next_pos)));
+        let per_second = pace_trace.with_untracked(|t| per_second_wpm(t));
+        set_pace_stats.set(Some((consistency(&per_second), burst_wpm(&per_second), adjusted_wpm(w, a))));
+        let room = (!test_mode.get_untracked()).then(|| room_name.get_untracked());
+        let finished_at = js_sys::Date::now() as i64;
+        set_recent_races.set(history::record(RecentRace { passage: Some(passage.get_untracked()), wpm: w, accuracy: a, finished_at, room }));
+        let replay = LocalReplay { finished_at, passage: passage.get_untracked(), mode: room_settings.get_untracked().error_mode, wpm: w, accuracy: a, keys: key_log.get_untracked() };
+        if !replay.keys.is_empty() {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = replays::save(&replay).await { web_sys::console::warn_2(&"Replay not saved".into(), &e); }
+                reload_replays();
+            });
+        }
+        if !test_mode.get_untracked() {
+            WS_REF.with(|cell| {
+                if let Some(ws) = cell.borrow().as_ref() {
+                    let msg = ClientMsg::Finish { wpm: w, accuracy: a, time: elapsed, ts: js_sys::Date::now() as u64, missed: cursor.with_untracked(|c| c.missed.clone()), errors: cursor.with_untracked(|c| c.errors) };
+                    if let Ok(json) = serde_json::to_string(&msg) { let _ = ws.send_with_str(&json); }
+                }
+            });
+        } else if drill_focus.get_untracked().is_some() {
+            // Where this drill's mistakes fell picks what the next one works on
+            let report = PracticeReport { passage: passage.get_untracked(), missed: cursor.with_untracked(|c| c.missed.clone()) };
+            set_next_drill.set(None);
+            wasm_bindgen_futures::spawn_local(async move { set_next_drill.set(post_json(&me_url("drill"), &report).await); });
+        } else if let Some(lesson) = current_lesson.get_untracked() {
+            // Counted here straight away, so the next lesson opens even when the server can't store it
+            let attempt = LessonAttempt { wpm: w, accuracy: a };
+            set_lesson_progress.update(|p| lessons::record(p, &lesson, &attempt));
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(progress) = post_json(&me_url(&format!("lessons/{}", lesson.id)), &attempt).await { set_lesson_progress.set(progress); }
+            });
+        }
+    };
+
     // Lightweight timer loop: update elapsed time every 100ms using server t0
     {
         let game_state_sig = game_state;
         let start_time_sig = start_time;
         let set_time_elapsed_sig = set_time_elapsed;
+        // Pacer clicks already scheduled this race
+        let mut clicked = 0;
         if let Some(win) = web_sys::window() {
             let cb = Closure::wrap(Box::new(move || {
-                if game_state_sig.get_untracked() == "racing" {
+                if watching.with_untracked(Option::is_some) {
+                    set_watch_ms.set((js_sys::Date::now() - watch_started.get_untracked()).max(0.0) as u32);
+                }
+                if game_state_sig.get_untracked() == ClientState::Countdown {
+                    if let Some(ends) = countdown_ends.get_untracked() {
+                        let left = ((ends - js_sys::Date::now()) / 1000.0).ceil().max(0.0) as u32;
+                        if left != countdown_left.get_untracked() { set_countdown_left.set(left); }
+                    }
+                }
+                if game_state_sig.get_untracked() == ClientState::Racing {
                     if let Some(t0_ms) = start_time_sig.get_untracked() {
+                        // A paused practice run keeps its clock stopped
+                        if paused.get_untracked().is_some() { return; }
                         let now_ms = js_sys::Date::now();
+                        if test_mode.get_untracked() && finish_time.get_untracked().is_none() {
+                            if let Some(pause) = Pause::idle(last_input.get_untracked(), now_ms) {
+                                set_time_elapsed_sig.set(pause.elapsed(t0_ms));
+                                set_paused.set(Some(pause));
+                                return;
+                            }
+                        }
                         let elapsed = (now_ms - t0_ms) / 1000.0;
                         if elapsed >= 0.0 {
                             set_time_elapsed_sig.set(elapsed);
+                            set_pace_trace.update(|t| t.push((elapsed, current_position.get_untracked())));
+                        }
+                        // Clicks go out a tick ahead, timed by the audio clock; a new race starts the count again
+                        if let Some(pacer) = untrack(pacing).filter(|p| p.sound) {
+                            let due = pacer.beats_by(elapsed + TICK_MS / 1000.0);
+                            if due > clicked { pacer::click(pacer.beat_at(due) - elapsed); }
+                            clicked = due;
+                        }
+                        if elapsed >= (wpm_samples.with_untracked(Vec::len) + 1) as f64 {
+                            let sample = shared::wpm::wpm(current_position.get_untracked(), elapsed);
+                            set_wpm_samples.update(|s| s.push(sample));
+                        }
+                        if let Some(limit) = room_settings.get_untracked().mode.time_limit_secs() {
+                            if elapsed >= f64::from(limit) && finish_time.get_untracked().is_none() {
+                                finish_race(f64::from(limit));
+                            }
                         }
                     }
                 }
             }) as Box<dyn FnMut()>);
-            let _ = win.set_interval_with_callback_and_timeout_and_arguments_0(cb.as_ref().unchecked_ref(), 100);
+            let _ = win.set_interval_with_callback_and_timeout_and_arguments_0(cb.as_ref().unchecked_ref(), TICK_MS as i32);
             cb.forget();
         }
     }
 
+    // Hiding the tab pauses local practice too
+    if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
+        let cb = Closure::wrap(Box::new(move || {
+            let hidden = web_sys::window().and_then(|w| w.document()).is_some_and(|d| d.hidden());
+            let practicing = test_mode.get_untracked() && game_state.get_untracked() == ClientState::Racing && finish_time.get_untracked().is_none();
+            let Some(t0_ms) = start_time.get_untracked().filter(|_| hidden && practicing && paused.get_untracked().is_none()) else { return };
+            let pause = Pause { at: js_sys::Date::now(), reason: PauseReason::Hidden };
+            set_time_elapsed.set(pause.elapsed(t0_ms));
+            set_paused.set(Some(pause));
+        }) as Box<dyn FnMut()>);
+        let _ = doc.add_event_listener_with_callback("visibilitychange", cb.as_ref().unchecked_ref());
+        cb.forget();
+    }
+
     let connect_websocket = {
         move || {
             let win = web_sys::window().unwrap();
@@ -70,7 +354,10 @@ pub fn App() -> impl IntoView {
             let host = loc.host().unwrap();
             let protocol = loc.protocol().unwrap_or_else(|_| "http:".into());
             let ws_scheme = if protocol == "https:" { "wss" } else { "ws" };
-            let ws_url = format!("{ws_scheme}://{host}/ws");
+            let ws_url = match stored_guest_token() {
+                Some(token) => format!("{ws_scheme}://{host}/ws?guest={}", String::from(js_sys::encode_uri_component(&token))),
+                None => format!("{ws_scheme}://{host}/ws"),
+            };
             
             match WebSocket::new(&ws_url) {
                 Ok(ws) => {
@@ -85,8 +372,18 @@ pub fn App() -> impl IntoView {
                         let onopen = Closure::wrap(Box::new(move || {
                             set_connected_cb.set(true);
                             set_connecting_cb.set(false);
-                            // Auto-join the room once the socket is open
-                            let msg = ClientMsg::Join { room: room_name_sig.get(), name: player_name_sig.get() };
+                            // Pace setting is per-connection, so send it ahead of the join
+                            if let Some(wpm) = pace_target.get_untracked() {
+                                if let Ok(json) = serde_json::to_string(&ClientMsg::SetPace { wpm: Some(wpm) }) {
+                                    WS_REF.with(|cell| { if let Some(ws) = cell.borrow().as_ref() { let _ = ws.send_with_str(&json); } });
+                                }
+                            }
+                            // Auto-join (or start watching) the room once the socket is open
+                            let msg = if spectating.get_untracked() {
+                                ClientMsg::Spectate { room: room_name_sig.get() }
+                            } else {
+                                ClientMsg::Join { room: room_name_sig.get(), name: player_name_sig.get() }
+                            };
                             if let Ok(json) = serde_json::to_string(&msg) {
                                 // Best-effort send
                                 WS_REF.with(|cell| {
@@ -107,9 +404,10 @@ pub fn App() -> impl IntoView {
                         let set_connecting_cb = set_connecting;
                         let onclose = Closure::wrap(Box::new(move |_e: web_sys::CloseEvent| {
                             set_connected_cb.set(false);
-                            set_state_cb.set("waiting".to_string());
+                            set_state_cb.set(ClientState::Waiting);
                             set_joined_cb.set(false);
                             set_connecting_cb.set(false);
+                            set_side_race.set(None);
                         }) as Box<dyn FnMut(_)>);
                         ws.set_onclose(Some(onclose.as_ref().unchecked_ref()));
                         onclose.forget();
@@ -128,11 +426,14 @@ pub fn App() -> impl IntoView {
                         let set_time_elapsed_cb = set_time_elapsed;
                         let set_error_message = set_error_message;
                         let set_player_positions2 = set_player_positions;
-                        let player_name_signal = player_name;
+                        let my_id_signal = my_id;
                         let set_leaderboard_cb = set_leaderboard;
                         let set_finish_time_cb = set_finish_time;
-                        let my_name_for_finish = player_name;
+                        let my_id_for_finish = my_id;
+                        let players_for_finish = players;
                         let test_mode_sig = test_mode;
+                        let set_joined_cb = set_joined;
+                        let set_player_name_cb = set_player_name;
                         
                         Closure::wrap(Box::new(move |e: web_sys::MessageEvent| {
                             if let Some(text) = e.data().as_string() {
@@ -141,42 +442,98 @@ pub fn App() -> impl IntoView {
                                         // Ignore server-driven flow while in local test mode, except errors
                                         if !matches!(msg, ServerMsg::Error { .. }) { return; }
                                     }
+                                    // Our race is our heat's; the others' go by, as do all of them for spectators
+                                    let msg = match msg {
+                                        ServerMsg::Heat { heat, msg } => {
+                                            let me = my_id_signal.get_untracked();
+                                            if !heats.with_untracked(|h| h.get(heat).is_some_and(|ids| ids.contains(&me))) { return; }
+                                            *msg
+                                        }
+                                        msg => msg,
+                                    };
+                                    let state = game_state.get_untracked();
+                                    if !state.accepts(&msg) {
+                                        web_sys::console::warn_1(&format!("Ignoring {msg:?} while {state:?}").into());
+                                        return;
+                                    }
+                                    let next = state.next(&msg, &my_id_signal.get_untracked());
+                                    if next != state { set_game_state.set(next); }
+                                    let resynced = matches!(msg, ServerMsg::Sync { .. });
                                     match msg {
-                                        ServerMsg::Lobby { players: p } => {
+                                        ServerMsg::Joined { id, name } => {
+                                            // The server may have renamed us (e.g. "Player" -> "Player-2")
+                                            set_player_name_cb.set(name);
+                                            set_my_id.set(id);
+                                        }
+                                        ServerMsg::Lobby { players: p, hall_of_fame, duel } => {
+                                            set_duel.set(duel);
                                             web_sys::console::log_1(&format!("Lobby update: {} players", p.len()).into());
                                             set_players.set(p);
+                                            set_hall_of_fame.set(hall_of_fame);
                                         }
-                                        ServerMsg::Countdown { passage: p } => {
+                                        ServerMsg::PlayerLeft { id } => {
+                                            // Their lane goes now rather than with the next Lobby
+                                            set_players.update(|p| p.retain(|p| p.id != id));
+                                            set_player_positions2.update(|m| { m.remove(&id); });
+                                            set_typing_ids.update(|ids| ids.retain(|i| *i != id));
+                                        }
+                                        ServerMsg::Countdown { passage: p, attribution, record, hash } => {
                                             // Prepare passage early so UI can render instantly
                                             set_passage.set(p);
-                                            set_game_state.set("countdown".to_string());
+                                            set_expected_hash.set(Some(hash));
+                                            set_byline.set(attribution.and_then(|a| a.byline()));
+                                            set_passage_record.set(record);
+                                            set_relay.set(None);
+                                            set_team_times.set(Vec::new());
+                                            let secs = room_settings.get_untracked().countdown_secs;
+                                            set_countdown_ends.set(Some(js_sys::Date::now() + f64::from(secs) * 1000.0));
+                                            set_countdown_left.set(secs);
                                             set_current_position.set(0);
+                                            set_cursor.set(Cursor::default());
                                             set_errors.set(0);
                                             set_wpm.set(0.0);
                                             set_accuracy.set(100.0);
                                             set_last_progress_sent.set(0.0);
                                             set_player_positions2.set(HashMap::new());
-                                            let me = player_name_signal.get();
+                                            let me = my_id_signal.get();
                                             set_player_positions2.update(|m| { m.insert(me, 0); });
                                         }
-                                        ServerMsg::Start { passage: p, t0 } => {
+                                        ServerMsg::Start { passage: p, t0, attribution, hash } => {
                                             set_passage.set(p);
-                                            set_game_state.set("racing".to_string());
+                                            set_expected_hash.set(Some(hash));
+                                            set_byline.set(attribution.and_then(|a| a.byline()));
                                             // Use server start time for sync across clients
                                             set_start_time.set(Some(t0 as f64));
                                             set_time_elapsed_cb.set(0.0);
                                             set_current_position.set(0);
+                                            set_cursor.set(Cursor::default());
                                             set_errors.set(0);
                                             set_wpm.set(0.0);
                                             set_accuracy.set(100.0);
                                             set_last_progress_sent.set(0.0);
                                             set_player_positions2.set(HashMap::new());
                                             // Initialize our own lane position to 0 for immediate render
-                                            let me = player_name_signal.get();
+                                            let me = my_id_signal.get();
                                             set_player_positions2.update(|m| { m.insert(me, 0); });
                                             set_waiting_seconds.set(0);
                                             set_finish_time_cb.set(None);
                                             set_leaderboard_cb.set(Vec::new());
+                                            set_splits.set(HashMap::new());
+                                            set_personal_best.set(None);
+                                            set_new_record.set(None);
+                                            set_xp_gained.set(None);
+                                            set_wpm_samples.set(Vec::new());
+                                            set_pace_trace.set(Vec::new());
+                                            set_key_log.set(Vec::new());
+                                            set_pace_stats.set(None);
+                                            set_race_ends.set(None);
+                                            set_dnf.set(Vec::new());
+                                            set_race_summary.set(Vec::new());
+                                            set_elimination_at.set(None);
+                                            set_eliminated.set(Vec::new());
+                                            set_race_order.set(Vec::new());
+                                            set_rank.set(None);
+                                            set_overtake.set(None);
 
                                             // Focus the typing area if present
                                             if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
@@ -192,22 +549,63 @@ pub fn App() -> impl IntoView {
                                                 positions.insert(id, pos);
                                             });
                                         }
-                                        ServerMsg::Finish { id, wpm: player_wpm, accuracy: player_accuracy } => {
+                                        ServerMsg::Ranks { order } => {
+                                            let me = my_id_signal.get_untracked();
+                                            let place = order.iter().position(|id| *id == me).map(|i| i + 1);
+                                            // Only a change of our own place re-renders, so its animation plays out
+                                            if place != rank.get_untracked().map(|(now, _)| now) {
+                                                set_rank.set(place.map(|now| (now, rank.get_untracked().map_or(now, |(previous, _)| previous))));
+                                            }
+                                            set_race_order.set(order);
+                                        }
+                                        ServerMsg::Overtake { overtaker, overtaken } => {
+                                            let me = my_id_signal.get_untracked();
+                                            let (phrase, other) = if overtaken == me {
+                                                (Phrase::PassedYou, overtaker)
+                                            } else if overtaker == me {
+                                                (Phrase::YouPassed, overtaken)
+                                            } else {
+                                                return;
+                                            };
+                                            let name = players_for_finish.get_untracked().iter().find(|p| p.id == other).map(|p| p.name.clone()).unwrap_or(other);
+                                            let seq = overtake.get_untracked().map_or(0, |(seq, ..)| seq.wrapping_add(1));
+                                            set_overtake.set(Some((seq, phrase, name)));
+                                            wasm_bindgen_futures::spawn_local(async move {
+                                                gloo_timers::future::TimeoutFuture::new(OVERTAKE_FLASH_MS).await;
+                                                if overtake.get_untracked().is_some_and(|(current, ..)| current == seq) { set_overtake.set(None); }
+                                            });
+                                        }
+                                        ServerMsg::Split { id, checkpoint, time } => {
+                                            set_splits.update(|s| s.entry(id).or_default().push((checkpoint, time)));
+                                        }
+                                        ServerMsg::Finish { id, wpm: player_wpm, accuracy: player_accuracy, errors: player_errors, raw_wpm, time, consistency, counted, .. } => {
                                             web_sys::console::log_1(&format!("Player {id} finished with {player_wpm} WPM, {player_accuracy}% accuracy").into());
                                             // Update leaderboard, append in arrival order
-                                            set_leaderboard_cb.update(|lb| lb.push((id.clone(), player_wpm, player_accuracy)));
+                                            let finisher = players_for_finish.get_untracked().iter().find(|p| p.id == id).map(|p| p.name.clone()).unwrap_or_else(|| id.clone());
+                                            let finisher_splits = splits.with_untracked(|s| s.get(&id).cloned().unwrap_or_default());
+                                            let golf = room_settings.get_untracked().scoring == Scoring::Golf;
+                                            let row = ResultRow { rank: 0, name: finisher, wpm: player_wpm, raw_wpm, accuracy: player_accuracy, errors: player_errors, counted, time, consistency, splits: finisher_splits, heat: None };
+                                            set_leaderboard_cb.update(|lb| results::add(lb, row, golf));
                                             // If this is me, update my stats and move to finished state
-                                            if id == my_name_for_finish.get() {
+                                            if id == my_id_for_finish.get() {
                                                 set_wpm.set(player_wpm);
                                                 set_accuracy.set(player_accuracy);
-                                                set_game_state.set("finished".to_string());
+                                                set_percentile.set(None);
+                                                wasm_bindgen_futures::spawn_local(async move {
+                                                    if let Some(p) = fetch_percentile(player_wpm).await { set_percentile.set(Some(p)); }
+                                                    // The result is stored in the background; a finished race may extend the streak
+                                                    gloo_timers::future::TimeoutFuture::new(1_000).await;
+                                                    if let Some(p) = fetch_profile().await { set_profile.set(Some(p)); }
+                                                    if identity.get_untracked().is_some() { set_server_races.set(fetch_races().await); }
+                                                });
                                             }
                                         }
                     ServerMsg::StateChange { state } => {
-                                            let is_waiting = state == "waiting";
-                                            set_game_state.set(state);
-                                            if is_waiting {
+                                            if state == GamePhase::Waiting {
+                                                set_rematch.set(None);
+                                                set_series_score.set(HashMap::new());
                                                 set_current_position.set(0);
+                                                set_cursor.set(Cursor::default());
                                                 set_errors.set(0);
                                                 set_wpm.set(0.0);
                                                 set_accuracy.set(100.0);
@@ -215,20 +613,108 @@ pub fn App() -> impl IntoView {
                         set_waiting_seconds.set(0);
                                                 set_finish_time_cb.set(None);
                                                 set_leaderboard_cb.set(Vec::new());
+                                                set_race_ends.set(None);
+                                                set_dnf.set(Vec::new());
+                                                set_elimination_at.set(None);
+                                                set_eliminated.set(Vec::new());
+                                                set_race_order.set(Vec::new());
+                                                set_rank.set(None);
+                                                set_paused_by.set(None);
+                                                set_heats.set(Vec::new());
+                                            }
+                                        }
+                                        ServerMsg::EliminationIn { secs_left } => set_elimination_at.set(Some(js_sys::Date::now() + f64::from(secs_left) * 1000.0)),
+                                        ServerMsg::Eliminated { id } => {
+                                            // The next EliminationIn, if any, restarts the clock
+                                            set_elimination_at.set(None);
+                                            set_eliminated.update(|out| out.push(id));
+                                        }
+                                        ServerMsg::RelayTeams { relay } => set_relay.set(Some(relay)),
+                                        ServerMsg::Heats { heats } => set_heats.set(heats),
+                                        // Taken out of its wrapper above
+                                        ServerMsg::Heat { .. } => {}
+                                        ServerMsg::HeatResults { results: everyone } => set_leaderboard_cb.update(|rows| *rows = results::overall(rows, &everyone)),
+                                        ServerMsg::Handoff { team, leg, id } => {
+                                            set_relay.update(|r| if let Some(r) = r.as_mut() { r.current[team] = leg; });
+                                            // Our leg picks up where the last runner handed off
+                                            if id == my_id_signal.get_untracked() {
+                                                let start = relay.with_untracked(|r| r.as_ref().and_then(|r| r.leg(leg)).map_or(0, |l| l.start));
+                                                set_current_position.set(start);
+                                                set_cursor.set(Cursor { pos: start, ..Cursor::default() });
                                             }
                                         }
-                                         ServerMsg::WaitingTimer { seconds_left } => {
-                                             set_waiting_seconds.set(seconds_left);
-                                             if seconds_left == 0 && game_state.get() == "waiting" {
-                                                 // Move to a lightweight countdown state so the race UI shows instantly
-                                                 set_game_state.set("countdown".to_string());
-                                             }
-                                         }
-                                        ServerMsg::Error { message } => {
-                                            set_error_message.set(Some(message.clone()));
-                                            web_sys::console::error_1(&message.into());
+                                        ServerMsg::TeamFinish { team, time, wpm } => {
+                                            set_relay.update(|r| if let Some(r) = r.as_mut() { r.current[team] = r.legs.len(); });
+                                            set_team_times.update(|t| t.push((team, time, wpm)));
+                                        }
+                                        ServerMsg::Paused { by } => set_paused_by.set(Some(by)),
+                                        ServerMsg::Resumed { t0 } => {
+                                            set_paused_by.set(None);
+                                            // The start moved on by the length of the pause, so the clock carries on where it stopped
+                                            set_start_time.set(Some(t0 as f64));
+                                        }
+                                        ServerMsg::RaceEnding { secs_left } => set_race_ends.set(Some(js_sys::Date::now() + f64::from(secs_left) * 1000.0)),
+                                        ServerMsg::DidNotFinish { ids } => {
+                                            set_race_ends.set(None);
+                                            set_dnf.set(ids);
+                                        }
+                                        ServerMsg::RaceSummary { moments } => set_race_summary.set(moments),
+                                        ServerMsg::WaitingTimer { seconds_left } => set_waiting_seconds.set(seconds_left),
+                                        ServerMsg::Presence { spectators, typing } => {
+                                            set_spectators.set(spectators);
+                                            set_typing_ids.set(typing);
+                                        }
+                                        ServerMsg::RematchProposed { by, accepted } => set_rematch.set(Some((by, accepted))),
+                                        ServerMsg::SeriesScore { wins } => set_series_score.set(wins),
+                                        ServerMsg::GuestToken { token } => store_guest_token(&token),
+                                        ServerMsg::Invite { from, from_name, room } => {
+                                            let who = from_name.unwrap_or_else(|| locale.get_untracked().format(Phrase::FriendNumber, &[("id", &from)]));
+                                            set_invited.set(Some((who, room)));
+                                        }
+                                        ServerMsg::Latency { rtt_ms } => set_rtt_ms.set(Some(rtt_ms)),
+                                        ServerMsg::Watched { room, msg } => set_side_race.update(|side| {
+                                            if let Some(side) = side.as_mut().filter(|side| side.room == room) { side.apply(*msg); }
+                                        }),
+                                        ServerMsg::PersonalBest { category, wpm, previous_wpm } => set_personal_best.set(Some((category, wpm, previous_wpm))),
+                                        ServerMsg::NewRecord { record, previous } => {
+                                            set_passage_record.set(Some(record.clone()));
+                                            set_new_record.set(Some((record, previous)));
+                                        }
+                                        ServerMsg::XpGained { xp, total_xp, leveled_up, .. } => set_xp_gained.set(Some((xp, total_xp, leveled_up))),
+                                        ServerMsg::Settings { settings } => {
+                                            if settings.auto_start_secs.is_none() { set_waiting_seconds.set(0); }
+                                            set_room_settings.set(settings);
+                                        }
+                                        ServerMsg::Sync { state: _, passage: p, players: roster, positions, t0, settings, attribution, record, relay, hash } => {
+                                            // Only a socket that fell behind is resynced mid-race
+                                            if matches!(state, ClientState::Racing | ClientState::Paused) { set_resyncs.update(|r| r.record(js_sys::Date::now())); }
+                                            set_relay.set(relay.map(|r| *r));
+                                            set_byline.set(attribution.and_then(|a| a.byline()));
+                                            set_passage_record.set(record);
+                                            // Authoritative snapshot: replace everything we know about the room
+                                            set_room_settings.set(settings);
+                                            set_players.set(roster);
+                                            set_passage.set(p.unwrap_or_default());
+                                            set_expected_hash.set(hash);
+                                            let my_pos = positions.get(&my_id_signal.get_untracked()).copied().unwrap_or(0);
+                                            set_player_positions2.set(positions);
+                                            set_current_position.set(my_pos);
+                                            set_cursor.set(Cursor { pos: my_pos, ..Cursor::default() });
+                                            set_start_time.set(t0.map(|t| t as f64));
+                                        }
+                                        ServerMsg::Error { code, message } => {
+                                            web_sys::console::error_1(&format!("{code:?}: {message}").into());
+                                            // Let the player fix their name / pick another room and join again
+                                            if matches!(code, ErrorCode::NameTaken | ErrorCode::RoomFull | ErrorCode::NameTooLong | ErrorCode::NameReserved | ErrorCode::NameNotAllowed | ErrorCode::Removed | ErrorCode::Banned) { set_joined_cb.set(false); }
+                                            set_error_message.set(Some(code));
                                         }
                                     }
+                                    // After a phase change or a snapshot our passage must still be the room's;
+                                    // if it drifted, take the room's again (unless that's what just arrived)
+                                    if (next != state || resynced) && !passage_agrees(expected_hash.get_untracked(), &passage.get_untracked()) {
+                                        web_sys::console::error_1(&format!("Passage differs from the room's while {next:?}").into());
+                                        if !resynced { send(&ClientMsg::Resync); }
+                                    }
                                 } else {
                                     web_sys::console::error_1(&"Failed to parse ServerMsg JSON".into());
                                 }
@@ -254,9 +740,10 @@ pub fn App() -> impl IntoView {
         move || {
         WS_REF.with(|cell| {
                 if let Some(ws) = cell.borrow().as_ref() {
-                    let msg = ClientMsg::Join {
-                        room: room_name.get(),
-                        name: player_name.get(),
+                    let msg = if spectating.get_untracked() {
+                        ClientMsg::Spectate { room: room_name.get() }
+                    } else {
+                        ClientMsg::Join { room: room_name.get(), name: player_name.get() }
                     };
                     if let Ok(json) = serde_json::to_string(&msg) {
                         let _ = ws.send_with_str(&json);
@@ -267,45 +754,322 @@ pub fn App() -> impl IntoView {
         }
     };
 
+    let join = move || {
+        // Spectators may still take a lane
+        if (joined.get() && !spectating.get()) || connecting.get() { return; }
+        set_spectating.set(false);
+        if !connected.get() { connect_websocket(); } else { join_room(); }
+    };
+    // Open a room under a fresh code and join it
+    let new_room = move || {
+        if joined.get_untracked() || connecting.get_untracked() { return; }
+        wasm_bindgen_futures::spawn_local(async move {
+            let Some(created) = fetch_json::<CreatedRoom>("/api/rooms/new").await else { return };
+            set_room_name.set(created.room);
+            join();
+        });
+    };
+    // A join link joins as soon as the page is up
+    if room_from_link().is_some() { join(); }
+    let watch = move || {
+        if joined.get() || connecting.get() { return; }
+        set_spectating.set(true);
+        if !connected.get() { connect_websocket(); } else { join_room(); }
+    };
+    let race_again = move || {
+        // Optimistic local reset for snappy UX
+        set_game_state.set(ClientState::Waiting);
+        set_current_position.set(0);
+        set_cursor.set(Cursor::default());
+        set_errors.set(0);
+        set_wpm.set(0.0);
+        set_accuracy.set(100.0);
+        set_time_elapsed.set(0.0);
+        set_finish_time.set(None);
+        set_leaderboard.set(Vec::new());
+        set_player_positions.set(HashMap::new());
+        set_test_mode.set(false);
+        set_drill_focus.set(None);
+        set_current_lesson.set(None);
+        set_paused.set(None);
+        set_race_summary.set(Vec::new());
+        send(&ClientMsg::Reset);
+    };
+    // Out of the room but still connected, ready to join another
+    let leave = move || {
+        send(&ClientMsg::Leave);
+        set_joined.set(false);
+        set_spectating.set(false);
+        set_test_mode.set(false);
+        set_drill_focus.set(None);
+        set_current_lesson.set(None);
+        set_game_state.set(ClientState::Waiting);
+        set_players.set(Vec::new());
+        set_player_positions.set(HashMap::new());
+        set_passage.set(String::new());
+        set_expected_hash.set(None);
+        set_byline.set(None);
+        set_passage_record.set(None);
+        set_new_record.set(None);
+        set_start_time.set(None);
+        set_finish_time.set(None);
+        set_leaderboard.set(Vec::new());
+        set_waiting_seconds.set(0);
+        set_rematch.set(None);
+        set_series_score.set(HashMap::new());
+        set_duel.set(None);
+        set_hall_of_fame.set(HallOfFame::default());
+        set_race_ends.set(None);
+        set_dnf.set(Vec::new());
+        set_race_summary.set(Vec::new());
+        set_elimination_at.set(None);
+        set_eliminated.set(Vec::new());
+        set_race_order.set(Vec::new());
+        set_rank.set(None);
+    };
+    // Type `text` on our own, with no server involved
+    let start_practice = move |text: String| {
+        set_test_mode.set(true);
+        set_passage.set(text);
+        set_expected_hash.set(None);
+        set_game_state.set(ClientState::Racing);
+        set_start_time.set(Some(js_sys::Date::now()));
+        set_last_input.set(js_sys::Date::now());
+        set_paused.set(None);
+        set_current_position.set(0);
+        set_cursor.set(Cursor::default());
+        set_errors.set(0);
+        set_wpm.set(0.0);
+        set_accuracy.set(100.0);
+        set_last_progress_sent.set(0.0);
+        set_player_positions.set(HashMap::new());
+        let me = PlayerInfo { id: "local".to_string(), name: player_name.get_untracked(), is_bot: false, is_host: true, rating: None, pace_for: None, level: None, color: 0 };
+        set_my_id.set(me.id.clone());
+        set_player_positions.update(|m| { m.insert(me.id.clone(), 0); });
+        set_players.set(vec![me]);
+        set_waiting_seconds.set(0);
+        set_finish_time.set(None);
+        set_leaderboard.set(Vec::new());
+        set_wpm_samples.set(Vec::new());
+        set_pace_trace.set(Vec::new());
+        set_key_log.set(Vec::new());
+        set_pace_stats.set(None);
+        set_byline.set(None);
+        set_passage_record.set(None);
+        set_new_record.set(None);
+    };
+    // A drill on what we miss most, from the server's count of our mistakes
+    let practice_drill = move |drill: PracticeDrill| {
+        set_current_lesson.set(None);
+        set_drill_focus.set(Some(drill.focus));
+        set_next_drill.set(None);
+        start_practice(drill.passage);
+    };
+    let start_lesson = move |lesson: Lesson| {
+        set_lessons_open.set(false);
+        set_drill_focus.set(None);
+        set_current_lesson.set(Some(lesson.clone()));
+        start_practice(lesson.text);
+    };
+    let open_lessons = move || {
+        set_lessons_open.update(|open| *open = !*open);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(lessons) = fetch_json::<Vec<Lesson>>("/api/lessons").await { set_course.set(lessons); }
+            if let Some(progress) = fetch_json::<Vec<LessonProgress>>(&me_url("lessons")).await { set_lesson_progress.set(progress); }
+        });
+    };
+    // The lesson after the current one, once the current one is passed
+    let next_lesson = move || {
+        let current = current_lesson.get()?;
+        let course = course.get();
+        let at = course.iter().position(|l| l.id == current.id)?;
+        (lessons::unlocked(&course, &lesson_progress.get()) > at + 1).then(|| course.get(at + 1).cloned()).flatten()
+    };
+    let practice_weak_spots = move || {
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(drill) = fetch_json::<PracticeDrill>(&me_url("drill")).await { practice_drill(drill); }
+        });
+    };
+
+    // Command palette (Ctrl/Cmd+K)
+    let (palette_open, set_palette_open) = signal(false);
+    let (palette_query, set_palette_query) = signal(String::new());
+    let (palette_pick, set_palette_pick) = signal(0usize);
+    let available = move |command: Command| {
+        let state = game_state.get();
+        let racer = joined.get() && !spectating.get();
+        let host_in_lobby = state == ClientState::Waiting && players.get().iter().any(|p| p.is_host && p.id == my_id.get());
+        match command {
+            Command::Join => !racer && !connecting.get(),
+            Command::Watch => !joined.get() && !connecting.get(),
+            Command::RaceAgain => state == ClientState::Finished,
+            Command::Rematch => state == ClientState::Finished && racer && !test_mode.get(),
+            Command::Leave => joined.get(),
+            Command::Track(scale) => track_scale.get() != scale,
+            Command::Mistakes(mode) => host_in_lobby && room_settings.get().error_mode != mode,
+            Command::Casual => host_in_lobby,
+            Command::Diagnostics => true,
+            Command::Language(l) => locale.get() != l,
+        }
+    };
+    let palette_commands = Memo::new(move |_| {
+        let query = palette_query.get();
+        Command::all().into_iter()
+            .filter(|c| available(*c))
+            .map(|c| (c, c.label(locale.get())))
+            .filter(|(_, label)| hotkeys::matches(label, &query))
+            .collect::<Vec<_>>()
+    });
+    let open_palette = move || {
+        set_palette_query.set(String::new());
+        set_palette_pick.set(0);
+        set_palette_open.set(true);
+        request_animation_frame(|| focus_element("commandPalette"));
+    };
+    let close_palette = move || {
+        set_palette_open.set(false);
+        focus_element("typingArea");
+    };
+    let run_command = move |command: Command| {
+        close_palette();
+        match command {
+            Command::Join => join(),
+            Command::Watch => watch(),
+            Command::RaceAgain => race_again(),
+            Command::Rematch => send(&ClientMsg::RematchAccept),
+            Command::Leave => leave(),
+            Command::Track(scale) => set_track_scale.set(scale),
+            Command::Mistakes(error_mode) => send_settings(RoomSettings { error_mode, ..room_settings.get_untracked() }),
+            Command::Casual => {
+                let settings = room_settings.get_untracked();
+                send_settings(RoomSettings { casual: !settings.casual, ..settings });
+            }
+            Command::Diagnostics => set_diagnostics_open.update(|open| *open = !*open),
+            Command::Language(picked) => {
+                i18n::store(picked);
+                set_locale.set(picked);
+            }
+        }
+    };
+
+    // Pasting into a race is blocked outright and flagged like any other cheating
+    let flag_paste = move || {
+        if game_state.get_untracked() != ClientState::Racing { return; }
+        web_sys::console::warn_1(&"Paste blocked in the typing area".into());
+        set_error_message.set(Some(ErrorCode::SuspiciousActivity));
+    };
+
+    // Global hotkeys; the typing area ignores every key they use
+    let _ = window_event_listener(ev::keydown, move |ev| {
+        let key = ev.key();
+        let chord = Chord { key: &key, ctrl: ev.ctrl_key(), meta: ev.meta_key(), alt: ev.alt_key(), shift: ev.shift_key() };
+        let at = Focus {
+            state: game_state.get_untracked(),
+            joined: joined.get_untracked(),
+            palette_open: palette_open.get_untracked(),
+            in_control: in_control(&ev),
+        };
+        let Some(hotkey) = hotkey(&chord, &at) else { return };
+        ev.prevent_default();
+        match hotkey {
+            Hotkey::Restart => race_again(),
+            Hotkey::Leave => leave(),
+            Hotkey::OpenPalette => open_palette(),
+            Hotkey::ClosePalette => close_palette(),
+        }
+    });
+
     view! {
         <div class="bg min-h-screen">
             <div class="container mx-auto p-4 max-w-6xl">
                 <div class="text-center mb-8">
                     <h1 class="text-5xl font-bold text-white mb-2">"🏁 rracer"</h1>
-                    <p class="text-white text-lg">"Real-time multiplayer typing races"</p>
+                    <p class="text-white text-lg">{move || tr(Phrase::Tagline)}</p>
+                    <div class="text-white text-sm mt-2">
+                        {move || profile.get().filter(|p| p.current_streak > 0).map(|p| {
+                            let title = locale.get().format(Phrase::LongestStreak, &[("days", &p.longest_streak)]);
+                            view! { <span class="mr-3" title=title>{locale.get().format(Phrase::Streak, &[("days", &p.current_streak)])}</span> }
+                        })}
+                        {move || match identity.get() {
+                            Some(me) => view! {
+                                <form method="post" action="/auth/logout" class="inline">
+                                    {locale.get().format(Phrase::SignedInAs, &[("name", &me.display_name), ("provider", &me.provider)])}
+                                    <button type="submit" class="underline">{tr(Phrase::SignOut)}</button>
+                                </form>
+                            }.into_any(),
+                            None => auth_providers.get().into_iter().map(|p| {
+                                let label = match p.as_str() { "github" => "GitHub", "google" => "Google", other => other }.to_string();
+                                view! { <a href=format!("/auth/{p}/login") class="underline mx-2">{locale.get().format(Phrase::SignInWith, &[("provider", &label)])}</a> }
+                            }).collect_view().into_any(),
+                        }}
+                        <select class="ml-3 bg-transparent underline" title=move || tr(Phrase::Language)
+                            prop:value=move || locale.get().tag()
+                            on:change=move |ev| {
+                                let Some(picked) = Locale::from_tag(&event_target_value(&ev)) else { return };
+                                i18n::store(picked);
+                                set_locale.set(picked);
+                            }>
+                            {Locale::ALL.into_iter().map(|l| view! { <option class="text-gray-800" value=l.tag()>{l.native_name()}</option> }).collect_view()}
+                        </select>
+                    </div>
                 </div>
 
                 <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
                     <div class="flex gap-4 mb-4">
-                        <input type="text" placeholder="Room name" class="border-2 border-gray-200 rounded-lg px-4 py-3 flex-1 focus:border-blue-500 focus:outline-none transition-colors" prop:value=room_name on:input=move |ev| set_room_name.set(event_target_value(&ev))/>
-                        <input type="text" placeholder="Your name" class="border-2 border-gray-200 rounded-lg px-4 py-3 flex-1 focus:border-blue-500 focus:outline-none transition-colors" prop:value=player_name on:input=move |ev| set_player_name.set(event_target_value(&ev))/>
+                        <input type="text" maxlength=MAX_ROOM_CHARS placeholder=move || tr(Phrase::RoomName) class="border-2 border-gray-200 rounded-lg px-4 py-3 flex-1 focus:border-blue-500 focus:outline-none transition-colors" prop:value=room_name on:input=move |ev| set_room_name.set(event_target_value(&ev))/>
+                        <input type="text" maxlength=MAX_NAME_CHARS placeholder=move || tr(Phrase::YourName) class="border-2 border-gray-200 rounded-lg px-4 py-3 flex-1 focus:border-blue-500 focus:outline-none transition-colors" prop:value=player_name on:input=move |ev| set_player_name.set(event_target_value(&ev))/>
+                        <input type="number" min="10" max="250" placeholder=move || tr(Phrase::PaceWpm) title=move || tr(Phrase::PaceWpmHint) class="border-2 border-gray-200 rounded-lg px-4 py-3 w-32 focus:border-blue-500 focus:outline-none transition-colors"
+                            on:change=move |ev| {
+                                let wpm = event_target_value(&ev).trim().parse::<f64>().ok().filter(|w| *w > 0.0);
+                                set_pace_target.set(wpm);
+                                WS_REF.with(|cell| {
+                                    if let Some(ws) = cell.borrow().as_ref() {
+                                        if let Ok(json) = serde_json::to_string(&ClientMsg::SetPace { wpm }) { let _ = ws.send_with_str(&json); }
+                                    }
+                                });
+                            }/>
                         <button class="bg text-white px-6 py-3 rounded-lg hover:bg-blue-600 transition-colors font-semibold disabled:opacity-50 disabled:cursor-not-allowed"
-                            on:click=move |_| {
-                                if joined.get() || connecting.get() { return; }
-                                if !connected.get() { connect_websocket(); } else { join_room(); }
-                            }
+                            on:click=move |_| join()
+                            prop:disabled=move || (joined.get() && !spectating.get()) || connecting.get()>
+                            {move || tr(if joined.get() && !spectating.get() { Phrase::Joined } else if connected.get() { Phrase::JoinRoom } else { Phrase::ConnectAndJoin })}
+                        </button>
+                        <Show when=move || joined.get()>
+                            <button class="bg-white text-gray-700 border-2 border-gray-200 px-6 py-3 rounded-lg hover:border-red-500 transition-colors font-semibold"
+                                on:click=move |_| leave()>
+                                {move || tr(Phrase::LeaveRoom)}
+                            </button>
+                        </Show>
+                        <button class="bg-white text-gray-700 border-2 border-gray-200 px-6 py-3 rounded-lg hover:border-blue-500 transition-colors font-semibold disabled:opacity-50 disabled:cursor-not-allowed"
+                            title=move || tr(Phrase::NewRoomHint)
+                            on:click=move |_| new_room()
+                            prop:disabled=move || joined.get() || connecting.get()>
+                            {move || tr(Phrase::NewRoom)}
+                        </button>
+                        <button class="bg-white text-gray-700 border-2 border-gray-200 px-6 py-3 rounded-lg hover:border-blue-500 transition-colors font-semibold disabled:opacity-50 disabled:cursor-not-allowed"
+                            title=move || tr(Phrase::WatchHint)
+                            on:click=move |_| watch()
+                            prop:disabled=move || joined.get() || connecting.get()>
+                            {move || tr(if spectating.get() && joined.get() { Phrase::Watching } else { Phrase::Watch })}
+                        </button>
+                        <button class="bg-white text-gray-700 border-2 border-gray-200 px-6 py-3 rounded-lg hover:border-blue-500 transition-colors font-semibold disabled:opacity-50 disabled:cursor-not-allowed"
+                            title=move || tr(Phrase::PracticeWeakSpotsHint)
+                            on:click=move |_| practice_weak_spots()
+                            prop:disabled=move || joined.get() || connecting.get()>
+                            {move || tr(Phrase::PracticeWeakSpots)}
+                        </button>
+                        <button class="bg-white text-gray-700 border-2 border-gray-200 px-6 py-3 rounded-lg hover:border-blue-500 transition-colors font-semibold disabled:opacity-50 disabled:cursor-not-allowed"
+                            title=move || tr(Phrase::LessonsHint)
+                            on:click=move |_| open_lessons()
                             prop:disabled=move || joined.get() || connecting.get()>
-                            {move || if joined.get() { "Joined" } else if connected.get() { "Join Room" } else { "Connect & Join" }}
+                            {move || tr(Phrase::Lessons)}
                         </button>
                         <Show when=|| ALLOW_TEST_UI>
                             <button class="bg-gray-700 text-white px-6 py-3 rounded-lg hover:bg-gray-800 transition-colors font-semibold"
                                 on:click=move |_| {
-                                    set_test_mode.set(true);
-                                    set_passage.set(crate::normalize::tests_passage());
-                                    set_game_state.set("racing".to_string());
-                                    set_start_time.set(Some(js_sys::Date::now()));
-                                    set_current_position.set(0);
-                                    set_errors.set(0);
-                                    set_wpm.set(0.0);
-                                    set_accuracy.set(100.0);
-                                    set_last_progress_sent.set(0.0);
-                                    set_player_positions.set(HashMap::new());
-                                    let me = player_name.get();
-                                    set_players.set(vec![me.clone()]);
-                                    set_player_positions.update(|m| { m.insert(me, 0); });
-                                    set_waiting_seconds.set(0);
-                                    set_finish_time.set(None);
-                                    set_leaderboard.set(Vec::new());
+                                    set_drill_focus.set(None);
+                                    set_current_lesson.set(None);
+                                    let text = crate::normalize::tests_passage();
+                                    start_practice(if room_settings.get_untracked().casual { casual(&text) } else { text });
                                 }>
                                 {move || if test_mode.get() { "Test Text Loaded" } else { "Load Test Text" }}
                             </button>
@@ -315,309 +1079,1249 @@ pub fn App() -> impl IntoView {
                             </button>
                         </Show>
                     </div>
+                    <div class="flex items-center gap-3 mb-4 text-sm text-gray-700">
+                        <input type="number" min=pacer::MIN_WPM max=pacer::MAX_WPM placeholder=move || tr(Phrase::PacerWpm) title=move || tr(Phrase::PacerWpmHint) class="border-2 border-gray-200 rounded-lg px-3 py-2 w-36 focus:border-blue-500 focus:outline-none transition-colors"
+                            prop:value=move || pacer.get().map(|p| format!("{:.0}", p.wpm)).unwrap_or_default()
+                            on:change=move |ev| {
+                                let sound = pacer.get_untracked().is_some_and(|p| p.sound);
+                                let picked = event_target_value(&ev).trim().parse::<f64>().ok().and_then(|wpm| Pacer::new(wpm, sound));
+                                pacer::store(picked);
+                                set_pacer.set(picked);
+                            }/>
+                        <label class="inline-flex items-center gap-1">
+                            <input type="checkbox" prop:checked=move || pacer.get().is_some_and(|p| p.sound) prop:disabled=move || pacer.get().is_none()
+                                on:change=move |ev| {
+                                    let picked = pacer.get_untracked().map(|p| Pacer { sound: event_target_checked(&ev), ..p });
+                                    pacer::store(picked);
+                                    set_pacer.set(picked);
+                                }/>
+                            {move || tr(Phrase::PacerSound)}
+                        </label>
+                    </div>
                     <div class="text-sm text-gray-600">
-                        "Status: "<span class="font-semibold">{move || if connected.get() { "Connected".to_string() } else { "Disconnected".to_string() }}</span>
+                        {move || tr(Phrase::Status)}<span class="font-semibold">{move || tr(if connected.get() { Phrase::Connected } else { Phrase::Disconnected })}</span>
+                        <Show when=move || connected.get()>
+                            {move || {
+                                let (dot, phrase) = match quality() {
+                                    Quality::Good => ("bg-green-500", Phrase::ConnectionGood),
+                                    Quality::Fair => ("bg-yellow-400", Phrase::ConnectionFair),
+                                    Quality::Poor => ("bg-red-500", Phrase::ConnectionPoor),
+                                };
+                                let title = rtt_ms.get().map(|ms| locale.get().format(Phrase::ServerRoundTrip, &[("ms", &ms)]));
+                                view! {
+                                    <span class="ms-3 inline-flex items-center gap-1" title=title>
+                                        <span class=format!("inline-block w-2 h-2 rounded-full {dot}")></span>
+                                        {tr(phrase)}
+                                    </span>
+                                }
+                            }}
+                        </Show>
                     </div>
                 </div>
 
         <Show when=move || _error_message.get().is_some()>
                     <div class="bg-red-100 border-2 border-red-400 text-red-700 p-4 rounded-lg mb-6">
-            {move || _error_message.get().unwrap_or_default()}
+            {move || _error_message.get().map(|code| tr(friendly_error(code)))}
                     </div>
                 </Show>
 
+                {move || invited.get().map(|(who, room)| view! {
+                    <div class="bg-blue-100 border-2 border-blue-400 text-blue-800 p-4 rounded-lg mb-6 flex justify-between items-center">
+                        <span>{locale.get().format(Phrase::InvitedBy, &[("name", &who), ("room", &room)])}</span>
+                        <span>
+                            <a class="font-semibold underline" href=join_link(&room)>{tr(Phrase::JoinInvite)}</a>
+                            <button class="ml-3" on:click=move |_| set_invited.set(None)>"×"</button>
+                        </span>
+                    </div>
+                })}
+
 
                 <Show when=move || {
-                    let s = game_state.get();
-                    s == "racing" || s == "countdown"
+                    game_state.get().on_track()
                 }>
                     <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
                         <div class="flex justify-between items-center mb-4">
-                            <h2 class="text-2xl font-bold text-gray-800">"🏁 Race in Progress"</h2>
+                            <h2 class="text-2xl font-bold text-gray-800">
+                                {move || if game_state.get() == ClientState::Countdown {
+                                    locale.get().format(Phrase::StartingIn, &[("secs", &countdown_left.get())])
+                                } else {
+                                    tr(if paused.get().is_some() || game_state.get() == ClientState::Paused { Phrase::Paused } else { Phrase::RaceInProgress }).to_string()
+                                }}
+                                <Show when=move || { spectators.get() > 0 }>
+                                    <span class="ml-3 text-sm font-normal text-gray-500">{move || locale.get().format(Phrase::Spectators, &[("count", &spectators.get())])}</span>
+                                </Show>
+                            </h2>
                             <div class="flex gap-6">
                                 <div class="text-center">
                                     <div class="text-3xl font-bold text-blue-600">{move || format!("{:.0}", wpm.get())}</div>
-                                    <div class="text-sm text-gray-500">"WPM"</div>
+                                    <div class="text-sm text-gray-500">{move || tr(Phrase::Wpm)}</div>
                                 </div>
                                 <div class="text-center">
                                     <div class="text-3xl font-bold text-green-600">{move || format!("{:.0}%", accuracy.get())}</div>
-                                    <div class="text-sm text-gray-500">"Accuracy"</div>
+                                    <div class="text-sm text-gray-500">{move || tr(Phrase::Accuracy)}</div>
                                 </div>
                                 <div class="text-center">
-                                    <div class="text-3xl font-bold text-purple-600">{move || format!("{:.1}s", time_elapsed.get())}</div>
-                                    <div class="text-sm text-gray-500">"Time"</div>
+                                    <div class="text-3xl font-bold text-purple-600">{move || match room_settings.get().mode.time_limit_secs() {
+                                        // Timed races count down instead
+                                        Some(limit) => format!("{:.1}s", (f64::from(limit) - time_elapsed.get()).max(0.0)),
+                                        None => format!("{:.1}s", time_elapsed.get()),
+                                    }}</div>
+                                    <div class="text-sm text-gray-500">{move || tr(Phrase::Time)}</div>
                                 </div>
                             </div>
                         </div>
-                        <div class="race-track mb-6" style="min-height: 240px;">
-                            <div class="finish-line"></div>
+                        {move || overtake.get().map(|(_, phrase, name)| view! {
+                            <p class="overtake-flash mb-4 p-3 bg-blue-50 text-blue-800 rounded-lg font-semibold text-center">{locale.get().format(phrase, &[("name", &name)])}</p>
+                        })}
+                        {move || race_ends.get().map(|ends| {
+                            // The timer ticks time_elapsed, which keeps this counting down
+                            time_elapsed.track();
+                            let secs = ((ends - js_sys::Date::now()) / 1000.0).ceil().max(0.0);
+                            view! { <p class="mb-4 p-3 bg-amber-50 text-amber-800 rounded-lg font-semibold">{locale.get().format(Phrase::RaceEndingIn, &[("secs", &secs)])}</p> }
+                        })}
+                        {move || elimination_at.get().map(|at| {
+                            time_elapsed.track();
+                            let secs = ((at - js_sys::Date::now()) / 1000.0).ceil().max(0.0);
+                            let locale = locale.get();
+                            let out = eliminated.get();
+                            let bubble = race_order.get().into_iter().rev().find(|id| !out.contains(id)).map(|id| {
+                                if id == my_id.get() { return locale.text(Phrase::YouOnTheBubble).to_string(); }
+                                let name = players.get().into_iter().find(|p| p.id == id).map_or(id, |p| p.name);
+                                locale.format(Phrase::OnTheBubble, &[("name", &name)])
+                            });
+                            view! {
+                                <p class="mb-4 p-3 bg-red-50 text-red-800 rounded-lg font-semibold flex justify-between">
+                                    <span>{locale.format(Phrase::EliminationIn, &[("secs", &secs)])}</span>
+                                    <span>{bubble}</span>
+                                </p>
+                            }
+                        })}
+                        {
+                        // Follow ourselves, or the leader when spectating
+                        let track_span = Memo::new(move |_| {
+                            let total = char_len(&passage.get());
+                            let positions = player_positions.get();
+                            let focus = positions.get(&my_id.get()).copied().unwrap_or_else(|| positions.values().copied().max().unwrap_or(0));
+                            visible_span(track_scale.get(), total, focus)
+                        });
+                        view! {
+                        <div class="flex justify-end gap-4 mb-2 text-sm text-gray-600">
+                            <label class="inline-flex items-center gap-1">
+                                <input type="checkbox" prop:checked=marks_in_text on:change=move |ev| set_marks_in_text.set(event_target_checked(&ev))/>
+                                {move || tr(Phrase::MarksInText)}
+                            </label>
+                            <label>{move || tr(Phrase::Track)}
+                                <select class="border-2 border-gray-200 rounded-lg px-2 py-1"
+                                    prop:value=move || if track_scale.get() == TrackScale::Follow { "follow" } else { "whole" }
+                                    on:change=move |ev| set_track_scale.set(if event_target_value(&ev) == "follow" { TrackScale::Follow } else { TrackScale::Whole })>
+                                    <option value="whole">{move || tr(Phrase::WholePassage)}</option>
+                                    <option value="follow">{move || tr(Phrase::Follow)}</option>
+                                </select>
+                            </label>
+                        </div>
+                        <div class="race-track mb-2" style="min-height: 240px;" dir=move || passage_dir.get()>
+                            <Show when=move || { track_span.get().1 >= char_len(&passage.get()) }>
+                                <div class="finish-line"></div>
+                            </Show>
+                            {move || {
+                                let span = track_span.get();
+                                checkpoint_positions(char_len(&passage.get()))
+                                    .filter(|(_, pos)| (span.0..span.1).contains(pos))
+                                    .map(|(checkpoint, pos)| view! {
+                                        <div class="checkpoint" style=format!("inset-inline-start: calc({:.2}% + 20px);", lane_percent(pos, span))>
+                                            <span>{format!("{checkpoint}%")}</span>
+                                        </div>
+                                    })
+                                    .collect_view()
+                            }}
                             <For
-                                each=move || players.get().into_iter().enumerate()
-                                key=|(i, p)| format!("{i}-{p}")
-                                children=move |(idx, player)| {
-                                    let player_for_pos = player.clone();
-                                    let player_for_self = player.clone();
+                                each=move || players.get()
+                                key=|p| p.id.clone()
+                                children=move |player| {
+                                    let player_for_pos = player.id.clone();
+                                    let player_for_self = player.id.clone();
+                                    let player_for_typing = player.id.clone();
+                                    let is_typing = move || typing_ids.with(|ids| ids.contains(&player_for_typing));
+                                    let player_for_split = player.id.clone();
+                                    let last_split = move || splits.with(|s| s.get(&player_for_split).and_then(|v| v.last().copied()));
                                     let position = move || player_positions.get().get(&player_for_pos).copied().unwrap_or(0);
-                                    let total = move || passage.get().len().max(1);
-                                    let percent = move || (position() as f64 / total() as f64) * 95.0;
-                                    let is_self = move || player_for_self == player_name.get();
-                                    let car_class = move || {
-                                        if is_self() { "car car-player".to_string() } else {
-                                            match idx % 4 {
-                                                0 => "car car-opponent1".to_string(),
-                                                1 => "car car-opponent2".to_string(),
-                                                2 => "car car-opponent3".to_string(),
-                                                _ => "car car-opponent4".to_string(),
-                                            }
-                                        }
-                                    };
-                                    let label = player.clone();
+                                    let percent = move || lane_percent(position(), track_span.get());
+                                    let is_self = move || player_for_self == my_id.get();
+                                    let is_pace = player.pace_for.is_some();
+                                    let color = car_color(player.color, is_pace);
+                                    let car_class = move || if is_self() { format!("car {color} car-player") } else { format!("car {color}") };
+                                    let label = if is_pace { format!("⏱ {}", player.name) } else if player.is_bot { format!("🤖 {}", player.name) } else { player.name.clone() };
                                     view! {
                                         <div class="race-lane">
-                                            <div class=car_class style=move || format!("left: {}%;", percent())>
-                                                "🚗"
+                                            <div class=car_class style=move || format!("inset-inline-start: {}%;", percent())>
+                                                {if is_pace { "⏱" } else { "🚗" }}
+                                            </div>
+                                            <div class="ms-14 ps-10 text-gray-700 font-medium">
+                                                {label}
+                                                {move || last_split().map(|(checkpoint, time)| view! {
+                                                    <span class="ms-2 text-xs text-gray-500">{format!("{checkpoint}% · {time:.1}s")}</span>
+                                                })}
+                                                <Show when=is_typing>
+                                                    <span class="ms-2 text-xs text-gray-400" title=move || tr(Phrase::Typing)>{move || tr(Phrase::TypingNow)}</span>
+                                                </Show>
                                             </div>
-                                            <div class="ml-14 pl-10 text-gray-700 font-medium">{label}</div>
                                         </div>
                                     }
                                 }
                             />
                         </div>
+                        // Whole-passage overview with the window the lanes show
+                        <div class="minimap mb-6" dir=move || passage_dir.get()>
+                            {move || {
+                                let (start, end) = track_span.get();
+                                let total = char_len(&passage.get()).max(1) as f64;
+                                view! { <div class="minimap-window" style=format!("inset-inline-start: {:.2}%; width: {:.2}%;", start as f64 / total * 100.0, (end - start) as f64 / total * 100.0)></div> }
+                            }}
+                            {move || {
+                                let total = char_len(&passage.get()).max(1) as f64;
+                                let me = my_id.get();
+                                players.get().into_iter().map(|p| {
+                                    let pos = player_positions.get().get(&p.id).copied().unwrap_or(0);
+                                    let class = if p.id == me { "minimap-dot minimap-me" } else { "minimap-dot" };
+                                    view! { <div class=class title=p.name style=format!("inset-inline-start: {:.2}%;", pos as f64 / total * 100.0)></div> }
+                                }).collect_view()
+                            }}
+                        </div>
+                        }
+                        }
                         <div class="mb-4">
-                            <h3 class="text-lg font-semibold mb-2 text-gray-700">"Type this passage:"</h3>
-                            <p class="text-xs text-gray-500 mb-2">"Tip: type straight quotes (\" '), hyphen (-), and space for curly quotes, long dashes, and non‑breaking spaces."</p>
-                <div id="typingArea" class="text-xl font-mono leading-relaxed p-6 bg-white rounded-lg border-2 border-gray-200 typing-area min-h-[120px] passage-text" tabindex="0"
+                            <h3 class="text-lg font-semibold mb-2 text-gray-700">{move || tr(Phrase::TypeThisPassage)}</h3>
+                            <p class="text-xs text-gray-500 mb-2">{move || tr(Phrase::TypingTip)}</p>
+                            {move || {
+                                let me = my_id.get();
+                                heats.with(|h| h.iter().position(|ids| ids.contains(&me)).map(|heat| (heat + 1, h.len())))
+                            }.map(|(heat, count)| view! { <p class="mb-2 text-sm font-medium text-gray-700">{locale.get().format(Phrase::HeatOf, &[("heat", &heat), ("heats", &count)])}</p> })}
+                            {move || relay.get().map(|r| view! { <div class="mb-2 text-sm text-gray-700">{relay_lines(locale.get(), &r, &my_id.get(), &players.get()).into_iter().map(|line| view! { <p>{line}</p> }).collect_view()}</div> })}
+                            {move || paused.get().map(|pause| view! {
+                                <p class="mb-2 p-3 rounded bg-yellow-100 border border-yellow-300 text-yellow-800 text-sm font-medium">{match pause.reason {
+                                    PauseReason::Hidden => tr(Phrase::PausedHidden).to_string(),
+                                    PauseReason::Idle => locale.get().format(Phrase::PausedIdle, &[("secs", &(IDLE_PAUSE_MS / 1000.0))]),
+                                }}</p>
+                            })}
+                            // The host of a pausable room can stop the race for everyone, and start it again
+                            {move || {
+                                let host = players.get().iter().any(|p| p.is_host && p.id == my_id.get()) && room_settings.get().pausable && !test_mode.get();
+                                let state = game_state.get();
+                                match state {
+                                    ClientState::Paused => Some(view! {
+                                        <p class="mb-2 p-3 rounded bg-yellow-100 border border-yellow-300 text-yellow-800 text-sm font-medium flex justify-between items-center">
+                                            <span>{match paused_by.get().and_then(|id| players.get().into_iter().find(|p| p.id == id)) {
+                                                Some(host) => locale.get().format(Phrase::PausedByHost, &[("name", &host.name)]),
+                                                None => tr(Phrase::Paused).to_string(),
+                                            }}</span>
+                                            <Show when=move || host>
+                                                <button class="bg-green-500 text-white px-3 py-1 rounded hover:bg-green-600" on:click=move |_| send(&ClientMsg::Resume)>{move || tr(Phrase::ResumeRace)}</button>
+                                            </Show>
+                                        </p>
+                                    }.into_any()),
+                                    ClientState::Racing if host => Some(view! {
+                                        <div class="mb-2 flex justify-end">
+                                            <button class="bg-gray-200 text-gray-800 px-3 py-1 rounded hover:bg-gray-300 text-sm" on:click=move |_| send(&ClientMsg::Pause)>{move || tr(Phrase::PauseRace)}</button>
+                                        </div>
+                                    }.into_any()),
+                                    _ => None,
+                                }
+                            }}
+                // Passages are in their own language, not the interface's
+                <div id="typingArea" lang="" dir=move || passage_dir.get() class=move || format!("text-xl font-mono leading-relaxed p-6 bg-white rounded-lg border-2 border-gray-200 typing-area min-h-[120px] passage-text{}", if game_state.get() == ClientState::Paused { " opacity-50" } else { "" }) tabindex="0"
                                 on:keydown=move |ev: web_sys::KeyboardEvent| {
                     // Only handle typing once the race has actually started
-                    if game_state.get() != "racing" { return; }
+                    if game_state.get() != ClientState::Racing { return; }
                     if start_time.get().is_none() { return; }
+                    if spectating.get_untracked() { return; }
+                    // Out of time (or done) and waiting for the server to confirm
+                    if finish_time.get_untracked().is_some() { return; }
                                     // Ignore modifier combos and non-character keys
                                     if ev.ctrl_key() || ev.meta_key() || ev.alt_key() { return; }
                                     let key = ev.key();
-                                    // Only process single-character keys
-                                    if key.chars().count() != 1 {
+                                    // Backspace only does anything in the modes that let racers fix mistakes
+                                    let Some(typed) = key_char(&key) else {
                                         if debug_flag.get() || test_mode.get() {
                                             web_sys::console::log_1(&format!("IGNORED (non-char): key='{}' code='{}'", key, ev.code()).into());
                                         }
                                         return;
-                                    }
+                                    };
                                     ev.prevent_default();
-                                    if let Some(ch_raw) = key.chars().next() {
-                                        // Normalize typed key (covers cases where browser reports a fancy char)
-                                        let ch = normalize_char(ch_raw);
-                                        let passage_text = passage.get();
-                                        let cur_pos = current_position.get();
-                                        if let Some(expected_char) = passage_text.chars().nth(cur_pos) {
-                                            // If the expected passage char is a skippable invisible, advance automatically
-                                            if is_skippable(expected_char) {
-                                                if debug_flag.get() || test_mode.get() {
-                                                    web_sys::console::log_1(&format!(
-                                                        "SKIP invisible at pos {}: expected='{}' (U+{:04X})",
-                                                        cur_pos,
-                                                        expected_char,
-                                                        expected_char as u32
-                                                    ).into());
-                                                }
-                                                set_current_position.set(cur_pos + 1);
-                                                return;
-                                            }
-                                            let typed_norm = ch;
-                                            let expected_norm = normalize_char(expected_char);
-                                            if debug_flag.get() || test_mode.get() {
-                                                web_sys::console::log_1(&format!(
-                                                    "COMPARE pos {} => raw='{}' (U+{:04X}) -> typed_norm='{}' (U+{:04X}); expected='{}' (U+{:04X}) -> expected_norm='{}' (U+{:04X}); equal={}",
-                                                    cur_pos,
-                                                    ch_raw,
-                                                    ch_raw as u32,
-                                                    typed_norm,
-                                                    typed_norm as u32,
-                                                    expected_char,
-                                                    expected_char as u32,
-                                                    expected_norm,
-                                                    expected_norm as u32,
-                                                    typed_norm == expected_norm
-                                                ).into());
-                                            }
-                        if typed_norm == expected_norm {
-                                                let next_pos = cur_pos + 1;
-                                                set_current_position.set(next_pos);
-
-                                                // Update local car position immediately
-                                                let me = player_name.get();
-                                                set_player_positions.update(|m| { m.insert(me.clone(), next_pos); });
-
-                        // Update realtime WPM & accuracy
-                                                if let Some(start) = start_time.get() {
-                                                    let now = js_sys::Date::now();
-                                                    // seconds (server-synced), clamp to avoid zero/negative due to clock skew
-                                                    let elapsed = ((now - start) / 1000.0).max(0.1);
-                                                    if elapsed > 0.0 {
-                                                        // Monkeytype-style WPM: only correct chars, no error penalty subtraction
-                                                        let chars_typed = next_pos;
-                                                        let wpm_now = (chars_typed as f64 / 5.0) / (elapsed / 60.0);
-                            set_wpm.set(wpm_now.max(0.0));
-
-                                                        let total_chars = chars_typed + errors.get();
-                                                        if total_chars > 0 { set_accuracy.set((chars_typed as f64 / total_chars as f64) * 100.0); }
-                                                    }
-                                                    // Throttle progress messages (>=100ms between sends)
-                                                    let last = last_progress_sent.get();
-                                                    if now - last >= 100.0 {
-                                                        if !test_mode.get() {
-                                                            WS_REF.with(|cell| {
-                                                                if let Some(ws) = cell.borrow().as_ref() {
-                                                                    let msg = ClientMsg::Progress { pos: next_pos, ts: now as u64 };
-                                                                    if let Ok(json) = serde_json::to_string(&msg) { let _ = ws.send_with_str(&json); }
-                                                                }
-                                                            });
-                                                        }
-                                                        set_last_progress_sent.set(now);
-                                                    }
-                                                }
-
-                                                // If finished, send Finish
-                        if next_pos >= passage_text.chars().count() {
-                                                    if let Some(start) = start_time.get() {
-                                                        let now = js_sys::Date::now();
-                                                        // seconds (server-synced), clamp
-                                                        let elapsed = ((now - start) / 1000.0).max(0.1);
-                            // Recompute WPM/accuracy at finish to avoid stale 0s
-                                                        let chars_typed = next_pos;
-                            let w = if elapsed > 0.0 { (chars_typed as f64 / 5.0) / (elapsed / 60.0) } else { 0.0 };
-                            let a = if (chars_typed + errors.get()) > 0 { (chars_typed as f64 / (chars_typed + errors.get()) as f64) * 100.0 } else { 100.0 };
-                            set_wpm.set(w.max(0.0));
-                            set_accuracy.set(a);
-                            set_finish_time.set(Some(elapsed));
-                                                        if !test_mode.get() {
-                                                            WS_REF.with(|cell| {
-                                                                if let Some(ws) = cell.borrow().as_ref() {
-                                                                    let msg = ClientMsg::Finish { wpm: w, accuracy: a, time: elapsed, ts: now as u64 };
-                                                                    if let Ok(json) = serde_json::to_string(&msg) { let _ = ws.send_with_str(&json); }
-                                                                }
-                                                            });
-                                                        }
-                                                    }
+                                    let now = js_sys::Date::now();
+                                    // The first key after a practice pause starts the clock again
+                                    if let Some(pause) = paused.get_untracked() {
+                                        if let Some(start) = start_time.get_untracked() { set_start_time.set(Some(pause.resume(start, now))); }
+                                        set_paused.set(None);
+                                    }
+                                    set_last_input.set(now);
+                                    // Keys landing in the same millisecond come from a paste or a script
+                                    let mut burst = key_burst.get_untracked();
+                                    if burst.key(now as u64) { flag_paste(); }
+                                    set_key_burst.set(burst);
+                                    if burst.pasting() { return; }
+                                    let passage_text = passage.get();
+                                    let mode = room_settings.get_untracked().error_mode;
+                                    let mut next = cursor.get_untracked();
+                                    let cur_pos = next.pos;
+                                    // In a relay only the runner types, within their leg
+                                    let leg = relay.with_untracked(|r| r.as_ref().map(|r| r.turn(&my_id.get_untracked())));
+                                    if let Some(leg) = &leg {
+                                        let Some(leg) = leg else { return };
+                                        if cur_pos >= leg.end || (typed == BACKSPACE && cur_pos <= leg.start) { return; }
+                                    }
+                                    let pressed = press(&mut next, &passage_text, typed, mode);
+                                    if let Some(start) = start_time.get_untracked() { set_key_log.update(|k| k.push(((now - start).max(0.0) as u32, typed))); }
+                                    if debug_flag.get() || test_mode.get() {
+                                        let expected = char_at(&passage_text, cur_pos).unwrap_or(' ');
+                                        web_sys::console::log_1(&format!(
+                                            "{:?} at pos {}: key='{}' -> typed='{}' (U+{:04X}); expected='{}' (U+{:04X})",
+                                            pressed, cur_pos, key, typed, typed as u32, expected, expected as u32
+                                        ).into());
+                                    }
+                                    let (next_pos, correct, keystrokes, errs) = (next.pos, next.correct(), next.keystrokes, next.errors);
+                                    let done = finished(&next, &passage_text);
+                                    set_cursor.set(next);
+                                    // Timed from the key event to the frame that draws it
+                                    let key_at = ev.time_stamp();
+                                    request_animation_frame(move || {
+                                        if let Some(perf) = web_sys::window().and_then(|w| w.performance()) { set_input_latency.update(|l| l.push(perf.now() - key_at)); }
+                                    });
+                                    set_errors.set(errs);
+                                    set_accuracy.set(shared::wpm::accuracy(keystrokes - errs, keystrokes));
+                                    if !pressed.moved() { return; }
+                                    set_current_position.set(next_pos);
+                                    // Update local car position immediately
+                                    let me = my_id.get();
+                                    set_player_positions.update(|m| { m.insert(me.clone(), next_pos); });
+                                    let Some(start) = start_time.get() else { return };
+                                    // Seconds since the server-synced start
+                                    let elapsed = (now - start) / 1000.0;
+                                    set_wpm.set(live_wpm(correct, elapsed));
+                                    // A finished leg goes out straight away so the next runner isn't kept waiting, and
+                                    // the end of the passage before our Finish, which the server only takes once we're there
+                                    let leg_done = leg.flatten().is_some_and(|leg| next_pos >= leg.end);
+                                    if done || leg_done || progress_due(last_progress_sent.get(), now, quality().progress_interval_ms()) {
+                                        if !test_mode.get() {
+                                            WS_REF.with(|cell| {
+                                                if let Some(ws) = cell.borrow().as_ref() {
+                                                    let msg = ClientMsg::Progress { pos: next_pos, ts: now as u64 };
+                                                    if let Ok(json) = serde_json::to_string(&msg) { let _ = ws.send_with_str(&json); }
                                                 }
-                                            } else {
-                                                set_errors.update(|e| *e += 1);
-                                                // Update accuracy on error
-                                                let total_chars = current_position.get() + errors.get();
-                                                if total_chars > 0 { set_accuracy.set((current_position.get() as f64 / total_chars as f64) * 100.0); }
-                                            }
+                                            });
                                         }
+                                        set_last_progress_sent.set(now);
+                                    }
+                                    // Relay teams finish together, when the server says so
+                                    if done && relay.with_untracked(Option::is_none) {
+                                        finish_race(elapsed.max(0.1));
+                                    }
+                                }
+                                on:paste=move |ev| {
+                                    ev.prevent_default();
+                                    flag_paste();
+                                }
+                                on:beforeinput=move |ev| {
+                                    if matches!(ev.input_type().as_str(), "insertFromPaste" | "insertFromPasteAsQuotation" | "insertFromDrop") {
+                                        ev.prevent_default();
+                                        flag_paste();
                                     }
                                 }>
-                                <span class="correct-char">{move || passage.get().chars().take(current_position.get()).collect::<String>()}</span>
-                                <span class="current-char">{move || passage.get().chars().nth(current_position.get()).unwrap_or(' ')}</span>
-                                <span>{move || passage.get().chars().skip(current_position.get() + 1).collect::<String>()}</span>
+                                {move || {
+                                    let passage = passage.get();
+                                    let typed = split_at_grapheme(&passage, current_position.get()).0;
+                                    let runs: Vec<_> = cursor.with(|c| typed_runs(typed, c)).into_iter().map(|(wrong, run)| (if wrong { "incorrect-char" } else { "correct-char" }, run)).collect();
+                                    passage_pieces(with_marks(&runs, 0, &text_marks.get()))
+                                }}
+                                <span class="current-char">{move || match split_at_grapheme(&passage.get(), current_position.get()).1 {
+                                    "" => " ".to_string(),
+                                    current => current.to_string(),
+                                }}</span>
+                                {move || {
+                                    let passage = passage.get();
+                                    let (typed, current, rest) = split_at_grapheme(&passage, current_position.get());
+                                    // The pacer's ghost caret, once it's ahead of ours
+                                    let start = char_len(typed) + char_len(current);
+                                    let segments = match ghost().filter(|g| *g >= start) {
+                                        Some(g) => {
+                                            let (before, at, after) = split_at_grapheme(rest, g - start);
+                                            vec![("", before.to_string()), ("ghost-char", at.to_string()), ("", after.to_string())]
+                                        }
+                                        None => vec![("", rest.to_string())],
+                                    };
+                                    passage_pieces(with_marks(&segments, start, &text_marks.get()))
+                                }}
                             </div>
+                            {move || byline.get().map(|b| view! { <p class="text-end text-sm italic text-gray-500 mt-2" dir=move || passage_dir.get()>{b}</p> })}
+                            {move || current_lesson.get().map(|lesson| view! {
+                                <p class="text-end text-sm text-gray-500 mt-2">{format!("{} · {}", lesson_title(locale.get(), &lesson), locale.get().format(Phrase::LessonKeys, &[("keys", &lesson.keys)]))}</p>
+                            })}
+                            {move || drill_focus.get().map(|focus| view! {
+                                <p class="text-end text-sm text-gray-500 mt-2">{drill_focus_text(locale.get(), &focus)}</p>
+                            })}
+                            {move || passage_record.get().map(|r| view! {
+                                <p class="text-end text-sm text-gray-500 mt-1">{locale.get().format(Phrase::PassageRecord, &[("wpm", &format!("{:.0}", r.wpm)), ("name", &r.name)])}</p>
+                            })}
                         </div>
                         <div class="flex justify-between text-sm text-gray-600 bg-gray-50 rounded-lg p-3">
-                            <span>"Progress: "<span class="font-semibold">{current_position}</span>" / "<span class="font-semibold">{move || passage.get().len()}</span>" characters"</span>
-                            <span>"Errors: "<span class="font-semibold text-red-600">{errors}</span></span>
-                            <span>"Rank: "<span class="font-semibold text-blue-600">"#1"</span></span>
+                            <span>{move || tr(Phrase::Progress)}<span class="font-semibold">{current_position}</span>" / "<span class="font-semibold">{move || char_len(&passage.get())}</span>{move || tr(Phrase::Characters)}</span>
+                            <span>{move || tr(Phrase::Errors)}<span class="font-semibold text-red-600">{errors}</span></span>
+                            {move || ghost().map(|g| {
+                                let pos = current_position.get();
+                                let (phrase, chars) = if pos >= g { (Phrase::PacerAhead, pos - g) } else { (Phrase::PacerBehind, g - pos) };
+                                view! { <span class="text-purple-700">{locale.get().format(phrase, &[("chars", &chars)])}</span> }
+                            })}
+                            <span>{move || tr(Phrase::Rank)}{move || {
+                                let (now, before) = rank.get().unwrap_or((1, 1));
+                                // Re-created on every change, which replays the animation
+                                let class = match now.cmp(&before) {
+                                    Ordering::Greater => "font-semibold text-red-600 rank-lost",
+                                    Ordering::Less => "font-semibold text-green-600 rank-gained",
+                                    Ordering::Equal => "font-semibold text-blue-600",
+                                };
+                                view! { <span class=class>{format!("#{now}")}</span> }
+                            }}</span>
+                        </div>
+                    </div>
+                </Show>
+
+                <Show when=move || lessons_open.get() && game_state.get() == ClientState::Waiting>
+                    <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
+                        <h2 class="text-2xl font-bold text-gray-800 mb-4">{move || tr(Phrase::Lessons)}</h2>
+                        <ol class="space-y-3">
+                            {move || {
+                                let course = course.get();
+                                let progress = lesson_progress.get();
+                                let open = lessons::unlocked(&course, &progress);
+                                course.into_iter().enumerate().map(|(i, lesson)| {
+                                    let best = progress.iter().find(|p| p.lesson == lesson.id).cloned();
+                                    let pass_mark = locale.get().format(Phrase::LessonPassMark, &[("wpm", &lesson.min_wpm), ("accuracy", &lesson.min_accuracy)]);
+                                    let title = lesson_title(locale.get(), &lesson);
+                                    let keys = locale.get().format(Phrase::LessonKeys, &[("keys", &lesson.keys)]);
+                                    view! {
+                                        <li class="flex items-center justify-between gap-4 p-3 rounded-lg bg-gray-50">
+                                            <div>
+                                                <div class="font-semibold text-gray-800">{format!("{}. {title}", i + 1)}{best.as_ref().is_some_and(|b| b.passed).then_some(" ✅")}</div>
+                                                <div class="text-sm text-gray-500"><span class="font-mono">{keys}</span>" · "{pass_mark}</div>
+                                                {best.map(|b| view! { <div class="text-sm text-gray-500">{locale.get().format(Phrase::LessonBest, &[("wpm", &format!("{:.0}", b.best_wpm)), ("accuracy", &format!("{:.0}", b.best_accuracy))])}</div> })}
+                                            </div>
+                                            {if i < open {
+                                                view! {
+                                                    <button class="bg-blue-500 text-white px-4 py-2 rounded-lg hover:bg-blue-600 transition-colors font-semibold"
+                                                        on:click=move |_| start_lesson(lesson.clone())>
+                                                        {move || tr(Phrase::StartLesson)}
+                                                    </button>
+                                                }.into_any()
+                                            } else {
+                                                view! { <span class="text-sm text-gray-400">{move || tr(Phrase::LessonLocked)}</span> }.into_any()
+                                            }}
+                                        </li>
+                                    }
+                                }).collect_view()
+                            }}
+                        </ol>
+                    </div>
+                </Show>
+
+                <Show when=move || game_state.get() == ClientState::Waiting && identity.get().is_some()>
+                    <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
+                        <div class="flex items-center justify-between mb-4">
+                            <h2 class="text-2xl font-bold text-gray-800">{move || tr(Phrase::Friends)}</h2>
+                            <span class="text-sm text-gray-500">{move || identity.get().map(|me| locale.get().format(Phrase::FriendCode, &[("code", &me.user_id)]))}</span>
+                        </div>
+                        <form class="flex gap-2 mb-4" on:submit=move |ev| {
+                            ev.prevent_default();
+                            let Ok(code) = friend_code.get_untracked().trim().parse::<i64>() else { return };
+                            wasm_bindgen_futures::spawn_local(async move {
+                                if let Some(list) = request_json::<Vec<Friend>>("PUT", &format!("/api/me/friends/{code}"), None).await {
+                                    set_friends.set(list);
+                                    set_friend_code.set(String::new());
+                                }
+                            });
+                        }>
+                            <input type="number" min="1" placeholder=move || tr(Phrase::FriendCodePlaceholder) class="border-2 border-gray-200 rounded-lg px-3 py-2 flex-1 focus:border-blue-500 focus:outline-none"
+                                prop:value=friend_code on:input=move |ev| set_friend_code.set(event_target_value(&ev))/>
+                            <button type="submit" class="bg-blue-500 text-white px-4 py-2 rounded-lg hover:bg-blue-600 font-semibold">{move || tr(Phrase::AddFriend)}</button>
+                        </form>
+                        {move || invite_note.get().map(|note| view! { <p class="text-sm text-gray-600 mb-2">{note}</p> })}
+                        <ul class="space-y-2">
+                            {move || friends.get().into_iter().map(|friend| {
+                                let id = friend.user_id;
+                                let name = friend.display_name.clone().unwrap_or_else(|| locale.get().format(Phrase::FriendNumber, &[("id", &id)]));
+                                let (dot, status) = if friend.online { ("bg-green-500", Phrase::FriendOnline) } else { ("bg-gray-400", Phrase::FriendOffline) };
+                                let invitee = name.clone();
+                                view! {
+                                    <li class="flex items-center justify-between gap-4 p-3 rounded-lg bg-gray-50">
+                                        <span class="inline-flex items-center gap-2 text-gray-800" title=tr(status)>
+                                            <span class=format!("inline-block w-2 h-2 rounded-full {dot}")></span>
+                                            {name}
+                                        </span>
+                                        <span>
+                                            <Show when=move || joined.get()>
+                                                <button class="text-blue-600 hover:underline" on:click={
+                                                    let invitee = invitee.clone();
+                                                    move |_| {
+                                                        let invitee = invitee.clone();
+                                                        let req = InviteRequest { room: room_name.get_untracked() };
+                                                        wasm_bindgen_futures::spawn_local(async move {
+                                                            let Some(invite) = post_json::<_, Invite>(&format!("/api/me/friends/{id}/invite"), &req).await else { return };
+                                                            let phrase = if invite.delivered {
+                                                                Phrase::InviteSent
+                                                            } else {
+                                                                // Not online: the link is theirs to follow whenever they see it
+                                                                if let Some(window) = web_sys::window() { let _ = window.navigator().clipboard().write_text(&invite.url); }
+                                                                Phrase::InviteLinkCopied
+                                                            };
+                                                            set_invite_note.set(Some(locale.get_untracked().format(phrase, &[("name", &invitee)])));
+                                                        });
+                                                    }
+                                                }>{move || tr(Phrase::InviteFriend)}</button>
+                                            </Show>
+                                            <button class="ml-3 text-gray-500 hover:underline" on:click=move |_| {
+                                                wasm_bindgen_futures::spawn_local(async move {
+                                                    if let Some(list) = request_json::<Vec<Friend>>("DELETE", &format!("/api/me/friends/{id}"), None).await { set_friends.set(list); }
+                                                });
+                                            }>{move || tr(Phrase::RemoveFriend)}</button>
+                                        </span>
+                                    </li>
+                                }
+                            }).collect_view()}
+                        </ul>
+                    </div>
+                </Show>
+
+                <Show when=move || game_state.get() == ClientState::Waiting && !recent.with(Vec::is_empty)>
+                    <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
+                        <h2 class="text-2xl font-bold text-gray-800 mb-4">{move || tr(Phrase::RecentRaces)}</h2>
+                        <ul class="space-y-2 max-h-80 overflow-y-auto">
+                            {move || recent.get().into_iter().map(|race| {
+                                let when = local_time(race.finished_at, locale.get());
+                                let place = match &race.room {
+                                    Some(room) => locale.get().format(Phrase::RecentRaceRoom, &[("room", room)]),
+                                    None => tr(Phrase::RecentRacePractice).to_string(),
+                                };
+                                let stats = locale.get().format(Phrase::RecentRaceStats, &[("wpm", &format!("{:.0}", race.wpm)), ("accuracy", &format!("{:.0}", race.accuracy))]);
+                                view! {
+                                    <li class="flex items-center justify-between gap-4 p-3 rounded-lg bg-gray-50">
+                                        <div class="min-w-0">
+                                            <div class="text-sm text-gray-500">{when}" · "{place}</div>
+                                            {race.passage.map(|text| {
+                                                let title = text.clone();
+                                                view! { <div class="text-gray-700 truncate" title=title>{text}</div> }
+                                            })}
+                                        </div>
+                                        <span class="font-semibold text-gray-800 whitespace-nowrap">{stats}</span>
+                                    </li>
+                                }
+                            }).collect_view()}
+                        </ul>
+                    </div>
+                </Show>
+
+                <Show when=move || game_state.get() == ClientState::Waiting && !local_replays.with(Vec::is_empty)>
+                    <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
+                        <div class="flex items-center justify-between mb-4">
+                            <h2 class="text-2xl font-bold text-gray-800">{move || tr(Phrase::YourReplays)}</h2>
+                            <span class="text-sm text-gray-500">{move || tr(Phrase::ReplaysLocalOnly)}</span>
                         </div>
+                        {move || watching.get().map(|replay| {
+                            let analysis = replay.analysis(SLOWEST_KEYS);
+                            let slowest = analysis.slowest.iter().map(|(c, ms)| format!("{} ({ms:.0} ms)", if *c == ' ' { '␣' } else { *c })).collect::<Vec<_>>().join(", ");
+                            let passage_text = replay.passage.clone();
+                            view! {
+                                <div class="mb-4 p-4 rounded-lg bg-gray-50">
+                                    <div class="text-lg font-mono leading-relaxed mb-3">
+                                        {move || {
+                                            let cursor = replay.cursor_at(watch_ms.get());
+                                            let typed: String = passage_text.chars().take(cursor.pos).collect();
+                                            let rest: String = passage_text.chars().skip(cursor.pos).collect();
+                                            let runs = typed_runs(&typed, &cursor).into_iter().map(|(wrong, run)| view! { <span class=if wrong { "incorrect-char" } else { "correct-char" }>{run}</span> }).collect_view();
+                                            view! { {runs}<span class="text-gray-400">{rest}</span> }
+                                        }}
+                                    </div>
+                                    <p class="text-sm text-gray-600">{move || locale.get().format(Phrase::ReplayAnalysis, &[("consistency", &format!("{:.0}", analysis.consistency)), ("burst", &format!("{:.0}", analysis.burst_wpm))])}</p>
+                                    {(!slowest.is_empty()).then(move || view! {
+                                        <p class="text-sm text-gray-600">{move || locale.get().format(Phrase::SlowestKeys, &[("keys", &slowest)])}</p>
+                                    })}
+                                    <button class="mt-3 bg-gray-100 text-gray-700 px-4 py-2 rounded-lg hover:bg-gray-200 transition-colors font-semibold"
+                                        on:click=move |_| set_watching.set(None)>
+                                        {move || tr(Phrase::CloseReplay)}
+                                    </button>
+                                </div>
+                            }
+                        })}
+                        <ul class="space-y-2 max-h-80 overflow-y-auto">
+                            {move || local_replays.get().into_iter().map(|replay| {
+                                let key = replay.finished_at;
+                                let stats = locale.get().format(Phrase::RecentRaceStats, &[("wpm", &format!("{:.0}", replay.wpm)), ("accuracy", &format!("{:.0}", replay.accuracy))]);
+                                let when = local_time(key, locale.get());
+                                view! {
+                                    <li class="flex items-center justify-between gap-4 p-3 rounded-lg bg-gray-50">
+                                        <div class="min-w-0">
+                                            <div class="text-sm text-gray-500">{when}" · "{stats}</div>
+                                            <div class="text-gray-700 truncate">{replay.passage.clone()}</div>
+                                        </div>
+                                        <div class="flex gap-2">
+                                            <button class="bg-blue-500 text-white px-3 py-1 rounded-lg hover:bg-blue-600 transition-colors font-semibold"
+                                                on:click=move |_| {
+                                                    set_watch_started.set(js_sys::Date::now());
+                                                    set_watch_ms.set(0);
+                                                    set_watching.set(Some(replay.clone()));
+                                                }>
+                                                {move || tr(Phrase::WatchReplay)}
+                                            </button>
+                                            <button class="bg-gray-100 text-gray-700 px-3 py-1 rounded-lg hover:bg-gray-200 transition-colors"
+                                                on:click=move |_| {
+                                                    if watching.with_untracked(|w| w.as_ref().is_some_and(|w| w.finished_at == key)) { set_watching.set(None); }
+                                                    wasm_bindgen_futures::spawn_local(async move {
+                                                        if let Err(e) = replays::erase(key).await { web_sys::console::warn_2(&"Replay not erased".into(), &e); }
+                                                        reload_replays();
+                                                    });
+                                                }>
+                                                {move || tr(Phrase::EraseReplay)}
+                                            </button>
+                                        </div>
+                                    </li>
+                                }
+                            }).collect_view()}
+                        </ul>
+                        <button class="mt-4 text-sm text-red-600 underline"
+                            on:click=move |_| {
+                                set_watching.set(None);
+                                wasm_bindgen_futures::spawn_local(async move {
+                                    if let Err(e) = replays::erase_all().await { web_sys::console::warn_2(&"Replays not erased".into(), &e); }
+                                    reload_replays();
+                                });
+                            }>
+                            {move || tr(Phrase::EraseAllReplays)}
+                        </button>
                     </div>
                 </Show>
 
-                <Show when=move || game_state.get() == "waiting">
+                <Show when=move || game_state.get() == ClientState::Waiting>
                     <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
                         <div class="text-center">
-                            <h2 class="text-2xl font-bold text-gray-800 mb-4">"🏁 Waiting for Race"</h2>
+                            <h2 class="text-2xl font-bold text-gray-800 mb-4">{move || tr(Phrase::WaitingForRace)}</h2>
                             <div class="text-gray-600 mb-6">
-                                <p class="text-lg">"Waiting for more players to join..."</p>
-                                <p class="text-sm mt-2">"Race starts when 2+ players join the room"</p>
-                                <Show when=move || (waiting_seconds.get() > 0)>
+                                <p class="text-lg">{move || tr(Phrase::WaitingForPlayers)}</p>
+                                <p class="text-sm mt-2">{move || tr(Phrase::InviteLink)}
+                                    <a class="font-mono text-blue-600 hover:underline" href=move || join_link(&room_name.get())>{move || join_link(&room_name.get())}</a>
+                                    <button class="ml-2 text-blue-600 hover:underline" on:click=move |_| {
+                                        let Some(window) = web_sys::window() else { return };
+                                        let _ = window.navigator().clipboard().write_text(&join_link(&room_name.get_untracked()));
+                                        set_link_copied.set(true);
+                                        wasm_bindgen_futures::spawn_local(async move {
+                                            gloo_timers::future::TimeoutFuture::new(2_000).await;
+                                            set_link_copied.set(false);
+                                        });
+                                    }>{move || tr(if link_copied.get() { Phrase::LinkCopied } else { Phrase::CopyLink })}</button>
+                                </p>
+                                <p class="text-sm mt-2">{move || {
+                                    let settings = room_settings.get();
+                                    let locale = locale.get();
+                                    match (settings.min_humans, settings.auto_start_secs) {
+                                        (1, _) => locale.text(Phrase::StartsSolo).to_string(),
+                                        (n, Some(secs)) => locale.format(Phrase::StartsWithBots, &[("players", &n), ("secs", &secs)]),
+                                        (n, None) => locale.format(Phrase::StartsWhen, &[("players", &n)]),
+                                    }
+                                }}</p>
+                                <Show when=move || room_settings.get().casual_text()>
+                                    <p class="text-sm mt-2">{move || locale.get().format(Phrase::CasualRace, &[("sample", &casual(CASUAL_SAMPLE))])}</p>
+                                </Show>
+                                <Show when=move || room_settings.get().relay>
+                                    <p class="text-sm mt-2">{move || tr(Phrase::RelayRace)}</p>
+                                </Show>
+                                {move || {
+                                    let settings = room_settings.get();
+                                    settings.max_words.filter(|_| settings.mode == RaceMode::Passages)
+                                        .map(|words| view! { <p class="text-sm mt-2">{locale.get().format(Phrase::RacingFirstWords, &[("words", &words)])}</p> })
+                                }}
+                                <Show when=move || room_settings.get().scoring == Scoring::Golf>
+                                    <p class="text-sm mt-2">{move || tr(Phrase::GolfRace)}</p>
+                                </Show>
+                                {move || duel_line(duel.get(), &players.get()).map(|line| view! { <p class="mt-4 text-lg font-semibold text-gray-800">{line}</p> })}
+                                <Show when=move || !hall_of_fame.get().recent_winners.is_empty()>
+                                    <div class="mt-4 text-sm text-gray-700">
+                                        {move || hall_of_fame.get().best.map(|b| view! {
+                                            <p class="font-semibold">{locale.get().format(Phrase::RoomBest, &[("name", &b.name), ("wpm", &format!("{:.0}", b.wpm))])}</p>
+                                        })}
+                                        <p class="mt-1">{move || tr(Phrase::RecentWinners)}{move || hall_of_fame.get().recent_winners.iter()
+                                            .map(|w| format!("{} ({:.0})", w.name, w.wpm))
+                                            .collect::<Vec<_>>()
+                                            .join(" · ")}</p>
+                                    </div>
+                                </Show>
+                                <Show when=move || { waiting_seconds.get() > 0 }>
                                     <div class="mt-4 p-3 bg-gray-50 rounded-lg inline-block">
-                                        <p class="text-gray-800 font-semibold">{move || format!("Starting in: {} seconds", waiting_seconds.get())}</p>
+                                        <p class="text-gray-800 font-semibold">{move || locale.get().format(Phrase::StartingInSeconds, &[("secs", &waiting_seconds.get())])}</p>
                                     </div>
                                 </Show>
                             </div>
+                            <Show when=move || players.get().iter().any(|p| p.is_host && p.id == my_id.get())>
+                                <div class="flex justify-center gap-4 mb-6 text-sm text-gray-700">
+                                    <label>{move || tr(Phrase::CountdownSecs)}
+                                        <input type="number" min="1" max=RoomSettings::MAX_COUNTDOWN_SECS class="border-2 border-gray-200 rounded-lg px-2 py-1 w-20"
+                                            prop:value=move || room_settings.get().countdown_secs.to_string()
+                                            on:change=move |ev| {
+                                                if let Ok(secs) = event_target_value(&ev).trim().parse::<u32>() {
+                                                    send_settings(RoomSettings { countdown_secs: secs, ..room_settings.get_untracked() });
+                                                }
+                                            }/>
+                                    </label>
+                                    <label>{move || tr(Phrase::MinPlayers)}
+                                        <input type="number" min="1" max=RoomSettings::MAX_MIN_HUMANS class="border-2 border-gray-200 rounded-lg px-2 py-1 w-20"
+                                            prop:value=move || room_settings.get().min_humans.to_string()
+                                            on:change=move |ev| {
+                                                if let Ok(n) = event_target_value(&ev).trim().parse::<u32>() {
+                                                    send_settings(RoomSettings { min_humans: n, ..room_settings.get_untracked() });
+                                                }
+                                            }/>
+                                    </label>
+                                    <label>{move || tr(Phrase::HeatSize)}
+                                        <input type="number" placeholder=move || tr(Phrase::Off) min=RoomSettings::MIN_HEAT_SIZE max=RoomSettings::MAX_HEAT_SIZE class="border-2 border-gray-200 rounded-lg px-2 py-1 w-20"
+                                            prop:value=move || room_settings.get().heat_size.map(|n| n.to_string()).unwrap_or_default()
+                                            on:change=move |ev| {
+                                                // Blank races everyone together
+                                                let value = event_target_value(&ev);
+                                                let heat_size = match value.trim() {
+                                                    "" => None,
+                                                    v => match v.parse::<u32>() { Ok(n) => Some(n), Err(_) => return },
+                                                };
+                                                send_settings(RoomSettings { heat_size, ..room_settings.get_untracked() });
+                                            }/>
+                                    </label>
+                                    <label>{move || tr(Phrase::AutoStartSecs)}
+                                        <input type="number" placeholder=move || tr(Phrase::Off) min=RoomSettings::MIN_AUTO_START_SECS max=RoomSettings::MAX_AUTO_START_SECS class="border-2 border-gray-200 rounded-lg px-2 py-1 w-20"
+                                            prop:value=move || room_settings.get().auto_start_secs.map(|s| s.to_string()).unwrap_or_default()
+                                            on:change=move |ev| {
+                                                // Blank turns the lobby timer off
+                                                let value = event_target_value(&ev);
+                                                let secs = match value.trim() {
+                                                    "" => None,
+                                                    v => match v.parse::<u32>() { Ok(s) => Some(s), Err(_) => return },
+                                                };
+                                                send_settings(RoomSettings { auto_start_secs: secs, ..room_settings.get_untracked() });
+                                            }/>
+                                    </label>
+                                    <label>{move || tr(Phrase::Mistakes)}
+                                        <select class="border-2 border-gray-200 rounded-lg px-2 py-1"
+                                            prop:value=move || error_mode_value(room_settings.get().error_mode)
+                                            on:change=move |ev| {
+                                                let error_mode = match event_target_value(&ev).as_str() {
+                                                    "forgiving" => ErrorMode::Forgiving,
+                                                    "stop_on_word" => ErrorMode::StopOnWord,
+                                                    _ => ErrorMode::Strict,
+                                                };
+                                                send_settings(RoomSettings { error_mode, ..room_settings.get_untracked() });
+                                            }>
+                                            <option value="strict">{move || tr(Phrase::MustFix)}</option>
+                                            <option value="forgiving">{move || tr(Phrase::KeepGoing)}</option>
+                                            <option value="stop_on_word">{move || tr(Phrase::FixBeforeNextWord)}</option>
+                                        </select>
+                                    </label>
+                                    <label>{move || tr(Phrase::FinishLine)}
+                                        <select class="border-2 border-gray-200 rounded-lg px-2 py-1"
+                                            prop:value=move || finish_rule_value(room_settings.get().finish)
+                                            on:change=move |ev| {
+                                                let finish = match event_target_value(&ev).as_str() {
+                                                    "top_three" => FinishRule::TopThree,
+                                                    "first_wins" => FinishRule::FirstWins,
+                                                    "elimination" => FinishRule::Elimination,
+                                                    _ => FinishRule::WaitForAll,
+                                                };
+                                                send_settings(RoomSettings { finish, ..room_settings.get_untracked() });
+                                            }>
+                                            <option value="wait_for_all">{move || tr(Phrase::WaitForAll)}</option>
+                                            <option value="top_three">{move || locale.get().format(Phrase::TopThreeGrace, &[("secs", &FinishRule::GRACE_SECS)])}</option>
+                                            <option value="first_wins">{move || tr(Phrase::FirstWins)}</option>
+                                            <option value="elimination">{move || locale.get().format(Phrase::EliminationRule, &[("secs", &FinishRule::ELIMINATION_SECS)])}</option>
+                                        </select>
+                                    </label>
+                                    <label>{move || tr(Phrase::TextSource)}
+                                        <select class="border-2 border-gray-200 rounded-lg px-2 py-1"
+                                            prop:value=move || race_mode_value(room_settings.get().mode)
+                                            on:change=move |ev| {
+                                                let current = room_settings.get_untracked();
+                                                let difficulty = match current.mode { RaceMode::Drill(d) => d.difficulty, _ => 1 };
+                                                let kind = match event_target_value(&ev).as_str() {
+                                                    "numbers" => Some(DrillKind::Numbers),
+                                                    "symbols" => Some(DrillKind::Symbols),
+                                                    "operators" => Some(DrillKind::Operators),
+                                                    _ => None,
+                                                };
+                                                let mode = match kind {
+                                                    Some(kind) => RaceMode::Drill(Drill { kind, difficulty }),
+                                                    None if event_target_value(&ev) == "words" => RaceMode::Words(Words { list: WordList::Top200, limit: WordLimit::Count(25) }),
+                                                    None => RaceMode::Passages,
+                                                };
+                                                send_settings(RoomSettings { mode, ..current });
+                                            }>
+                                            <option value="passages">{move || tr(Phrase::Passages)}</option>
+                                            <option value="numbers">{move || tr(Phrase::NumbersDrill)}</option>
+                                            <option value="symbols">{move || tr(Phrase::SymbolsDrill)}</option>
+                                            <option value="operators">{move || tr(Phrase::OperatorsDrill)}</option>
+                                            <option value="words">{move || tr(Phrase::RandomWords)}</option>
+                                        </select>
+                                    </label>
+                                    {move || match room_settings.get().mode {
+                                        RaceMode::Drill(drill) => Some(view! {
+                                            <label>{move || tr(Phrase::Difficulty)}
+                                                <input type="number" min="1" max=Drill::MAX_DIFFICULTY class="border-2 border-gray-200 rounded-lg px-2 py-1 w-16"
+                                                    prop:value=drill.difficulty.to_string()
+                                                    on:change=move |ev| {
+                                                        if let Ok(difficulty) = event_target_value(&ev).trim().parse::<u8>() {
+                                                            send_settings(RoomSettings { mode: RaceMode::Drill(Drill { difficulty, ..drill }), ..room_settings.get_untracked() });
+                                                        }
+                                                    }/>
+                                            </label>
+                                        }.into_any()),
+                                        RaceMode::Words(words) => Some(view! {
+                                            <label>{move || tr(Phrase::Words)}
+                                                <select class="border-2 border-gray-200 rounded-lg px-2 py-1"
+                                                    prop:value=if words.list == WordList::Top1000 { "1000" } else { "200" }
+                                                    on:change=move |ev| {
+                                                        let list = if event_target_value(&ev) == "1000" { WordList::Top1000 } else { WordList::Top200 };
+                                                        send_settings(RoomSettings { mode: RaceMode::Words(Words { list, ..words }), ..room_settings.get_untracked() });
+                                                    }>
+                                                    <option value="200">{move || tr(Phrase::Top200)}</option>
+                                                    <option value="1000">{move || tr(Phrase::Top1000)}</option>
+                                                </select>
+                                            </label>
+                                            <label>
+                                                <input type="number" class="border-2 border-gray-200 rounded-lg px-2 py-1 w-20"
+                                                    prop:value=match words.limit { WordLimit::Count(n) | WordLimit::Seconds(n) => n.to_string() }
+                                                    on:change=move |ev| {
+                                                        let Ok(n) = event_target_value(&ev).trim().parse::<u32>() else { return };
+                                                        let limit = match words.limit { WordLimit::Count(_) => WordLimit::Count(n), WordLimit::Seconds(_) => WordLimit::Seconds(n) };
+                                                        send_settings(RoomSettings { mode: RaceMode::Words(Words { limit, ..words }), ..room_settings.get_untracked() });
+                                                    }/>
+                                                <select class="border-2 border-gray-200 rounded-lg px-2 py-1 ml-1"
+                                                    prop:value=if words.limit.time_limit_secs().is_some() { "seconds" } else { "words" }
+                                                    on:change=move |ev| {
+                                                        let limit = if event_target_value(&ev) == "seconds" { WordLimit::Seconds(30) } else { WordLimit::Count(25) };
+                                                        send_settings(RoomSettings { mode: RaceMode::Words(Words { limit, ..words }), ..room_settings.get_untracked() });
+                                                    }>
+                                                    <option value="words">{move || tr(Phrase::UnitWords)}</option>
+                                                    <option value="seconds">{move || tr(Phrase::UnitSeconds)}</option>
+                                                </select>
+                                            </label>
+                                        }.into_any()),
+                                        RaceMode::Passages => Some(view! {
+                                            <label>{move || tr(Phrase::WordsToRace)}
+                                                <input type="number" placeholder=move || tr(Phrase::WholePassage) min=RoomSettings::MIN_WORDS max=RoomSettings::MAX_WORDS class="border-2 border-gray-200 rounded-lg px-2 py-1 w-20"
+                                                    prop:value=move || room_settings.get().max_words.map(|w| w.to_string()).unwrap_or_default()
+                                                    on:change=move |ev| {
+                                                        // Blank races passages whole
+                                                        let value = event_target_value(&ev);
+                                                        let words = match value.trim() {
+                                                            "" => None,
+                                                            v => match v.parse::<u32>() { Ok(w) => Some(w), Err(_) => return },
+                                                        };
+                                                        send_settings(RoomSettings { max_words: words, ..room_settings.get_untracked() });
+                                                    }/>
+                                            </label>
+                                            <label>{move || tr(Phrase::RaceLength)}
+                                                <select class="border-2 border-gray-200 rounded-lg px-2 py-1"
+                                                    prop:value=move || match room_settings.get().length { PassageLength::Whole => "whole", PassageLength::Racers => "racers", PassageLength::Seconds(_) => "seconds" }
+                                                    on:change=move |ev| {
+                                                        let length = match event_target_value(&ev).as_str() {
+                                                            "racers" => PassageLength::Racers,
+                                                            "seconds" => PassageLength::Seconds(PassageLength::DEFAULT_SECS),
+                                                            _ => PassageLength::Whole,
+                                                        };
+                                                        send_settings(RoomSettings { length, ..room_settings.get_untracked() });
+                                                    }>
+                                                    <option value="whole">{move || tr(Phrase::WholePassage)}</option>
+                                                    <option value="racers">{move || tr(Phrase::LengthByRacers)}</option>
+                                                    <option value="seconds">{move || tr(Phrase::LengthBySeconds)}</option>
+                                                </select>
+                                                {move || match room_settings.get().length {
+                                                    PassageLength::Seconds(secs) => Some(view! {
+                                                        <input type="number" min=PassageLength::MIN_SECS max=PassageLength::MAX_SECS class="border-2 border-gray-200 rounded-lg px-2 py-1 w-20 ml-1"
+                                                            prop:value=secs.to_string()
+                                                            on:change=move |ev| {
+                                                                if let Ok(secs) = event_target_value(&ev).trim().parse::<u32>() {
+                                                                    send_settings(RoomSettings { length: PassageLength::Seconds(secs), ..room_settings.get_untracked() });
+                                                                }
+                                                            }/>
+                                                        <span class="ml-1">{move || tr(Phrase::UnitSeconds)}</span>
+                                                    }),
+                                                    _ => None,
+                                                }}
+                                            </label>
+                                        }.into_any()),
+                                    }}
+                                    <label>
+                                        <input type="checkbox" class="mr-1"
+                                            prop:checked=move || room_settings.get().casual
+                                            on:change=move |ev| {
+                                                send_settings(RoomSettings { casual: event_target_checked(&ev), ..room_settings.get_untracked() });
+                                            }/>
+                                        {move || tr(Phrase::Casual)}
+                                    </label>
+                                    <label>
+                                        <input type="checkbox" class="mr-1"
+                                            prop:checked=move || room_settings.get().pausable
+                                            on:change=move |ev| {
+                                                send_settings(RoomSettings { pausable: event_target_checked(&ev), ..room_settings.get_untracked() });
+                                            }/>
+                                        {move || tr(Phrase::Pausable)}
+                                    </label>
+                                    <label>
+                                        <input type="checkbox" class="mr-1"
+                                            prop:checked=move || room_settings.get().scoring == Scoring::Golf
+                                            on:change=move |ev| {
+                                                let scoring = if event_target_checked(&ev) { Scoring::Golf } else { Scoring::Speed };
+                                                send_settings(RoomSettings { scoring, ..room_settings.get_untracked() });
+                                            }/>
+                                        {move || tr(Phrase::Golf)}
+                                    </label>
+                                    <Show when=move || room_settings.get().mode.time_limit_secs().is_none()>
+                                        <label>
+                                            <input type="checkbox" class="mr-1"
+                                                prop:checked=move || room_settings.get().relay
+                                                on:change=move |ev| {
+                                                    send_settings(RoomSettings { relay: event_target_checked(&ev), ..room_settings.get_untracked() });
+                                                }/>
+                                            {move || tr(Phrase::Relay)}
+                                        </label>
+                                    </Show>
+                                </div>
+                            </Show>
                             <div class="mb-6">
-                                <h3 class="text-lg font-semibold mb-3 text-gray-700">"Players in Room:"</h3>
+                                <h3 class="text-lg font-semibold mb-3 text-gray-700">{move || tr(Phrase::PlayersInRoom)}</h3>
                                 <div class="flex flex-wrap justify-center gap-3">
                                     <For
                                         each=move || players.get().into_iter().enumerate()
-                                        key=|(i, p)| format!("{i}-{p}")
+                                        key=|(_, p)| p.id.clone()
                                         children=move |(_idx, player)| {
+                                            let badge = if player.is_host { "👑 " } else if player.pace_for.is_some() { "⏱ " } else if player.is_bot { "🤖 " } else { "" };
+                                            let chip = move || {
+                                                let locale = locale.get();
+                                                let rating = player.rating.map(|r| locale.format(Phrase::PlayerRating, &[("wpm", &format!("{r:.0}"))])).unwrap_or_default();
+                                                let level = player.level.map(|l| locale.format(Phrase::PlayerLevel, &[("level", &l)])).unwrap_or_default();
+                                                format!("{badge}{}{rating}{level}", player.name)
+                                            };
                                             view! {
                                                 <div class="bg-gradient-to-r from-sky-400 to-cyan-500 text-white px-4 py-2 rounded-full font-semibold shadow-lg">
-                                                    {player}
+                                                    {chip}
                                                 </div>
                                             }
                                         }
                                     />
                                 </div>
                             </div>
+                            <div class="border-t border-gray-200 pt-4 text-sm text-gray-700">
+                                {move || match side_race.get() {
+                                    None => view! {
+                                        <form class="flex justify-center gap-2" on:submit=move |ev| {
+                                            ev.prevent_default();
+                                            let room = side_room.get_untracked().trim().to_string();
+                                            if room.is_empty() || room == room_name.get_untracked() { return; }
+                                            send(&ClientMsg::Watch { room: room.clone() });
+                                            set_side_race.set(Some(SideRace::new(room)));
+                                        }>
+                                            <input type="text" maxlength=MAX_ROOM_CHARS class="border-2 border-gray-200 rounded-lg px-2 py-1"
+                                                placeholder=move || tr(Phrase::WatchAnotherRoom)
+                                                prop:value=move || side_room.get()
+                                                on:input=move |ev| set_side_room.set(event_target_value(&ev))/>
+                                            <button type="submit" class="bg-gray-600 text-white px-3 py-1 rounded-lg hover:bg-gray-700">{move || tr(Phrase::Watch)}</button>
+                                        </form>
+                                    }.into_any(),
+                                    Some(side) => view! {
+                                        <div>
+                                            <p class="font-semibold mb-2">
+                                                {locale.get().format(Phrase::AlsoWatching, &[("room", &side.room)])}
+                                                <button class="ml-3 text-blue-600 hover:underline" on:click=move |_| {
+                                                    if let Some(side) = side_race.get_untracked() { send(&ClientMsg::Unwatch { room: side.room }); }
+                                                    set_side_race.set(None);
+                                                }>{move || tr(Phrase::StopWatching)}</button>
+                                            </p>
+                                            <ul>
+                                                {side.standings().into_iter().map(|(name, percent)| view! { <li>{format!("{name} {percent}%")}</li> }).collect_view()}
+                                            </ul>
+                                        </div>
+                                    }.into_any(),
+                                }}
+                            </div>
                         </div>
                     </div>
                 </Show>
 
-                <Show when=move || game_state.get() == "finished">
+                <Show when=move || game_state.get() == ClientState::Finished>
                     <div class="stat-card rounded-xl shadow-xl p-6 mb-6">
                         <div class="text-center mb-6">
-                            <h2 class="text-3xl font-bold text-gray-800 mb-2">"🏆 Race Complete!"</h2>
+                            <h2 class="text-3xl font-bold text-gray-800 mb-2">{move || tr(Phrase::RaceComplete)}</h2>
+                            {move || byline.get().map(|b| view! { <p class="text-sm italic text-gray-500">{b}</p> })}
                         </div>
-                        <Show when=move || (ALLOW_TEST_UI && test_mode.get())>
+                        <Show when=move || { ALLOW_TEST_UI && test_mode.get() }>
                             <div class="mb-4 p-3 rounded bg-yellow-100 border border-yellow-300 text-yellow-800 text-sm font-medium">"TEST MODE — Local practice (no server sync)"</div>
                         </Show>
                         <div class="grid grid-cols-1 md:grid-cols-3 gap-6 mb-6">
                             <div class="text-center p-4 bg-blue-50 rounded-lg">
                                 <div class="text-4xl font-bold text-blue-600">{move || format!("{:.0}", wpm.get())}</div>
-                                <div class="text-gray-600">"Words per Minute"</div>
+                                <div class="text-gray-600">{move || tr(Phrase::WordsPerMinute)}</div>
                             </div>
                             <div class="text-center p-4 bg-green-50 rounded-lg">
                                 <div class="text-4xl font-bold text-green-600">{move || format!("{:.0}%", accuracy.get())}</div>
-                                <div class="text-gray-600">"Accuracy"</div>
+                                <div class="text-gray-600">{move || tr(Phrase::Accuracy)}</div>
                             </div>
                             <div class="text-center p-4 bg-purple-50 rounded-lg">
                                 <div class="text-4xl font-bold text-purple-600">{move || finish_time.get().map(|t| format!("{t:.1}s")).unwrap_or_else(|| "0s".to_string())}</div>
-                                <div class="text-gray-600">"Total Time"</div>
+                                <div class="text-gray-600">{move || tr(Phrase::TotalTime)}</div>
                             </div>
                         </div>
+                        <Show when=move || pace_target.get().is_some() && !test_mode.get()>
+                            <p class="text-center text-lg font-semibold mb-4">{move || {
+                                let target = format!("{:.0}", pace_target.get().unwrap_or(0.0));
+                                let phrase = if wpm.get() >= pace_target.get().unwrap_or(0.0) { Phrase::BeatPaceCar } else { Phrase::PaceCarWon };
+                                locale.get().format(phrase, &[("wpm", &target)])
+                            }}</p>
+                        </Show>
+                        {move || pace_stats.get().map(|(consistency, burst, adjusted)| view! {
+                            <p class="text-center text-gray-700 mb-4">
+                                {move || locale.get().format(Phrase::PaceStats, &[("consistency", &format!("{consistency:.0}")), ("burst", &format!("{burst:.0}")), ("adjusted", &format!("{adjusted:.0}"))])}
+                            </p>
+                        })}
+                        {move || wpm_samples.with(|s| polyline_points(s, 300.0, 60.0)).map(|points| {
+                            let peak = wpm_samples.with(|s| s.iter().cloned().fold(0.0_f64, f64::max));
+                            view! {
+                                <div class="text-center mb-6">
+                                    <svg viewBox="0 0 300 60" class="w-full max-w-md h-16 mx-auto" preserveAspectRatio="none">
+                                        <polyline points=points fill="none" stroke="#7c3aed" stroke-width="2" stroke-linejoin="round"/>
+                                    </svg>
+                                    <p class="text-xs text-gray-500">{move || locale.get().format(Phrase::WpmOverRace, &[("peak", &format!("{peak:.0}"))])}</p>
+                                </div>
+                            }
+                        })}
+                        {move || xp_gained.get().map(|(xp, total, leveled_up)| {
+                            let (into, needed) = level_progress(total);
+                            let pct = into as f64 / needed as f64 * 100.0;
+                            view! {
+                                <div class="text-center mb-4">
+                                    <p class="text-lg font-semibold text-gray-800">
+                                        {move || locale.get().format(Phrase::XpGained, &[("xp", &xp), ("level", &level_for_xp(total))])}
+                                        {move || leveled_up.then(|| tr(Phrase::LevelUp))}
+                                    </p>
+                                    <div class="w-64 h-2 bg-gray-200 rounded-full mx-auto mt-2">
+                                        <div class="h-2 bg-purple-500 rounded-full" style=format!("width: {pct:.0}%")></div>
+                                    </div>
+                                    <p class="text-xs text-gray-500 mt-1">{move || locale.get().format(Phrase::XpToNextLevel, &[("into", &into), ("needed", &needed)])}</p>
+                                </div>
+                            }
+                        })}
+                        <Show when=move || personal_best.get().is_some()>
+                            <div class="text-center mb-4 p-4 rounded-xl bg-gradient-to-r from-yellow-300 to-amber-400 text-gray-900 shadow-lg">
+                                <p class="text-2xl font-bold">{move || tr(Phrase::NewPersonalBest)}</p>
+                                <p class="text-lg">{move || personal_best.get().map(|(category, wpm, previous)| {
+                                    let category = tr(match category {
+                                        PassageCategory::Short => Phrase::CategoryShort,
+                                        PassageCategory::Medium => Phrase::CategoryMedium,
+                                        PassageCategory::Long => Phrase::CategoryLong,
+                                    });
+                                    locale.get().format(Phrase::PersonalBestDetail, &[
+                                        ("wpm", &format!("{wpm:.1}")),
+                                        ("category", &category),
+                                        ("previous", &format!("{previous:.1}")),
+                                        ("gain", &format!("{:.1}", wpm - previous)),
+                                    ])
+                                }).unwrap_or_default()}</p>
+                            </div>
+                        </Show>
+                        {move || new_record.get().map(|(record, previous)| view! {
+                            <div class="text-center mb-4 p-4 rounded-xl bg-gradient-to-r from-sky-300 to-indigo-400 text-gray-900 shadow-lg">
+                                <p class="text-2xl font-bold">{tr(Phrase::NewPassageRecord)}</p>
+                                <p class="text-lg">{locale.get().format(Phrase::NewPassageRecordDetail, &[
+                                    ("name", &record.name),
+                                    ("wpm", &format!("{:.0}", record.wpm)),
+                                    ("previous", &format!("{:.0}", previous.wpm)),
+                                    ("previous_name", &previous.name),
+                                ])}</p>
+                            </div>
+                        })}
+                        <Show when=move || percentile.get().is_some()>
+                            <p class="text-center text-lg text-gray-700 mb-6">{move || locale.get().format(Phrase::Percentile, &[("percent", &percentile.get().unwrap_or(0))])}</p>
+                        </Show>
+                        <Show when=move || !heats.get().is_empty() && leaderboard.with(|lb| lb.iter().all(|r| r.heat.is_none()))>
+                            <p class="text-center text-sm text-gray-600 mb-4">{move || tr(Phrase::OtherHeatsRacing)}</p>
+                        </Show>
                         <Show when=move || !leaderboard.get().is_empty()>
                             <div class="mb-6">
-                                <h3 class="text-xl font-semibold mb-3 text-gray-700">"Final Results:"</h3>
-                                <div class="space-y-2">
-                                    <For
-                                        each=move || leaderboard.get().into_iter().enumerate()
-                                        key=|(i, (name, _, _))| format!("{i}-{name}")
-                                        children=move |(idx, (name, lwpm, lacc))| {
-                                            view! { <div class="p-3 bg-gray-50 rounded-lg">{format!("#{}  {} — {:.0} WPM, {:.0}%", idx + 1, name, lwpm, lacc)}</div> }
-                                        }
-                                    />
+                                <h3 class="text-xl font-semibold mb-3 text-gray-700">{move || tr(Phrase::FinalResults)}</h3>
+                                <div class="overflow-x-auto">
+                                    <table class="results-table w-full text-left">
+                                        <thead>
+                                            <tr>
+                                                {Column::ALL.into_iter().map(|column| {
+                                                    let label = match column {
+                                                        Column::Rank => Phrase::RankColumn,
+                                                        Column::Name => Phrase::RacerColumn,
+                                                        Column::Wpm => Phrase::Wpm,
+                                                        Column::RawWpm => Phrase::RawWpm,
+                                                        Column::Accuracy => Phrase::Accuracy,
+                                                        Column::Errors => Phrase::ErrorsColumn,
+                                                        Column::Time => Phrase::Time,
+                                                        Column::Consistency => Phrase::Consistency,
+                                                    };
+                                                    let arrow = move || {
+                                                        let sort = results_sort.get();
+                                                        if sort.column != column { "" } else if sort.descending { " ▼" } else { " ▲" }
+                                                    };
+                                                    view! {
+                                                        <th class="sortable" aria-sort=move || {
+                                                            let sort = results_sort.get();
+                                                            if sort.column != column { "none" } else if sort.descending { "descending" } else { "ascending" }
+                                                        }>
+                                                            <button on:click=move |_| set_results_sort.update(|s| *s = s.click(column))>{move || tr(label)}{arrow}</button>
+                                                        </th>
+                                                    }
+                                                }).collect_view()}
+                                                <th>{move || tr(Phrase::Splits)}</th>
+                                            </tr>
+                                        </thead>
+                                        <tbody>
+                                            <For
+                                                each=move || results::sorted(&leaderboard.get(), results_sort.get())
+                                                key=|r| format!("{}-{}", r.rank, r.name)
+                                                children=move |r| {
+                                                    let split_text = r.splits.iter().map(|(c, t)| format!("{c}% {t:.1}s")).collect::<Vec<_>>().join(" · ");
+                                                    view! {
+                                                        <tr>
+                                                            <td>{r.rank}</td>
+                                                            <td class="font-semibold">
+                                                                {r.name}
+                                                                {r.heat.map(|heat| view! { <span class="text-xs font-normal text-gray-500 ms-1">{move || locale.get().format(Phrase::HeatNumber, &[("heat", &(heat + 1))])}</span> })}
+                                                            </td>
+                                                            <td>{format!("{:.0}", r.wpm)}</td>
+                                                            <td>{format!("{:.0}", r.raw_wpm)}</td>
+                                                            <td>{format!("{:.0}%", r.accuracy)}</td>
+                                                            <td>{r.errors}</td>
+                                                            <td>{format!("{:.1}s", r.time)}</td>
+                                                            <td>{format!("{:.0}%", r.consistency)}</td>
+                                                            <td class="text-sm text-gray-500">{split_text}</td>
+                                                        </tr>
+                                                    }
+                                                }
+                                            />
+                                        </tbody>
+                                    </table>
                                 </div>
                             </div>
                         </Show>
+                        {move || team_times.get().into_iter().map(|(team, time, team_wpm)| view! {
+                            <p class="text-center text-lg font-semibold text-gray-700 mb-2">{locale.get().format(Phrase::TeamHome, &[("team", &(team + 1)), ("time", &format!("{time:.1}")), ("wpm", &format!("{team_wpm:.0}"))])}</p>
+                        }).collect_view()}
+                        <Show when=move || !eliminated.get().is_empty()>
+                            <p class="text-center text-gray-600 mb-4">{move || {
+                                let roster = players.get();
+                                let names = eliminated.get().iter()
+                                    .map(|id| roster.iter().find(|p| &p.id == id).map(|p| p.name.clone()).unwrap_or_else(|| id.clone()))
+                                    .collect::<Vec<_>>().join(", ");
+                                locale.get().format(Phrase::EliminatedList, &[("names", &names)])
+                            }}</p>
+                        </Show>
+                        <Show when=move || !dnf.get().is_empty()>
+                            <p class="text-center text-gray-600 mb-4">{move || {
+                                let roster = players.get();
+                                let names = dnf.get().iter()
+                                    .map(|id| roster.iter().find(|p| &p.id == id).map(|p| p.name.clone()).unwrap_or_else(|| id.clone()))
+                                    .collect::<Vec<_>>().join(", ");
+                                locale.get().format(Phrase::DidNotFinish, &[("names", &names)])
+                            }}</p>
+                        </Show>
+                        <Show when=move || !race_summary.get().is_empty()>
+                            <div class="mb-6">
+                                <h3 class="text-xl font-semibold mb-3 text-gray-700">{move || tr(Phrase::RecapTitle)}</h3>
+                                <ul class="space-y-1 text-gray-700">
+                                    {move || recap_lines(locale.get(), &race_summary.get(), &players.get()).into_iter().map(|line| view! { <li>{line}</li> }).collect_view()}
+                                </ul>
+                            </div>
+                        </Show>
+                        {move || rematch.get().map(|(by, accepted)| {
+                            let names = players.get();
+                            let name_of = |id: &str| names.iter().find(|p| p.id == id).map(|p| p.name.clone()).unwrap_or_default();
+                            let humans = names.iter().filter(|p| !p.is_bot).count();
+                            view! { <p class="text-center text-gray-700 mb-4">{locale.get().format(Phrase::RematchOffer, &[("name", &name_of(&by)), ("accepted", &accepted.len()), ("humans", &humans)])}</p> }
+                        })}
+                        {move || duel_line(duel.get(), &players.get()).map(|line| view! { <p class="text-center text-lg font-semibold text-gray-700 mb-4">{line}</p> })}
+                        <Show when=move || !series_score.get().is_empty()>
+                            <p class="text-center text-lg font-semibold text-gray-700 mb-4">{move || {
+                                let names = players.get();
+                                let mut score: Vec<(String, u32)> = series_score.get().into_iter()
+                                    .map(|(id, wins)| (names.iter().find(|p| p.id == id).map(|p| p.name.clone()).unwrap_or(id), wins))
+                                    .collect();
+                                score.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                                let score = score.iter().map(|(name, wins)| format!("{name} {wins}")).collect::<Vec<_>>().join(" · ");
+                                locale.get().format(Phrase::Series, &[("score", &score)])
+                            }}</p>
+                        </Show>
                         <div class="text-center">
                             <button class="bg-green-500 text-white px-8 py-3 rounded-lg hover:bg-green-600 transition-colors font-semibold text-lg"
-                                on:click=move |_| {
-                                    // Optimistic local reset for snappy UX
-                                    set_game_state.set("waiting".to_string());
-                                    set_current_position.set(0);
-                                    set_errors.set(0);
-                                    set_wpm.set(0.0);
-                                    set_accuracy.set(100.0);
-                                    set_time_elapsed.set(0.0);
-                                    set_finish_time.set(None);
-                                    set_leaderboard.set(Vec::new());
-                                    set_player_positions.set(HashMap::new());
-                                    set_test_mode.set(false);
-                                    WS_REF.with(|cell| {
-                                        if let Some(ws) = cell.borrow().as_ref() {
-                                            let msg = ClientMsg::Reset;
-                                            if let Ok(json) = serde_json::to_string(&msg) { let _ = ws.send_with_str(&json); }
-                                        }
-                                    });
-                                }>
-                                "🏁 Race Again"
+                                on:click=move |_| race_again()>
+                                {move || tr(Phrase::RaceAgain)}
                             </button>
-                            <Show when=move || (ALLOW_TEST_UI && test_mode.get())>
+                            {move || current_lesson.get().map(|lesson| {
+                                let passed = lesson.passes(wpm.get(), accuracy.get());
+                                let verdict = if passed {
+                                    tr(Phrase::LessonPassed).to_string()
+                                } else {
+                                    locale.get().format(Phrase::LessonNotYet, &[("wpm", &lesson.min_wpm), ("accuracy", &lesson.min_accuracy)])
+                                };
+                                view! {
+                                    <p class="text-lg font-semibold mb-4">{verdict}</p>
+                                    <button class="mr-3 bg-blue-500 text-white px-6 py-3 rounded-lg hover:bg-blue-600 transition-colors font-semibold text-lg"
+                                        on:click=move |_| start_lesson(lesson.clone())>
+                                        {move || tr(Phrase::TryAgain)}
+                                    </button>
+                                    {move || next_lesson().map(|next| view! {
+                                        <button class="mr-3 bg-green-500 text-white px-6 py-3 rounded-lg hover:bg-green-600 transition-colors font-semibold text-lg"
+                                            on:click=move |_| start_lesson(next.clone())>
+                                            {move || tr(Phrase::NextLesson)}
+                                        </button>
+                                    })}
+                                }
+                            })}
+                            <Show when=move || drill_focus.get().is_some()>
+                                <button class="ml-3 bg-blue-500 text-white px-6 py-3 rounded-lg hover:bg-blue-600 transition-colors font-semibold text-lg disabled:opacity-50 disabled:cursor-not-allowed"
+                                    prop:disabled=move || next_drill.get().is_none()
+                                    on:click=move |_| { if let Some(drill) = next_drill.get_untracked() { practice_drill(drill); } }>
+                                    {move || tr(Phrase::NextDrill)}
+                                </button>
+                            </Show>
+                            <Show when=move || !test_mode.get()>
+                                <button class="ml-3 bg-blue-500 text-white px-6 py-3 rounded-lg hover:bg-blue-600 transition-colors font-semibold text-lg disabled:opacity-50 disabled:cursor-not-allowed"
+                                    title=move || tr(Phrase::RematchHint)
+                                    prop:disabled=move || rematch.get().is_some_and(|(_, accepted)| accepted.contains(&my_id.get()))
+                                    on:click=move |_| send(&ClientMsg::RematchAccept)>
+                                    {move || tr(match rematch.get() {
+                                        Some((_, accepted)) if accepted.contains(&my_id.get()) => Phrase::WaitingForOthers,
+                                        Some(_) => Phrase::AcceptRematch,
+                                        None => Phrase::Rematch,
+                                    })}
+                                </button>
+                            </Show>
+                            <a class="ml-3 inline-block bg-gray-100 text-gray-700 px-6 py-3 rounded-lg hover:bg-gray-200 transition-colors font-semibold text-lg"
+                                href=move || with_query(&me_url("export"), "format=csv")
+                                download>
+                                {move || tr(Phrase::ExportRaces)}
+                            </a>
+                            <Show when=move || !spectating.get()>
+                                <a class="ml-3 inline-block bg-gray-100 text-gray-700 px-6 py-3 rounded-lg hover:bg-gray-200 transition-colors font-semibold text-lg"
+                                    href=move || {
+                                        let name = player_name.get();
+                                        let place = leaderboard.with(|lb| lb.iter().find(|r| r.name == name).map(|r| r.rank));
+                                        card_url(&name, wpm.get(), accuracy.get(), place.map(|p| (p, players.with(Vec::len))), byline.get().as_deref())
+                                    }
+                                    target="_blank" rel="noopener">
+                                    {move || tr(Phrase::ShareCard)}
+                                </a>
+                            </Show>
+                            <Show when=move || { ALLOW_TEST_UI && test_mode.get() }>
                                 <button class="ml-3 bg-gray-600 text-white px-6 py-3 rounded-lg hover:bg-gray-700 transition-colors font-semibold text-lg"
                                     on:click=move |_| {
                                         // Exit local test mode back to waiting
-                                        set_game_state.set("waiting".to_string());
+                                        set_game_state.set(ClientState::Waiting);
                                         set_current_position.set(0);
+                                        set_cursor.set(Cursor::default());
                                         set_errors.set(0);
                                         set_wpm.set(0.0);
                                         set_accuracy.set(100.0);
@@ -626,19 +2330,362 @@ pub fn App() -> impl IntoView {
                                         set_leaderboard.set(Vec::new());
                                         set_player_positions.set(HashMap::new());
                                         set_test_mode.set(false);
+                                        set_drill_focus.set(None);
+                                        set_current_lesson.set(None);
+                                        set_paused.set(None);
                                     }>
                                     "Exit Test"
                                 </button>
                             </Show>
+                            <p class="mt-3 text-sm text-gray-500">{move || tr(Phrase::HotkeyHint)}</p>
+                        </div>
+                    </div>
+                </Show>
+
+                <Show when=move || diagnostics_open.get()>
+                    <div class="fixed bottom-4 right-4 z-40 bg-gray-900/90 text-gray-100 text-xs font-mono rounded-lg shadow-lg p-3 space-y-1">
+                        <p class="font-semibold">{move || tr(Phrase::Diagnostics)}</p>
+                        <p>{move || input_latency.with(|l| match (l.average(), l.worst()) {
+                            (Some(avg), Some(worst)) => locale.get().format(Phrase::KeyToScreen, &[("avg", &format!("{avg:.0}")), ("worst", &format!("{worst:.0}"))]),
+                            _ => tr(Phrase::KeyToScreenUnmeasured).to_string(),
+                        })}</p>
+                        <p>{move || match rtt_ms.get() {
+                            Some(ms) => locale.get().format(Phrase::ServerRoundTrip, &[("ms", &ms)]),
+                            None => tr(Phrase::ServerRoundTripUnmeasured).to_string(),
+                        }}</p>
+                        {move || diagnostics::lag(input_latency.with(InputLatency::average), rtt_ms.get()).map(|lag| view! {
+                            <p class="text-yellow-300">{tr(match lag { Lag::Device => Phrase::LagOnDevice, Lag::Network => Phrase::LagOnNetwork })}</p>
+                        })}
+                    </div>
+                </Show>
+
+                <Show when=move || palette_open.get()>
+                    <div class="fixed inset-0 z-50 bg-black/40 flex items-start justify-center pt-24" on:click=move |_| close_palette()>
+                        <div class="stat-card rounded-xl shadow-2xl w-full max-w-md p-3" on:click=|ev| ev.stop_propagation()>
+                            <input id="commandPalette" type="text" autocomplete="off" placeholder=move || tr(Phrase::PaletteHint)
+                                class="border-2 border-gray-200 rounded-lg px-4 py-3 w-full focus:border-blue-500 focus:outline-none"
+                                prop:value=palette_query
+                                on:input=move |ev| {
+                                    set_palette_query.set(event_target_value(&ev));
+                                    set_palette_pick.set(0);
+                                }
+                                on:keydown=move |ev| {
+                                    let count = palette_commands.with(Vec::len);
+                                    match ev.key().as_str() {
+                                        "ArrowDown" if count > 0 => set_palette_pick.update(|i| *i = (*i + 1) % count),
+                                        "ArrowUp" if count > 0 => set_palette_pick.update(|i| *i = (*i + count - 1) % count),
+                                        "Enter" => {
+                                            let picked = palette_commands.with(|commands| commands.get(palette_pick.get_untracked()).map(|(c, _)| *c));
+                                            if let Some(command) = picked { run_command(command); }
+                                        }
+                                        // Escape and Ctrl+K reach the window listener
+                                        _ => return,
+                                    }
+                                    ev.prevent_default();
+                                }/>
+                            <ul class="mt-2 max-h-80 overflow-y-auto">
+                                {move || palette_commands.get().into_iter().enumerate().map(|(i, (command, label))| view! {
+                                    <li class=move || if palette_pick.get() == i { "px-3 py-2 rounded-lg cursor-pointer bg-blue-500 text-white" } else { "px-3 py-2 rounded-lg cursor-pointer text-gray-700" }
+                                        on:mouseenter=move |_| set_palette_pick.set(i)
+                                        on:click=move |_| run_command(command)>
+                                        {label}
+                                    </li>
+                                }).collect_view()}
+                            </ul>
+                            <Show when=move || palette_commands.with(Vec::is_empty)>
+                                <p class="px-3 py-2 text-gray-500">{move || tr(Phrase::NoCommands)}</p>
+                            </Show>
                         </div>
                     </div>
                 </Show>
 
                 <div class="text-center text-white text-sm mt-8">
-                    <p>"Built with ❤️ using Rust 🦀 + WebAssembly + WebSockets"</p>
-                    <p class="mt-1">"by ystdin"</p>
+                    <p>{move || tr(Phrase::BuiltWith)}</p>
+                    <p class="mt-1">{move || locale.get().format(Phrase::ByAuthor, &[("author", &"ystdin")])}</p>
                 </div>
             </div>
         </div>
     }
 }
+
+/// "⚔️ Ann 3 – 2 Bob" for a two-human room's head-to-head record.
+fn duel_line(duel: Option<DuelRecord>, players: &[PlayerInfo]) -> Option<String> {
+    let duel = duel?;
+    let name = |id: &str| players.iter().find(|p| p.id == id).map(|p| p.name.clone());
+    Some(format!("⚔️ {} {} – {} {}", name(&duel.a)?, duel.a_wins, duel.b_wins, name(&duel.b)?))
+}
+
+/// The race's moments as sentences, in the order they happened.
+fn recap_lines(locale: Locale, moments: &[Moment], players: &[PlayerInfo]) -> Vec<String> {
+    let name_of = |id: &str| players.iter().find(|p| p.id == id).map_or_else(|| id.to_string(), |p| p.name.clone());
+    moments.iter().map(|moment| match moment {
+        Moment::FirstToHalf { id, time } => locale.format(Phrase::RecapFirstToHalf, &[("time", &format!("{time:.1}")), ("name", &name_of(id))]),
+        Moment::TookLead { overtaker, overtaken, time } => locale.format(Phrase::RecapTookLead, &[("time", &format!("{time:.1}")), ("name", &name_of(overtaker)), ("other", &name_of(overtaken))]),
+        Moment::FastestBurst { id, wpm, time } => locale.format(Phrase::RecapFastestBurst, &[("time", &format!("{time:.0}")), ("name", &name_of(id)), ("wpm", &format!("{wpm:.0}")), ("secs", &BURST_SECS)]),
+        Moment::FinalOrder { ids } => locale.format(Phrase::RecapFinalOrder, &[("names", &ids.iter().map(|id| name_of(id)).collect::<Vec<_>>().join(", "))]),
+    }).collect()
+}
+
+/// Image of a race result to share: `place` is our finishing place and the number of racers.
+/// The teams in running order, then who has our team's current leg.
+fn relay_lines(locale: Locale, relay: &Relay, me: &str, players: &[PlayerInfo]) -> Vec<String> {
+    let name_of = |id: &str| players.iter().find(|p| p.id == id).map_or_else(|| id.to_string(), |p| p.name.clone());
+    let mut lines: Vec<String> = relay.teams.iter().enumerate()
+        .map(|(team, members)| locale.format(Phrase::RelayTeam, &[("team", &(team + 1)), ("names", &members.iter().map(|m| name_of(m)).collect::<Vec<_>>().join(", "))]))
+        .collect();
+    let Some(team) = relay.team_of(me) else { return lines };
+    let (Some(runner), leg) = (relay.runner(team), relay.current[team]) else { return lines };
+    let args: [(&str, &dyn std::fmt::Display); 3] = [("name", &name_of(runner)), ("leg", &(leg + 1)), ("legs", &relay.legs.len())];
+    lines.push(locale.format(if runner == me { Phrase::RelayYourLeg } else { Phrase::RelayTeammateLeg }, &args));
+    lines
+}
+
+fn card_url(name: &str, wpm: f64, accuracy: f64, place: Option<(usize, usize)>, source: Option<&str>) -> String {
+    let encode = |s: &str| String::from(js_sys::encode_uri_component(s));
+    let mut url = format!("/api/card?name={}&wpm={wpm:.1}&accuracy={accuracy:.1}", encode(name));
+    if let Some((rank, racers)) = place {
+        url.push_str(&format!("&rank={rank}&racers={racers}"));
+    }
+    if let Some(source) = source {
+        url.push_str(&format!("&source={}", encode(source)));
+    }
+    url
+}
+
+/// Send `msg` on the room socket, if connected.
+fn send(msg: &ClientMsg) {
+    WS_REF.with(|cell| {
+        if let Some(ws) = cell.borrow().as_ref() {
+            if let Ok(json) = serde_json::to_string(msg) { let _ = ws.send_with_str(&json); }
+        }
+    });
+}
+
+/// Move keyboard focus to the element with `id`, if it's on the page.
+fn focus_element(id: &str) {
+    let elem = web_sys::window().and_then(|w| w.document()).and_then(|doc| doc.get_element_by_id(id));
+    if let Some(html) = elem.and_then(|e| e.dyn_into::<HtmlElement>().ok()) {
+        let _ = html.focus();
+    }
+}
+
+/// Whether a key went to a form field, button or link, which keep Tab and Enter for themselves.
+fn in_control(ev: &web_sys::KeyboardEvent) -> bool {
+    let Some(elem) = ev.target().and_then(|t| t.dyn_into::<web_sys::Element>().ok()) else { return false };
+    matches!(elem.tag_name().as_str(), "INPUT" | "SELECT" | "TEXTAREA" | "BUTTON" | "A")
+}
+
+/// User-facing text for a server error code
+/// Ask the server to apply new room settings (it only accepts them from the host).
+fn send_settings(settings: RoomSettings) {
+    WS_REF.with(|cell| {
+        if let Some(ws) = cell.borrow().as_ref() {
+            if let Ok(json) = serde_json::to_string(&ClientMsg::UpdateSettings { settings }) { let _ = ws.send_with_str(&json); }
+        }
+    });
+}
+
+/// The colour class of a racer's car and their marker in the passage, from the lane colour the
+/// room gave them; pace cars keep their stripes.
+fn car_color(color: u8, is_pace: bool) -> &'static str {
+    if is_pace { return "car-pace"; }
+    const CLASSES: [&str; LANE_COLORS as usize] = ["car-color0", "car-color1", "car-color2", "car-color3", "car-color4", "car-color5", "car-color6", "car-color7"];
+    CLASSES[usize::from(color) % CLASSES.len()]
+}
+
+/// Passage text with the other racers' markers in it.
+fn passage_pieces(pieces: Vec<Piece<(&'static str, String)>>) -> impl IntoView {
+    pieces.into_iter().map(|piece| match piece {
+        Piece::Text(class, run) => view! { <span class=class>{run}</span> }.into_any(),
+        Piece::Mark((color, name)) => view! { <span class=format!("racer-mark {color}") title=name></span> }.into_any(),
+    }).collect_view()
+}
+
+/// `ms` since the Unix epoch as a date and time in `locale`.
+fn local_time(ms: i64, locale: Locale) -> String {
+    String::from(js_sys::Date::new(&JsValue::from_f64(ms as f64)).to_locale_string(locale.tag(), &JsValue::UNDEFINED))
+}
+
+/// Split the typed part of the passage into runs of right and wrong chars for highlighting.
+fn typed_runs(typed: &str, cursor: &Cursor) -> Vec<(bool, String)> {
+    let mut runs: Vec<(bool, String)> = Vec::new();
+    for (i, ch) in typed.chars().enumerate() {
+        let wrong = cursor.is_wrong(i);
+        match runs.last_mut() {
+            Some((w, run)) if *w == wrong => run.push(ch),
+            _ => runs.push((wrong, ch.to_string())),
+        }
+    }
+    runs
+}
+
+fn error_mode_value(mode: ErrorMode) -> &'static str {
+    match mode {
+        ErrorMode::Strict => "strict",
+        ErrorMode::Forgiving => "forgiving",
+        ErrorMode::StopOnWord => "stop_on_word",
+    }
+}
+
+fn finish_rule_value(rule: FinishRule) -> &'static str {
+    match rule {
+        FinishRule::WaitForAll => "wait_for_all",
+        FinishRule::TopThree => "top_three",
+        FinishRule::FirstWins => "first_wins",
+        FinishRule::Elimination => "elimination",
+    }
+}
+
+fn race_mode_value(mode: RaceMode) -> &'static str {
+    match mode {
+        RaceMode::Passages => "passages",
+        RaceMode::Drill(Drill { kind: DrillKind::Numbers, .. }) => "numbers",
+        RaceMode::Drill(Drill { kind: DrillKind::Symbols, .. }) => "symbols",
+        RaceMode::Drill(Drill { kind: DrillKind::Operators, .. }) => "operators",
+        RaceMode::Words(_) => "words",
+    }
+}
+
+/// Room named by a `/r/{room}` join link, which joins as soon as the page loads.
+fn room_from_link() -> Option<String> {
+    let path = web_sys::window()?.location().pathname().ok()?;
+    let room = String::from(js_sys::decode_uri_component(path.strip_prefix("/r/")?).ok()?);
+    let room = room.trim_end_matches('/').trim().to_string();
+    (!room.is_empty()).then_some(room)
+}
+
+/// The link that joins `room`, on this site.
+fn join_link(room: &str) -> String {
+    let origin = web_sys::window().and_then(|w| w.location().origin().ok()).unwrap_or_default();
+    format!("{origin}/r/{}", String::from(js_sys::encode_uri_component(room)))
+}
+
+/// Room named by a `?room=` invite link, which only fills in the room.
+fn room_from_url() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let room = web_sys::UrlSearchParams::new_with_str(&search).ok()?.get("room")?;
+    let room = room.trim().to_string();
+    (!room.is_empty()).then_some(room)
+}
+
+fn friendly_error(code: ErrorCode) -> Phrase {
+    match code {
+        ErrorCode::RoomFull => Phrase::RoomFull,
+        ErrorCode::NameTaken => Phrase::NameTaken,
+        ErrorCode::InvalidState => Phrase::InvalidState,
+        ErrorCode::RateLimited => Phrase::RateLimited,
+        ErrorCode::SuspiciousActivity => Phrase::SuspiciousActivity,
+        ErrorCode::InvalidMessage => Phrase::InvalidMessage,
+        ErrorCode::NotHost => Phrase::NotHost,
+        ErrorCode::NameTooLong => Phrase::NameTooLong,
+        ErrorCode::NameReserved => Phrase::NameReserved,
+        ErrorCode::NameNotAllowed => Phrase::NameNotAllowed,
+        ErrorCode::Removed => Phrase::Removed,
+        ErrorCode::Banned => Phrase::Banned,
+    }
+}
+
+/// Ask the server how this WPM ranks against the current season's results.
+async fn fetch_percentile(wpm: f64) -> Option<i32> {
+    let body: PercentileResponse = fetch_json(&format!("/api/percentile?wpm={wpm}")).await?;
+    Some(body.percentile)
+}
+
+const GUEST_TOKEN_KEY: &str = "rracer_guest_token";
+
+/// Guest token from an earlier visit, so this browser keeps the same guest identity.
+fn stored_guest_token() -> Option<String> {
+    web_sys::window()?.local_storage().ok()??.get_item(GUEST_TOKEN_KEY).ok()?
+}
+
+fn store_guest_token(token: &str) {
+    if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = storage.set_item(GUEST_TOKEN_KEY, token);
+    }
+}
+
+/// Streaks for this browser's account or guest token, with days split at local midnight.
+async fn fetch_profile() -> Option<Profile> {
+    let options = js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new()).resolved_options();
+    let tz = js_sys::Reflect::get(&options, &"timeZone".into()).ok()?.as_string().unwrap_or_else(|| "UTC".to_string());
+    let mut url = format!("/api/me/profile?tz={}", String::from(js_sys::encode_uri_component(&tz)));
+    if let Some(token) = stored_guest_token() {
+        url.push_str(&format!("&guest={}", String::from(js_sys::encode_uri_component(&token))));
+    }
+    fetch_json(&url).await
+}
+
+/// The signed-in racer's latest stored results, for the Recent races panel.
+async fn fetch_races() -> Vec<RaceResult> {
+    let url = with_query(&me_url("results"), &format!("limit={MAX_RECENT}"));
+    fetch_json::<Page<RaceResult>>(&url).await.map(|page| page.items).unwrap_or_default()
+}
+
+/// `/api/me/{path}` for this browser's account or guest token.
+fn me_url(path: &str) -> String {
+    match stored_guest_token() {
+        Some(token) => format!("/api/me/{path}?guest={}", String::from(js_sys::encode_uri_component(&token))),
+        None => format!("/api/me/{path}"),
+    }
+}
+
+/// `url` with `query` added to whatever query it has.
+fn with_query(url: &str, query: &str) -> String {
+    format!("{url}{}{query}", if url.contains('?') { '&' } else { '?' })
+}
+
+/// A lesson's title in `locale`; lessons the client doesn't know keep the server's title.
+fn lesson_title(locale: Locale, lesson: &Lesson) -> String {
+    let phrase = match lesson.id.as_str() {
+        "home-row" => Phrase::LessonHomeRow,
+        "top-row" => Phrase::LessonTopRow,
+        "bottom-row" => Phrase::LessonBottomRow,
+        "capitals" => Phrase::LessonCapitals,
+        "punctuation" => Phrase::LessonPunctuation,
+        _ => return lesson.title.clone(),
+    };
+    locale.text(phrase).to_string()
+}
+
+/// "Working on: th, qu, their" for a drill's focus, or a note that it is a warm-up.
+fn drill_focus_text(locale: Locale, focus: &Weaknesses) -> String {
+    let items: Vec<&str> = focus.bigrams.iter().chain(&focus.words).map(String::as_str).collect();
+    if items.is_empty() {
+        return locale.text(Phrase::DrillWarmUp).to_string();
+    }
+    locale.format(Phrase::DrillFocus, &[("focus", &items.join(", "))])
+}
+
+/// GET a JSON endpoint; None on network errors and non-2xx responses.
+async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Option<T> {
+    let win = web_sys::window()?;
+    let resp = wasm_bindgen_futures::JsFuture::from(win.fetch_with_str(url)).await.ok()?;
+    let resp: web_sys::Response = resp.dyn_into().ok()?;
+    if !resp.ok() { return None; }
+    let text = wasm_bindgen_futures::JsFuture::from(resp.text().ok()?).await.ok()?.as_string()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// POST `body` as JSON and read a JSON reply; None on network errors and non-2xx responses.
+async fn post_json<B: serde::Serialize, T: serde::de::DeserializeOwned>(url: &str, body: &B) -> Option<T> {
+    request_json("POST", url, Some(&serde_json::to_string(body).ok()?)).await
+}
+
+/// Send `method` to `url`, with `body` as JSON if given, and read a JSON reply; None on network
+/// errors and non-2xx responses.
+async fn request_json<T: serde::de::DeserializeOwned>(method: &str, url: &str, body: Option<&str>) -> Option<T> {
+    let win = web_sys::window()?;
+    let init = web_sys::RequestInit::new();
+    init.set_method(method);
+    if let Some(body) = body {
+        let headers = web_sys::Headers::new().ok()?;
+        headers.set("Content-Type", "application/json").ok()?;
+        init.set_headers(&headers);
+        init.set_body(&JsValue::from_str(body));
+    }
+    let resp = wasm_bindgen_futures::JsFuture::from(win.fetch_with_str_and_init(url, &init)).await.ok()?;
+    let resp: web_sys::Response = resp.dyn_into().ok()?;
+    if !resp.ok() { return None; }
+    let text = wasm_bindgen_futures::JsFuture::from(resp.text().ok()?).await.ok()?.as_string()?;
+    serde_json::from_str(&text).ok()
+}
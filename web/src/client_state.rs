@@ -0,0 +1,169 @@
+// The client's view of the race, mirroring the server's room phases. Server messages move it
+// along explicit transitions, and messages that make no sense in the current state (progress
+// in the lobby, say) are dropped instead of half-applied.
+
+use shared::{
+    protocol::{GamePhase, ServerMsg},
+    text::passage_hash,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClientState {
+    /// In the lobby, or not in a room at all
+    #[default]
+    Waiting,
+    /// The passage is known and the race is about to start
+    Countdown,
+    Racing,
+    /// The host has stopped the race for now; the track stays up but nothing is typed
+    Paused,
+    /// Our own race is over: we finished, ran out of time, were eliminated, or the whole race
+    /// has ended
+    Finished,
+}
+
+impl ClientState {
+    /// Countdown and racing (paused or not) show the track.
+    pub fn on_track(self) -> bool {
+        matches!(self, ClientState::Countdown | ClientState::Racing | ClientState::Paused)
+    }
+
+    /// Whether `msg` belongs in this state. Race traffic only counts while a race is on or
+    /// its results are coming in; everything else is always welcome. The server announces a
+    /// phase before the message that goes with it, so `Start` arrives after `racing`.
+    pub fn accepts(self, msg: &ServerMsg) -> bool {
+        use ClientState::*;
+        match msg {
+            ServerMsg::Progress { .. } | ServerMsg::Split { .. } | ServerMsg::Finish { .. } | ServerMsg::Ranks { .. } | ServerMsg::Overtake { .. } | ServerMsg::NewRecord { .. } | ServerMsg::Handoff { .. } | ServerMsg::TeamFinish { .. } | ServerMsg::EliminationIn { .. } | ServerMsg::Eliminated { .. } => {
+                matches!(self, Racing | Paused | Finished)
+            }
+            ServerMsg::RaceSummary { .. } => self == Finished,
+            ServerMsg::Start { .. } => self != Finished,
+            ServerMsg::Countdown { .. } => self != Racing,
+            ServerMsg::WaitingTimer { .. } => self == Waiting,
+            _ => true,
+        }
+    }
+
+    /// The state after `msg`, which `accepts` must allow; `my_id` is our player id.
+    pub fn next(self, msg: &ServerMsg, my_id: &str) -> Self {
+        match msg {
+            ServerMsg::Countdown { .. } => ClientState::Countdown,
+            ServerMsg::Start { .. } => ClientState::Racing,
+            ServerMsg::Finish { id, .. } | ServerMsg::Eliminated { id } if id == my_id => ClientState::Finished,
+            // Show the race screen the moment the lobby timer runs out
+            ServerMsg::WaitingTimer { seconds_left: 0 } => ClientState::Countdown,
+            // The server's word goes, except that our own finish stands until the race ends
+            ServerMsg::StateChange { state } | ServerMsg::Sync { state, .. } => match state {
+                GamePhase::Racing | GamePhase::Paused if self == ClientState::Finished => self,
+                phase => (*phase).into(),
+            },
+            _ => self,
+        }
+    }
+}
+
+/// Whether our copy of the passage is the one the room hashed (`text::passage_hash`); true
+/// when there is no hash to go by.
+pub fn passage_agrees(expected: Option<u32>, passage: &str) -> bool {
+    expected.is_none_or(|hash| passage_hash(passage) == hash)
+}
+
+impl From<GamePhase> for ClientState {
+    fn from(phase: GamePhase) -> Self {
+        match phase {
+            GamePhase::Waiting => ClientState::Waiting,
+            GamePhase::Countdown => ClientState::Countdown,
+            GamePhase::Racing => ClientState::Racing,
+            GamePhase::Paused => ClientState::Paused,
+            GamePhase::Finished => ClientState::Finished,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ClientState::*;
+
+    fn progress() -> ServerMsg {
+        ServerMsg::Progress { id: "bob".into(), pos: 3 }
+    }
+
+    fn finish(id: &str) -> ServerMsg {
+        ServerMsg::Finish { id: id.into(), wpm: 60.0, accuracy: 100.0, normalized_wpm: 60.0, errors: 0, raw_wpm: 60.0, time: 30.0, consistency: 100.0, verified: false, counted: true }
+    }
+
+    fn state_change(state: GamePhase) -> ServerMsg {
+        ServerMsg::StateChange { state }
+    }
+
+    /// Feed `msgs` through the machine, skipping whatever it doesn't accept.
+    fn run(from: ClientState, msgs: &[ServerMsg]) -> ClientState {
+        msgs.iter().fold(from, |s, m| if s.accepts(m) { s.next(m, "me") } else { s })
+    }
+
+    #[test]
+    fn a_race_runs_waiting_to_finished() {
+        let msgs = [
+            state_change(GamePhase::Countdown),
+            ServerMsg::Countdown { passage: "ab".into(), attribution: None, record: None, hash: 0 },
+            state_change(GamePhase::Racing),
+            ServerMsg::Start { passage: "ab".into(), t0: 1, attribution: None, hash: 0 },
+            progress(),
+            finish("me"),
+            finish("bob"),
+            state_change(GamePhase::Finished),
+            state_change(GamePhase::Waiting),
+        ];
+        let states: Vec<ClientState> = (1..=msgs.len()).map(|n| run(Waiting, &msgs[..n])).collect();
+        assert_eq!(states, vec![Countdown, Countdown, Racing, Racing, Racing, Finished, Finished, Finished, Waiting]);
+        assert!(msgs.iter().all(|m| states.iter().any(|s| s.accepts(m))));
+    }
+
+    #[test]
+    fn race_traffic_is_ignored_in_the_lobby() {
+        assert!(!Waiting.accepts(&progress()));
+        assert!(!Waiting.accepts(&finish("me")));
+        assert!(!Waiting.accepts(&ServerMsg::Overtake { overtaker: "bob".into(), overtaken: "me".into() }));
+        assert!(!Finished.accepts(&ServerMsg::Start { passage: "ab".into(), t0: 1, attribution: None, hash: 0 }));
+        assert!(!Racing.accepts(&ServerMsg::WaitingTimer { seconds_left: 0 }));
+        assert_eq!(run(Waiting, &[progress(), finish("me")]), Waiting);
+    }
+
+    #[test]
+    fn our_finish_stands_while_others_race() {
+        assert_eq!(Finished.next(&state_change(GamePhase::Racing), "me"), Finished);
+        assert_eq!(Racing.next(&finish("bob"), "me"), Racing);
+        assert_eq!(Racing.next(&ServerMsg::Eliminated { id: "bob".into() }, "me"), Racing);
+        assert_eq!(Racing.next(&ServerMsg::Eliminated { id: "me".into() }, "me"), Finished);
+        // Such as when a lagging socket is resynced from a snapshot
+        let sync = |state| ServerMsg::Sync { state, passage: None, players: vec![], positions: Default::default(), t0: None, settings: Default::default(), attribution: None, record: None, relay: None, hash: None };
+        assert_eq!(Finished.next(&sync(GamePhase::Racing), "me"), Finished);
+        assert_eq!(Waiting.next(&sync(GamePhase::Racing), "me"), Racing);
+    }
+
+    #[test]
+    fn a_paused_race_picks_up_where_it_stopped() {
+        let msgs = [state_change(GamePhase::Paused), ServerMsg::Paused { by: "host".into() }, progress(), state_change(GamePhase::Racing), ServerMsg::Resumed { t0: 2 }];
+        let states: Vec<ClientState> = (1..=msgs.len()).map(|n| run(Racing, &msgs[..n])).collect();
+        assert_eq!(states, vec![Paused, Paused, Paused, Racing, Racing]);
+        assert!(Paused.on_track());
+        assert_eq!(Finished.next(&state_change(GamePhase::Paused), "me"), Finished);
+    }
+
+    #[test]
+    fn the_lobby_timer_jumps_to_the_countdown() {
+        assert_eq!(run(Waiting, &[ServerMsg::WaitingTimer { seconds_left: 3 }]), Waiting);
+        assert_eq!(run(Waiting, &[ServerMsg::WaitingTimer { seconds_left: 0 }]), Countdown);
+    }
+
+    #[test]
+    fn a_drifted_passage_is_caught_by_its_hash() {
+        let hash = passage_hash("the quick fox");
+        assert!(passage_agrees(Some(hash), "the quick fox"));
+        assert!(!passage_agrees(Some(hash), "the quick box"));
+        assert!(!passage_agrees(Some(hash), ""));
+        assert!(passage_agrees(None, "anything"));
+    }
+}
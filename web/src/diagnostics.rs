@@ -0,0 +1,165 @@
+// The diagnostics overlay: how long a key takes to reach the screen, and the round trip to
+// the server. Keys are timed from the keydown event to the next animation frame, which is
+// when the typed char gets drawn; the round trip comes from the server's own pings
+// (ServerMsg::Latency), which the browser answers without the page ever seeing them.
+//
+// The round trip and how often the race had to be resynced from a snapshot (the server
+// skips us past broadcasts we fell behind on) grade the connection for the header, and set
+// how often our progress goes out: often on a good line, sparingly on a struggling one.
+
+use std::collections::VecDeque;
+
+/// Key timings kept for the averages.
+pub const SAMPLES: usize = 50;
+
+/// A key taking longer than this to draw (about three frames) is lag on this device, in ms.
+pub const SLOW_FRAME_MS: f64 = 50.0;
+
+/// A round trip longer than this is lag on the connection, in ms.
+pub const SLOW_RTT_MS: u64 = 200;
+
+/// Round trips up to this long make a good connection, in ms.
+pub const GOOD_RTT_MS: u64 = 80;
+
+/// Resyncs this recent count against the connection, in ms.
+pub const RESYNC_WINDOW_MS: f64 = 60_000.0;
+
+/// The latest key-to-screen times, in ms
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputLatency {
+    samples: VecDeque<f64>,
+}
+
+impl InputLatency {
+    /// Count one key, dropping the oldest beyond `SAMPLES`.
+    pub fn push(&mut self, ms: f64) {
+        if !ms.is_finite() || ms < 0.0 { return; }
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ms);
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        (!self.samples.is_empty()).then(|| self.samples.iter().sum::<f64>() / self.samples.len() as f64)
+    }
+
+    pub fn worst(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::max)
+    }
+}
+
+/// Where a laggy race is coming from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lag {
+    /// The page is slow to draw keys
+    Device,
+    /// The server is slow to answer
+    Network,
+}
+
+/// What to blame, if anything, for keys averaging `input_ms` to draw over a `rtt_ms` round
+/// trip. A slow device is named first: it delays our pongs too.
+pub fn lag(input_ms: Option<f64>, rtt_ms: Option<u64>) -> Option<Lag> {
+    if input_ms.is_some_and(|ms| ms > SLOW_FRAME_MS) {
+        Some(Lag::Device)
+    } else if rtt_ms.is_some_and(|ms| ms > SLOW_RTT_MS) {
+        Some(Lag::Network)
+    } else {
+        None
+    }
+}
+
+/// Times (ms) the race was resynced from a snapshot after missing broadcasts
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Resyncs {
+    at: VecDeque<f64>,
+}
+
+impl Resyncs {
+    pub fn record(&mut self, now: f64) {
+        self.at.retain(|&t| now - t < RESYNC_WINDOW_MS);
+        self.at.push_back(now);
+    }
+
+    /// Resyncs within `RESYNC_WINDOW_MS` of `now`.
+    pub fn recent(&self, now: f64) -> usize {
+        self.at.iter().filter(|&&t| now - t < RESYNC_WINDOW_MS).count()
+    }
+}
+
+/// How well the connection to the server is holding up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quality {
+    Good,
+    Fair,
+    Poor,
+}
+
+impl Quality {
+    /// A round trip of `rtt_ms` with `resyncs` recent resyncs. Until the first round trip is
+    /// in, a connection that hasn't missed anything is taken as fair.
+    pub fn of(rtt_ms: Option<u64>, resyncs: usize) -> Self {
+        match rtt_ms {
+            _ if resyncs > 1 => Quality::Poor,
+            Some(ms) if ms > SLOW_RTT_MS => Quality::Poor,
+            Some(ms) if ms <= GOOD_RTT_MS && resyncs == 0 => Quality::Good,
+            _ => Quality::Fair,
+        }
+    }
+
+    /// Least time between `Progress` messages, in ms.
+    pub fn progress_interval_ms(self) -> f64 {
+        match self {
+            Quality::Good => 50.0,
+            Quality::Fair => 100.0,
+            Quality::Poor => 250.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn only_the_latest_keys_count() {
+        let mut latency = InputLatency::default();
+        assert_eq!(latency.average(), None);
+        latency.push(100.0);
+        for _ in 0..SAMPLES {
+            latency.push(10.0);
+        }
+        latency.push(f64::NAN);
+        assert_eq!(latency.average(), Some(10.0));
+        assert_eq!(latency.worst(), Some(10.0));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn resyncs_age_out() {
+        let mut resyncs = Resyncs::default();
+        resyncs.record(1_000.0);
+        resyncs.record(2_000.0);
+        assert_eq!(resyncs.recent(2_000.0), 2);
+        assert_eq!(resyncs.recent(1_000.0 + RESYNC_WINDOW_MS), 1);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn progress_goes_out_less_often_on_a_poor_connection() {
+        assert_eq!(Quality::of(None, 0), Quality::Fair);
+        assert_eq!(Quality::of(Some(30), 0), Quality::Good);
+        assert_eq!(Quality::of(Some(30), 1), Quality::Fair);
+        assert_eq!(Quality::of(Some(30), 2), Quality::Poor);
+        assert_eq!(Quality::of(Some(SLOW_RTT_MS + 1), 0), Quality::Poor);
+        assert!(Quality::Good.progress_interval_ms() < Quality::Fair.progress_interval_ms());
+        assert!(Quality::Fair.progress_interval_ms() < Quality::Poor.progress_interval_ms());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn a_slow_device_is_blamed_before_the_network() {
+        assert_eq!(lag(Some(16.0), Some(40)), None);
+        assert_eq!(lag(None, Some(SLOW_RTT_MS + 1)), Some(Lag::Network));
+        assert_eq!(lag(Some(SLOW_FRAME_MS + 1.0), Some(SLOW_RTT_MS + 1)), Some(Lag::Device));
+    }
+}
@@ -0,0 +1,117 @@
+// This browser's recent results, kept in localStorage so a guest has a history too: the last
+// `MAX_RECENT` races, newest first. Signed in, they're shown merged with the account's
+// history from the server, each race listed once.
+
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+use shared::api::RaceResult;
+
+/// Races kept on this browser, and shown in the Recent races panel.
+pub const MAX_RECENT: usize = 50;
+
+/// localStorage key for the recent races.
+const HISTORY_KEY: &str = "rracer_history";
+
+/// How far apart this browser's clock and the server's may put the same finish, in ms.
+const SAME_RACE_MS: i64 = 60_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecentRace {
+    /// The passage typed; None for races only the server knows of, which don't keep it
+    pub passage: Option<String>,
+    pub wpm: f64,
+    pub accuracy: f64,
+    /// Milliseconds since the Unix epoch
+    pub finished_at: i64,
+    /// The room raced in; None for practice on this browser
+    pub room: Option<String>,
+}
+
+impl From<RaceResult> for RecentRace {
+    fn from(r: RaceResult) -> Self {
+        RecentRace { passage: None, wpm: r.wpm, accuracy: r.accuracy, finished_at: r.finished_at, room: Some(r.room) }
+    }
+}
+
+impl RecentRace {
+    /// Whether `other` is this race as the other side recorded it.
+    fn same_race(&self, other: &RecentRace) -> bool {
+        (self.finished_at - other.finished_at).abs() <= SAME_RACE_MS && (self.wpm - other.wpm).abs() < 0.05 && self.room == other.room
+    }
+}
+
+/// Put `race` at the front of `recent`, dropping the oldest beyond `MAX_RECENT`.
+pub fn push(recent: &mut Vec<RecentRace>, race: RecentRace) {
+    recent.insert(0, race);
+    recent.truncate(MAX_RECENT);
+}
+
+/// This browser's races with the server's, newest first and at most `MAX_RECENT`. A race
+/// both have is listed once, as this browser's copy, which keeps the passage.
+pub fn merge(local: &[RecentRace], server: Vec<RaceResult>) -> Vec<RecentRace> {
+    let mut all = local.to_vec();
+    for race in server.into_iter().map(RecentRace::from) {
+        if !local.iter().any(|l| l.same_race(&race)) {
+            all.push(race);
+        }
+    }
+    all.sort_by_key(|r| Reverse(r.finished_at));
+    all.truncate(MAX_RECENT);
+    all
+}
+
+/// The races kept from earlier visits, newest first.
+pub fn stored() -> Vec<RecentRace> {
+    let json = web_sys::window().and_then(|w| w.local_storage().ok().flatten()).and_then(|s| s.get_item(HISTORY_KEY).ok().flatten());
+    json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+/// Keep `race` for later visits; the updated list, newest first.
+pub fn record(race: RecentRace) -> Vec<RecentRace> {
+    let mut recent = stored();
+    push(&mut recent, race);
+    if let (Some(storage), Ok(json)) = (web_sys::window().and_then(|w| w.local_storage().ok().flatten()), serde_json::to_string(&recent)) {
+        let _ = storage.set_item(HISTORY_KEY, &json);
+    }
+    recent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn local(wpm: f64, finished_at: i64) -> RecentRace {
+        RecentRace { passage: Some("the quick brown fox".to_string()), wpm, accuracy: 98.0, finished_at, room: Some("main".to_string()) }
+    }
+
+    fn server(id: i64, wpm: f64, finished_at: i64) -> RaceResult {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "room": "main", "player_name": "ana", "wpm": wpm, "accuracy": 98.0,
+            "time_secs": 30.0, "finished_at": finished_at,
+        }))
+        .unwrap()
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn only_the_latest_races_are_kept() {
+        let mut recent = Vec::new();
+        for i in 0..MAX_RECENT as i64 + 5 {
+            push(&mut recent, local(60.0, i));
+        }
+        assert_eq!(recent.len(), MAX_RECENT);
+        assert_eq!(recent[0].finished_at, MAX_RECENT as i64 + 4);
+        assert_eq!(recent.last().unwrap().finished_at, 5);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn a_race_both_sides_have_is_listed_once() {
+        let mine = [local(72.0, 100_000), local(65.0, 50_000)];
+        // The server's clock puts the first a few seconds later; its other race was on another device
+        let merged = merge(&mine, vec![server(1, 72.0, 104_000), server(2, 80.0, 75_000)]);
+        assert_eq!(merged.iter().map(|r| r.finished_at).collect::<Vec<_>>(), [100_000, 75_000, 50_000]);
+        assert!(merged[0].passage.is_some());
+        assert_eq!(merged[1].passage, None);
+    }
+}
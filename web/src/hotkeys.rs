@@ -0,0 +1,175 @@
+// Keyboard shortcuts and the command palette. The typing area only takes keys that type a
+// char (and Backspace), so Tab, Enter, Escape and Ctrl/Cmd combos are free for these and
+// never reach the race. Which key does what is decided here, away from the view.
+
+use shared::typing::ErrorMode;
+
+use crate::client_state::ClientState;
+use crate::i18n::{Locale, Phrase};
+use crate::track::TrackScale;
+
+/// A key press as read off a `KeyboardEvent`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Chord<'a> {
+    pub key: &'a str,
+    pub ctrl: bool,
+    pub meta: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl Chord<'_> {
+    fn plain(&self) -> bool {
+        !(self.ctrl || self.meta || self.alt || self.shift)
+    }
+}
+
+/// What the page was doing when a key was pressed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Focus {
+    pub state: ClientState,
+    /// Racing in or watching a room
+    pub joined: bool,
+    pub palette_open: bool,
+    /// Focus is on a form field, button or link, which keep their own Tab and Enter
+    pub in_control: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    /// Tab or Enter on the results: race again
+    Restart,
+    /// Escape: leave the room
+    Leave,
+    /// Ctrl+K (Cmd+K on macOS)
+    OpenPalette,
+    ClosePalette,
+}
+
+/// The shortcut `chord` triggers, if any. Everything else is left to the browser.
+pub fn hotkey(chord: &Chord, at: &Focus) -> Option<Hotkey> {
+    if (chord.ctrl || chord.meta) && !chord.alt && chord.key.eq_ignore_ascii_case("k") {
+        return Some(if at.palette_open { Hotkey::ClosePalette } else { Hotkey::OpenPalette });
+    }
+    if at.palette_open {
+        // The palette's own input handles the rest
+        return (chord.plain() && chord.key == "Escape").then_some(Hotkey::ClosePalette);
+    }
+    match chord.key {
+        "Escape" if chord.plain() && at.joined => Some(Hotkey::Leave),
+        "Tab" | "Enter" if chord.plain() && at.state == ClientState::Finished && !at.in_control => Some(Hotkey::Restart),
+        _ => None,
+    }
+}
+
+/// Something the command palette can do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Join,
+    Watch,
+    RaceAgain,
+    Rematch,
+    Leave,
+    Track(TrackScale),
+    Mistakes(ErrorMode),
+    Casual,
+    /// Show or hide the latency overlay
+    Diagnostics,
+    Language(Locale),
+}
+
+impl Command {
+    /// Every command, in palette order.
+    pub fn all() -> Vec<Command> {
+        let mut all = vec![Command::Join, Command::Watch, Command::RaceAgain, Command::Rematch, Command::Leave];
+        all.extend([TrackScale::Whole, TrackScale::Follow].map(Command::Track));
+        all.extend([ErrorMode::Strict, ErrorMode::Forgiving, ErrorMode::StopOnWord].map(Command::Mistakes));
+        all.extend([Command::Casual, Command::Diagnostics]);
+        all.extend(Locale::ALL.map(Command::Language));
+        all
+    }
+
+    pub fn label(self, locale: Locale) -> String {
+        let setting = |name: Phrase, value: &str| format!("{}: {value}", locale.text(name).trim());
+        match self {
+            Command::Join => locale.text(Phrase::JoinRoom).to_string(),
+            Command::Watch => locale.text(Phrase::Watch).to_string(),
+            Command::RaceAgain => locale.text(Phrase::RaceAgain).to_string(),
+            Command::Rematch => locale.text(Phrase::Rematch).to_string(),
+            Command::Leave => locale.text(Phrase::LeaveRoom).to_string(),
+            Command::Track(TrackScale::Whole) => setting(Phrase::Track, locale.text(Phrase::WholePassage)),
+            Command::Track(TrackScale::Follow) => setting(Phrase::Track, locale.text(Phrase::Follow)),
+            Command::Mistakes(ErrorMode::Strict) => setting(Phrase::Mistakes, locale.text(Phrase::MustFix)),
+            Command::Mistakes(ErrorMode::Forgiving) => setting(Phrase::Mistakes, locale.text(Phrase::KeepGoing)),
+            Command::Mistakes(ErrorMode::StopOnWord) => setting(Phrase::Mistakes, locale.text(Phrase::FixBeforeNextWord)),
+            Command::Casual => locale.text(Phrase::Casual).to_string(),
+            Command::Diagnostics => locale.text(Phrase::Diagnostics).to_string(),
+            Command::Language(l) => setting(Phrase::Language, l.native_name()),
+        }
+    }
+}
+
+/// Whether `label` matches a palette query: the query's chars appear in it in order,
+/// ignoring case and spaces, so "jr" finds "Join Room".
+pub fn matches(label: &str, query: &str) -> bool {
+    let mut label = label.chars().flat_map(char::to_lowercase);
+    query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).all(|q| label.any(|c| c == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> Chord<'_> {
+        Chord { key, ..Chord::default() }
+    }
+
+    #[test]
+    fn ctrl_or_cmd_k_toggles_the_palette() {
+        let closed = Focus::default();
+        let open = Focus { palette_open: true, ..closed };
+        assert_eq!(hotkey(&Chord { ctrl: true, ..key("k") }, &closed), Some(Hotkey::OpenPalette));
+        assert_eq!(hotkey(&Chord { meta: true, ..key("K") }, &closed), Some(Hotkey::OpenPalette));
+        assert_eq!(hotkey(&Chord { ctrl: true, ..key("k") }, &open), Some(Hotkey::ClosePalette));
+        assert_eq!(hotkey(&key("Escape"), &open), Some(Hotkey::ClosePalette));
+        assert_eq!(hotkey(&key("k"), &closed), None);
+        assert_eq!(hotkey(&Chord { ctrl: true, alt: true, ..key("k") }, &closed), None);
+    }
+
+    #[test]
+    fn tab_and_enter_restart_only_on_the_results() {
+        let finished = Focus { state: ClientState::Finished, joined: true, ..Focus::default() };
+        assert_eq!(hotkey(&key("Tab"), &finished), Some(Hotkey::Restart));
+        assert_eq!(hotkey(&key("Enter"), &finished), Some(Hotkey::Restart));
+        // Shift+Tab still moves focus back, and buttons keep Enter
+        assert_eq!(hotkey(&Chord { shift: true, ..key("Tab") }, &finished), None);
+        assert_eq!(hotkey(&key("Enter"), &Focus { in_control: true, ..finished }), None);
+        assert_eq!(hotkey(&key("Tab"), &Focus { state: ClientState::Racing, ..finished }), None);
+        assert_eq!(hotkey(&key("Enter"), &Focus { palette_open: true, ..finished }), None);
+    }
+
+    #[test]
+    fn escape_leaves_a_joined_room() {
+        let racing = Focus { state: ClientState::Racing, joined: true, ..Focus::default() };
+        assert_eq!(hotkey(&key("Escape"), &racing), Some(Hotkey::Leave));
+        assert_eq!(hotkey(&key("Escape"), &Focus { joined: false, ..racing }), None);
+    }
+
+    #[test]
+    fn queries_match_in_order() {
+        assert!(matches("Join Room", "jr"));
+        assert!(matches("Join Room", "join room"));
+        assert!(matches("Mistakes: Keep going", ""));
+        assert!(!matches("Join Room", "rj"));
+        assert!(!matches("Watch", "watching"));
+    }
+
+    #[test]
+    fn every_command_has_a_label() {
+        for locale in Locale::ALL {
+            for command in Command::all() {
+                assert!(!command.label(locale).trim().is_empty(), "{locale:?} {command:?}");
+            }
+        }
+    }
+}
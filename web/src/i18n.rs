@@ -0,0 +1,1160 @@
+// UI translations: a catalog per locale, the locale the page starts in, and the picker's
+// saved choice. Only the interface is translated; passages are typed in their own language
+// whatever the UI shows. The test controls of debug builds stay in English.
+//
+// To add a language, add a `Locale` variant and a catalog function; the compiler lists every
+// phrase it still needs, and the tests check each one keeps the English placeholders.
+
+use std::fmt::Display;
+
+/// localStorage key for the language picked in the UI.
+const LOCALE_KEY: &str = "rracer_locale";
+
+/// Locale a deployment starts everyone in unless they picked another, set when building the
+/// client (`RRACER_UI_LOCALE=es trunk build`). Without it the browser's languages decide.
+const BUILD_LOCALE: Option<&str> = option_env!("RRACER_UI_LOCALE");
+
+/// A language the interface is translated into
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+    De,
+}
+
+impl Locale {
+    /// Every locale, in picker order.
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::Es, Locale::De];
+
+    /// BCP 47 language tag, for `<html lang>` and the saved choice.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
+            Self::De => "de",
+        }
+    }
+
+    /// The language's name in itself, as the picker shows it.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::Es => "Español",
+            Self::De => "Deutsch",
+        }
+    }
+
+    /// The locale for a language tag such as `es-MX` or `de_AT`, going by its primary
+    /// language; None for languages without a catalog.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        Self::ALL.into_iter().find(|l| l.tag().eq_ignore_ascii_case(primary))
+    }
+
+    pub fn text(self, phrase: Phrase) -> &'static str {
+        match self {
+            Self::En => en(phrase),
+            Self::Es => es(phrase),
+            Self::De => de(phrase),
+        }
+    }
+
+    /// `phrase` with its `{name}` placeholders filled from `args`.
+    pub fn format(self, phrase: Phrase, args: &[(&str, &dyn Display)]) -> String {
+        fill(self.text(phrase), args)
+    }
+}
+
+/// The first of the preferred language tags (most preferred first) that has a catalog.
+pub fn negotiate<'a>(preferred: impl IntoIterator<Item = &'a str>) -> Option<Locale> {
+    preferred.into_iter().find_map(Locale::from_tag)
+}
+
+/// Replace each `{name}` in `template` with its value in `args`; unknown names are left as is.
+pub fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = rest.find('}').and_then(|close| Some((close, args.iter().find(|(name, _)| *name == &rest[1..close])?.1)));
+        match value {
+            Some((close, value)) => {
+                out.push_str(&value.to_string());
+                rest = &rest[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The locale to start in: the one picked here before, else the deployment's, else the
+/// browser's first supported language, else English.
+pub fn detect() -> Locale {
+    stored()
+        .or_else(|| BUILD_LOCALE.and_then(Locale::from_tag))
+        .or_else(|| negotiate(browser_languages().iter().map(String::as_str)))
+        .unwrap_or_default()
+}
+
+/// Remember the locale picked in the UI for later visits.
+pub fn store(locale: Locale) {
+    if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = storage.set_item(LOCALE_KEY, locale.tag());
+    }
+}
+
+/// Mark the document as written in `locale`, for screen readers and hyphenation.
+pub fn set_document_lang(locale: Locale) {
+    if let Some(root) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.document_element()) {
+        let _ = root.set_attribute("lang", locale.tag());
+    }
+}
+
+fn stored() -> Option<Locale> {
+    let tag = web_sys::window()?.local_storage().ok()??.get_item(LOCALE_KEY).ok()??;
+    Locale::from_tag(&tag)
+}
+
+fn browser_languages() -> Vec<String> {
+    let Some(navigator) = web_sys::window().map(|w| w.navigator()) else { return Vec::new() };
+    let mut tags: Vec<String> = navigator.languages().iter().filter_map(|t| t.as_string()).collect();
+    tags.extend(navigator.language());
+    tags
+}
+
+/// Every piece of interface text. `{name}` marks a value filled in by `Locale::format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phrase {
+    Tagline,
+    Language,
+    LongestStreak,
+    Streak,
+    SignedInAs,
+    SignOut,
+    SignInWith,
+    RoomName,
+    YourName,
+    PaceWpm,
+    PaceWpmHint,
+    PacerWpm,
+    PacerWpmHint,
+    PacerSound,
+    PacerAhead,
+    PacerBehind,
+    Joined,
+    JoinRoom,
+    ConnectAndJoin,
+    WatchHint,
+    NewRoom,
+    NewRoomHint,
+    PracticeWeakSpots,
+    PracticeWeakSpotsHint,
+    Lessons,
+    LessonsHint,
+    InviteLink,
+    CopyLink,
+    LinkCopied,
+    Watch,
+    Watching,
+    WatchAnotherRoom,
+    AlsoWatching,
+    StopWatching,
+    Status,
+    Connected,
+    Disconnected,
+    StartingIn,
+    RaceInProgress,
+    Paused,
+    PausedHidden,
+    PausedIdle,
+    PausedByHost,
+    PauseRace,
+    ResumeRace,
+    Spectators,
+    Wpm,
+    Accuracy,
+    Time,
+    Track,
+    WholePassage,
+    Follow,
+    MarksInText,
+    Typing,
+    TypingNow,
+    TypeThisPassage,
+    TypingTip,
+    RelayYourLeg,
+    RelayTeammateLeg,
+    RelayTeam,
+    Progress,
+    Characters,
+    Errors,
+    Rank,
+    PassedYou,
+    YouPassed,
+    WaitingForRace,
+    WaitingForPlayers,
+    StartsSolo,
+    StartsWithBots,
+    StartsWhen,
+    CasualRace,
+    RelayRace,
+    GolfRace,
+    RacingFirstWords,
+    RoomBest,
+    PassageRecord,
+    NewPassageRecord,
+    NewPassageRecordDetail,
+    DrillFocus,
+    DrillWarmUp,
+    LessonHomeRow,
+    LessonTopRow,
+    LessonBottomRow,
+    LessonCapitals,
+    LessonPunctuation,
+    LessonKeys,
+    LessonPassMark,
+    LessonBest,
+    LessonLocked,
+    StartLesson,
+    RecentWinners,
+    StartingInSeconds,
+    CountdownSecs,
+    MinPlayers,
+    AutoStartSecs,
+    Off,
+    Mistakes,
+    MustFix,
+    KeepGoing,
+    FixBeforeNextWord,
+    FinishLine,
+    WaitForAll,
+    TopThreeGrace,
+    FirstWins,
+    EliminationRule,
+    TextSource,
+    Passages,
+    NumbersDrill,
+    SymbolsDrill,
+    OperatorsDrill,
+    RandomWords,
+    Difficulty,
+    Words,
+    Top200,
+    Top1000,
+    UnitWords,
+    UnitSeconds,
+    WordsToRace,
+    RaceLength,
+    LengthByRacers,
+    LengthBySeconds,
+    HeatSize,
+    Casual,
+    Pausable,
+    Relay,
+    Golf,
+    PlayersInRoom,
+    PlayerRating,
+    PlayerLevel,
+    RaceComplete,
+    WordsPerMinute,
+    TotalTime,
+    BeatPaceCar,
+    PaceCarWon,
+    PaceStats,
+    WpmOverRace,
+    XpGained,
+    LevelUp,
+    XpToNextLevel,
+    NewPersonalBest,
+    PersonalBestDetail,
+    CategoryShort,
+    CategoryMedium,
+    CategoryLong,
+    Percentile,
+    FinalResults,
+    RankColumn,
+    RacerColumn,
+    RawWpm,
+    ErrorsColumn,
+    Consistency,
+    Splits,
+    RaceEndingIn,
+    EliminationIn,
+    OnTheBubble,
+    YouOnTheBubble,
+    DidNotFinish,
+    EliminatedList,
+    TeamHome,
+    RecapTitle,
+    RecapFirstToHalf,
+    RecapTookLead,
+    RecapFastestBurst,
+    RecapFinalOrder,
+    RematchOffer,
+    Series,
+    RaceAgain,
+    NextDrill,
+    LessonPassed,
+    LessonNotYet,
+    TryAgain,
+    NextLesson,
+    RematchHint,
+    WaitingForOthers,
+    AcceptRematch,
+    Rematch,
+    ExportRaces,
+    ShareCard,
+    LeaveRoom,
+    RecentRaces,
+    RecentRaceStats,
+    RecentRaceRoom,
+    RecentRacePractice,
+    Friends,
+    FriendCode,
+    FriendCodePlaceholder,
+    AddFriend,
+    FriendNumber,
+    FriendOnline,
+    FriendOffline,
+    InviteFriend,
+    RemoveFriend,
+    InviteSent,
+    InviteLinkCopied,
+    InvitedBy,
+    JoinInvite,
+    YourReplays,
+    ReplaysLocalOnly,
+    HeatOf,
+    HeatNumber,
+    OtherHeatsRacing,
+    WatchReplay,
+    EraseReplay,
+    EraseAllReplays,
+    CloseReplay,
+    ReplayAnalysis,
+    SlowestKeys,
+    PaletteHint,
+    NoCommands,
+    ConnectionGood,
+    ConnectionFair,
+    ConnectionPoor,
+    Diagnostics,
+    KeyToScreen,
+    KeyToScreenUnmeasured,
+    ServerRoundTrip,
+    ServerRoundTripUnmeasured,
+    LagOnDevice,
+    LagOnNetwork,
+    HotkeyHint,
+    BuiltWith,
+    ByAuthor,
+    RoomFull,
+    NameTaken,
+    InvalidState,
+    RateLimited,
+    SuspiciousActivity,
+    InvalidMessage,
+    NotHost,
+    NameTooLong,
+    NameReserved,
+    NameNotAllowed,
+    Removed,
+    Banned,
+}
+
+fn en(phrase: Phrase) -> &'static str {
+    use Phrase::*;
+    match phrase {
+        Tagline => "Real-time multiplayer typing races",
+        Language => "Language",
+        LongestStreak => "Longest streak: {days} days",
+        Streak => "🔥 {days}-day streak",
+        SignedInAs => "Signed in as {name} ({provider}) · ",
+        SignOut => "Sign out",
+        SignInWith => "Sign in with {provider}",
+        RoomName => "Room name",
+        YourName => "Your name",
+        PaceWpm => "Pace WPM",
+        PaceWpmHint => "Race a pace car at this WPM (leave empty for none)",
+        PacerWpm => "Pacer WPM",
+        PacerWpmHint => "In practice and when racing alone, a ghost caret moves through the passage at this WPM (leave empty for none)",
+        PacerSound => "Click every word",
+        PacerAhead => "{chars} chars ahead of the pacer",
+        PacerBehind => "{chars} chars behind the pacer",
+        Joined => "Joined",
+        JoinRoom => "Join Room",
+        ConnectAndJoin => "Connect & Join",
+        WatchHint => "Watch the races in this room without taking part",
+        NewRoom => "New Room",
+        NewRoomHint => "Open a room of your own under a fresh code to share with friends",
+        PracticeWeakSpots => "🎯 Practice weak spots",
+        PracticeWeakSpotsHint => "Practice on your own with a drill built from the letter pairs and words you miss most",
+        Lessons => "📚 Lessons",
+        LessonsHint => "Learn to type one step at a time: pass a lesson to unlock the next",
+        InviteLink => "Invite friends: ",
+        CopyLink => "Copy link",
+        LinkCopied => "Copied!",
+        Watch => "Watch",
+        Watching => "Watching",
+        WatchAnotherRoom => "Watch another room",
+        AlsoWatching => "Also watching {room}",
+        StopWatching => "Stop watching",
+        Status => "Status: ",
+        Connected => "Connected",
+        Disconnected => "Disconnected",
+        StartingIn => "⏳ Starting in {secs}…",
+        RaceInProgress => "🏁 Race in Progress",
+        Paused => "⏸ Paused",
+        PausedHidden => "Paused while the tab was hidden. Start typing to carry on.",
+        PausedIdle => "Paused after {secs} seconds without typing. Start typing to carry on.",
+        PausedByHost => "{name} paused the race. The clock is stopped until it resumes.",
+        PauseRace => "⏸ Pause race",
+        ResumeRace => "▶ Resume",
+        Spectators => "👀 {count} watching",
+        Wpm => "WPM",
+        Accuracy => "Accuracy",
+        Time => "Time",
+        Track => "Track ",
+        WholePassage => "Whole passage",
+        Follow => "Follow",
+        MarksInText => "Show racers in the text",
+        Typing => "Typing",
+        TypingNow => "✎ typing…",
+        TypeThisPassage => "Type this passage:",
+        TypingTip => "Tip: type straight quotes (\" '), hyphen (-), and space for curly quotes, long dashes, and non‑breaking spaces.",
+        RelayYourLeg => "Your turn: leg {leg} of {legs}",
+        RelayTeammateLeg => "{name} is typing leg {leg} of {legs}",
+        RelayTeam => "Team {team}: {names}",
+        Progress => "Progress: ",
+        Characters => " characters",
+        Errors => "Errors: ",
+        Rank => "Rank: ",
+        PassedYou => "{name} passed you!",
+        YouPassed => "You passed {name}!",
+        WaitingForRace => "🏁 Waiting for Race",
+        WaitingForPlayers => "Waiting for more players to join...",
+        StartsSolo => "Race starts as soon as you're ready (solo races fill up with bots)",
+        StartsWithBots => "Race starts when {players}+ players join the room, or against bots after {secs} seconds",
+        StartsWhen => "Race starts when {players}+ players join the room",
+        CasualRace => "Casual race: passages are typed like “{sample}”",
+        RelayRace => "Relay race: teammates take turns typing the passage, a leg each",
+        GolfRace => "Golf: fewest errors wins, and the earlier finish breaks a tie",
+        RacingFirstWords => "Racing the first {words} words of each passage",
+        RoomBest => "🏆 Room best: {name} — {wpm} WPM",
+        PassageRecord => "Passage record: {wpm} WPM by {name}",
+        NewPassageRecord => "🏆 New passage record!",
+        NewPassageRecordDetail => "{name} typed it at {wpm} WPM, beating {previous_name}'s {previous} WPM",
+        DrillFocus => "Working on: {focus}",
+        DrillWarmUp => "Nothing missed yet: these are common words, and your next drill works on what you miss",
+        LessonHomeRow => "Home row",
+        LessonTopRow => "Top row",
+        LessonBottomRow => "Bottom row",
+        LessonCapitals => "Capitals",
+        LessonPunctuation => "Punctuation",
+        LessonKeys => "New keys: {keys}",
+        LessonPassMark => "Pass: {wpm} WPM at {accuracy}% accuracy",
+        LessonBest => "Best: {wpm} WPM, {accuracy}% accuracy",
+        LessonLocked => "🔒 Pass the lesson before first",
+        StartLesson => "Start",
+        RecentWinners => "Recent winners: ",
+        StartingInSeconds => "Starting in: {secs} seconds",
+        CountdownSecs => "Countdown (s) ",
+        MinPlayers => "Min. players ",
+        AutoStartSecs => "Auto-start (s) ",
+        Off => "off",
+        Mistakes => "Mistakes ",
+        MustFix => "Must fix to continue",
+        KeepGoing => "Keep going",
+        FixBeforeNextWord => "Fix before next word",
+        FinishLine => "Finish line ",
+        WaitForAll => "Wait for everyone",
+        TopThreeGrace => "Top 3, then {secs}s for the rest",
+        FirstWins => "First to finish wins",
+        EliminationRule => "Elimination: last place is out every {secs}s",
+        TextSource => "Text ",
+        Passages => "Passages",
+        NumbersDrill => "Numbers drill",
+        SymbolsDrill => "Symbols drill",
+        OperatorsDrill => "Operators drill",
+        RandomWords => "Random words",
+        Difficulty => "Difficulty ",
+        Words => "Words ",
+        Top200 => "Top 200",
+        Top1000 => "Top 1000",
+        UnitWords => "words",
+        UnitSeconds => "seconds",
+        WordsToRace => "Words to race",
+        RaceLength => "Length",
+        LengthByRacers => "Longer with more racers",
+        LengthBySeconds => "Timed to the racers' speed",
+        HeatSize => "Heats of at most",
+        Casual => "Casual (no caps or punctuation)",
+        Pausable => "Host can pause the race",
+        Relay => "Relay (teams type the passage in legs)",
+        Golf => "Golf (fewest errors wins)",
+        PlayersInRoom => "Players in Room:",
+        PlayerRating => " ({wpm} WPM)",
+        PlayerLevel => " · Lv {level}",
+        RaceComplete => "🏆 Race Complete!",
+        WordsPerMinute => "Words per Minute",
+        TotalTime => "Total Time",
+        BeatPaceCar => "🏁 You beat the {wpm} WPM pace car!",
+        PaceCarWon => "⏱ The {wpm} WPM pace car beat you this time",
+        PaceStats => "Consistency {consistency}% · Burst {burst} WPM · Adjusted {adjusted} WPM",
+        WpmOverRace => "WPM over the race (peak {peak})",
+        XpGained => "+{xp} XP · Level {level}",
+        LevelUp => " 🎊 Level up!",
+        XpToNextLevel => "{into} / {needed} XP to next level",
+        NewPersonalBest => "🎉 New personal best!",
+        PersonalBestDetail => "{wpm} WPM on {category} passages (previous best {previous}, +{gain})",
+        CategoryShort => "short",
+        CategoryMedium => "medium",
+        CategoryLong => "long",
+        Percentile => "You're faster than {percent}% of racers this season",
+        FinalResults => "Final Results:",
+        RankColumn => "#",
+        RacerColumn => "Racer",
+        RawWpm => "Raw WPM",
+        ErrorsColumn => "Errors",
+        Consistency => "Consistency",
+        Splits => "Splits",
+        RaceEndingIn => "🏁 The podium is full! The race ends in {secs}s",
+        EliminationIn => "Last place is out in {secs}s",
+        OnTheBubble => "On the bubble: {name}",
+        YouOnTheBubble => "You're on the bubble!",
+        DidNotFinish => "Did not finish: {names}",
+        EliminatedList => "Eliminated: {names}",
+        TeamHome => "Team {team} finished in {time}s at {wpm} WPM",
+        RecapTitle => "How it went",
+        RecapFirstToHalf => "{time}s: {name} reached halfway first",
+        RecapTookLead => "{time}s: {name} took the lead from {other}",
+        RecapFastestBurst => "{time}s: {name} put in the fastest burst, {wpm} WPM for {secs} seconds",
+        RecapFinalOrder => "Final order: {names}",
+        RematchOffer => "🔁 {name} wants a rematch — {accepted}/{humans} accepted",
+        Series => "Series: {score}",
+        RaceAgain => "🏁 Race Again",
+        NextDrill => "🎯 Next drill",
+        LessonPassed => "✅ Lesson passed!",
+        LessonNotYet => "Not yet: pass with {wpm} WPM at {accuracy}% accuracy",
+        TryAgain => "↻ Try again",
+        NextLesson => "Next lesson →",
+        RematchHint => "Race the same passage again with the same players",
+        WaitingForOthers => "Waiting for the others…",
+        AcceptRematch => "🔁 Accept Rematch",
+        Rematch => "🔁 Rematch",
+        ExportRaces => "⬇ Export My Races",
+        ShareCard => "🖼 Share Result",
+        LeaveRoom => "Leave room",
+        RecentRaces => "Recent races",
+        RecentRaceStats => "{wpm} WPM · {accuracy}%",
+        RecentRaceRoom => "Room {room}",
+        RecentRacePractice => "Practice",
+        Friends => "Friends",
+        FriendCode => "Your friend code: {code}",
+        FriendCodePlaceholder => "Friend code",
+        AddFriend => "Add friend",
+        FriendNumber => "Player #{id}",
+        FriendOnline => "Online",
+        FriendOffline => "Offline",
+        InviteFriend => "Invite",
+        RemoveFriend => "Remove",
+        InviteSent => "Invite sent to {name}",
+        InviteLinkCopied => "{name} is offline: invite link copied",
+        InvitedBy => "{name} invited you to race in {room}",
+        JoinInvite => "Join",
+        YourReplays => "Your replays",
+        ReplaysLocalOnly => "Kept on this device only",
+        HeatOf => "Heat {heat} of {heats}",
+        HeatNumber => "heat {heat}",
+        OtherHeatsRacing => "The other heats are still racing; everyone's results follow when they're done",
+        WatchReplay => "Watch",
+        EraseReplay => "Erase",
+        EraseAllReplays => "Erase all",
+        CloseReplay => "Close",
+        ReplayAnalysis => "Consistency {consistency}% · Burst {burst} WPM",
+        SlowestKeys => "Slowest keys: {keys}",
+        PaletteHint => "Type a command…",
+        NoCommands => "No matching commands",
+        ConnectionGood => "Good connection",
+        ConnectionFair => "Fair connection",
+        ConnectionPoor => "Poor connection",
+        Diagnostics => "Latency diagnostics",
+        KeyToScreen => "Key to screen: {avg} ms average, {worst} ms worst",
+        KeyToScreenUnmeasured => "Key to screen: type a few keys to measure",
+        ServerRoundTrip => "Server round trip: {ms} ms",
+        ServerRoundTripUnmeasured => "Server round trip: not measured yet",
+        LagOnDevice => "Lag is on this device: the page is slow to draw your keys",
+        LagOnNetwork => "Lag is on the connection: the server is slow to answer",
+        HotkeyHint => "Tab or Enter: race again · Esc: leave the room · Ctrl+K: commands",
+        BuiltWith => "Built with ❤️ using Rust 🦀 + WebAssembly + WebSockets",
+        ByAuthor => "by {author}",
+        RoomFull => "That room is full. Try another room name.",
+        NameTaken => "That name is already taken in this room. Pick another one and join again.",
+        InvalidState => "That action isn't available right now.",
+        RateLimited => "Slow down! You're sending messages too quickly.",
+        SuspiciousActivity => "Suspicious typing detected. Your race may not count.",
+        InvalidMessage => "The server couldn't understand the last message.",
+        NotHost => "Only the room host can do that.",
+        NameTooLong => "That name is too long. Pick a shorter one and join again.",
+        NameReserved => "That name belongs to a bot. Pick another one and join again.",
+        NameNotAllowed => "That name isn't allowed. Pick another one and join again.",
+        Removed => "A moderator removed you from the room.",
+        Banned => "You have been banned from racing here.",
+    }
+}
+
+fn es(phrase: Phrase) -> &'static str {
+    use Phrase::*;
+    match phrase {
+        Tagline => "Carreras de mecanografía multijugador en tiempo real",
+        Language => "Idioma",
+        LongestStreak => "Racha más larga: {days} días",
+        Streak => "🔥 Racha de {days} días",
+        SignedInAs => "Sesión iniciada como {name} ({provider}) · ",
+        SignOut => "Cerrar sesión",
+        SignInWith => "Iniciar sesión con {provider}",
+        RoomName => "Nombre de la sala",
+        YourName => "Tu nombre",
+        PaceWpm => "PPM guía",
+        PaceWpmHint => "Compite contra un coche guía a estas PPM (déjalo vacío para no usarlo)",
+        PacerWpm => "PPM del marcapasos",
+        PacerWpmHint => "Al practicar y al competir solo, un cursor fantasma recorre el texto a estas PPM (déjalo vacío para no usarlo)",
+        PacerSound => "Un clic por palabra",
+        PacerAhead => "{chars} caracteres por delante del marcapasos",
+        PacerBehind => "{chars} caracteres por detrás del marcapasos",
+        Joined => "Dentro",
+        JoinRoom => "Unirse a la sala",
+        ConnectAndJoin => "Conectar y unirse",
+        WatchHint => "Mira las carreras de esta sala sin participar",
+        NewRoom => "Nueva sala",
+        NewRoomHint => "Abre una sala propia con un código nuevo para compartir con tus amigos",
+        PracticeWeakSpots => "🎯 Practicar puntos débiles",
+        PracticeWeakSpotsHint => "Practica por tu cuenta con un ejercicio hecho con los pares de letras y las palabras que más fallas",
+        Lessons => "📚 Lecciones",
+        LessonsHint => "Aprende a escribir paso a paso: aprueba una lección para desbloquear la siguiente",
+        InviteLink => "Invita a tus amigos: ",
+        CopyLink => "Copiar enlace",
+        LinkCopied => "¡Copiado!",
+        Watch => "Mirar",
+        Watching => "Mirando",
+        WatchAnotherRoom => "Mirar otra sala",
+        AlsoWatching => "Mirando también {room}",
+        StopWatching => "Dejar de mirar",
+        Status => "Estado: ",
+        Connected => "Conectado",
+        Disconnected => "Desconectado",
+        StartingIn => "⏳ Empieza en {secs}…",
+        RaceInProgress => "🏁 Carrera en curso",
+        Paused => "⏸ En pausa",
+        PausedHidden => "En pausa mientras la pestaña estaba oculta. Empieza a escribir para continuar.",
+        PausedIdle => "En pausa tras {secs} segundos sin escribir. Empieza a escribir para continuar.",
+        PausedByHost => "{name} ha pausado la carrera. El reloj está detenido hasta que se reanude.",
+        PauseRace => "⏸ Pausar carrera",
+        ResumeRace => "▶ Reanudar",
+        Spectators => "👀 {count} mirando",
+        Wpm => "PPM",
+        Accuracy => "Precisión",
+        Time => "Tiempo",
+        Track => "Pista ",
+        WholePassage => "Texto completo",
+        Follow => "Seguir",
+        MarksInText => "Mostrar a los corredores en el texto",
+        Typing => "Escribiendo",
+        TypingNow => "✎ escribiendo…",
+        TypeThisPassage => "Escribe este texto:",
+        TypingTip => "Consejo: escribe comillas rectas (\" '), guion (-) y espacio para las comillas tipográficas, las rayas y los espacios de no separación.",
+        RelayYourLeg => "Tu turno: tramo {leg} de {legs}",
+        RelayTeammateLeg => "{name} escribe el tramo {leg} de {legs}",
+        RelayTeam => "Equipo {team}: {names}",
+        Progress => "Progreso: ",
+        Characters => " caracteres",
+        Errors => "Errores: ",
+        Rank => "Puesto: ",
+        PassedYou => "¡{name} te ha adelantado!",
+        YouPassed => "¡Has adelantado a {name}!",
+        WaitingForRace => "🏁 Esperando la carrera",
+        WaitingForPlayers => "Esperando a que se unan más jugadores...",
+        StartsSolo => "La carrera empieza en cuanto estés listo (las carreras en solitario se completan con bots)",
+        StartsWithBots => "La carrera empieza cuando haya {players} o más jugadores en la sala, o contra bots tras {secs} segundos",
+        StartsWhen => "La carrera empieza cuando haya {players} o más jugadores en la sala",
+        CasualRace => "Carrera informal: los textos se escriben así: “{sample}”",
+        RelayRace => "Carrera de relevos: los compañeros de equipo se turnan para escribir el texto, un tramo cada uno",
+        GolfRace => "Golf: gana quien cometa menos errores; en caso de empate, quien termine antes",
+        RacingFirstWords => "Se corren las primeras {words} palabras de cada texto",
+        RoomBest => "🏆 Récord de la sala: {name} — {wpm} PPM",
+        PassageRecord => "Récord del texto: {wpm} PPM de {name}",
+        NewPassageRecord => "🏆 ¡Nuevo récord del texto!",
+        NewPassageRecordDetail => "{name} lo escribió a {wpm} PPM y superó los {previous} PPM de {previous_name}",
+        DrillFocus => "Practicando: {focus}",
+        DrillWarmUp => "Aún no has fallado nada: son palabras comunes, y el próximo ejercicio trabajará lo que falles",
+        LessonHomeRow => "Fila guía",
+        LessonTopRow => "Fila superior",
+        LessonBottomRow => "Fila inferior",
+        LessonCapitals => "Mayúsculas",
+        LessonPunctuation => "Puntuación",
+        LessonKeys => "Teclas nuevas: {keys}",
+        LessonPassMark => "Aprobado: {wpm} PPM con {accuracy}% de precisión",
+        LessonBest => "Mejor: {wpm} PPM, {accuracy}% de precisión",
+        LessonLocked => "🔒 Aprueba antes la lección anterior",
+        StartLesson => "Empezar",
+        RecentWinners => "Ganadores recientes: ",
+        StartingInSeconds => "Empieza en: {secs} segundos",
+        CountdownSecs => "Cuenta atrás (s) ",
+        MinPlayers => "Mín. jugadores ",
+        AutoStartSecs => "Inicio automático (s) ",
+        Off => "no",
+        Mistakes => "Errores ",
+        MustFix => "Corregir para seguir",
+        KeepGoing => "Seguir escribiendo",
+        FixBeforeNextWord => "Corregir antes de la siguiente palabra",
+        FinishLine => "Meta ",
+        WaitForAll => "Esperar a todos",
+        TopThreeGrace => "Top 3 y {secs} s para el resto",
+        FirstWins => "Gana quien termine primero",
+        EliminationRule => "Eliminación: el último queda fuera cada {secs} s",
+        TextSource => "Texto ",
+        Passages => "Textos",
+        NumbersDrill => "Práctica de números",
+        SymbolsDrill => "Práctica de símbolos",
+        OperatorsDrill => "Práctica de operadores",
+        RandomWords => "Palabras al azar",
+        Difficulty => "Dificultad ",
+        Words => "Palabras ",
+        Top200 => "Las 200 más comunes",
+        Top1000 => "Las 1000 más comunes",
+        UnitWords => "palabras",
+        UnitSeconds => "segundos",
+        WordsToRace => "Palabras por carrera",
+        RaceLength => "Longitud",
+        LengthByRacers => "Más largo con más corredores",
+        LengthBySeconds => "Según la velocidad de los corredores",
+        HeatSize => "Mangas de como máximo",
+        Casual => "Informal (sin mayúsculas ni puntuación)",
+        Pausable => "El anfitrión puede pausar la carrera",
+        Relay => "Relevos (los equipos escriben el texto por tramos)",
+        Golf => "Golf (gana quien cometa menos errores)",
+        PlayersInRoom => "Jugadores en la sala:",
+        PlayerRating => " ({wpm} PPM)",
+        PlayerLevel => " · Nv. {level}",
+        RaceComplete => "🏆 ¡Carrera terminada!",
+        WordsPerMinute => "Palabras por minuto",
+        TotalTime => "Tiempo total",
+        BeatPaceCar => "🏁 ¡Has ganado al coche guía de {wpm} PPM!",
+        PaceCarWon => "⏱ Esta vez te ganó el coche guía de {wpm} PPM",
+        PaceStats => "Regularidad {consistency}% · Ráfaga {burst} PPM · Ajustado {adjusted} PPM",
+        WpmOverRace => "PPM durante la carrera (máximo {peak})",
+        XpGained => "+{xp} XP · Nivel {level}",
+        LevelUp => " 🎊 ¡Subes de nivel!",
+        XpToNextLevel => "{into} / {needed} XP para el siguiente nivel",
+        NewPersonalBest => "🎉 ¡Nuevo récord personal!",
+        PersonalBestDetail => "{wpm} PPM en textos {category} (récord anterior {previous}, +{gain})",
+        CategoryShort => "cortos",
+        CategoryMedium => "medianos",
+        CategoryLong => "largos",
+        Percentile => "Eres más rápido que el {percent}% de los corredores esta temporada",
+        FinalResults => "Resultados finales:",
+        RankColumn => "#",
+        RacerColumn => "Corredor",
+        RawWpm => "PPM brutas",
+        ErrorsColumn => "Errores",
+        Consistency => "Constancia",
+        Splits => "Parciales",
+        RaceEndingIn => "🏁 ¡El podio está completo! La carrera termina en {secs} s",
+        EliminationIn => "El último queda fuera en {secs} s",
+        OnTheBubble => "En la cuerda floja: {name}",
+        YouOnTheBubble => "¡Estás en la cuerda floja!",
+        DidNotFinish => "No terminaron: {names}",
+        EliminatedList => "Eliminados: {names}",
+        TeamHome => "El equipo {team} terminó en {time} s a {wpm} PPM",
+        RecapTitle => "Cómo fue",
+        RecapFirstToHalf => "{time} s: {name} llegó primero a la mitad",
+        RecapTookLead => "{time} s: {name} le quitó el primer puesto a {other}",
+        RecapFastestBurst => "{time} s: {name} hizo la racha más rápida, {wpm} PPM durante {secs} segundos",
+        RecapFinalOrder => "Orden final: {names}",
+        RematchOffer => "🔁 {name} quiere la revancha — {accepted}/{humans} aceptan",
+        Series => "Serie: {score}",
+        RaceAgain => "🏁 Otra carrera",
+        NextDrill => "🎯 Siguiente ejercicio",
+        LessonPassed => "✅ ¡Lección aprobada!",
+        LessonNotYet => "Todavía no: se aprueba con {wpm} PPM y {accuracy}% de precisión",
+        TryAgain => "↻ Reintentar",
+        NextLesson => "Siguiente lección →",
+        RematchHint => "Repite el mismo texto con los mismos jugadores",
+        WaitingForOthers => "Esperando a los demás…",
+        AcceptRematch => "🔁 Aceptar revancha",
+        Rematch => "🔁 Revancha",
+        ExportRaces => "⬇ Exportar mis carreras",
+        ShareCard => "🖼 Compartir resultado",
+        LeaveRoom => "Salir de la sala",
+        RecentRaces => "Carreras recientes",
+        RecentRaceStats => "{wpm} PPM · {accuracy}%",
+        RecentRaceRoom => "Sala {room}",
+        RecentRacePractice => "Práctica",
+        Friends => "Amigos",
+        FriendCode => "Tu código de amigo: {code}",
+        FriendCodePlaceholder => "Código de amigo",
+        AddFriend => "Añadir amigo",
+        FriendNumber => "Jugador n.º {id}",
+        FriendOnline => "En línea",
+        FriendOffline => "Desconectado",
+        InviteFriend => "Invitar",
+        RemoveFriend => "Quitar",
+        InviteSent => "Invitación enviada a {name}",
+        InviteLinkCopied => "{name} no está conectado: enlace de invitación copiado",
+        InvitedBy => "{name} te invita a competir en {room}",
+        JoinInvite => "Unirse",
+        YourReplays => "Tus repeticiones",
+        ReplaysLocalOnly => "Solo se guardan en este dispositivo",
+        HeatOf => "Manga {heat} de {heats}",
+        HeatNumber => "manga {heat}",
+        OtherHeatsRacing => "Las otras mangas siguen corriendo; los resultados de todos llegan cuando terminen",
+        WatchReplay => "Ver",
+        EraseReplay => "Borrar",
+        EraseAllReplays => "Borrar todas",
+        CloseReplay => "Cerrar",
+        ReplayAnalysis => "Constancia {consistency}% · Ráfaga {burst} PPM",
+        SlowestKeys => "Teclas más lentas: {keys}",
+        PaletteHint => "Escribe un comando…",
+        NoCommands => "Ningún comando coincide",
+        ConnectionGood => "Buena conexión",
+        ConnectionFair => "Conexión aceptable",
+        ConnectionPoor => "Mala conexión",
+        Diagnostics => "Diagnóstico de latencia",
+        KeyToScreen => "De la tecla a la pantalla: {avg} ms de media, {worst} ms como máximo",
+        KeyToScreenUnmeasured => "De la tecla a la pantalla: escribe unas teclas para medirlo",
+        ServerRoundTrip => "Ida y vuelta al servidor: {ms} ms",
+        ServerRoundTripUnmeasured => "Ida y vuelta al servidor: aún sin medir",
+        LagOnDevice => "El retraso está en este dispositivo: la página tarda en dibujar tus teclas",
+        LagOnNetwork => "El retraso está en la conexión: el servidor tarda en responder",
+        HotkeyHint => "Tab o Intro: otra carrera · Esc: salir de la sala · Ctrl+K: comandos",
+        BuiltWith => "Hecho con ❤️ usando Rust 🦀 + WebAssembly + WebSockets",
+        ByAuthor => "por {author}",
+        RoomFull => "Esa sala está llena. Prueba con otro nombre de sala.",
+        NameTaken => "Ese nombre ya está en uso en esta sala. Elige otro y vuelve a unirte.",
+        InvalidState => "Esa acción no está disponible ahora.",
+        RateLimited => "¡Más despacio! Estás enviando mensajes demasiado rápido.",
+        SuspiciousActivity => "Se detectó una escritura sospechosa. Puede que tu carrera no cuente.",
+        InvalidMessage => "El servidor no entendió el último mensaje.",
+        NotHost => "Solo el anfitrión de la sala puede hacer eso.",
+        NameTooLong => "Ese nombre es demasiado largo. Elige uno más corto y vuelve a unirte.",
+        NameReserved => "Ese nombre es de un bot. Elige otro y vuelve a unirte.",
+        NameNotAllowed => "Ese nombre no está permitido. Elige otro y vuelve a unirte.",
+        Removed => "Un moderador te sacó de la sala.",
+        Banned => "Se te ha prohibido competir aquí.",
+    }
+}
+
+fn de(phrase: Phrase) -> &'static str {
+    use Phrase::*;
+    match phrase {
+        Tagline => "Mehrspieler-Tipprennen in Echtzeit",
+        Language => "Sprache",
+        LongestStreak => "Längste Serie: {days} Tage",
+        Streak => "🔥 {days} Tage in Folge",
+        SignedInAs => "Angemeldet als {name} ({provider}) · ",
+        SignOut => "Abmelden",
+        SignInWith => "Mit {provider} anmelden",
+        RoomName => "Raumname",
+        YourName => "Dein Name",
+        PaceWpm => "Tempo (WPM)",
+        PaceWpmHint => "Gegen ein Tempoauto mit diesen WPM fahren (leer lassen für keins)",
+        PacerWpm => "Taktgeber (WPM)",
+        PacerWpmHint => "Beim Üben und allein im Rennen läuft ein Geister-Cursor mit diesen WPM durch den Text (leer lassen für keinen)",
+        PacerSound => "Klick bei jedem Wort",
+        PacerAhead => "{chars} Zeichen vor dem Taktgeber",
+        PacerBehind => "{chars} Zeichen hinter dem Taktgeber",
+        Joined => "Beigetreten",
+        JoinRoom => "Raum betreten",
+        ConnectAndJoin => "Verbinden & betreten",
+        WatchHint => "Die Rennen in diesem Raum ansehen, ohne mitzufahren",
+        NewRoom => "Neuer Raum",
+        NewRoomHint => "Einen eigenen Raum mit neuem Code öffnen, um ihn mit Freunden zu teilen",
+        PracticeWeakSpots => "🎯 Schwachstellen üben",
+        PracticeWeakSpotsHint => "Allein üben mit einer Übung aus den Buchstabenpaaren und Wörtern, bei denen du dich am häufigsten vertippst",
+        Lessons => "📚 Lektionen",
+        LessonsHint => "Schritt für Schritt tippen lernen: eine bestandene Lektion schaltet die nächste frei",
+        InviteLink => "Freunde einladen: ",
+        CopyLink => "Link kopieren",
+        LinkCopied => "Kopiert!",
+        Watch => "Zuschauen",
+        Watching => "Schaust zu",
+        WatchAnotherRoom => "Anderen Raum ansehen",
+        AlsoWatching => "Schaust auch {room} zu",
+        StopWatching => "Nicht mehr zusehen",
+        Status => "Status: ",
+        Connected => "Verbunden",
+        Disconnected => "Getrennt",
+        StartingIn => "⏳ Start in {secs}…",
+        RaceInProgress => "🏁 Rennen läuft",
+        Paused => "⏸ Pausiert",
+        PausedHidden => "Pausiert, während der Tab im Hintergrund war. Tippe einfach weiter.",
+        PausedIdle => "Nach {secs} Sekunden ohne Tippen pausiert. Tippe einfach weiter.",
+        PausedByHost => "{name} hat das Rennen pausiert. Die Uhr steht, bis es weitergeht.",
+        PauseRace => "⏸ Rennen pausieren",
+        ResumeRace => "▶ Weiter",
+        Spectators => "👀 {count} schauen zu",
+        Wpm => "WPM",
+        Accuracy => "Genauigkeit",
+        Time => "Zeit",
+        Track => "Strecke ",
+        WholePassage => "Ganzer Text",
+        Follow => "Folgen",
+        MarksInText => "Fahrer im Text zeigen",
+        Typing => "Tippt",
+        TypingNow => "✎ tippt…",
+        TypeThisPassage => "Tippe diesen Text:",
+        TypingTip => "Tipp: Gerade Anführungszeichen (\" '), Bindestrich (-) und Leerzeichen stehen für typografische Anführungszeichen, lange Striche und geschützte Leerzeichen.",
+        RelayYourLeg => "Du bist dran: Etappe {leg} von {legs}",
+        RelayTeammateLeg => "{name} tippt Etappe {leg} von {legs}",
+        RelayTeam => "Team {team}: {names}",
+        Progress => "Fortschritt: ",
+        Characters => " Zeichen",
+        Errors => "Fehler: ",
+        Rank => "Platz: ",
+        PassedYou => "{name} hat dich überholt!",
+        YouPassed => "Du hast {name} überholt!",
+        WaitingForRace => "🏁 Warten auf das Rennen",
+        WaitingForPlayers => "Warten auf weitere Spieler...",
+        StartsSolo => "Das Rennen startet, sobald du bereit bist (Solorennen werden mit Bots aufgefüllt)",
+        StartsWithBots => "Das Rennen startet, sobald {players}+ Spieler im Raum sind, oder nach {secs} Sekunden gegen Bots",
+        StartsWhen => "Das Rennen startet, sobald {players}+ Spieler im Raum sind",
+        CasualRace => "Lockeres Rennen: Texte werden so getippt: „{sample}“",
+        RelayRace => "Staffelrennen: Teammitglieder tippen den Text abwechselnd, je eine Etappe",
+        GolfRace => "Golf: Die wenigsten Fehler gewinnen, bei Gleichstand entscheidet der frühere Zieleinlauf",
+        RacingFirstWords => "Gerannt werden die ersten {words} Wörter jedes Texts",
+        RoomBest => "🏆 Raumrekord: {name} — {wpm} WPM",
+        PassageRecord => "Textrekord: {wpm} WPM von {name}",
+        NewPassageRecord => "🏆 Neuer Textrekord!",
+        NewPassageRecordDetail => "{name} schaffte {wpm} WPM und schlug {previous_name}s {previous} WPM",
+        DrillFocus => "Geübt wird: {focus}",
+        DrillWarmUp => "Noch keine Fehler: das sind häufige Wörter, die nächste Übung nimmt sich deine Fehler vor",
+        LessonHomeRow => "Grundreihe",
+        LessonTopRow => "Obere Reihe",
+        LessonBottomRow => "Untere Reihe",
+        LessonCapitals => "Großbuchstaben",
+        LessonPunctuation => "Satzzeichen",
+        LessonKeys => "Neue Tasten: {keys}",
+        LessonPassMark => "Bestanden ab {wpm} WPM bei {accuracy}% Genauigkeit",
+        LessonBest => "Bestwert: {wpm} WPM, {accuracy}% Genauigkeit",
+        LessonLocked => "🔒 Erst die Lektion davor bestehen",
+        StartLesson => "Starten",
+        RecentWinners => "Letzte Sieger: ",
+        StartingInSeconds => "Start in: {secs} Sekunden",
+        CountdownSecs => "Countdown (s) ",
+        MinPlayers => "Min. Spieler ",
+        AutoStartSecs => "Autostart (s) ",
+        Off => "aus",
+        Mistakes => "Fehler ",
+        MustFix => "Korrigieren, um weiterzumachen",
+        KeepGoing => "Weitertippen",
+        FixBeforeNextWord => "Vor dem nächsten Wort korrigieren",
+        FinishLine => "Ziellinie ",
+        WaitForAll => "Auf alle warten",
+        TopThreeGrace => "Top 3, dann {secs} s für den Rest",
+        FirstWins => "Wer zuerst fertig ist, gewinnt",
+        EliminationRule => "Ausscheiden: Alle {secs} s fliegt der Letzte raus",
+        TextSource => "Text ",
+        Passages => "Texte",
+        NumbersDrill => "Zahlenübung",
+        SymbolsDrill => "Symbolübung",
+        OperatorsDrill => "Operatorenübung",
+        RandomWords => "Zufällige Wörter",
+        Difficulty => "Schwierigkeit ",
+        Words => "Wörter ",
+        Top200 => "Top 200",
+        Top1000 => "Top 1000",
+        UnitWords => "Wörter",
+        UnitSeconds => "Sekunden",
+        WordsToRace => "Wörter pro Rennen",
+        RaceLength => "Länge",
+        LengthByRacers => "Länger mit mehr Teilnehmern",
+        LengthBySeconds => "Passend zum Tempo der Teilnehmer",
+        HeatSize => "Vorläufe mit höchstens",
+        Casual => "Locker (ohne Großschreibung und Satzzeichen)",
+        Pausable => "Gastgeber kann das Rennen pausieren",
+        Relay => "Staffel (Teams tippen den Text in Etappen)",
+        Golf => "Golf (die wenigsten Fehler gewinnen)",
+        PlayersInRoom => "Spieler im Raum:",
+        PlayerRating => " ({wpm} WPM)",
+        PlayerLevel => " · Lv. {level}",
+        RaceComplete => "🏆 Rennen beendet!",
+        WordsPerMinute => "Wörter pro Minute",
+        TotalTime => "Gesamtzeit",
+        BeatPaceCar => "🏁 Du hast das Tempoauto mit {wpm} WPM geschlagen!",
+        PaceCarWon => "⏱ Diesmal hat dich das Tempoauto mit {wpm} WPM geschlagen",
+        PaceStats => "Gleichmäßigkeit {consistency}% · Spitze {burst} WPM · Bereinigt {adjusted} WPM",
+        WpmOverRace => "WPM im Rennverlauf (Spitze {peak})",
+        XpGained => "+{xp} XP · Level {level}",
+        LevelUp => " 🎊 Levelaufstieg!",
+        XpToNextLevel => "{into} / {needed} XP bis zum nächsten Level",
+        NewPersonalBest => "🎉 Neue persönliche Bestleistung!",
+        PersonalBestDetail => "{wpm} WPM bei {category} Texten (bisher {previous}, +{gain})",
+        CategoryShort => "kurzen",
+        CategoryMedium => "mittellangen",
+        CategoryLong => "langen",
+        Percentile => "Du bist schneller als {percent}% der Fahrer dieser Saison",
+        FinalResults => "Endergebnis:",
+        RankColumn => "#",
+        RacerColumn => "Fahrer",
+        RawWpm => "Roh-WPM",
+        ErrorsColumn => "Fehler",
+        Consistency => "Gleichmäßigkeit",
+        Splits => "Zwischenzeiten",
+        RaceEndingIn => "🏁 Das Podium ist voll! Das Rennen endet in {secs} s",
+        EliminationIn => "Der Letzte fliegt in {secs} s raus",
+        OnTheBubble => "Auf der Kippe: {name}",
+        YouOnTheBubble => "Du stehst auf der Kippe!",
+        DidNotFinish => "Nicht ins Ziel gekommen: {names}",
+        EliminatedList => "Ausgeschieden: {names}",
+        TeamHome => "Team {team} war nach {time} s im Ziel, mit {wpm} WPM",
+        RecapTitle => "So lief es",
+        RecapFirstToHalf => "{time} s: {name} war als Erste(r) bei der Hälfte",
+        RecapTookLead => "{time} s: {name} übernahm die Führung von {other}",
+        RecapFastestBurst => "{time} s: {name} legte den schnellsten Sprint hin, {wpm} WPM über {secs} Sekunden",
+        RecapFinalOrder => "Endstand: {names}",
+        RematchOffer => "🔁 {name} will eine Revanche — {accepted}/{humans} dabei",
+        Series => "Serie: {score}",
+        RaceAgain => "🏁 Noch ein Rennen",
+        NextDrill => "🎯 Nächste Übung",
+        LessonPassed => "✅ Lektion bestanden!",
+        LessonNotYet => "Noch nicht: bestanden ab {wpm} WPM bei {accuracy}% Genauigkeit",
+        TryAgain => "↻ Nochmal",
+        NextLesson => "Nächste Lektion →",
+        RematchHint => "Denselben Text noch einmal mit denselben Spielern fahren",
+        WaitingForOthers => "Warten auf die anderen…",
+        AcceptRematch => "🔁 Revanche annehmen",
+        Rematch => "🔁 Revanche",
+        ExportRaces => "⬇ Meine Rennen exportieren",
+        ShareCard => "🖼 Ergebnis teilen",
+        LeaveRoom => "Raum verlassen",
+        RecentRaces => "Letzte Rennen",
+        RecentRaceStats => "{wpm} WPM · {accuracy} %",
+        RecentRaceRoom => "Raum {room}",
+        RecentRacePractice => "Übung",
+        Friends => "Freunde",
+        FriendCode => "Dein Freundescode: {code}",
+        FriendCodePlaceholder => "Freundescode",
+        AddFriend => "Freund hinzufügen",
+        FriendNumber => "Spieler Nr. {id}",
+        FriendOnline => "Online",
+        FriendOffline => "Offline",
+        InviteFriend => "Einladen",
+        RemoveFriend => "Entfernen",
+        InviteSent => "Einladung an {name} gesendet",
+        InviteLinkCopied => "{name} ist offline: Einladungslink kopiert",
+        InvitedBy => "{name} lädt dich zu einem Rennen in {room} ein",
+        JoinInvite => "Beitreten",
+        YourReplays => "Deine Wiederholungen",
+        ReplaysLocalOnly => "Nur auf diesem Gerät gespeichert",
+        HeatOf => "Vorlauf {heat} von {heats}",
+        HeatNumber => "Vorlauf {heat}",
+        OtherHeatsRacing => "Die anderen Vorläufe laufen noch; die Ergebnisse aller folgen, sobald sie fertig sind",
+        WatchReplay => "Ansehen",
+        EraseReplay => "Löschen",
+        EraseAllReplays => "Alle löschen",
+        CloseReplay => "Schließen",
+        ReplayAnalysis => "Gleichmäßigkeit {consistency} % · Spitze {burst} WPM",
+        SlowestKeys => "Langsamste Tasten: {keys}",
+        PaletteHint => "Befehl eingeben…",
+        NoCommands => "Keine passenden Befehle",
+        ConnectionGood => "Gute Verbindung",
+        ConnectionFair => "Mäßige Verbindung",
+        ConnectionPoor => "Schlechte Verbindung",
+        Diagnostics => "Latenzdiagnose",
+        KeyToScreen => "Taste bis Bildschirm: {avg} ms im Schnitt, {worst} ms höchstens",
+        KeyToScreenUnmeasured => "Taste bis Bildschirm: tippe ein paar Tasten zum Messen",
+        ServerRoundTrip => "Umlaufzeit zum Server: {ms} ms",
+        ServerRoundTripUnmeasured => "Umlaufzeit zum Server: noch nicht gemessen",
+        LagOnDevice => "Die Verzögerung liegt an diesem Gerät: die Seite zeichnet deine Tasten langsam",
+        LagOnNetwork => "Die Verzögerung liegt an der Verbindung: der Server antwortet langsam",
+        HotkeyHint => "Tab oder Enter: noch ein Rennen · Esc: Raum verlassen · Strg+K: Befehle",
+        BuiltWith => "Mit ❤️ gebaut aus Rust 🦀 + WebAssembly + WebSockets",
+        ByAuthor => "von {author}",
+        RoomFull => "Dieser Raum ist voll. Versuch einen anderen Raumnamen.",
+        NameTaken => "Dieser Name ist in diesem Raum schon vergeben. Wähl einen anderen und tritt erneut bei.",
+        InvalidState => "Diese Aktion ist gerade nicht möglich.",
+        RateLimited => "Langsamer! Du sendest zu schnell Nachrichten.",
+        SuspiciousActivity => "Verdächtiges Tippen erkannt. Dein Rennen zählt eventuell nicht.",
+        InvalidMessage => "Der Server konnte die letzte Nachricht nicht verstehen.",
+        NotHost => "Nur der Raumleiter kann das.",
+        NameTooLong => "Dieser Name ist zu lang. Wähl einen kürzeren und tritt erneut bei.",
+        NameReserved => "Dieser Name gehört einem Bot. Wähl einen anderen und tritt erneut bei.",
+        NameNotAllowed => "Dieser Name ist nicht erlaubt. Wähl einen anderen und tritt erneut bei.",
+        Removed => "Ein Moderator hat dich aus dem Raum entfernt.",
+        Banned => "Du bist hier vom Rennen ausgeschlossen.",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Phrase; 235] = {
+        use Phrase::*;
+        [
+            Tagline, Language, LongestStreak, Streak, SignedInAs, SignOut, SignInWith, RoomName, YourName, PaceWpm,
+            PaceWpmHint, PacerWpm, PacerWpmHint, PacerSound, PacerAhead, PacerBehind, Joined, JoinRoom, ConnectAndJoin, WatchHint, NewRoom, NewRoomHint, PracticeWeakSpots, PracticeWeakSpotsHint, Lessons, LessonsHint, InviteLink, CopyLink, LinkCopied, Watch, Watching, WatchAnotherRoom, AlsoWatching, StopWatching, Status, Connected, Disconnected,
+            StartingIn, RaceInProgress, Paused, PausedHidden, PausedIdle, PausedByHost, PauseRace, ResumeRace, Spectators, Wpm, Accuracy, Time, Track, WholePassage, Follow, MarksInText, Typing, TypingNow,
+            TypeThisPassage, TypingTip, RelayYourLeg, RelayTeammateLeg, RelayTeam, Progress, Characters, Errors, Rank, PassedYou, YouPassed, WaitingForRace, WaitingForPlayers, StartsSolo,
+            StartsWithBots, StartsWhen, CasualRace, RelayRace, GolfRace, RacingFirstWords, RoomBest, PassageRecord, NewPassageRecord, NewPassageRecordDetail, DrillFocus, DrillWarmUp, LessonHomeRow, LessonTopRow, LessonBottomRow, LessonCapitals, LessonPunctuation, LessonKeys, LessonPassMark, LessonBest, LessonLocked, StartLesson, RecentWinners, StartingInSeconds, CountdownSecs, MinPlayers,
+            AutoStartSecs, Off, Mistakes, MustFix, KeepGoing, FixBeforeNextWord, FinishLine, WaitForAll, TopThreeGrace, FirstWins, EliminationRule, TextSource, Passages, NumbersDrill,
+            SymbolsDrill, OperatorsDrill, RandomWords, Difficulty, Words, Top200, Top1000, UnitWords, UnitSeconds, WordsToRace, RaceLength, LengthByRacers, LengthBySeconds, HeatSize, Casual, Pausable, Relay, Golf,
+            PlayersInRoom, PlayerRating, PlayerLevel, RaceComplete, WordsPerMinute, TotalTime, BeatPaceCar, PaceCarWon,
+            PaceStats, WpmOverRace, XpGained, LevelUp, XpToNextLevel, NewPersonalBest, PersonalBestDetail, CategoryShort,
+            CategoryMedium, CategoryLong, Percentile, FinalResults, RankColumn, RacerColumn, RawWpm, ErrorsColumn, Consistency, Splits, RaceEndingIn, EliminationIn, OnTheBubble, YouOnTheBubble, DidNotFinish, EliminatedList, TeamHome, RecapTitle, RecapFirstToHalf, RecapTookLead, RecapFastestBurst, RecapFinalOrder, RematchOffer, Series, RaceAgain, NextDrill, LessonPassed, LessonNotYet, TryAgain, NextLesson, RematchHint,
+            WaitingForOthers, AcceptRematch, Rematch, ExportRaces, ShareCard, LeaveRoom, RecentRaces, RecentRaceStats, RecentRaceRoom, RecentRacePractice, Friends, FriendCode, FriendCodePlaceholder, AddFriend, FriendNumber, FriendOnline, FriendOffline, InviteFriend, RemoveFriend, InviteSent, InviteLinkCopied, InvitedBy, JoinInvite, YourReplays, ReplaysLocalOnly, HeatOf, HeatNumber, OtherHeatsRacing, WatchReplay, EraseReplay, EraseAllReplays, CloseReplay, ReplayAnalysis, SlowestKeys, PaletteHint, NoCommands, ConnectionGood, ConnectionFair, ConnectionPoor, Diagnostics, KeyToScreen, KeyToScreenUnmeasured, ServerRoundTrip, ServerRoundTripUnmeasured, LagOnDevice, LagOnNetwork, HotkeyHint, BuiltWith, ByAuthor, RoomFull, NameTaken, InvalidState,
+            RateLimited, SuspiciousActivity, InvalidMessage, NotHost, NameTooLong, NameReserved, NameNotAllowed, Removed, Banned,
+        ]
+    };
+
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text.split('{').skip(1).filter_map(|s| s.split_once('}')).map(|(name, _)| name).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn translations_keep_the_english_placeholders() {
+        for phrase in ALL {
+            let english = placeholders(Locale::En.text(phrase));
+            for locale in Locale::ALL {
+                let text = locale.text(phrase);
+                assert!(!text.trim().is_empty(), "{locale:?} {phrase:?} is empty");
+                assert_eq!(placeholders(text), english, "{locale:?} {phrase:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn tags_match_by_primary_language() {
+        assert_eq!(Locale::from_tag("es-MX"), Some(Locale::Es));
+        assert_eq!(Locale::from_tag("de_AT"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("EN"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr-FR"), None);
+        assert_eq!(Locale::from_tag(""), None);
+        for locale in Locale::ALL {
+            assert_eq!(Locale::from_tag(locale.tag()), Some(locale));
+        }
+    }
+
+    #[test]
+    fn first_supported_preference_wins() {
+        assert_eq!(negotiate(["fr-CA", "de-DE", "es"]), Some(Locale::De));
+        assert_eq!(negotiate(["ja", "ko"]), None);
+        assert_eq!(negotiate([]), None);
+    }
+
+    #[test]
+    fn fills_named_placeholders() {
+        assert_eq!(Locale::En.format(Phrase::StartsWithBots, &[("secs", &30), ("players", &2)]), "Race starts when 2+ players join the room, or against bots after 30 seconds");
+        assert_eq!(fill("{a} {missing} {a", &[("a", &"x")]), "x {missing} {a");
+        assert_eq!(fill("no braces", &[]), "no braces");
+    }
+}
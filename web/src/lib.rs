@@ -1,5 +1,19 @@
 mod app;
+pub mod client_state;
+pub mod diagnostics;
+pub mod history;
+pub mod hotkeys;
+pub mod i18n;
+pub mod markers;
 pub mod normalize;
+pub mod pacer;
+pub mod pause;
+pub mod replays;
+pub mod results;
+pub mod side_race;
+pub mod sparkline;
+pub mod track;
+pub mod typing_engine;
 
 use app::App;
 use leptos::prelude::*;
@@ -0,0 +1,68 @@
+// Other racers drawn inside the passage: a thin coloured marker before the char each one has
+// got to, so how far ahead or behind they are reads to the letter rather than off the lanes.
+// Markers take no room of their own, so the text doesn't shift as they move.
+
+/// Something to draw in the passage: a run of text with its class, or a marker
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece<M> {
+    Text(&'static str, String),
+    Mark(M),
+}
+
+/// `segments` of (class, text), which cover the passage from char `offset` on, with each of
+/// `marks` (char position, marker) put in before the char at its position. A mark at the end
+/// of the segments comes last; marks outside them are left out. `marks` must be sorted.
+pub fn with_marks<M: Clone>(segments: &[(&'static str, String)], offset: usize, marks: &[(usize, M)]) -> Vec<Piece<M>> {
+    let mut marks = marks.iter().skip_while(|(pos, _)| *pos < offset).peekable();
+    let mut pieces = Vec::new();
+    let mut pos = offset;
+    for (class, text) in segments {
+        let mut run = String::new();
+        for ch in text.chars() {
+            while let Some((_, mark)) = marks.next_if(|(at, _)| *at <= pos) {
+                if !run.is_empty() {
+                    pieces.push(Piece::Text(class, std::mem::take(&mut run)));
+                }
+                pieces.push(Piece::Mark(mark.clone()));
+            }
+            run.push(ch);
+            pos += 1;
+        }
+        if !run.is_empty() {
+            pieces.push(Piece::Text(class, run));
+        }
+    }
+    pieces.extend(marks.take_while(|(at, _)| *at <= pos).map(|(_, mark)| Piece::Mark(mark.clone())));
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use Piece::*;
+
+    fn text(class: &'static str, s: &str) -> Piece<char> {
+        Text(class, s.to_string())
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn marks_split_runs_before_their_char() {
+        let segments = [("ok", "abc".to_string()), ("bad", "de".to_string())];
+        assert_eq!(with_marks(&segments, 10, &[(11, 'x'), (13, 'y'), (13, 'z')]), vec![
+            text("ok", "a"),
+            Mark('x'),
+            text("ok", "bc"),
+            Mark('y'),
+            Mark('z'),
+            text("bad", "de"),
+        ]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn marks_outside_the_segments_are_left_out() {
+        let segments = [("", "abc".to_string())];
+        assert_eq!(with_marks(&segments, 5, &[(2, 'a'), (5, 'b'), (8, 'c'), (9, 'd')]), vec![Mark('b'), text("", "abc"), Mark('c')]);
+        assert_eq!(with_marks::<char>(&segments, 5, &[]), vec![text("", "abc")]);
+    }
+}
@@ -15,6 +15,14 @@ pub fn normalize_char(c: char) -> char {
     '\u{2053}' => '~',
         // Ellipsis → treat as a single '.' for typing equivalence
         '\u{2026}' => '.',
+        // Arabic punctuation → its ASCII counterpart
+        '\u{060C}' => ',',
+        '\u{061B}' => ';',
+        '\u{061F}' => '?',
+        '\u{066A}' => '%',
+        // Arabic-Indic and Persian digits → ASCII digits, whichever the keyboard types
+        '\u{0660}'..='\u{0669}' => char::from(b'0' + (c as u32 - 0x0660) as u8),
+        '\u{06F0}'..='\u{06F9}' => char::from(b'0' + (c as u32 - 0x06F0) as u8),
     // Unicode spaces and line breaks → normal space
     // ASCII whitespace: space, tab, newlines, vertical tab, form feed, carriage return
     '\u{0009}' /* TAB */ | '\u{000A}' /* LF */ | '\u{000B}' /* VT */ | '\u{000C}' /* FF */ | '\u{000D}' /* CR */ |
@@ -33,6 +41,8 @@ pub fn is_skippable(c: char) -> bool {
     '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
     // Soft hyphen (conditionally invisible)
     | '\u{00AD}'
+    // Direction marks, embeddings and isolates around mixed-direction text
+    | '\u{200E}' | '\u{200F}' | '\u{061C}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
     )
 }
 
@@ -115,6 +125,17 @@ mod tests {
         assert!(!is_skippable('\u{2009}')); // thin space should not be auto-skipped
         assert!(!is_skippable('\u{00A0}')); // nbsp should not be auto-skipped
         assert!(!is_skippable(' ')); // normal space should not be skippable
+        assert!(is_skippable('\u{200F}')); // right-to-left mark
+        assert!(is_skippable('\u{2067}')); // right-to-left isolate
+    }
+
+    #[test]
+    fn arabic_digits_and_punctuation_normalize() {
+        assert!(eq('3', '\u{0663}')); // ٣
+        assert!(eq('7', '\u{06F7}')); // ۷
+        assert!(eq(',', '\u{060C}')); // ،
+        assert!(eq('?', '\u{061F}')); // ؟
+        assert!(matches_normalized("\u{0639}\u{0627}\u{0645} \u{0662}\u{0660}\u{0662}\u{0664}\u{061F}", "\u{0639}\u{0627}\u{0645} 2024?"));
     }
 
     #[test]
@@ -142,4 +163,81 @@ mod tests {
     let typed3 = "swing~dash";
     assert!(matches_normalized(expected3, typed3));
     }
+
+    mod props {
+        use super::{is_skippable, matches_normalized, n};
+        use proptest::prelude::*;
+
+        /// Every char `normalize_char` maps, so they turn up far more often than they would
+        /// among arbitrary chars.
+        const MAPPED: &[char] = &[
+            '\u{2018}', '\u{2019}', '\u{201B}', '\u{2032}', '\u{FF07}', '\u{201C}', '\u{201D}', '\u{201F}', '\u{2033}',
+            '\u{00AB}', '\u{00BB}', '\u{2039}', '\u{203A}', '\u{FF02}', '\u{2010}', '\u{2011}', '\u{2012}', '\u{2013}',
+            '\u{2014}', '\u{2015}', '\u{2212}', '\u{FE58}', '\u{FE63}', '\u{FF0D}', '\u{2043}', '\u{2E3A}', '\u{2E3B}',
+            '\u{2053}', '\u{2026}', '\t', '\n', '\u{000B}', '\u{000C}', '\r', '\u{0085}', '\u{2028}', '\u{2029}',
+            '\u{00A0}', '\u{2007}', '\u{202F}', '\u{2000}', '\u{2009}', '\u{205F}', '\u{3000}',
+        ];
+        const SKIPPABLE: &[char] = &['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}', '\u{00AD}'];
+
+        fn passage_char() -> impl Strategy<Value = char> {
+            prop_oneof![any::<char>(), proptest::sample::select(MAPPED), proptest::sample::select(SKIPPABLE), proptest::char::range(' ', '~')]
+        }
+
+        fn passage() -> impl Strategy<Value = String> {
+            proptest::collection::vec(passage_char(), 0..40).prop_map(|cs| cs.into_iter().collect())
+        }
+
+        /// What a racer with a plain keyboard types for `expected`.
+        fn ascii_typing(expected: &str) -> String {
+            expected.chars().filter(|&c| !is_skippable(c)).map(n).collect()
+        }
+
+        proptest! {
+            #[test]
+            fn normalizing_is_idempotent(c in passage_char()) {
+                prop_assert_eq!(n(n(c)), n(c));
+            }
+
+            #[test]
+            fn chars_either_stay_or_become_ascii(c in passage_char()) {
+                let m = n(c);
+                prop_assert!(m == c || m.is_ascii(), "{:?} -> {:?}", c, m);
+            }
+
+            #[test]
+            fn printable_ascii_is_left_alone(c in proptest::char::range(' ', '~')) {
+                prop_assert_eq!(n(c), c);
+                prop_assert!(!is_skippable(c));
+            }
+
+            #[test]
+            fn skippables_are_left_alone(c in proptest::sample::select(SKIPPABLE)) {
+                prop_assert!(is_skippable(c));
+                prop_assert_eq!(n(c), c);
+            }
+
+            #[test]
+            fn plain_typing_matches_the_passage(expected in passage()) {
+                prop_assert!(matches_normalized(&expected, &ascii_typing(&expected)));
+            }
+
+            #[test]
+            fn skippables_never_change_a_match(expected in passage(), typed in passage(), at in any::<proptest::sample::Index>(), z in proptest::sample::select(SKIPPABLE)) {
+                let mut with: Vec<char> = expected.chars().collect();
+                with.insert(at.index(with.len() + 1), z);
+                let with: String = with.into_iter().collect();
+                prop_assert_eq!(matches_normalized(&with, &typed), matches_normalized(&expected, &typed));
+            }
+
+            #[test]
+            fn too_few_or_too_many_keys_never_match(expected in passage(), cut in any::<proptest::sample::Index>(), extra in passage_char()) {
+                let typed: Vec<char> = ascii_typing(&expected).chars().collect();
+                let long: String = typed.iter().copied().chain([extra]).collect();
+                prop_assert!(!matches_normalized(&expected, &long));
+                prop_assume!(!typed.is_empty());
+                let short: String = typed[..cut.index(typed.len())].iter().collect();
+                prop_assert!(!matches_normalized(&expected, &short));
+            }
+        }
+    }
 }
@@ -0,0 +1,120 @@
+// The pacing aid for solo races and practice: a ghost caret that moves through the passage at
+// a steady target WPM, with an optional click on every word (five chars) it passes, so a
+// typist can practise an even rhythm rather than typing in bursts. It only runs on this
+// browser; the server never hears of it.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+
+/// The pacer's speed range, the same as the server's pace car.
+pub const MIN_WPM: f64 = 10.0;
+pub const MAX_WPM: f64 = 250.0;
+
+/// localStorage key for the pacer picked in the UI.
+const PACER_KEY: &str = "rracer_pacer";
+
+/// How long a click lasts, in seconds.
+const CLICK_SECS: f64 = 0.03;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Pacer {
+    pub wpm: f64,
+    /// Click on every word as well as moving the ghost caret
+    pub sound: bool,
+}
+
+impl Pacer {
+    /// A pacer at `wpm`, clamped to the allowed range; None for no speed at all.
+    pub fn new(wpm: f64, sound: bool) -> Option<Pacer> {
+        (wpm.is_finite() && wpm > 0.0).then(|| Pacer { wpm: wpm.clamp(MIN_WPM, MAX_WPM), sound })
+    }
+
+    /// Where the ghost caret is `elapsed` seconds in, in chars, stopping at the end of a
+    /// passage `len` chars long.
+    pub fn position(self, elapsed: f64, len: usize) -> usize {
+        ((elapsed.max(0.0) * self.wpm * 5.0 / 60.0) as usize).min(len)
+    }
+
+    /// Seconds into the race of the `n`th click, one a word.
+    pub fn beat_at(self, n: u64) -> f64 {
+        n as f64 * 60.0 / self.wpm
+    }
+
+    /// Clicks due by `elapsed` seconds in.
+    pub fn beats_by(self, elapsed: f64) -> u64 {
+        (elapsed.max(0.0) * self.wpm / 60.0) as u64
+    }
+}
+
+/// The pacer from an earlier visit, if one was set.
+pub fn stored() -> Option<Pacer> {
+    let json = web_sys::window()?.local_storage().ok()??.get_item(PACER_KEY).ok()??;
+    serde_json::from_str(&json).ok()
+}
+
+/// Remember the pacer for later visits, or forget it.
+pub fn store(pacer: Option<Pacer>) {
+    let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) else { return };
+    let _ = match pacer.and_then(|p| serde_json::to_string(&p).ok()) {
+        Some(json) => storage.set_item(PACER_KEY, &json),
+        None => storage.remove_item(PACER_KEY),
+    };
+}
+
+thread_local! {
+    // Made on the first click, by when the page has had the user gesture browsers want for sound
+    static AUDIO: Option<web_sys::AudioContext> = web_sys::AudioContext::new().ok();
+}
+
+/// A short click `delay` seconds from now. Scheduled on the audio clock rather than played
+/// when the timer gets round to it, so the beat stays even.
+pub fn click(delay: f64) {
+    AUDIO.with(|audio| {
+        let Some(audio) = audio else { return };
+        let (Ok(osc), Ok(gain)) = (audio.create_oscillator(), audio.create_gain()) else { return };
+        let at = audio.current_time() + delay.max(0.0);
+        osc.frequency().set_value(1_000.0);
+        let _ = gain.gain().set_value_at_time(0.2, at);
+        let _ = gain.gain().exponential_ramp_to_value_at_time(0.001, at + CLICK_SECS);
+        let _ = osc.connect_with_audio_node(&gain);
+        let _ = gain.connect_with_audio_node(&audio.destination());
+        let source: &web_sys::AudioScheduledSourceNode = osc.unchecked_ref();
+        let _ = source.start_with_when(at);
+        let _ = source.stop_with_when(at + CLICK_SECS);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn the_ghost_moves_five_chars_a_word() {
+        let pacer = Pacer::new(60.0, false).unwrap();
+        assert_eq!(pacer.position(0.0, 100), 0);
+        assert_eq!(pacer.position(1.0, 100), 5);
+        assert_eq!(pacer.position(12.5, 100), 62);
+        assert_eq!(pacer.position(60.0, 100), 100);
+        assert_eq!(pacer.position(-1.0, 100), 0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn clicks_fall_one_a_word() {
+        let pacer = Pacer::new(120.0, true).unwrap();
+        assert_eq!(pacer.beat_at(1), 0.5);
+        assert_eq!(pacer.beat_at(4), 2.0);
+        assert_eq!(pacer.beats_by(0.4), 0);
+        assert_eq!(pacer.beats_by(pacer.beat_at(4)), 4);
+        // Each click lands as the ghost reaches the next word
+        assert_eq!(pacer.position(pacer.beat_at(3), 100), 15);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn speeds_are_kept_in_range() {
+        assert_eq!(Pacer::new(5.0, false).map(|p| p.wpm), Some(MIN_WPM));
+        assert_eq!(Pacer::new(900.0, false).map(|p| p.wpm), Some(MAX_WPM));
+        assert_eq!(Pacer::new(0.0, false), None);
+        assert_eq!(Pacer::new(f64::NAN, false), None);
+    }
+}
@@ -0,0 +1,64 @@
+// Pausing local practice. A run stops its clock when the tab is hidden or the typist goes
+// quiet, and picks up on the next key as if the break never happened: the race start is
+// pushed forward by the time spent paused, so every elapsed time worked out from it (the
+// clock, WPM, the pace trace) carries on where it stopped.
+
+/// Practice pauses after this long without a key, in ms.
+pub const IDLE_PAUSE_MS: f64 = 10_000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseReason {
+    /// The tab was hidden
+    Hidden,
+    /// No keys for `IDLE_PAUSE_MS`
+    Idle,
+}
+
+/// A paused practice run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pause {
+    /// When the clock stopped (ms)
+    pub at: f64,
+    pub reason: PauseReason,
+}
+
+impl Pause {
+    /// The pause due at `now` for a run last typed in at `last_input`, if it has gone idle.
+    /// It starts at the last key, so the wait before it was noticed doesn't count either.
+    pub fn idle(last_input: f64, now: f64) -> Option<Pause> {
+        (now - last_input >= IDLE_PAUSE_MS).then_some(Pause { at: last_input, reason: PauseReason::Idle })
+    }
+
+    /// Where a run that started at `start` starts from when resumed at `now`.
+    pub fn resume(self, start: f64, now: f64) -> f64 {
+        start + (now - self.at).max(0.0)
+    }
+
+    /// Seconds on the clock while paused, for a run that started at `start`.
+    pub fn elapsed(self, start: f64) -> f64 {
+        ((self.at - start) / 1000.0).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn idle_pauses_start_at_the_last_key() {
+        assert_eq!(Pause::idle(5_000.0, 5_000.0 + IDLE_PAUSE_MS - 1.0), None);
+        let pause = Pause::idle(5_000.0, 20_000.0).unwrap();
+        assert_eq!(pause, Pause { at: 5_000.0, reason: PauseReason::Idle });
+        assert_eq!(pause.elapsed(1_000.0), 4.0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn resuming_keeps_the_clock_where_it_stopped() {
+        let start = 1_000.0;
+        let pause = Pause { at: 4_000.0, reason: PauseReason::Hidden };
+        let resumed = pause.resume(start, 64_000.0);
+        // Three seconds in before the pause, three seconds in after it
+        assert_eq!((64_000.0 - resumed) / 1000.0, pause.elapsed(start));
+    }
+}
@@ -0,0 +1,245 @@
+// Replays of this browser's own races, kept in IndexedDB so a racer, guest or not, can watch
+// and study their typing without the server: every key with its time from the start, played
+// back through the same cursor the race used. The browser may want its storage back, so the
+// oldest replays go first when they near their share of the quota (or the browser refuses a
+// write), and the racer can erase any of them.
+
+use serde::{Deserialize, Serialize};
+use shared::typing::{Cursor, ErrorMode, BACKSPACE};
+use shared::wpm::{burst_wpm, consistency, per_second_wpm};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+use crate::typing_engine::press;
+
+/// Replays kept at most, whatever room the browser gives.
+pub const MAX_REPLAYS: usize = 100;
+
+const DB_NAME: &str = "rracer";
+const DB_VERSION: u32 = 1;
+const STORE: &str = "replays";
+
+/// Share of the space the browser has left that replays may fill.
+const QUOTA_SHARE: f64 = 0.5;
+
+/// Writes tried after the browser says it's out of room, each after dropping the oldest replay.
+const QUOTA_RETRIES: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LocalReplay {
+    /// Milliseconds since the Unix epoch; the replay's key in the store
+    pub finished_at: i64,
+    pub passage: String,
+    pub mode: ErrorMode,
+    pub wpm: f64,
+    pub accuracy: f64,
+    /// (ms from the start, key) for every key pressed, `BACKSPACE` included
+    pub keys: Vec<(u32, char)>,
+}
+
+/// What a replay says about the typing in it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    pub consistency: f64,
+    pub burst_wpm: f64,
+    /// (char, average ms it took to reach) for the slowest chars, slowest first
+    pub slowest: Vec<(char, f64)>,
+}
+
+impl LocalReplay {
+    /// Ms from the start to the last key.
+    pub fn duration_ms(&self) -> u32 {
+        self.keys.last().map_or(0, |&(t, _)| t)
+    }
+
+    /// The cursor `ms` into the race.
+    pub fn cursor_at(&self, ms: u32) -> Cursor {
+        let mut cursor = Cursor::default();
+        for &(_, key) in self.keys.iter().take_while(|&&(t, _)| t <= ms) {
+            press(&mut cursor, &self.passage, key, self.mode);
+        }
+        cursor
+    }
+
+    /// Pace, consistency and burst as the finish screen measures them, and the `n` chars that
+    /// took longest to type right, each timed from the key before.
+    pub fn analysis(&self, n: usize) -> Analysis {
+        let mut cursor = Cursor::default();
+        let mut trace = Vec::with_capacity(self.keys.len());
+        let mut gaps: Vec<(char, f64, usize)> = Vec::new();
+        let mut last = 0;
+        for &(t, key) in &self.keys {
+            let before = cursor.correct();
+            press(&mut cursor, &self.passage, key, self.mode);
+            trace.push((f64::from(t) / 1000.0, cursor.pos));
+            if key != BACKSPACE && cursor.correct() > before {
+                let gap = f64::from(t.saturating_sub(last));
+                match gaps.iter_mut().find(|(c, ..)| *c == key) {
+                    Some((_, total, count)) => {
+                        *total += gap;
+                        *count += 1;
+                    }
+                    None => gaps.push((key, gap, 1)),
+                }
+            }
+            last = t;
+        }
+        let per_second = per_second_wpm(&trace);
+        let mut slowest: Vec<(char, f64)> = gaps.into_iter().map(|(c, total, count)| (c, total / count as f64)).collect();
+        slowest.sort_by(|a, b| b.1.total_cmp(&a.1));
+        slowest.truncate(n);
+        Analysis { consistency: consistency(&per_second), burst_wpm: burst_wpm(&per_second), slowest }
+    }
+}
+
+/// Keys of replays to drop, oldest first, to make room for one more of `incoming` bytes.
+/// `stored` is (key, bytes) oldest first; `room` is what replays may take in all, if known.
+pub fn to_evict(stored: &[(i64, usize)], incoming: usize, room: Option<usize>) -> Vec<i64> {
+    let mut total: usize = stored.iter().map(|&(_, bytes)| bytes).sum::<usize>() + incoming;
+    let mut count = stored.len() + 1;
+    let mut evict = Vec::new();
+    for &(key, bytes) in stored {
+        if count <= MAX_REPLAYS && room.is_none_or(|room| total <= room) {
+            break;
+        }
+        evict.push(key);
+        total -= bytes;
+        count -= 1;
+    }
+    evict
+}
+
+/// Every stored replay, newest first.
+pub async fn all() -> Result<Vec<LocalReplay>, JsValue> {
+    let store = store(IdbTransactionMode::Readonly).await?;
+    let values: js_sys::Array = done(&store.get_all()?).await?.unchecked_into();
+    let mut replays: Vec<LocalReplay> = values.iter().filter_map(|v| serde_json::from_str(&v.as_string()?).ok()).collect();
+    replays.sort_by_key(|r| std::cmp::Reverse(r.finished_at));
+    Ok(replays)
+}
+
+/// Keep `replay`, first dropping the oldest as its room or the replay cap calls for.
+pub async fn save(replay: &LocalReplay) -> Result<(), JsValue> {
+    let json = serde_json::to_string(replay).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let mut stored: Vec<(i64, usize)> = all().await?.iter().rev().map(|r| (r.finished_at, serde_json::to_string(r).map_or(0, |j| j.len()))).collect();
+    let ours: usize = stored.iter().map(|&(_, bytes)| bytes).sum();
+    // Replays may fill their share of the free space plus what they hold now. Sizes here are in
+    // JSON chars, which the store keeps at two bytes each
+    let room = free_space().await.map(|free| ((free + ours as f64 * 2.0) * QUOTA_SHARE / 2.0) as usize);
+    for key in to_evict(&stored, json.len(), room) {
+        erase(key).await?;
+        stored.retain(|&(k, _)| k != key);
+    }
+    let mut retries = 0;
+    loop {
+        let store = store(IdbTransactionMode::Readwrite).await?;
+        match done(&store.put_with_key(&JsValue::from_str(&json), &JsValue::from_f64(replay.finished_at as f64))?).await {
+            Ok(_) => return Ok(()),
+            Err(e) if is_quota_error(&e) && retries < QUOTA_RETRIES && !stored.is_empty() => {
+                erase(stored.remove(0).0).await?;
+                retries += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Drop the replay that finished at `finished_at`.
+pub async fn erase(finished_at: i64) -> Result<(), JsValue> {
+    let store = store(IdbTransactionMode::Readwrite).await?;
+    done(&store.delete(&JsValue::from_f64(finished_at as f64))?).await.map(drop)
+}
+
+/// Drop every replay.
+pub async fn erase_all() -> Result<(), JsValue> {
+    let store = store(IdbTransactionMode::Readwrite).await?;
+    done(&store.clear()?).await.map(drop)
+}
+
+/// Bytes the browser says this site may still store; None where it won't say.
+async fn free_space() -> Option<f64> {
+    let estimate = JsFuture::from(web_sys::window()?.navigator().storage().estimate().ok()?).await.ok()?;
+    let get = |field: &str| js_sys::Reflect::get(&estimate, &field.into()).ok()?.as_f64();
+    Some((get("quota")? - get("usage").unwrap_or(0.0)).max(0.0))
+}
+
+fn is_quota_error(e: &JsValue) -> bool {
+    e.dyn_ref::<web_sys::DomException>().is_some_and(|e| e.name() == "QuotaExceededError")
+}
+
+async fn store(mode: IdbTransactionMode) -> Result<IdbObjectStore, JsValue> {
+    open().await?.transaction_with_str_and_mode(STORE, mode)?.object_store(STORE)
+}
+
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window().ok_or("no window")?.indexed_db()?.ok_or("no IndexedDB")?;
+    let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+    let upgrade = Closure::once_into_js(move |ev: web_sys::Event| {
+        let Some(db) = ev.target().and_then(|t| t.dyn_into::<IdbRequest>().ok()).and_then(|r| r.result().ok()) else { return };
+        let db: IdbDatabase = db.unchecked_into();
+        if !db.object_store_names().contains(STORE) {
+            let _ = db.create_object_store(STORE);
+        }
+    });
+    request.set_onupgradeneeded(Some(upgrade.unchecked_ref()));
+    Ok(done(&request).await?.unchecked_into())
+}
+
+/// The result of `request` once it succeeds, or its error.
+async fn done(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let ok = request.clone();
+        let failed = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            let _ = resolve.call1(&JsValue::NULL, &ok.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        let on_error = Closure::once_into_js(move || {
+            let error = failed.error().ok().flatten().map_or(JsValue::UNDEFINED, JsValue::from);
+            let _ = reject.call1(&JsValue::NULL, &error);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn replay(keys: &[(u32, char)]) -> LocalReplay {
+        LocalReplay { finished_at: 1, passage: "abab".to_string(), mode: ErrorMode::Strict, wpm: 60.0, accuracy: 100.0, keys: keys.to_vec() }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn playback_runs_the_keys_up_to_the_moment() {
+        let r = replay(&[(100, 'a'), (250, 'x'), (300, 'b'), (900, 'a')]);
+        assert_eq!(r.cursor_at(0).pos, 0);
+        assert_eq!(r.cursor_at(260).errors, 1);
+        assert_eq!(r.cursor_at(300).pos, 2);
+        assert_eq!(r.cursor_at(r.duration_ms()).pos, 3);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn the_slowest_chars_come_first() {
+        // Each 'b' took 200ms from the key before; each 'a' 100ms
+        let r = replay(&[(100, 'a'), (300, 'b'), (400, 'a'), (600, 'b')]);
+        let analysis = r.analysis(5);
+        assert_eq!(analysis.slowest, vec![('b', 200.0), ('a', 100.0)]);
+        assert_eq!(r.analysis(1).slowest.len(), 1);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn the_oldest_replays_make_room() {
+        let stored = [(1, 100), (2, 100), (3, 100)];
+        assert!(to_evict(&stored, 100, None).is_empty());
+        assert!(to_evict(&stored, 100, Some(400)).is_empty());
+        assert_eq!(to_evict(&stored, 100, Some(250)), vec![1, 2]);
+        // However much room there is, only so many are kept
+        let full: Vec<(i64, usize)> = (0..MAX_REPLAYS as i64).map(|k| (k, 1)).collect();
+        assert_eq!(to_evict(&full, 1, None), vec![0]);
+    }
+}
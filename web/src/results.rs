@@ -0,0 +1,180 @@
+// The finish screen's results table: a row per finisher, kept in finishing order, which the
+// racer can re-sort by any column. The rank stays the finishing place whatever the sort. A
+// room raced in heats swaps its heat's rows for everyone's once the last heat is over.
+
+use shared::heats::HeatResult;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultRow {
+    /// Finishing place, from 1
+    pub rank: usize,
+    pub name: String,
+    pub wpm: f64,
+    pub raw_wpm: f64,
+    pub accuracy: f64,
+    pub errors: usize,
+    /// The server counted `errors` itself rather than taking the racer's word for them
+    pub counted: bool,
+    /// Seconds from the start
+    pub time: f64,
+    pub consistency: f64,
+    /// (checkpoint percent, seconds from the start)
+    pub splits: Vec<(u8, f64)>,
+    /// The heat raced in, from 0, in results from a room raced in heats
+    pub heat: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    Rank,
+    Name,
+    Wpm,
+    RawWpm,
+    Accuracy,
+    Errors,
+    Time,
+    Consistency,
+}
+
+impl Column {
+    pub const ALL: [Column; 8] = [Column::Rank, Column::Name, Column::Wpm, Column::RawWpm, Column::Accuracy, Column::Errors, Column::Time, Column::Consistency];
+
+    /// Whether bigger is better, so the first click on the column puts the biggest on top.
+    fn descending_first(self) -> bool {
+        matches!(self, Column::Wpm | Column::RawWpm | Column::Accuracy | Column::Consistency)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sort {
+    pub column: Column,
+    pub descending: bool,
+}
+
+impl Default for Sort {
+    /// Finishing order
+    fn default() -> Self {
+        Sort { column: Column::Rank, descending: false }
+    }
+}
+
+impl Sort {
+    /// The sort after clicking `column`'s header: the same column again flips it, another
+    /// column starts best first.
+    pub fn click(self, column: Column) -> Sort {
+        if column == self.column {
+            Sort { column, descending: !self.descending }
+        } else {
+            Sort { column, descending: column.descending_first() }
+        }
+    }
+}
+
+/// Add a finisher to `rows`, which are in finishing order: at the end, or under golf scoring
+/// after everyone with no more errors, errors the server didn't count going after those it did. Ranks are renumbered to match.
+pub fn add(rows: &mut Vec<ResultRow>, row: ResultRow, golf: bool) {
+    rows.push(row);
+    // Stable, so equal error counts stay in the order they finished
+    if golf {
+        rows.sort_by_key(|r| (!r.counted, r.errors));
+    }
+    for (i, r) in rows.iter_mut().enumerate() {
+        r.rank = i + 1;
+    }
+}
+
+/// Rows for every heat's finishers, `results` being best first. Splits are only known for
+/// our own heat, whose `rows` keep them.
+pub fn overall(rows: &[ResultRow], results: &[HeatResult]) -> Vec<ResultRow> {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let splits = rows.iter().find(|row| row.name == r.name).map(|row| row.splits.clone()).unwrap_or_default();
+            ResultRow { rank: i + 1, name: r.name.clone(), wpm: r.wpm, raw_wpm: r.raw_wpm, accuracy: r.accuracy, errors: r.errors, counted: r.counted, time: r.time, consistency: r.consistency, splits, heat: Some(r.heat) }
+        })
+        .collect()
+}
+
+/// `rows` in `sort` order, ties in finishing order.
+pub fn sorted(rows: &[ResultRow], sort: Sort) -> Vec<ResultRow> {
+    let mut rows = rows.to_vec();
+    rows.sort_by(|a, b| {
+        let order = match sort.column {
+            Column::Rank => a.rank.cmp(&b.rank),
+            Column::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            Column::Wpm => a.wpm.total_cmp(&b.wpm),
+            Column::RawWpm => a.raw_wpm.total_cmp(&b.raw_wpm),
+            Column::Accuracy => a.accuracy.total_cmp(&b.accuracy),
+            Column::Errors => a.errors.cmp(&b.errors),
+            Column::Time => a.time.total_cmp(&b.time),
+            Column::Consistency => a.consistency.total_cmp(&b.consistency),
+        };
+        if sort.descending { order.reverse() } else { order }.then(a.rank.cmp(&b.rank))
+    });
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn row(name: &str, wpm: f64, errors: usize) -> ResultRow {
+        ResultRow { rank: 0, name: name.to_string(), wpm, raw_wpm: wpm + errors as f64, accuracy: 100.0, errors, counted: true, time: 30.0, consistency: 80.0, splits: Vec::new(), heat: None }
+    }
+
+    fn names(rows: &[ResultRow]) -> Vec<&str> {
+        rows.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn finishers_are_ranked_as_they_come_or_by_errors_under_golf() {
+        let mut rows = Vec::new();
+        for r in [row("a", 90.0, 3), row("b", 80.0, 1), row("c", 70.0, 3)] {
+            add(&mut rows, r, false);
+        }
+        assert_eq!(names(&rows), ["a", "b", "c"]);
+        assert_eq!(rows.iter().map(|r| r.rank).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let mut golf = Vec::new();
+        for r in [row("a", 90.0, 3), row("b", 80.0, 1), row("c", 70.0, 3)] {
+            add(&mut golf, r, true);
+        }
+        assert_eq!(names(&golf), ["b", "a", "c"]);
+        assert_eq!(golf[0].rank, 1);
+        // Errors the server didn't count itself rank after those it did
+        add(&mut golf, ResultRow { counted: false, ..row("d", 99.0, 0) }, true);
+        assert_eq!(names(&golf), ["b", "a", "c", "d"]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn columns_sort_best_first_then_flip() {
+        let mut rows = Vec::new();
+        for r in [row("b", 70.0, 0), row("a", 90.0, 2), row("c", 70.0, 1)] {
+            add(&mut rows, r, false);
+        }
+        let by_wpm = Sort::default().click(Column::Wpm);
+        assert!(by_wpm.descending);
+        // Ties keep finishing order
+        assert_eq!(names(&sorted(&rows, by_wpm)), ["a", "b", "c"]);
+        assert_eq!(names(&sorted(&rows, by_wpm.click(Column::Wpm))), ["b", "c", "a"]);
+
+        let by_errors = by_wpm.click(Column::Errors);
+        assert!(!by_errors.descending);
+        assert_eq!(names(&sorted(&rows, by_errors)), ["b", "c", "a"]);
+        assert_eq!(names(&sorted(&rows, Sort::default().click(Column::Name))), ["a", "b", "c"]);
+        assert_eq!(names(&sorted(&rows, Sort::default())), ["b", "a", "c"]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn every_heat_is_ranked_together_at_the_end() {
+        let mut ours = row("a", 70.0, 0);
+        ours.splits = vec![(50, 10.0)];
+        let result = |heat, name: &str, wpm| HeatResult { heat, id: name.to_string(), name: name.to_string(), wpm, raw_wpm: wpm, accuracy: 100.0, errors: 0, time: 30.0, consistency: 80.0, counted: true };
+        let rows = overall(&[ours], &[result(1, "b", 90.0), result(0, "a", 70.0)]);
+        assert_eq!(names(&rows), ["b", "a"]);
+        assert_eq!((rows[1].rank, rows[1].heat, rows[1].splits.len()), (2, Some(0), 1));
+        assert!(rows[0].splits.is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+// A room watched from the lobby of another (ClientMsg::Watch). Its messages arrive wrapped in
+// ServerMsg::Watched, and just enough of them are kept to show who is racing there and how
+// far along they are.
+
+use std::collections::HashMap;
+
+use shared::protocol::{GamePhase, PlayerInfo, ServerMsg};
+use shared::text::char_len;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SideRace {
+    pub room: String,
+    pub phase: GamePhase,
+    pub players: Vec<PlayerInfo>,
+    /// Chars in the passage being raced; 0 until one is known
+    pub len: usize,
+    /// Player id -> chars typed
+    pub positions: HashMap<String, usize>,
+}
+
+impl SideRace {
+    pub fn new(room: String) -> Self {
+        Self { room, ..Self::default() }
+    }
+
+    /// Fold in one message from the watched room.
+    pub fn apply(&mut self, msg: ServerMsg) {
+        match msg {
+            ServerMsg::Sync { state, passage, players, positions, .. } => {
+                self.phase = state;
+                self.len = passage.as_deref().map_or(0, char_len);
+                self.players = players;
+                self.positions = positions;
+            }
+            ServerMsg::Lobby { players, .. } => self.players = players,
+            ServerMsg::PlayerLeft { id } => {
+                self.players.retain(|p| p.id != id);
+                self.positions.remove(&id);
+            }
+            ServerMsg::Countdown { passage, .. } => self.new_race(GamePhase::Countdown, &passage),
+            ServerMsg::Start { passage, .. } => self.new_race(GamePhase::Racing, &passage),
+            ServerMsg::Progress { id, pos } => {
+                self.positions.insert(id, pos);
+            }
+            ServerMsg::Finish { id, .. } => {
+                self.positions.insert(id, self.len);
+            }
+            ServerMsg::StateChange { state } => self.phase = state,
+            _ => {}
+        }
+    }
+
+    fn new_race(&mut self, phase: GamePhase, passage: &str) {
+        self.phase = phase;
+        self.len = char_len(passage);
+        self.positions.clear();
+    }
+
+    /// (name, percent of the passage typed) for each racer, furthest first.
+    pub fn standings(&self) -> Vec<(String, u32)> {
+        let mut standings: Vec<(String, u32)> = self
+            .players
+            .iter()
+            .map(|p| {
+                let pos = self.positions.get(&p.id).copied().unwrap_or(0);
+                let percent = (pos.min(self.len) * 100).checked_div(self.len).unwrap_or(0) as u32;
+                (p.name.clone(), percent)
+            })
+            .collect();
+        standings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        standings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn racer(id: &str) -> PlayerInfo {
+        PlayerInfo { id: id.to_string(), name: id.to_uppercase(), is_bot: false, is_host: false, rating: None, pace_for: None, level: None, color: 0 }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn a_watched_race_tracks_its_racers() {
+        let mut side = SideRace::new("featured".to_string());
+        side.apply(ServerMsg::Lobby { players: vec![racer("a"), racer("b")], hall_of_fame: Default::default(), duel: None });
+        side.apply(ServerMsg::Start { passage: "0123456789".to_string(), t0: 1, attribution: None, hash: 0 });
+        side.apply(ServerMsg::Progress { id: "a".to_string(), pos: 3 });
+        side.apply(ServerMsg::Finish { id: "b".to_string(), wpm: 90.0, accuracy: 100.0, normalized_wpm: 90.0, errors: 0, raw_wpm: 90.0, time: 20.0, consistency: 100.0, verified: false, counted: true });
+        assert_eq!(side.phase, GamePhase::Racing);
+        assert_eq!(side.standings(), vec![("B".to_string(), 100), ("A".to_string(), 30)]);
+
+        // The next race starts everyone from scratch
+        side.apply(ServerMsg::Countdown { passage: "abcd".to_string(), attribution: None, record: None, hash: 0 });
+        assert_eq!(side.phase, GamePhase::Countdown);
+        assert_eq!(side.standings(), vec![("A".to_string(), 0), ("B".to_string(), 0)]);
+        side.apply(ServerMsg::PlayerLeft { id: "a".to_string() });
+        assert_eq!(side.standings(), vec![("B".to_string(), 0)]);
+    }
+}
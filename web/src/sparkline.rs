@@ -0,0 +1,38 @@
+// SVG polyline geometry for the per-second WPM graph on the finish screen.
+
+/// `points` attribute for a polyline spanning a `width` x `height` box, scaled so the
+/// fastest sample touches the top edge. Needs at least two samples to draw a line.
+pub fn polyline_points(samples: &[f64], width: f64, height: f64) -> Option<String> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let max = samples.iter().cloned().fold(0.0_f64, f64::max).max(1.0);
+    let step = width / (samples.len() - 1) as f64;
+    let points: Vec<String> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| format!("{:.1},{:.1}", i as f64 * step, height - s.max(0.0) / max * height))
+        .collect();
+    Some(points.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_few_samples_draw_nothing() {
+        assert_eq!(polyline_points(&[], 100.0, 20.0), None);
+        assert_eq!(polyline_points(&[50.0], 100.0, 20.0), None);
+    }
+
+    #[test]
+    fn scales_to_the_box_with_peak_at_top() {
+        assert_eq!(polyline_points(&[0.0, 50.0, 100.0], 100.0, 20.0).unwrap(), "0.0,20.0 50.0,10.0 100.0,0.0");
+    }
+
+    #[test]
+    fn negative_samples_sit_on_the_baseline() {
+        assert_eq!(polyline_points(&[-5.0, 10.0], 10.0, 10.0).unwrap(), "0.0,10.0 10.0,0.0");
+    }
+}
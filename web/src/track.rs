@@ -0,0 +1,74 @@
+// Geometry for the race track: which stretch of the passage the lanes show and where a car
+// or checkpoint sits on it. Everything is in chars, never bytes.
+
+use shared::protocol::{checkpoint_position, SPLIT_CHECKPOINTS};
+
+/// Car offsets run from 0% to this, leaving room for the car before the finish line.
+pub const TRACK_END: f64 = 95.0;
+
+/// Chars the lanes span when following a racer.
+pub const ZOOM_CHARS: usize = 120;
+
+/// How much of the passage the lanes span
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrackScale {
+    /// Start to finish, so the lanes double as the overview
+    #[default]
+    Whole,
+    /// A `ZOOM_CHARS` window around one racer, so progress stays visible on long passages
+    Follow,
+}
+
+/// `[start, end)` of the passage the lanes show, in chars. `focus` is the followed racer's
+/// position; the window keeps it a quarter of the way in until the finish comes into view.
+pub fn visible_span(scale: TrackScale, total: usize, focus: usize) -> (usize, usize) {
+    match scale {
+        TrackScale::Follow if total > ZOOM_CHARS => {
+            let start = focus.saturating_sub(ZOOM_CHARS / 4).min(total - ZOOM_CHARS);
+            (start, start + ZOOM_CHARS)
+        }
+        _ => (0, total),
+    }
+}
+
+/// Left offset (in % of the lane) for a car at `pos`; cars outside the span are pinned to its edges.
+pub fn lane_percent(pos: usize, (start, end): (usize, usize)) -> f64 {
+    let width = end.saturating_sub(start).max(1);
+    pos.clamp(start, end.max(start)).saturating_sub(start) as f64 / width as f64 * TRACK_END
+}
+
+/// (percent, char position) of each split checkpoint in a passage of `total` chars.
+pub fn checkpoint_positions(total: usize) -> impl Iterator<Item = (u8, usize)> {
+    SPLIT_CHECKPOINTS.iter().map(move |&c| (c, checkpoint_position(total, c)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_track_scales_by_chars() {
+        let span = visible_span(TrackScale::Whole, 400, 10);
+        assert_eq!(span, (0, 400));
+        assert_eq!(lane_percent(0, span), 0.0);
+        assert_eq!(lane_percent(200, span), TRACK_END / 2.0);
+        assert_eq!(lane_percent(999, span), TRACK_END);
+        assert_eq!(lane_percent(0, (0, 0)), 0.0);
+    }
+
+    #[test]
+    fn follow_keeps_the_racer_in_view_until_the_finish() {
+        assert_eq!(visible_span(TrackScale::Follow, 1000, 0), (0, ZOOM_CHARS));
+        assert_eq!(visible_span(TrackScale::Follow, 1000, 500), (470, 590));
+        assert_eq!(visible_span(TrackScale::Follow, 1000, 990), (880, 1000));
+        // Short passages fit anyway
+        assert_eq!(visible_span(TrackScale::Follow, 80, 40), (0, 80));
+        // Racers behind the window sit at its start
+        assert_eq!(lane_percent(100, (470, 590)), 0.0);
+    }
+
+    #[test]
+    fn checkpoints_are_quarters_of_the_passage() {
+        assert_eq!(checkpoint_positions(10).collect::<Vec<_>>(), vec![(25, 3), (50, 5), (75, 8)]);
+    }
+}
@@ -0,0 +1,137 @@
+// What a key press in the typing area does, kept out of the view so it can be tested: which
+// char a browser key types, how it moves the cursor, the live WPM, and how often progress is
+// reported to the server.
+
+use shared::text::{char_at, char_len};
+use shared::typing::{Cursor, ErrorMode, BACKSPACE};
+
+use crate::normalize::{is_skippable, normalize_char};
+
+/// Elapsed times are clamped to this many seconds so the first keys don't read as huge speeds.
+const MIN_ELAPSED_SECS: f64 = 0.1;
+
+/// The passage char a `KeyboardEvent.key` types, normalized, or `BACKSPACE`; None for keys
+/// that type nothing (Shift, ArrowLeft and so on).
+pub fn key_char(key: &str) -> Option<char> {
+    if key == "Backspace" {
+        return Some(BACKSPACE);
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(normalize_char(c)),
+        _ => None,
+    }
+}
+
+/// What a key press did to the cursor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Press {
+    /// Typed (or backspaced) a char
+    Moved,
+    /// Stepped over an invisible char instead
+    Skipped,
+    /// Nothing; a wrong key in strict mode, say
+    Stayed,
+}
+
+impl Press {
+    pub fn moved(self) -> bool {
+        self != Press::Stayed
+    }
+}
+
+/// Apply `typed` at the cursor. Invisible chars in the passage (zero-width spaces, soft
+/// hyphens) are stepped over by whichever key comes next, except Backspace.
+pub fn press(cursor: &mut Cursor, passage: &str, typed: char, mode: ErrorMode) -> Press {
+    if typed != BACKSPACE && char_at(passage, cursor.pos).is_some_and(is_skippable) {
+        cursor.pos += 1;
+        return Press::Skipped;
+    }
+    if cursor.press(passage, typed, mode, |typed, expected| typed == normalize_char(expected)) {
+        Press::Moved
+    } else {
+        Press::Stayed
+    }
+}
+
+/// Monkeytype-style WPM: correct chars only, with no penalty for errors.
+pub fn live_wpm(correct: usize, elapsed_secs: f64) -> f64 {
+    (correct as f64 / 5.0) / (elapsed_secs.max(MIN_ELAPSED_SECS) / 60.0)
+}
+
+/// Whether progress at `now` (ms) should go to the server, given the last send at `last_sent`
+/// and the least time between `Progress` messages the connection calls for
+/// (`diagnostics::Quality::progress_interval_ms`).
+pub fn progress_due(last_sent: f64, now: f64, interval_ms: f64) -> bool {
+    now - last_sent >= interval_ms
+}
+
+pub fn finished(cursor: &Cursor, passage: &str) -> bool {
+    cursor.pos >= char_len(passage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn type_keys(cursor: &mut Cursor, passage: &str, keys: &[&str], mode: ErrorMode) -> Vec<Press> {
+        keys.iter().filter_map(|k| key_char(k)).map(|c| press(cursor, passage, c, mode)).collect()
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn browser_keys_become_passage_chars() {
+        assert_eq!(key_char("a"), Some('a'));
+        assert_eq!(key_char("\u{2019}"), Some('\''));
+        assert_eq!(key_char("Backspace"), Some(BACKSPACE));
+        assert_eq!(key_char("Shift"), None);
+        assert_eq!(key_char(""), None);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ascii_keys_type_a_typographic_passage() {
+        let passage = "\u{201C}It\u{2019}s\u{00A0}fine\u{2014}ok\u{201D}";
+        let mut cursor = Cursor::default();
+        let keys: Vec<String> = "\"It's fine-ok\"".chars().map(String::from).collect();
+        let presses = type_keys(&mut cursor, passage, &keys.iter().map(String::as_str).collect::<Vec<_>>(), ErrorMode::Strict);
+        assert!(presses.iter().all(|p| *p == Press::Moved));
+        assert!(finished(&cursor, passage));
+        assert_eq!((cursor.errors, cursor.correct()), (0, char_len(passage)));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn invisible_chars_take_a_key_but_not_backspace() {
+        let passage = "a\u{200B}b";
+        let mut cursor = Cursor::default();
+        assert_eq!(type_keys(&mut cursor, passage, &["a", "Backspace"], ErrorMode::Forgiving), vec![Press::Moved, Press::Moved]);
+        assert_eq!(cursor.pos, 0);
+        assert_eq!(type_keys(&mut cursor, passage, &["a", "x", "b"], ErrorMode::Strict), vec![Press::Moved, Press::Skipped, Press::Moved]);
+        assert!(finished(&cursor, passage));
+        assert_eq!(cursor.errors, 0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn wrong_keys_follow_the_error_mode() {
+        let mut strict = Cursor::default();
+        assert_eq!(type_keys(&mut strict, "ab", &["x"], ErrorMode::Strict), vec![Press::Stayed]);
+        assert_eq!((strict.pos, strict.errors), (0, 1));
+        let mut forgiving = Cursor::default();
+        assert!(type_keys(&mut forgiving, "ab", &["x"], ErrorMode::Forgiving)[0].moved());
+        assert!(forgiving.is_wrong(0));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn live_wpm_counts_correct_chars() {
+        assert_eq!(live_wpm(50, 12.0), 50.0);
+        // The first key doesn't read as thousands of WPM
+        assert_eq!(live_wpm(1, 0.0), live_wpm(1, MIN_ELAPSED_SECS));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn progress_is_sent_at_most_every_interval() {
+        assert!(progress_due(0.0, 1_000.0, 100.0));
+        assert!(!progress_due(1_000.0, 1_099.0, 100.0));
+        assert!(progress_due(1_000.0, 1_100.0, 100.0));
+        assert!(!progress_due(1_000.0, 1_100.0, 250.0));
+    }
+}